@@ -0,0 +1,116 @@
+use crate::symmetric_encryption::SymmetricEncryptionScheme;
+use crate::utils::scalar_to_byte_vector;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use elliptic_curve::CurveArithmetic;
+use hkdf::Hkdf;
+use rand_core::OsRng;
+use sha2::Sha256;
+use std::marker::PhantomData;
+
+// Authenticated encryption of byte strings of any length with ChaCha20-Poly1305, keyed by a
+// scalar. It is meant to be keyed by the witness w of an adaptor signature: the seller encrypts
+// the data with w, and the buyer decrypts it with the w extracted from the adapted signature.
+// The key of ChaCha20-Poly1305 is derived from w with HKDF-SHA256, and the cyphertext is a random
+// nonce followed by the output of ChaCha20-Poly1305, so the same w can encrypt several messages.
+// Unlike OneTimePad, a wrong key is detected when decrypting.
+pub struct AeadEncryption<C>
+where
+    C: CurveArithmetic,
+{
+    _curve_marker: PhantomData<C>,
+}
+
+const NONCE_BYTES: usize = 12;
+
+impl<C> AeadEncryption<C>
+where
+    C: CurveArithmetic,
+{
+    fn cipher(key: &C::Scalar) -> ChaCha20Poly1305 {
+        let hkdf = Hkdf::<Sha256>::new(None, &scalar_to_byte_vector::<C>(key));
+        let mut key = Key::default();
+        hkdf.expand(b"adaptor_witness_chacha20poly1305_key", &mut key)
+            .expect("32 bytes is a valid length for HKDF-SHA256");
+
+        ChaCha20Poly1305::new(&key)
+    }
+}
+
+impl<C> SymmetricEncryptionScheme for AeadEncryption<C>
+where
+    C: CurveArithmetic,
+{
+    type Key = C::Scalar;
+    type Message = Vec<u8>;
+    type CypherText = Vec<u8>;
+
+    fn enc(key: &Self::Key, msg: &Self::Message) -> Self::CypherText {
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let mut ct = Self::cipher(key)
+            .encrypt(&nonce, msg.as_slice())
+            .expect("the message is shorter than the maximum length of ChaCha20-Poly1305");
+        let mut v: Vec<u8> = nonce.to_vec();
+        v.append(&mut ct);
+
+        v
+    }
+
+    fn dec(key: &Self::Key, ct: &Self::CypherText) -> Option<Self::Message> {
+        if ct.len() < NONCE_BYTES {
+            return None;
+        }
+        let (nonce, ct) = ct.split_at(NONCE_BYTES);
+
+        Self::cipher(key).decrypt(Nonce::from_slice(nonce), ct).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adaptor_signatures::AdaptorSignatureScheme;
+    use crate::hard_relation::HardRelation;
+    use crate::schnorr_adaptor_signatures::SchnorrAdaptorSignature;
+    use elliptic_curve::scalar::NonZeroScalar;
+    use k256::Secp256k1;
+
+    type C = Secp256k1;
+    type Point = <C as CurveArithmetic>::ProjectivePoint;
+    type ASig = SchnorrAdaptorSignature<C, Sha256>;
+    type SymEnc = AeadEncryption<C>;
+
+    #[test]
+    fn aead_encryption() {
+        let (w, _) = NonZeroScalar::<C>::gen(&Point::GENERATOR);
+        let msg = vec![7u8; 1000];
+        let ct = SymEnc::enc(&w, &msg);
+        assert_eq!(ct.len(), NONCE_BYTES + msg.len() + 16);
+        assert_eq!(SymEnc::dec(&w, &ct).unwrap(), msg);
+
+        // a fresh nonce is used for every encryption
+        assert_ne!(SymEnc::enc(&w, &msg), ct);
+
+        let (other_w, _) = NonZeroScalar::<C>::gen(&Point::GENERATOR);
+        assert!(SymEnc::dec(&other_w, &ct).is_none());
+        let mut tampered = ct.clone();
+        tampered[NONCE_BYTES] ^= 1;
+        assert!(SymEnc::dec(&w, &tampered).is_none());
+        assert!(SymEnc::dec(&w, &ct[..NONCE_BYTES - 1].to_vec()).is_none());
+    }
+
+    #[test]
+    fn aead_encryption_with_extracted_witness() {
+        let (sk, pk) = ASig::gen();
+        let (w, x) = NonZeroScalar::<C>::gen(&Point::GENERATOR);
+        let data = b"the purchased file".to_vec();
+        let ct = SymEnc::enc(&w, &data);
+
+        let tx = "(alpha, pk_b) -> (alpha, pk_s)";
+        let pre_signature = ASig::pre_sign(&sk, tx, &x);
+        let signature = ASig::adapt(&pk, &pre_signature, &w);
+        let extracted_w = ASig::extract(&pk, &pre_signature, &signature).unwrap();
+
+        assert_eq!(SymEnc::dec(&extracted_w, &ct).unwrap(), data);
+    }
+}
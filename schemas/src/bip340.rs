@@ -0,0 +1,342 @@
+use elliptic_curve::ff::PrimeField;
+use elliptic_curve::ops::Reduce;
+use elliptic_curve::point::{AffineCoordinates, DecompressPoint};
+use elliptic_curve::sec1::ToEncodedPoint;
+use elliptic_curve::subtle::Choice;
+use k256::{AffinePoint, FieldBytes, NonZeroScalar, ProjectivePoint, Scalar, U256};
+use rand_core::{OsRng, RngCore};
+use sha2::{Digest, Sha256};
+
+// BIP-340 Schnorr signatures over secp256k1, as used by Bitcoin Taproot: public keys are the x
+// coordinate of a point with even y, and the nonce point of a signature has even y as well.
+// Besides single-signer signatures, it implements 2-of-2 adaptor signatures under an aggregated
+// (and optionally tweaked) key, so that the cooperative key-path spend of a Taproot output can be
+// a pre-signature of both parties that only the holder of the witness w of x = g^w completes.
+//
+// The aggregation uses the coefficients of MuSig, a_i = H(L, P_i) where L commits to both keys,
+// so that none of the parties can choose its key to cancel the other one. It is not BIP-327: the
+// nonces are single points and are used for one session only.
+
+pub type XOnly = [u8; 32];
+pub type Signature = [u8; 64];
+
+pub fn tagged_hash(tag: &str, data: &[&[u8]]) -> [u8; 32] {
+    let tag_hash = Sha256::digest(tag.as_bytes());
+    let mut hasher = Sha256::new().chain_update(tag_hash).chain_update(tag_hash);
+    for d in data {
+        hasher.update(d);
+    }
+
+    hasher.finalize().into()
+}
+
+pub fn hash_to_scalar(tag: &str, data: &[&[u8]]) -> Scalar {
+    <Scalar as Reduce<U256>>::reduce_bytes(&FieldBytes::from(tagged_hash(tag, data)))
+}
+
+pub fn x_only(point: &ProjectivePoint) -> XOnly {
+    point.to_affine().x().into()
+}
+
+pub fn has_even_y(point: &ProjectivePoint) -> bool {
+    !bool::from(point.to_affine().y_is_odd())
+}
+
+// The point with x coordinate x and even y, if any
+pub fn lift_x(x: &XOnly) -> Option<ProjectivePoint> {
+    let point: Option<AffinePoint> = AffinePoint::decompress(&(*x).into(), Choice::from(0)).into();
+
+    point.map(ProjectivePoint::from)
+}
+
+// 1 if the point has even y and -1 otherwise, so that point * sign has even y
+fn parity_factor(point: &ProjectivePoint) -> Scalar {
+    if has_even_y(point) {
+        Scalar::ONE
+    } else {
+        -Scalar::ONE
+    }
+}
+
+fn challenge(r: &ProjectivePoint, pk: &ProjectivePoint, msg: &[u8]) -> Scalar {
+    hash_to_scalar("BIP0340/challenge", &[&x_only(r), &x_only(pk), msg])
+}
+
+pub fn public_key(sk: &NonZeroScalar) -> XOnly {
+    x_only(&(ProjectivePoint::GENERATOR * sk.as_ref()))
+}
+
+// Signs with the nonce derivation of BIP-340 and the auxiliary randomness aux
+pub fn sign_with_aux(sk: &NonZeroScalar, msg: &[u8], aux: &[u8; 32]) -> Signature {
+    let pk = ProjectivePoint::GENERATOR * sk.as_ref();
+    let d = *sk.as_ref() * parity_factor(&pk);
+    let mut t: [u8; 32] = d.to_repr().into();
+    for (t, a) in t.iter_mut().zip(tagged_hash("BIP0340/aux", &[aux])) {
+        *t ^= a;
+    }
+    let k = hash_to_scalar("BIP0340/nonce", &[&t, &x_only(&pk), msg]);
+    // the probability of k = 0 is negligible
+    let r = ProjectivePoint::GENERATOR * k;
+    let k = k * parity_factor(&r);
+    let e = challenge(&r, &pk, msg);
+
+    let mut sig = [0u8; 64];
+    sig[..32].copy_from_slice(&x_only(&r));
+    sig[32..].copy_from_slice(&(k + e * d).to_repr());
+
+    sig
+}
+
+pub fn sign(sk: &NonZeroScalar, msg: &[u8]) -> Signature {
+    let mut aux = [0u8; 32];
+    OsRng.fill_bytes(&mut aux);
+
+    sign_with_aux(sk, msg, &aux)
+}
+
+pub fn verify(pk: &XOnly, msg: &[u8], sig: &Signature) -> bool {
+    let Some(pk) = lift_x(pk) else {
+        return false;
+    };
+    let r: XOnly = sig[..32].try_into().expect("a signature has 64 bytes");
+    let s: Option<Scalar> = Scalar::from_repr(FieldBytes::clone_from_slice(&sig[32..])).into();
+    let Some(s) = s else {
+        return false;
+    };
+    let Some(r_point) = lift_x(&r) else {
+        return false;
+    };
+    let e = challenge(&r_point, &pk, msg);
+    let r_computed = ProjectivePoint::GENERATOR * s - pk * e;
+
+    r_computed != ProjectivePoint::IDENTITY && has_even_y(&r_computed) && x_only(&r_computed) == r
+}
+
+// The key of a 2-of-2 between the parties 0 and 1: Q = a_0 P_0 + a_1 P_1, optionally tweaked as
+// Q' = lift_x(Q) + t G. Signatures under it verify with the x-only key output_key().
+#[derive(Clone, Copy, Debug)]
+pub struct AggregateKey {
+    pks: [ProjectivePoint; 2],
+    coefficients: [Scalar; 2],
+    // lift_x(Q) = g1 Q
+    g1: Scalar,
+    internal_key: ProjectivePoint,
+    tweak: Scalar,
+    // lift_x(Q') = g2 Q'
+    g2: Scalar,
+    output_key: ProjectivePoint,
+}
+
+impl AggregateKey {
+    pub fn new(pk_0: &ProjectivePoint, pk_1: &ProjectivePoint) -> Self {
+        let bytes_0 = pk_0.to_affine().to_encoded_point(true);
+        let bytes_1 = pk_1.to_affine().to_encoded_point(true);
+        let list = tagged_hash("KeyAgg list", &[bytes_0.as_bytes(), bytes_1.as_bytes()]);
+        let coefficients = [
+            hash_to_scalar("KeyAgg coefficient", &[&list, bytes_0.as_bytes()]),
+            hash_to_scalar("KeyAgg coefficient", &[&list, bytes_1.as_bytes()]),
+        ];
+        let q = *pk_0 * coefficients[0] + *pk_1 * coefficients[1];
+        let g1 = parity_factor(&q);
+
+        Self {
+            pks: [*pk_0, *pk_1],
+            coefficients,
+            g1,
+            internal_key: q * g1,
+            tweak: Scalar::ZERO,
+            g2: Scalar::ONE,
+            output_key: q * g1,
+        }
+    }
+
+    // The key with x-only internal key internal_key() tweaked by t
+    pub fn with_tweak(self, tweak: Scalar) -> Self {
+        let q = self.internal_key + ProjectivePoint::GENERATOR * tweak;
+        let g2 = parity_factor(&q);
+
+        Self {
+            tweak,
+            g2,
+            output_key: q * g2,
+            ..self
+        }
+    }
+
+    pub fn internal_key(&self) -> XOnly {
+        x_only(&self.internal_key)
+    }
+
+    pub fn output_key(&self) -> XOnly {
+        x_only(&self.output_key)
+    }
+
+    // The factor that multiplies the secret key of the party i in its partial signature
+    fn factor(&self, i: usize) -> Scalar {
+        self.g2 * self.g1 * self.coefficients[i]
+    }
+}
+
+// A fresh nonce of a party for one signing session
+pub fn nonce() -> (NonZeroScalar, ProjectivePoint) {
+    let k = NonZeroScalar::random(&mut OsRng);
+
+    (k, ProjectivePoint::GENERATOR * k.as_ref())
+}
+
+// A session where both parties pre-sign msg under an AggregateKey w.r.t. the statement x. With
+// the nonces R_0 and R_1, the nonce of the signature is R = R_0 + R_1 + x, negated by h = -1 if
+// it has odd y. Each party i computes the partial signature s_i = h k_i + e f_i sk_i, where
+// f_i = g2 g1 a_i, and the pre-signature is s' = s_0 + s_1 + e g2 t. The signature is
+// (R, s' + h w), from which anyone that knows s' extracts w.
+pub struct AdaptorSession {
+    key: AggregateKey,
+    nonces: [ProjectivePoint; 2],
+    r: ProjectivePoint,
+    h: Scalar,
+    e: Scalar,
+}
+
+impl AdaptorSession {
+    pub fn new(
+        key: &AggregateKey,
+        nonces: [ProjectivePoint; 2],
+        x: &ProjectivePoint,
+        msg: &[u8],
+    ) -> Self {
+        let r = nonces[0] + nonces[1] + x;
+        let e = challenge(&r, &key.output_key, msg);
+
+        Self {
+            key: *key,
+            nonces,
+            r,
+            h: parity_factor(&r),
+            e,
+        }
+    }
+
+    pub fn partial_sign(&self, i: usize, sk: &NonZeroScalar, k: &NonZeroScalar) -> Scalar {
+        self.h * k.as_ref() + self.e * self.key.factor(i) * sk.as_ref()
+    }
+
+    pub fn partial_verify(&self, i: usize, s: &Scalar) -> bool {
+        ProjectivePoint::GENERATOR * s
+            == self.nonces[i] * self.h + self.key.pks[i] * (self.e * self.key.factor(i))
+    }
+
+    pub fn pre_signature(&self, partial_signatures: &[Scalar; 2]) -> Scalar {
+        partial_signatures[0] + partial_signatures[1] + self.e * self.key.g2 * self.key.tweak
+    }
+
+    pub fn adapt(&self, pre_signature: &Scalar, w: &NonZeroScalar) -> Signature {
+        let s = *pre_signature + self.h * w.as_ref();
+        let mut sig = [0u8; 64];
+        sig[..32].copy_from_slice(&x_only(&self.r));
+        sig[32..].copy_from_slice(&s.to_repr());
+
+        sig
+    }
+
+    pub fn extract(&self, pre_signature: &Scalar, sig: &Signature) -> Option<NonZeroScalar> {
+        if sig[..32] != x_only(&self.r) {
+            return None;
+        }
+        let s: Option<Scalar> = Scalar::from_repr(FieldBytes::clone_from_slice(&sig[32..])).into();
+
+        NonZeroScalar::new((s? - pre_signature) * self.h).into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hard_relation::HardRelation;
+
+    fn from_hex<const N: usize>(hex: &str) -> [u8; N] {
+        let mut bytes = [0u8; N];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).unwrap();
+        }
+
+        bytes
+    }
+
+    #[test]
+    fn bip340_test_vectors() {
+        // test vectors 0 and 1 of BIP-340
+        let vectors = [
+            (
+                "0000000000000000000000000000000000000000000000000000000000000003",
+                "F9308A019258C31049344F85F89D5229B531C845836F99B08601F113BCE036F9",
+                "0000000000000000000000000000000000000000000000000000000000000000",
+                "0000000000000000000000000000000000000000000000000000000000000000",
+                "E907831F80848D1069A5371B402410364BDF1C5F8307B0084C55F1CE2DCA8215\
+                 25F66A4A85EA8B71E482A74F382D2CE5EBEEE8FDB2172F477DF4900D310536C0",
+            ),
+            (
+                "B7E151628AED2A6ABF7158809CF4F3C762E7160F38B4DA56A784D9045190CFEF",
+                "DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659",
+                "0000000000000000000000000000000000000000000000000000000000000001",
+                "243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89",
+                "6896BD60EEAE296DB48A229FF71DFE071BDE413E6D43F917DC8DCF8C78DE3341\
+                 8906D11AC976ABCCB20B091292BFF4EA897EFCB639EA871CFA95F6DE339E4B0A",
+            ),
+        ];
+        for (sk, pk, aux, msg, sig) in vectors {
+            let sk = NonZeroScalar::from_repr(from_hex::<32>(sk).into()).unwrap();
+            let pk = from_hex::<32>(pk);
+            let msg = from_hex::<32>(msg);
+            let sig = from_hex::<64>(&sig.replace(' ', ""));
+            assert_eq!(public_key(&sk), pk);
+            assert_eq!(sign_with_aux(&sk, &msg, &from_hex(aux)), sig);
+            assert!(verify(&pk, &msg, &sig));
+        }
+    }
+
+    #[test]
+    fn bip340_signature() {
+        let sk = NonZeroScalar::random(&mut OsRng);
+        let pk = public_key(&sk);
+        let sig = sign(&sk, b"message");
+        assert!(verify(&pk, b"message", &sig));
+        assert!(!verify(&pk, b"other message", &sig));
+        let mut tampered = sig;
+        tampered[63] ^= 1;
+        assert!(!verify(&pk, b"message", &tampered));
+    }
+
+    #[test]
+    fn two_of_two_adaptor_signature() {
+        let (sk_0, pk_0) = NonZeroScalar::gen(&ProjectivePoint::GENERATOR);
+        let (sk_1, pk_1) = NonZeroScalar::gen(&ProjectivePoint::GENERATOR);
+        let (w, x) = NonZeroScalar::gen(&ProjectivePoint::GENERATOR);
+        let tweak = hash_to_scalar("TapTweak", &[b"tweak"]);
+        let msg = b"sighash";
+
+        for key in [
+            AggregateKey::new(&pk_0, &pk_1),
+            AggregateKey::new(&pk_0, &pk_1).with_tweak(tweak),
+        ] {
+            let (k_0, r_0) = nonce();
+            let (k_1, r_1) = nonce();
+            let session = AdaptorSession::new(&key, [r_0, r_1], &x, msg);
+            let s_0 = session.partial_sign(0, &sk_0, &k_0);
+            let s_1 = session.partial_sign(1, &sk_1, &k_1);
+            assert!(session.partial_verify(0, &s_0));
+            assert!(session.partial_verify(1, &s_1));
+            assert!(!session.partial_verify(1, &s_0));
+
+            let pre_signature = session.pre_signature(&[s_0, s_1]);
+            let sig = session.adapt(&pre_signature, &w);
+            assert!(verify(&key.output_key(), msg, &sig));
+            let extracted_w = session.extract(&pre_signature, &sig).unwrap();
+            assert!(extracted_w.as_ref() == w.as_ref());
+
+            // without the witness, the pre-signature is not a signature
+            let mut pre = sig;
+            pre[32..].copy_from_slice(&pre_signature.to_repr());
+            assert!(!verify(&key.output_key(), msg, &pre));
+        }
+    }
+}
@@ -1,85 +1,115 @@
 use crate::hard_relation::HardRelation;
 use crate::signature_scheme::SignatureScheme;
-use crate::utils::{point_to_byte_vector, proj, scalar_to_byte_vector};
-use digest::Digest;
-use elliptic_curve::ops::Reduce;
-use elliptic_curve::scalar::NonZeroScalar;
-use elliptic_curve::CurveArithmetic;
-use elliptic_curve::Group;
+use bls12_381::hash_to_curve::{ExpandMsgXmd, HashToCurve};
+use bls12_381::{pairing, G1Affine, G1Projective, G2Affine, G2Projective, Scalar};
+use elliptic_curve::ff::Field;
 use rand_core::OsRng;
-use std::marker::PhantomData;
+use sha2_09::Sha256;
 
-// The type representing SchnorrSignatures over an elliptic curve C
+// domain separation tag of the messages of the signatures, the one of the basic scheme of the
+// IETF draft on BLS signatures with the signatures in G1
+pub const SIGNATURE_DST: &[u8] = b"BLS_SIG_BLS12381G1_XMD:SHA-256_SSWU_RO_NUL_";
+
+// BLS signatures over BLS12-381, with the signatures in G1 and the public keys in G2:
+// sign(sk, m) = H(m)^sk and the signature sig is valid if e(sig, g2) == e(H(m), pk).
 #[derive(Debug)]
-pub struct SchnorrSignature<C>
-where
-    C: CurveArithmetic,
-{
-    pub proof: C::Scalar,
-    pub sig: C::Scalar,
+pub struct BLSSignature {
+    pub sig: G1Projective,
 }
 
-impl<C> SchnorrSignature<C>
-where
-    C: CurveArithmetic,
-{
+impl BLSSignature {
     pub fn to_byte_vector(&self) -> Vec<u8> {
-        let mut v: Vec<u8> = Vec::new();
-        v.append(&mut scalar_to_byte_vector::<C>(&self.proof));
-        v.append(&mut scalar_to_byte_vector::<C>(&self.sig));
-        v
+        G1Affine::from(self.sig).to_compressed().to_vec()
     }
 }
 
-pub struct SchnorrSignatureScheme<C, H>
-where
-    C: CurveArithmetic,
-    H: Digest<OutputSize = C::FieldBytesSize>,
-{
-    _curve_marker: PhantomData<C>,
-    _hash_marker: PhantomData<H>,
+pub struct BLSSignatureScheme {}
+
+impl HardRelation<G2Projective, Scalar> for Scalar {
+    type PP = G2Projective;
+
+    fn R(pp: &Self::PP, w: &Scalar, x: &G2Projective) -> bool {
+        *pp * w == *x
+    }
+    fn statement(pp: &Self::PP, w: &Scalar) -> G2Projective {
+        *pp * w
+    }
+    fn gen(pp: &Self::PP) -> (Scalar, G2Projective) {
+        let w = Scalar::random(&mut OsRng);
+        let x = Self::statement(pp, &w);
+
+        (w, x)
+    }
 }
 
-impl<C, H> SignatureScheme for SchnorrSignatureScheme<C, H>
-where
-    C: CurveArithmetic,
-    H: Digest<OutputSize = C::FieldBytesSize>,
-{
-    type PK = C::ProjectivePoint;
-    type SK = NonZeroScalar<C>;
-    type Signature = SchnorrSignature<C>;
+impl SignatureScheme for BLSSignatureScheme {
+    type PK = G2Projective;
+    type SK = Scalar;
+    type Signature = BLSSignature;
 
     fn gen() -> (Self::SK, Self::PK) {
-        Self::SK::gen()
+        Self::SK::gen(&G2Projective::generator())
     }
 
     fn sign(sk: &Self::SK, msg: &str) -> Self::Signature {
-        let g = C::ProjectivePoint::generator();
+        BLSSignature {
+            sig: hash_to_g1(SIGNATURE_DST, msg.as_bytes()) * sk,
+        }
+    }
 
-        let nz_k = NonZeroScalar::<C>::random(&mut OsRng);
-        let k = nz_k.as_ref();
-        let r = g * k;
+    fn verify(pk: &Self::PK, msg: &str, sig: &Self::Signature) -> bool {
+        let h = hash_to_g1(SIGNATURE_DST, msg.as_bytes());
 
-        let proj_r = proj::<C>(&r);
-        let hasher = H::new();
-        let e = <C::Scalar as Reduce<C::Uint>>::reduce_bytes(
-            &hasher.chain_update(proj_r).chain_update(msg).finalize(),
-        );
-        let s = *k - e * sk.as_ref();
+        pairing(&sig.sig.into(), &G2Affine::generator()) == pairing(&h.into(), &(*pk).into())
+    }
+}
+
+// Hash into G1 with the hash_to_curve of RFC 9380 (suite BLS12381G1_XMD:SHA-256_SSWU_RO_), under
+// the domain separation tag dst
+pub fn hash_to_g1(dst: &[u8], msg: &[u8]) -> G1Projective {
+    <G1Projective as HashToCurve<ExpandMsgXmd<Sha256>>>::hash_to_curve(msg, dst)
+}
+
+// same as hash_to_g1, for G2 (suite BLS12381G2_XMD:SHA-256_SSWU_RO_)
+pub fn hash_to_g2(dst: &[u8], msg: &[u8]) -> G2Projective {
+    <G2Projective as HashToCurve<ExpandMsgXmd<Sha256>>>::hash_to_curve(msg, dst)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        SchnorrSignature::<C> { proof: e, sig: s }
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
     }
 
-    fn verify(pk: &Self::PK, msg: &str, sig: &Self::Signature) -> bool {
-        let g = C::ProjectivePoint::generator();
-        let r = g * sig.sig + *pk * sig.proof;
+    // test vectors of the suites BLS12381G1_XMD:SHA-256_SSWU_RO_ and
+    // BLS12381G2_XMD:SHA-256_SSWU_RO_ with the empty message, RFC 9380 appendices J.9.1 and J.10.1
+    #[test]
+    fn hash_to_curve_vectors() {
+        let p = hash_to_g1(b"QUUX-V01-CS02-with-BLS12381G1_XMD:SHA-256_SSWU_RO_", b"");
+        assert_eq!(
+            hex(&G1Affine::from(p).to_uncompressed()),
+            "052926add2207b76ca4fa57a8734416c8dc95e24501772c814278700eed6d1e4e8cf62d9c09db0fac349612b759e79a1\
+             08ba738453bfed09cb546dbb0783dbb3a5f1f566ed67bb6be0e8c67e2e81a4cc68ee29813bb7994998f3eae0c9c6a265"
+        );
 
-        let proj_r = proj::<C>(&r);
-        let hasher = H::new();
-        let e = <C::Scalar as Reduce<C::Uint>>::reduce_bytes(
-            &hasher.chain_update(proj_r).chain_update(msg).finalize(),
+        // the encoding of an element c0 + c1 * u of Fp2 is c1 || c0
+        let p = hash_to_g2(b"QUUX-V01-CS02-with-BLS12381G2_XMD:SHA-256_SSWU_RO_", b"");
+        assert_eq!(
+            hex(&G2Affine::from(p).to_uncompressed()),
+            "05cb8437535e20ecffaef7752baddf98034139c38452458baeefab379ba13dff5bf5dd71b72418717047f5b0f37da03d\
+             0141ebfbdca40eb85b87142e130ab689c673cf60f1a3e98d69335266f30d9b8d4ac44c1038e9dcdd5393faf5c41fb78a\
+             12424ac32561493f3fe3c260708a12b7c620e7be00099a974e259ddc7d1f6395c3c811cdd19f1e8dbf3e9ecfdcbab8d6\
+             0503921d7f6a12805e72940b963c0cf3471c7b2a524950ca195d11062ee75ec076daf2d4bc358c4b190c0c98064fdd92"
         );
+    }
 
-        e == sig.proof
+    #[test]
+    fn bls_signature() {
+        let (sk, pk) = BLSSignatureScheme::gen();
+        let sig = BLSSignatureScheme::sign(&sk, "message");
+        assert!(BLSSignatureScheme::verify(&pk, "message", &sig));
+        assert!(!BLSSignatureScheme::verify(&pk, "other message", &sig));
     }
 }
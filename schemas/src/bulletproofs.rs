@@ -0,0 +1,639 @@
+use crate::crs::hash_to_curve_generator;
+use crate::hard_relation::HardRelation;
+use crate::nizk::NIZK;
+use crate::pedersen_commitment::PedersenGens;
+use crate::utils::{point_to_byte_vector, scalar_to_byte_vector};
+use digest::Digest;
+use elliptic_curve::ff::Field;
+use elliptic_curve::group::cofactor::CofactorGroup;
+use elliptic_curve::hash2curve::GroupDigest;
+use elliptic_curve::ops::Reduce;
+use elliptic_curve::point::DecompressPoint;
+use elliptic_curve::{CurveArithmetic, Group};
+use rand_core::OsRng;
+use std::marker::PhantomData;
+
+// Bulletproofs range proofs (Bünz et al., "Bulletproofs: Short Proofs for Confidential
+// Transactions and More", sections 4.1 and 4.3).
+// The prover convinces the verifier that each of the m Pedersen commitments V_j = g^v_j * h^gamma_j
+// opens to a value v_j in [0, 2^n). A single range proof is the case m = 1. The proof size is
+// logarithmic in n * m, which must be a power of two.
+pub struct RangeProof<C, H>
+where
+    C: CurveArithmetic,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+{
+    _curve_marker: PhantomData<C>,
+    _hash_marker: PhantomData<H>,
+}
+
+// Default capacity of the generators returned by crs_gen: 64 bit ranges for up to 8 aggregated
+// commitments.
+pub const DEFAULT_BITS: usize = 64;
+pub const DEFAULT_PARTIES: usize = 8;
+
+// The common reference string. It contains the Pedersen generators used for the commitments
+// and the vectors of generators used for the bit commitments and the inner product argument.
+// All of them are derived with hash_to_curve, so no trusted setup is needed.
+pub struct BulletproofGens<C>
+where
+    C: CurveArithmetic,
+{
+    pub pedersen: PedersenGens<C>,
+    g_vec: Vec<C::ProjectivePoint>,
+    h_vec: Vec<C::ProjectivePoint>,
+}
+
+impl<C> BulletproofGens<C>
+where
+    C: CurveArithmetic,
+{
+    // capacity is the maximum value of n * m that can be proven with these generators
+    pub fn new(capacity: usize) -> Self
+    where
+        C: GroupDigest,
+        C::ProjectivePoint: CofactorGroup,
+    {
+        let pedersen = PedersenGens::<C>::nums();
+        let g_vec = (0..capacity)
+            .map(|i| hash_to_curve_generator::<C>(b"bulletproofs_G", i as u64))
+            .collect();
+        let h_vec = (0..capacity)
+            .map(|i| hash_to_curve_generator::<C>(b"bulletproofs_H", i as u64))
+            .collect();
+
+        Self {
+            pedersen,
+            g_vec,
+            h_vec,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.g_vec.len()
+    }
+}
+
+pub struct Witness<C>
+where
+    C: CurveArithmetic,
+{
+    values: Vec<u64>,
+    blindings: Vec<C::Scalar>,
+}
+
+impl<C> Witness<C>
+where
+    C: CurveArithmetic,
+{
+    pub fn new(values: Vec<u64>, blindings: Vec<C::Scalar>) -> Self {
+        Self { values, blindings }
+    }
+}
+
+pub struct Statement<C>
+where
+    C: CurveArithmetic,
+{
+    commitments: Vec<C::ProjectivePoint>,
+    n: usize,
+}
+
+impl<C> Statement<C>
+where
+    C: CurveArithmetic,
+{
+    // n is the bit size of the range [0, 2^n)
+    pub fn new(commitments: Vec<C::ProjectivePoint>, n: usize) -> Self {
+        Self { commitments, n }
+    }
+
+    pub fn to_byte_vector(&self) -> Vec<u8> {
+        let mut v: Vec<u8> = Vec::new();
+        for commitment in self.commitments.iter() {
+            v.append(&mut point_to_byte_vector::<C>(commitment));
+        }
+        v.push(self.n as u8);
+
+        v
+    }
+}
+
+// Relation_range = {(V_1..V_m, n; v_1..v_m, gamma_1..gamma_m) | V_j = g^v_j * h^gamma_j &&
+//                                                               0 <= v_j < 2^n}
+impl<C> HardRelation<Statement<C>, Witness<C>> for Witness<C>
+where
+    C: CurveArithmetic,
+{
+    type PP = PedersenGens<C>;
+
+    fn R(pp: &Self::PP, w: &Witness<C>, x: &Statement<C>) -> bool {
+        w.values.len() == x.commitments.len()
+            && w.blindings.len() == x.commitments.len()
+            && w.values
+                .iter()
+                .zip(w.blindings.iter())
+                .zip(x.commitments.iter())
+                .all(|((v, gamma), commitment)| {
+                    (x.n >= 64 || (*v >> x.n) == 0)
+                        && pp.commit(&C::Scalar::from(*v), gamma) == *commitment
+                })
+    }
+
+    fn statement(_pp: &Self::PP, _w: &Witness<C>) -> Statement<C> {
+        unimplemented!("This function should never be called!");
+    }
+
+    fn gen(_pp: &Self::PP) -> (Witness<C>, Statement<C>) {
+        unimplemented!("This function should never be called!");
+    }
+}
+
+// Inner product argument (protocol 2 of the paper): proves knowledge of vectors a, b such that
+// P = <a, G> + <b, H> + Q * <a, b>, sending 2 * log2(len) points and 2 scalars.
+pub struct InnerProductProof<C>
+where
+    C: CurveArithmetic,
+{
+    l_vec: Vec<C::ProjectivePoint>,
+    r_vec: Vec<C::ProjectivePoint>,
+    a: C::Scalar,
+    b: C::Scalar,
+}
+
+impl<C> InnerProductProof<C>
+where
+    C: CurveArithmetic,
+{
+    fn create<H>(
+        seed: &C::Scalar,
+        q: &C::ProjectivePoint,
+        g_vec: &[C::ProjectivePoint],
+        h_vec: &[C::ProjectivePoint],
+        a_vec: &[C::Scalar],
+        b_vec: &[C::Scalar],
+    ) -> Self
+    where
+        H: Digest<OutputSize = C::FieldBytesSize>,
+    {
+        let mut g_vec = g_vec.to_vec();
+        let mut h_vec = h_vec.to_vec();
+        let mut a_vec = a_vec.to_vec();
+        let mut b_vec = b_vec.to_vec();
+        let mut l_vec = Vec::new();
+        let mut r_vec = Vec::new();
+        let mut u = *seed;
+
+        while a_vec.len() > 1 {
+            let half = a_vec.len() / 2;
+            let (a_lo, a_hi) = a_vec.split_at(half);
+            let (b_lo, b_hi) = b_vec.split_at(half);
+            let (g_lo, g_hi) = g_vec.split_at(half);
+            let (h_lo, h_hi) = h_vec.split_at(half);
+
+            let c_l = inner_product::<C>(a_lo, b_hi);
+            let c_r = inner_product::<C>(a_hi, b_lo);
+            let l = multi_mul::<C>(g_hi, a_lo) + multi_mul::<C>(h_lo, b_hi) + (*q) * c_l;
+            let r = multi_mul::<C>(g_lo, a_hi) + multi_mul::<C>(h_hi, b_lo) + (*q) * c_r;
+
+            u = compute_challenge::<C, H>(b"bulletproofs_ipp", &[u], &[l, r]);
+            let u_inv = u.invert().unwrap();
+
+            a_vec = fold_scalars::<C>(a_lo, a_hi, &u, &u_inv);
+            b_vec = fold_scalars::<C>(b_lo, b_hi, &u_inv, &u);
+            g_vec = fold_points::<C>(g_lo, g_hi, &u_inv, &u);
+            h_vec = fold_points::<C>(h_lo, h_hi, &u, &u_inv);
+
+            l_vec.push(l);
+            r_vec.push(r);
+        }
+
+        Self {
+            l_vec,
+            r_vec,
+            a: a_vec[0],
+            b: b_vec[0],
+        }
+    }
+
+    fn verify<H>(
+        &self,
+        seed: &C::Scalar,
+        q: &C::ProjectivePoint,
+        g_vec: &[C::ProjectivePoint],
+        h_vec: &[C::ProjectivePoint],
+        p: &C::ProjectivePoint,
+    ) -> bool
+    where
+        H: Digest<OutputSize = C::FieldBytesSize>,
+    {
+        if self.l_vec.len() != self.r_vec.len()
+            || Some(g_vec.len()) != 1usize.checked_shl(self.l_vec.len() as u32)
+        {
+            return false;
+        }
+
+        let mut g_vec = g_vec.to_vec();
+        let mut h_vec = h_vec.to_vec();
+        let mut p = *p;
+        let mut u = *seed;
+
+        for (l, r) in self.l_vec.iter().zip(self.r_vec.iter()) {
+            let half = g_vec.len() / 2;
+            let (g_lo, g_hi) = g_vec.split_at(half);
+            let (h_lo, h_hi) = h_vec.split_at(half);
+
+            u = compute_challenge::<C, H>(b"bulletproofs_ipp", &[u], &[*l, *r]);
+            let u_inv = u.invert().unwrap();
+            let u_sq = u.square();
+            let u_inv_sq = u_inv.square();
+
+            p = (*l) * u_sq + p + (*r) * u_inv_sq;
+            g_vec = fold_points::<C>(g_lo, g_hi, &u_inv, &u);
+            h_vec = fold_points::<C>(h_lo, h_hi, &u, &u_inv);
+        }
+
+        p == g_vec[0] * self.a + h_vec[0] * self.b + (*q) * (self.a * self.b)
+    }
+
+    pub fn to_byte_vector(&self) -> Vec<u8> {
+        let mut v: Vec<u8> = Vec::new();
+        for (l, r) in self.l_vec.iter().zip(self.r_vec.iter()) {
+            v.append(&mut point_to_byte_vector::<C>(l));
+            v.append(&mut point_to_byte_vector::<C>(r));
+        }
+        v.append(&mut scalar_to_byte_vector::<C>(&self.a));
+        v.append(&mut scalar_to_byte_vector::<C>(&self.b));
+
+        v
+    }
+}
+
+pub struct Proof<C>
+where
+    C: CurveArithmetic,
+{
+    a: (
+        C::ProjectivePoint,
+        C::ProjectivePoint,
+        C::ProjectivePoint,
+        C::ProjectivePoint,
+    ),
+    r: (C::Scalar, C::Scalar, C::Scalar),
+    ipp: InnerProductProof<C>,
+}
+
+impl<C> Proof<C>
+where
+    C: CurveArithmetic,
+{
+    pub fn to_byte_vector(&self) -> Vec<u8> {
+        let (a_a, a_s, a_t1, a_t2) = self.a;
+        let (tau_x, mu, t_hat) = self.r;
+        let mut v: Vec<u8> = Vec::new();
+        v.append(&mut point_to_byte_vector::<C>(&a_a));
+        v.append(&mut point_to_byte_vector::<C>(&a_s));
+        v.append(&mut point_to_byte_vector::<C>(&a_t1));
+        v.append(&mut point_to_byte_vector::<C>(&a_t2));
+        v.append(&mut scalar_to_byte_vector::<C>(&tau_x));
+        v.append(&mut scalar_to_byte_vector::<C>(&mu));
+        v.append(&mut scalar_to_byte_vector::<C>(&t_hat));
+        v.append(&mut self.ipp.to_byte_vector());
+
+        v
+    }
+}
+
+// Fiat-Shamir challenges. Each challenge is chained with the previous ones (passed as scalars)
+// so the whole transcript is bound to the last challenge.
+fn compute_challenge<C, H>(
+    label: &[u8],
+    scalars: &[C::Scalar],
+    points: &[C::ProjectivePoint],
+) -> C::Scalar
+where
+    C: CurveArithmetic,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+{
+    let mut hasher = H::new();
+    hasher.update(label);
+    for scalar in scalars.iter() {
+        hasher.update(scalar_to_byte_vector::<C>(scalar));
+    }
+    for point in points.iter() {
+        hasher.update(point_to_byte_vector::<C>(point));
+    }
+
+    <C::Scalar as Reduce<C::Uint>>::reduce_bytes(&hasher.finalize())
+}
+
+fn inner_product<C: CurveArithmetic>(a: &[C::Scalar], b: &[C::Scalar]) -> C::Scalar {
+    a.iter()
+        .zip(b.iter())
+        .fold(C::Scalar::ZERO, |acc, (a_i, b_i)| acc + (*a_i) * b_i)
+}
+
+fn multi_mul<C: CurveArithmetic>(
+    points: &[C::ProjectivePoint],
+    scalars: &[C::Scalar],
+) -> C::ProjectivePoint {
+    points
+        .iter()
+        .zip(scalars.iter())
+        .fold(C::ProjectivePoint::identity(), |acc, (p, s)| acc + (*p) * s)
+}
+
+fn fold_scalars<C: CurveArithmetic>(
+    lo: &[C::Scalar],
+    hi: &[C::Scalar],
+    x_lo: &C::Scalar,
+    x_hi: &C::Scalar,
+) -> Vec<C::Scalar> {
+    lo.iter()
+        .zip(hi.iter())
+        .map(|(l, h)| (*l) * x_lo + (*h) * x_hi)
+        .collect()
+}
+
+fn fold_points<C: CurveArithmetic>(
+    lo: &[C::ProjectivePoint],
+    hi: &[C::ProjectivePoint],
+    x_lo: &C::Scalar,
+    x_hi: &C::Scalar,
+) -> Vec<C::ProjectivePoint> {
+    lo.iter()
+        .zip(hi.iter())
+        .map(|(l, h)| (*l) * x_lo + (*h) * x_hi)
+        .collect()
+}
+
+// [1, x, x^2, ..., x^(len - 1)]
+fn powers<C: CurveArithmetic>(x: &C::Scalar, len: usize) -> Vec<C::Scalar> {
+    let mut v = Vec::with_capacity(len);
+    let mut acc = C::Scalar::ONE;
+    for _ in 0..len {
+        v.push(acc);
+        acc *= x;
+    }
+
+    v
+}
+
+impl<C, H> RangeProof<C, H>
+where
+    C: CurveArithmetic,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+{
+    fn initial_seed(crs: &BulletproofGens<C>, x: &Statement<C>) -> C::Scalar {
+        let mut points = vec![crs.pedersen.g, crs.pedersen.h];
+        points.extend_from_slice(&x.commitments);
+        let n = C::Scalar::from(x.n as u64);
+        let m = C::Scalar::from(x.commitments.len() as u64);
+
+        compute_challenge::<C, H>(b"bulletproofs_range_proof", &[n, m], &points)
+    }
+
+    // delta(y, z) = (z - z^2) * <1, y^nm> - sum_j z^(3 + j) * <1, 2^n>
+    fn delta(n: usize, m: usize, y: &C::Scalar, z: &C::Scalar) -> C::Scalar {
+        let sum_y = powers::<C>(y, n * m)
+            .iter()
+            .fold(C::Scalar::ZERO, |acc, y_i| acc + y_i);
+        let sum_2 = powers::<C>(&C::Scalar::from(2u64), n)
+            .iter()
+            .fold(C::Scalar::ZERO, |acc, p| acc + p);
+        let z_sq = z.square();
+        let sum_z = powers::<C>(z, m)
+            .iter()
+            .fold(C::Scalar::ZERO, |acc, z_j| acc + (*z_j) * z_sq * z);
+
+        (*z - z_sq) * sum_y - sum_z * sum_2
+    }
+
+    // The generators H'_i = H_i * y^(-i) used by the inner product argument.
+    fn h_prime(h_vec: &[C::ProjectivePoint], y: &C::Scalar) -> Vec<C::ProjectivePoint> {
+        let y_inv = y.invert().unwrap();
+        h_vec
+            .iter()
+            .zip(powers::<C>(&y_inv, h_vec.len()).iter())
+            .map(|(h_i, y_inv_i)| (*h_i) * y_inv_i)
+            .collect()
+    }
+
+    fn valid_dimensions(crs: &BulletproofGens<C>, x: &Statement<C>) -> bool {
+        let nm = x.n * x.commitments.len();
+        x.n > 0 && x.n <= 64 && nm.is_power_of_two() && nm <= crs.capacity()
+    }
+}
+
+impl<C, H> NIZK for RangeProof<C, H>
+where
+    C: CurveArithmetic,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+    C::AffinePoint: DecompressPoint<C>,
+    C: GroupDigest,
+    C::ProjectivePoint: CofactorGroup,
+{
+    type CRS = BulletproofGens<C>;
+    type Statement = Statement<C>;
+    type Witness = Witness<C>;
+    type Proof = Proof<C>;
+
+    fn crs_gen() -> Self::CRS {
+        BulletproofGens::<C>::new(DEFAULT_BITS * DEFAULT_PARTIES)
+    }
+
+    fn prove(crs: &Self::CRS, x: &Self::Statement, w: &Self::Witness) -> Self::Proof {
+        assert!(
+            Self::valid_dimensions(crs, x),
+            "n * m must be a power of two not larger than the capacity of the crs"
+        );
+
+        let n = x.n;
+        let m = x.commitments.len();
+        let nm = n * m;
+        let g = crs.pedersen.g;
+        let h = crs.pedersen.h;
+        let g_vec = &crs.g_vec[..nm];
+        let h_vec = &crs.h_vec[..nm];
+
+        // bit decomposition of the values: a_L in {0, 1}^nm and a_R = a_L - 1
+        let a_l: Vec<C::Scalar> = (0..nm)
+            .map(|i| C::Scalar::from((w.values[i / n] >> (i % n)) & 1))
+            .collect();
+        let a_r: Vec<C::Scalar> = a_l.iter().map(|a| *a - C::Scalar::ONE).collect();
+
+        let alpha = C::Scalar::random(&mut OsRng);
+        let a_a = h * alpha + multi_mul::<C>(g_vec, &a_l) + multi_mul::<C>(h_vec, &a_r);
+
+        let s_l: Vec<C::Scalar> = (0..nm).map(|_| C::Scalar::random(&mut OsRng)).collect();
+        let s_r: Vec<C::Scalar> = (0..nm).map(|_| C::Scalar::random(&mut OsRng)).collect();
+        let rho = C::Scalar::random(&mut OsRng);
+        let a_s = h * rho + multi_mul::<C>(g_vec, &s_l) + multi_mul::<C>(h_vec, &s_r);
+
+        let seed = Self::initial_seed(crs, x);
+        let y = compute_challenge::<C, H>(b"bulletproofs_y", &[seed], &[a_a, a_s]);
+        let z = compute_challenge::<C, H>(b"bulletproofs_z", &[y], &[]);
+
+        // l(X) = l0 + l1 * X and r(X) = r0 + r1 * X
+        let y_pows = powers::<C>(&y, nm);
+        let z_pows = powers::<C>(&z, m);
+        let two_pows = powers::<C>(&C::Scalar::from(2u64), n);
+        let z_sq = z.square();
+
+        let l0: Vec<C::Scalar> = a_l.iter().map(|a| *a - z).collect();
+        let l1 = s_l;
+        let r0: Vec<C::Scalar> = (0..nm)
+            .map(|i| y_pows[i] * (a_r[i] + z) + z_sq * z_pows[i / n] * two_pows[i % n])
+            .collect();
+        let r1: Vec<C::Scalar> = (0..nm).map(|i| y_pows[i] * s_r[i]).collect();
+
+        // t(X) = <l(X), r(X)> = t0 + t1 * X + t2 * X^2
+        let t1 = inner_product::<C>(&l0, &r1) + inner_product::<C>(&l1, &r0);
+        let t2 = inner_product::<C>(&l1, &r1);
+
+        let tau1 = C::Scalar::random(&mut OsRng);
+        let tau2 = C::Scalar::random(&mut OsRng);
+        let a_t1 = g * t1 + h * tau1;
+        let a_t2 = g * t2 + h * tau2;
+
+        let c = compute_challenge::<C, H>(b"bulletproofs_x", &[z], &[a_t1, a_t2]);
+
+        let l: Vec<C::Scalar> = (0..nm).map(|i| l0[i] + l1[i] * c).collect();
+        let r: Vec<C::Scalar> = (0..nm).map(|i| r0[i] + r1[i] * c).collect();
+        let t_hat = inner_product::<C>(&l, &r);
+        let tau_x = w
+            .blindings
+            .iter()
+            .zip(z_pows.iter())
+            .fold(tau2 * c.square() + tau1 * c, |acc, (gamma, z_j)| {
+                acc + z_sq * z_j * gamma
+            });
+        let mu = alpha + rho * c;
+
+        let seed_ipp = compute_challenge::<C, H>(b"bulletproofs_w", &[c, tau_x, mu, t_hat], &[]);
+        let q = g * seed_ipp;
+        let h_prime = Self::h_prime(h_vec, &y);
+        let ipp = InnerProductProof::<C>::create::<H>(&seed_ipp, &q, g_vec, &h_prime, &l, &r);
+
+        Proof::<C> {
+            a: (a_a, a_s, a_t1, a_t2),
+            r: (tau_x, mu, t_hat),
+            ipp,
+        }
+    }
+
+    fn verify(crs: &Self::CRS, x: &Self::Statement, p: &Self::Proof) -> bool {
+        if !Self::valid_dimensions(crs, x) {
+            return false;
+        }
+
+        let (a_a, a_s, a_t1, a_t2) = p.a;
+        let (tau_x, mu, t_hat) = p.r;
+        let n = x.n;
+        let m = x.commitments.len();
+        let nm = n * m;
+        let g = crs.pedersen.g;
+        let h = crs.pedersen.h;
+        let g_vec = &crs.g_vec[..nm];
+        let h_vec = &crs.h_vec[..nm];
+
+        let seed = Self::initial_seed(crs, x);
+        let y = compute_challenge::<C, H>(b"bulletproofs_y", &[seed], &[a_a, a_s]);
+        let z = compute_challenge::<C, H>(b"bulletproofs_z", &[y], &[]);
+        let c = compute_challenge::<C, H>(b"bulletproofs_x", &[z], &[a_t1, a_t2]);
+        let seed_ipp = compute_challenge::<C, H>(b"bulletproofs_w", &[c, tau_x, mu, t_hat], &[]);
+
+        let y_pows = powers::<C>(&y, nm);
+        let z_pows = powers::<C>(&z, m);
+        let two_pows = powers::<C>(&C::Scalar::from(2u64), n);
+        let z_sq = z.square();
+
+        // t_hat = t(x) and the commitments V_j open to the constant term of t(X)
+        let v_sum = x
+            .commitments
+            .iter()
+            .zip(z_pows.iter())
+            .fold(C::ProjectivePoint::identity(), |acc, (v_j, z_j)| {
+                acc + (*v_j) * (z_sq * z_j)
+            });
+        let b1 = g * t_hat + h * tau_x
+            == v_sum + g * Self::delta(n, m, &y, &z) + a_t1 * c + a_t2 * c.square();
+
+        // l and r are the evaluations of l(X) and r(X) at x
+        let q = g * seed_ipp;
+        let h_prime = Self::h_prime(h_vec, &y);
+        let exponents_h: Vec<C::Scalar> = (0..nm)
+            .map(|i| z * y_pows[i] + z_sq * z_pows[i / n] * two_pows[i % n])
+            .collect();
+        let sum_g = g_vec
+            .iter()
+            .fold(C::ProjectivePoint::identity(), |acc, g_i| acc + g_i);
+        let point_p =
+            a_a + a_s * c - sum_g * z + multi_mul::<C>(&h_prime, &exponents_h) - h * mu + q * t_hat;
+        let b2 = p.ipp.verify::<H>(&seed_ipp, &q, g_vec, &h_prime, &point_p);
+
+        b1 && b2
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k256::Secp256k1;
+    use sha2::Sha256;
+
+    type C = Secp256k1;
+    type Scalar = <C as CurveArithmetic>::Scalar;
+    type Nizk = RangeProof<C, Sha256>;
+
+    fn commit_all(
+        crs: &BulletproofGens<C>,
+        values: &[u64],
+    ) -> (Witness<C>, Vec<<C as CurveArithmetic>::ProjectivePoint>) {
+        let blindings: Vec<Scalar> = values.iter().map(|_| Scalar::random(&mut OsRng)).collect();
+        let commitments = values
+            .iter()
+            .zip(blindings.iter())
+            .map(|(v, gamma)| crs.pedersen.commit(&Scalar::from(*v), gamma))
+            .collect();
+
+        (Witness::new(values.to_vec(), blindings), commitments)
+    }
+
+    #[test]
+    fn single_range_proof() {
+        let crs = BulletproofGens::<C>::new(64);
+        let (w, commitments) = commit_all(&crs, &[1037578891]);
+        let x = Statement::new(commitments, 32);
+        assert!(Witness::R(&crs.pedersen, &w, &x));
+
+        let proof = Nizk::prove(&crs, &x, &w);
+        assert!(Nizk::verify(&crs, &x, &proof));
+        // 4 points, 3 scalars and an inner product proof of 2 * log2(32) points and 2 scalars
+        assert_eq!(proof.to_byte_vector().len(), 14 * 33 + 5 * 32);
+    }
+
+    #[test]
+    fn aggregated_range_proof() {
+        let crs = BulletproofGens::<C>::new(64);
+        let (w, commitments) = commit_all(&crs, &[0, 255, 17, 128]);
+        let x = Statement::new(commitments, 8);
+
+        let proof = Nizk::prove(&crs, &x, &w);
+        assert!(Nizk::verify(&crs, &x, &proof));
+
+        let other = Statement::new(x.commitments[1..].to_vec(), 8);
+        assert!(!Nizk::verify(&crs, &other, &proof));
+    }
+
+    #[test]
+    fn value_out_of_range() {
+        let crs = BulletproofGens::<C>::new(64);
+        let (w, commitments) = commit_all(&crs, &[256]);
+        let x = Statement::new(commitments, 8);
+        assert!(!Witness::R(&crs.pedersen, &w, &x));
+
+        let proof = Nizk::prove(&crs, &x, &w);
+        assert!(!Nizk::verify(&crs, &x, &proof));
+    }
+}
@@ -0,0 +1,29 @@
+// A commitment scheme with public parameters PP. commit samples the randomness and returns it
+// together with the commitment, the pair (msg, randomness) is the opening of the commitment and
+// verify checks an opening against a commitment.
+pub trait CommitmentScheme {
+    type PP;
+    type Message;
+    type Randomness;
+    type Commitment;
+
+    fn commit(pp: &Self::PP, msg: &Self::Message) -> (Self::Commitment, Self::Randomness);
+    fn commit_with(pp: &Self::PP, msg: &Self::Message, r: &Self::Randomness) -> Self::Commitment;
+    fn verify(
+        pp: &Self::PP,
+        c: &Self::Commitment,
+        msg: &Self::Message,
+        r: &Self::Randomness,
+    ) -> bool;
+}
+
+// Commitment schemes where the product of the commitments of two messages is a commitment of the
+// sum of the messages, opened with the sum of the randomness, and where the power k of the
+// commitment of a message is a commitment of k times the message, opened with k times the
+// randomness.
+pub trait HomomorphicCommitmentScheme: CommitmentScheme {
+    type Scalar;
+
+    fn add(c1: &Self::Commitment, c2: &Self::Commitment) -> Self::Commitment;
+    fn mul(c: &Self::Commitment, k: &Self::Scalar) -> Self::Commitment;
+}
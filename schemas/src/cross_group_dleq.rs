@@ -0,0 +1,995 @@
+use crate::crs::{generators_from_byte_vector, generators_to_byte_vector, hash_to_curve_generator};
+use crate::hard_relation::HardRelation;
+use crate::nizk::NIZK;
+use crate::sigma_protocol::{SigmaProtocol, SpecialSoundness};
+use crate::utils::{point_to_byte_vector, proj, scalar_to_byte_vector, ByteReader};
+use digest::Digest;
+use elliptic_curve::ff::{Field, PrimeField};
+use elliptic_curve::group::cofactor::CofactorGroup;
+use elliptic_curve::hash2curve::GroupDigest;
+use elliptic_curve::point::DecompressPoint;
+use elliptic_curve::scalar::NonZeroScalar;
+use elliptic_curve::{CurveArithmetic, FieldBytes, Group};
+use rand_core::{OsRng, RngCore};
+use std::marker::PhantomData;
+
+// Discrete logarithm equality across two groups C1 and C2 with different orders: the prover shows
+// that P1 = g1^s * h1^t1 and P2 = g2^s * h2^t2 commit to the same integer s, with 0 <= s < 2^n
+// and 2^n smaller than both group orders, so that s is the same number in both groups (reducing a
+// scalar of C1 modulo the order of C2 is not enough, the prover could use any s' = s mod q2).
+//
+// s is decomposed in bits s = sum b_i 2^i and every bit is committed in both groups,
+// C1_i = g1^b_i * h1^r1_i and C2_i = g2^b_i * h2^r2_i, with blindings such that
+// sum 2^i r1_i = t1 and sum 2^i r2_i = t2, so the verifier can check that the bit commitments add
+// up to P1 and P2. For every bit, a ring signature with two members proves that (C1_i, C2_i) are
+// both commitments to 0 or both commitments to 1, i.e. the prover knows (r1_i, r2_i) such that
+//     (C1_i = h1^r1_i && C2_i = h2^r2_i) || (C1_i / g1 = h1^r1_i && C2_i / g2 = h2^r2_i).
+// The challenges are integers of CHALLENGE_BYTES bytes, smaller than both group orders, and the
+// challenge of the verifier is split between both members of the ring with a XOR (instead of a
+// sum as in PoRSchnorrSignature) so that every challenge has the same value in both groups.
+pub struct CrossGroupDLEQ<C1, C2, H1, H2>
+where
+    C1: CurveArithmetic,
+    C2: CurveArithmetic,
+    H1: Digest<OutputSize = C1::FieldBytesSize>,
+    H2: Digest<OutputSize = C2::FieldBytesSize>,
+{
+    _curve_marker_1: PhantomData<C1>,
+    _curve_marker_2: PhantomData<C2>,
+    _hash_marker_1: PhantomData<H1>,
+    _hash_marker_2: PhantomData<H2>,
+}
+
+pub const CHALLENGE_BYTES: usize = 16;
+
+// number of bits n of the secrets: 2^n <= 2^(NUM_BITS - 1) < q for both group orders q
+pub fn bit_length<C1, C2>() -> usize
+where
+    C1: CurveArithmetic,
+    C2: CurveArithmetic,
+{
+    let bits = std::cmp::min(C1::Scalar::NUM_BITS, C2::Scalar::NUM_BITS);
+
+    (bits - 1) as usize
+}
+
+// little-endian bits of s, or None if s >= 2^n
+pub fn to_bits<C: CurveArithmetic>(s: &C::Scalar, n: usize) -> Option<Vec<bool>> {
+    let mut s = *s;
+    let mut bits: Vec<bool> = Vec::with_capacity(n);
+    for _ in 0..n {
+        let bit = bool::from(s.is_odd());
+        if bit {
+            s -= C::Scalar::ONE;
+        }
+        s *= C::Scalar::TWO_INV;
+        bits.push(bit);
+    }
+
+    bool::from(s.is_zero()).then_some(bits)
+}
+
+pub fn from_bits<C: CurveArithmetic>(bits: &[bool]) -> C::Scalar {
+    bits.iter().rev().fold(C::Scalar::ZERO, |acc, bit| {
+        if *bit {
+            acc.double() + C::Scalar::ONE
+        } else {
+            acc.double()
+        }
+    })
+}
+
+// The same integer s < 2^n as a scalar of C2, or None if s is too big to have the same value in
+// both groups.
+pub fn transfer_scalar<C1, C2>(s: &C1::Scalar) -> Option<C2::Scalar>
+where
+    C1: CurveArithmetic,
+    C2: CurveArithmetic,
+{
+    let bits = to_bits::<C1>(s, bit_length::<C1, C2>())?;
+
+    Some(from_bits::<C2>(&bits))
+}
+
+pub fn random_challenge() -> u128 {
+    let mut bytes = [0u8; CHALLENGE_BYTES];
+    OsRng.fill_bytes(&mut bytes);
+
+    u128::from_be_bytes(bytes)
+}
+
+// the first CHALLENGE_BYTES bytes of a hash as a challenge
+pub fn challenge_from_digest(digest: &[u8]) -> u128 {
+    let mut bytes = [0u8; CHALLENGE_BYTES];
+    bytes.copy_from_slice(&digest[..CHALLENGE_BYTES]);
+
+    u128::from_be_bytes(bytes)
+}
+
+// sum 2^i v_i
+pub fn weighted_sum<T, S>(values: &[T]) -> T
+where
+    T: Group<Scalar = S>,
+    S: Field,
+{
+    let mut power = S::ONE;
+    let mut sum = T::identity();
+    for v in values {
+        sum += *v * power;
+        power = power.double();
+    }
+
+    sum
+}
+
+pub fn weighted_scalar_sum<S: Field>(values: &[S]) -> S {
+    let mut power = S::ONE;
+    let mut sum = S::ZERO;
+    for v in values {
+        sum += *v * power;
+        power = power.double();
+    }
+
+    sum
+}
+
+// n random blindings such that sum 2^i r_i = t
+pub fn blindings<S: Field>(t: &S, n: usize) -> Vec<S> {
+    let mut r: Vec<S> = (0..n - 1).map(|_| S::random(&mut OsRng)).collect();
+    let power = (0..n - 1).fold(S::ONE, |acc, _| acc.double());
+    let last = (*t - weighted_scalar_sum(&r)) * power.invert().unwrap();
+    r.push(last);
+
+    r
+}
+
+// n random points such that sum 2^i p_i = p
+pub fn random_points<T, S>(h: &T, p: &T, n: usize) -> Vec<T>
+where
+    T: Group<Scalar = S>,
+    S: Field,
+{
+    let mut points: Vec<T> = (0..n - 1).map(|_| *h * S::random(&mut OsRng)).collect();
+    let power = (0..n - 1).fold(S::ONE, |acc, _| acc.double());
+    let last = (*p - weighted_sum(&points)) * power.invert().unwrap();
+    points.push(last);
+
+    points
+}
+
+pub struct Crs<C1, C2>
+where
+    C1: CurveArithmetic,
+    C2: CurveArithmetic,
+{
+    pub g1: C1::ProjectivePoint,
+    pub h1: C1::ProjectivePoint,
+    pub g2: C2::ProjectivePoint,
+    pub h2: C2::ProjectivePoint,
+}
+
+impl<C1, C2> Crs<C1, C2>
+where
+    C1: CurveArithmetic,
+    C2: CurveArithmetic,
+{
+    pub fn new(
+        g1: C1::ProjectivePoint,
+        h1: C1::ProjectivePoint,
+        g2: C2::ProjectivePoint,
+        h2: C2::ProjectivePoint,
+    ) -> Self {
+        Crs { g1, h1, g2, h2 }
+    }
+
+    // g1 and g2 are the generators of the curves and h1 and h2 are derived with hash_to_curve
+    pub fn nums() -> Self
+    where
+        C1: GroupDigest,
+        C1::ProjectivePoint: CofactorGroup,
+        C2: GroupDigest,
+        C2::ProjectivePoint: CofactorGroup,
+    {
+        Crs {
+            g1: C1::ProjectivePoint::generator(),
+            h1: hash_to_curve_generator::<C1>(b"cross_group_dleq_h", 0),
+            g2: C2::ProjectivePoint::generator(),
+            h2: hash_to_curve_generator::<C2>(b"cross_group_dleq_h", 0),
+        }
+    }
+
+    pub fn to_byte_vector(&self) -> Vec<u8> {
+        let mut v = generators_to_byte_vector::<C1>(&[self.g1, self.h1]);
+        v.append(&mut generators_to_byte_vector::<C2>(&[self.g2, self.h2]));
+
+        v
+    }
+
+    pub fn from_byte_vector(bytes: &[u8]) -> Option<Self>
+    where
+        C1::AffinePoint: DecompressPoint<C1>,
+        C2::AffinePoint: DecompressPoint<C2>,
+    {
+        let len_1 = 2 * (FieldBytes::<C1>::default().len() + 1);
+        if bytes.len() < len_1 {
+            return None;
+        }
+        let (bytes_1, bytes_2) = bytes.split_at(len_1);
+        let generators_1 = generators_from_byte_vector::<C1>(bytes_1, 2)?;
+        let generators_2 = generators_from_byte_vector::<C2>(bytes_2, 2)?;
+
+        Some(Self::new(
+            generators_1[0],
+            generators_1[1],
+            generators_2[0],
+            generators_2[1],
+        ))
+    }
+
+    // chains the generators to a hasher computing a challenge
+    pub fn chain_to<H: Digest>(&self, hasher: H) -> H {
+        hasher
+            .chain_update(proj::<C1>(&self.g1))
+            .chain_update(proj::<C1>(&self.h1))
+            .chain_update(proj::<C2>(&self.g2))
+            .chain_update(proj::<C2>(&self.h2))
+    }
+}
+
+pub struct Statement<C1, C2>
+where
+    C1: CurveArithmetic,
+    C2: CurveArithmetic,
+{
+    p1: C1::ProjectivePoint,
+    p2: C2::ProjectivePoint,
+}
+
+impl<C1, C2> Statement<C1, C2>
+where
+    C1: CurveArithmetic,
+    C2: CurveArithmetic,
+{
+    pub fn new(p1: C1::ProjectivePoint, p2: C2::ProjectivePoint) -> Self {
+        Statement { p1, p2 }
+    }
+
+    pub fn to_byte_vector(&self) -> Vec<u8> {
+        let mut v: Vec<u8> = Vec::new();
+        v.append(&mut point_to_byte_vector::<C1>(&self.p1));
+        v.append(&mut point_to_byte_vector::<C2>(&self.p2));
+
+        v
+    }
+}
+
+pub struct Witness<C1, C2>
+where
+    C1: CurveArithmetic,
+    C2: CurveArithmetic,
+{
+    s: C1::Scalar,
+    t1: C1::Scalar,
+    t2: C2::Scalar,
+}
+
+impl<C1, C2> Witness<C1, C2>
+where
+    C1: CurveArithmetic,
+    C2: CurveArithmetic,
+{
+    // s must be smaller than 2^bit_length()
+    pub fn new(s: C1::Scalar, t1: C1::Scalar, t2: C2::Scalar) -> Self {
+        Witness { s, t1, t2 }
+    }
+
+    pub fn s(&self) -> C1::Scalar {
+        self.s
+    }
+
+    pub fn t1(&self) -> C1::Scalar {
+        self.t1
+    }
+}
+
+// Relation_DLEQ = {(P1, P2; s, t1, t2) | s < 2^n && P1 = g1^s * h1^t1 && P2 = g2^s * h2^t2}
+impl<C1, C2> HardRelation<Statement<C1, C2>, Witness<C1, C2>> for Witness<C1, C2>
+where
+    C1: CurveArithmetic,
+    C2: CurveArithmetic,
+{
+    type PP = Crs<C1, C2>;
+
+    fn R(pp: &Self::PP, w: &Witness<C1, C2>, x: &Statement<C1, C2>) -> bool {
+        match transfer_scalar::<C1, C2>(&w.s) {
+            Some(s2) => x.p1 == pp.g1 * w.s + pp.h1 * w.t1 && x.p2 == pp.g2 * s2 + pp.h2 * w.t2,
+            None => false,
+        }
+    }
+
+    fn statement(_pp: &Self::PP, _w: &Witness<C1, C2>) -> Statement<C1, C2> {
+        unimplemented!("This function should never be called!");
+    }
+
+    fn gen(_pp: &Self::PP) -> (Witness<C1, C2>, Statement<C1, C2>) {
+        unimplemented!("This function should never be called!");
+    }
+}
+
+// (C1_i, C2_i, [(R1, R2) of the member 0 of the ring, (R1, R2) of the member 1])
+pub type BitCommitment<C1, C2> = (
+    <C1 as CurveArithmetic>::ProjectivePoint,
+    <C2 as CurveArithmetic>::ProjectivePoint,
+    [(
+        <C1 as CurveArithmetic>::ProjectivePoint,
+        <C2 as CurveArithmetic>::ProjectivePoint,
+    ); 2],
+);
+
+// (challenge of the member 0 of the ring, [(z1, z2) of the member 0, (z1, z2) of the member 1]),
+// the challenge of the member 1 is the XOR of the challenge of the verifier and this one
+pub type BitResponse<C1, C2> = (
+    u128,
+    [(
+        <C1 as CurveArithmetic>::Scalar,
+        <C2 as CurveArithmetic>::Scalar,
+    ); 2],
+);
+
+// (b_i, (r1_i, r2_i), nonces of the real member, challenge and responses of the simulated one)
+type BitState<C1, C2> = (
+    bool,
+    (
+        <C1 as CurveArithmetic>::Scalar,
+        <C2 as CurveArithmetic>::Scalar,
+    ),
+    (
+        <C1 as CurveArithmetic>::Scalar,
+        <C2 as CurveArithmetic>::Scalar,
+    ),
+    u128,
+    (
+        <C1 as CurveArithmetic>::Scalar,
+        <C2 as CurveArithmetic>::Scalar,
+    ),
+);
+
+pub struct Proof<C1, C2>
+where
+    C1: CurveArithmetic,
+    C2: CurveArithmetic,
+{
+    a: Vec<BitCommitment<C1, C2>>,
+    r: Vec<BitResponse<C1, C2>>,
+}
+
+impl<C1, C2> Proof<C1, C2>
+where
+    C1: CurveArithmetic,
+    C2: CurveArithmetic,
+{
+    pub fn to_byte_vector(&self) -> Vec<u8> {
+        let mut v: Vec<u8> = Vec::new();
+        v.append(&mut bits_to_byte_vector::<C1, C2>(&self.a));
+        v.append(&mut rings_to_byte_vector::<C1, C2>(&self.a));
+        v.append(&mut response_to_byte_vector::<C1, C2>(&self.r));
+
+        v
+    }
+}
+
+// The encoding of a proof is the bit commitments, followed by the commitments of the rings and
+// the responses. The commitments of the rings can be recomputed from the challenge (see
+// ring_commitments), so the encoding of proofs using this one as a building block can drop them.
+pub fn bits_to_byte_vector<C1, C2>(a: &[BitCommitment<C1, C2>]) -> Vec<u8>
+where
+    C1: CurveArithmetic,
+    C2: CurveArithmetic,
+{
+    let mut v: Vec<u8> = Vec::new();
+    for (c1, c2, _) in a.iter() {
+        v.append(&mut point_to_byte_vector::<C1>(c1));
+        v.append(&mut point_to_byte_vector::<C2>(c2));
+    }
+
+    v
+}
+
+pub fn rings_to_byte_vector<C1, C2>(a: &[BitCommitment<C1, C2>]) -> Vec<u8>
+where
+    C1: CurveArithmetic,
+    C2: CurveArithmetic,
+{
+    let mut v: Vec<u8> = Vec::new();
+    for (_, _, ring) in a.iter() {
+        for (r1, r2) in ring.iter() {
+            v.append(&mut point_to_byte_vector::<C1>(r1));
+            v.append(&mut point_to_byte_vector::<C2>(r2));
+        }
+    }
+
+    v
+}
+
+pub fn response_to_byte_vector<C1, C2>(r: &[BitResponse<C1, C2>]) -> Vec<u8>
+where
+    C1: CurveArithmetic,
+    C2: CurveArithmetic,
+{
+    let mut v: Vec<u8> = Vec::new();
+    for (e0, z) in r.iter() {
+        v.extend_from_slice(&e0.to_be_bytes());
+        for (z1, z2) in z.iter() {
+            v.append(&mut scalar_to_byte_vector::<C1>(z1));
+            v.append(&mut scalar_to_byte_vector::<C2>(z2));
+        }
+    }
+
+    v
+}
+
+// reads n bit commitments, with the commitments of the rings if read_rings is true (otherwise
+// they are left as the identity and have to be recomputed with ring_commitments)
+pub fn read_commitment<C1, C2>(
+    reader: &mut ByteReader,
+    n: usize,
+    read_rings: bool,
+) -> Option<Vec<BitCommitment<C1, C2>>>
+where
+    C1: CurveArithmetic,
+    C2: CurveArithmetic,
+    C1::AffinePoint: DecompressPoint<C1>,
+    C2::AffinePoint: DecompressPoint<C2>,
+{
+    let identity = (
+        C1::ProjectivePoint::identity(),
+        C2::ProjectivePoint::identity(),
+    );
+    let mut a: Vec<BitCommitment<C1, C2>> = Vec::with_capacity(n);
+    for _ in 0..n {
+        let c1 = reader.read_point::<C1>()?;
+        let c2 = reader.read_point::<C2>()?;
+        a.push((c1, c2, [identity; 2]));
+    }
+    if read_rings {
+        for (_, _, ring) in a.iter_mut() {
+            for member in ring.iter_mut() {
+                *member = (reader.read_point::<C1>()?, reader.read_point::<C2>()?);
+            }
+        }
+    }
+
+    Some(a)
+}
+
+pub fn read_response<C1, C2>(reader: &mut ByteReader, n: usize) -> Option<Vec<BitResponse<C1, C2>>>
+where
+    C1: CurveArithmetic,
+    C2: CurveArithmetic,
+{
+    let mut r: Vec<BitResponse<C1, C2>> = Vec::with_capacity(n);
+    for _ in 0..n {
+        let e0 = challenge_from_digest(reader.read_bytes(CHALLENGE_BYTES)?);
+        let z0 = (reader.read_scalar::<C1>()?, reader.read_scalar::<C2>()?);
+        let z1 = (reader.read_scalar::<C1>()?, reader.read_scalar::<C2>()?);
+        r.push((e0, [z0, z1]));
+    }
+
+    Some(r)
+}
+
+// recomputes the commitments of the rings from the challenge c and the responses, these are the
+// only ones that pass the verification
+pub fn ring_commitments<C1, C2>(
+    crs: &Crs<C1, C2>,
+    a: &mut [BitCommitment<C1, C2>],
+    c: u128,
+    r: &[BitResponse<C1, C2>],
+) where
+    C1: CurveArithmetic,
+    C2: CurveArithmetic,
+{
+    for ((c1, c2, ring), (e0, z)) in a.iter_mut().zip(r.iter()) {
+        let keys = ring_keys(&crs.g1, &crs.g2, c1, c2);
+        let e = [*e0, c ^ e0];
+        for k in 0..2 {
+            ring[k] = ring_commitment(&crs.h1, &crs.h2, &keys[k], e[k], &z[k]);
+        }
+    }
+}
+
+// (sum 2^i C1_i, sum 2^i C2_i), the values of P1 and P2 in an accepting proof
+pub fn commitment_sum<C1, C2>(
+    a: &[BitCommitment<C1, C2>],
+) -> (C1::ProjectivePoint, C2::ProjectivePoint)
+where
+    C1: CurveArithmetic,
+    C2: CurveArithmetic,
+{
+    let c1: Vec<C1::ProjectivePoint> = a.iter().map(|bit| bit.0).collect();
+    let c2: Vec<C2::ProjectivePoint> = a.iter().map(|bit| bit.1).collect();
+
+    (weighted_sum(&c1), weighted_sum(&c2))
+}
+
+// public keys of the members of the ring of a bit: (C1_i, C2_i) and (C1_i / g1, C2_i / g2)
+pub fn ring_keys<T1, T2>(g1: &T1, g2: &T2, c1: &T1, c2: &T2) -> [(T1, T2); 2]
+where
+    T1: Group,
+    T2: Group,
+{
+    [(*c1, *c2), (*c1 - g1, *c2 - g2)]
+}
+
+// commitment of a member of the ring with key k, challenge e and responses z
+pub fn ring_commitment<T1, T2>(
+    h1: &T1,
+    h2: &T2,
+    k: &(T1, T2),
+    e: u128,
+    z: &(T1::Scalar, T2::Scalar),
+) -> (T1, T2)
+where
+    T1: Group,
+    T2: Group,
+{
+    (
+        *h1 * z.0 - k.0 * T1::Scalar::from_u128(e),
+        *h2 * z.1 - k.1 * T2::Scalar::from_u128(e),
+    )
+}
+
+impl<C1, C2, H1, H2> CrossGroupDLEQ<C1, C2, H1, H2>
+where
+    C1: CurveArithmetic,
+    C2: CurveArithmetic,
+    H1: Digest<OutputSize = C1::FieldBytesSize>,
+    H2: Digest<OutputSize = C2::FieldBytesSize>,
+{
+    // chains the commitments of the bits to a hasher computing a challenge
+    pub fn chain_commitment<H: Digest>(hasher: H, a: &[BitCommitment<C1, C2>]) -> H {
+        a.iter().fold(hasher, |hasher, (c1, c2, ring)| {
+            ring.iter().fold(
+                hasher
+                    .chain_update(point_to_byte_vector::<C1>(c1))
+                    .chain_update(point_to_byte_vector::<C2>(c2)),
+                |hasher, (r1, r2)| {
+                    hasher
+                        .chain_update(point_to_byte_vector::<C1>(r1))
+                        .chain_update(point_to_byte_vector::<C2>(r2))
+                },
+            )
+        })
+    }
+
+    fn compute_challenge(
+        crs: &Crs<C1, C2>,
+        x: &Statement<C1, C2>,
+        a: &[BitCommitment<C1, C2>],
+    ) -> u128 {
+        let hasher = crs
+            .chain_to(H1::new())
+            .chain_update(point_to_byte_vector::<C1>(&x.p1))
+            .chain_update(point_to_byte_vector::<C2>(&x.p2));
+
+        challenge_from_digest(&Self::chain_commitment(hasher, a).finalize())
+    }
+}
+
+impl<C1, C2, H1, H2> NIZK for CrossGroupDLEQ<C1, C2, H1, H2>
+where
+    C1: CurveArithmetic,
+    C2: CurveArithmetic,
+    H1: Digest<OutputSize = C1::FieldBytesSize>,
+    H2: Digest<OutputSize = C2::FieldBytesSize>,
+    C1::AffinePoint: DecompressPoint<C1>,
+    C2::AffinePoint: DecompressPoint<C2>,
+    C1: GroupDigest,
+    C1::ProjectivePoint: CofactorGroup,
+    C2: GroupDigest,
+    C2::ProjectivePoint: CofactorGroup,
+{
+    type CRS = Crs<C1, C2>;
+    type Statement = Statement<C1, C2>;
+    type Witness = Witness<C1, C2>;
+    type Proof = Proof<C1, C2>;
+
+    fn crs_gen() -> Self::CRS {
+        Crs::nums()
+    }
+
+    fn prove(crs: &Self::CRS, x: &Self::Statement, w: &Self::Witness) -> Self::Proof {
+        let (a, st) = Self::commit(crs, x, w);
+        let c = Self::compute_challenge(crs, x, &a);
+        let r = Self::respond(crs, x, w, &st, &c);
+
+        Proof::<C1, C2> { a, r }
+    }
+
+    fn verify(crs: &Self::CRS, x: &Self::Statement, p: &Self::Proof) -> bool {
+        let c = Self::compute_challenge(crs, x, &p.a);
+
+        Self::check(crs, x, &p.a, &c, &p.r)
+    }
+}
+
+impl<C1, C2, H1, H2> SigmaProtocol for CrossGroupDLEQ<C1, C2, H1, H2>
+where
+    C1: CurveArithmetic,
+    C2: CurveArithmetic,
+    H1: Digest<OutputSize = C1::FieldBytesSize>,
+    H2: Digest<OutputSize = C2::FieldBytesSize>,
+    C1::AffinePoint: DecompressPoint<C1>,
+    C2::AffinePoint: DecompressPoint<C2>,
+    C1: GroupDigest,
+    C1::ProjectivePoint: CofactorGroup,
+    C2: GroupDigest,
+    C2::ProjectivePoint: CofactorGroup,
+{
+    type Commitment = Vec<BitCommitment<C1, C2>>;
+    type State = Vec<BitState<C1, C2>>;
+    type Challenge = u128;
+    type Response = Vec<BitResponse<C1, C2>>;
+
+    fn commit(
+        crs: &Self::CRS,
+        _x: &Self::Statement,
+        w: &Self::Witness,
+    ) -> (Self::Commitment, Self::State) {
+        let n = bit_length::<C1, C2>();
+        let bits = to_bits::<C1>(&w.s, n).expect("the secret does not fit in the bit length");
+        let r1 = blindings(&w.t1, n);
+        let r2 = blindings(&w.t2, n);
+
+        bits.iter()
+            .zip(r1.iter().zip(r2.iter()))
+            .map(|(bit, (r1, r2))| {
+                let (c1, c2) = if *bit {
+                    (crs.g1 + crs.h1 * r1, crs.g2 + crs.h2 * r2)
+                } else {
+                    (crs.h1 * r1, crs.h2 * r2)
+                };
+                let keys = ring_keys(&crs.g1, &crs.g2, &c1, &c2);
+                let real = *bit as usize;
+
+                let u1 = *NonZeroScalar::<C1>::random(&mut OsRng).as_ref();
+                let u2 = *NonZeroScalar::<C2>::random(&mut OsRng).as_ref();
+                let e_sim = random_challenge();
+                let z_sim = (
+                    C1::Scalar::random(&mut OsRng),
+                    C2::Scalar::random(&mut OsRng),
+                );
+
+                let mut ring = [(crs.h1 * u1, crs.h2 * u2); 2];
+                ring[1 - real] = ring_commitment(&crs.h1, &crs.h2, &keys[1 - real], e_sim, &z_sim);
+
+                ((c1, c2, ring), (*bit, (*r1, *r2), (u1, u2), e_sim, z_sim))
+            })
+            .unzip()
+    }
+
+    fn respond(
+        _crs: &Self::CRS,
+        _x: &Self::Statement,
+        _w: &Self::Witness,
+        st: &Self::State,
+        c: &Self::Challenge,
+    ) -> Self::Response {
+        st.iter()
+            .map(|(bit, (r1, r2), (u1, u2), e_sim, z_sim)| {
+                let real = *bit as usize;
+                let e = *c ^ e_sim;
+                let z = (
+                    *u1 + C1::Scalar::from_u128(e) * r1,
+                    *u2 + C2::Scalar::from_u128(e) * r2,
+                );
+
+                let mut responses = [*z_sim; 2];
+                responses[real] = z;
+                let e0 = if real == 0 { e } else { *e_sim };
+
+                (e0, responses)
+            })
+            .collect()
+    }
+
+    fn check(
+        crs: &Self::CRS,
+        x: &Self::Statement,
+        a: &Self::Commitment,
+        c: &Self::Challenge,
+        r: &Self::Response,
+    ) -> bool {
+        let n = bit_length::<C1, C2>();
+        if a.len() != n || r.len() != n {
+            return false;
+        }
+        if commitment_sum::<C1, C2>(a) != (x.p1, x.p2) {
+            return false;
+        }
+
+        a.iter().zip(r.iter()).all(|((c1, c2, ring), (e0, z))| {
+            let keys = ring_keys(&crs.g1, &crs.g2, c1, c2);
+            let e = [*e0, *c ^ e0];
+            (0..2).all(|k| ring_commitment(&crs.h1, &crs.h2, &keys[k], e[k], &z[k]) == ring[k])
+        })
+    }
+
+    fn simulate(
+        crs: &Self::CRS,
+        x: &Self::Statement,
+        c: &Self::Challenge,
+    ) -> (Self::Commitment, Self::Response) {
+        let n = bit_length::<C1, C2>();
+        let c1 = random_points(&crs.h1, &x.p1, n);
+        let c2 = random_points(&crs.h2, &x.p2, n);
+
+        c1.iter()
+            .zip(c2.iter())
+            .map(|(c1, c2)| {
+                let keys = ring_keys(&crs.g1, &crs.g2, c1, c2);
+                let e0 = random_challenge();
+                let e = [e0, *c ^ e0];
+                let z = [
+                    (
+                        C1::Scalar::random(&mut OsRng),
+                        C2::Scalar::random(&mut OsRng),
+                    ),
+                    (
+                        C1::Scalar::random(&mut OsRng),
+                        C2::Scalar::random(&mut OsRng),
+                    ),
+                ];
+                let ring = [
+                    ring_commitment(&crs.h1, &crs.h2, &keys[0], e[0], &z[0]),
+                    ring_commitment(&crs.h1, &crs.h2, &keys[1], e[1], &z[1]),
+                ];
+
+                ((*c1, *c2, ring), (e0, z))
+            })
+            .unzip()
+    }
+}
+
+// For every bit, the challenge of at least one member of the ring is different in both
+// transcripts, and we extract the blindings of that member (which tells us the bit).
+impl<C1, C2, H1, H2> SpecialSoundness for CrossGroupDLEQ<C1, C2, H1, H2>
+where
+    C1: CurveArithmetic,
+    C2: CurveArithmetic,
+    H1: Digest<OutputSize = C1::FieldBytesSize>,
+    H2: Digest<OutputSize = C2::FieldBytesSize>,
+    C1::AffinePoint: DecompressPoint<C1>,
+    C2::AffinePoint: DecompressPoint<C2>,
+    C1: GroupDigest,
+    C1::ProjectivePoint: CofactorGroup,
+    C2: GroupDigest,
+    C2::ProjectivePoint: CofactorGroup,
+{
+    fn extract(
+        crs: &Self::CRS,
+        x: &Self::Statement,
+        a: &Self::Commitment,
+        c1: &Self::Challenge,
+        r1: &Self::Response,
+        c2: &Self::Challenge,
+        r2: &Self::Response,
+    ) -> Option<Self::Witness> {
+        if !Self::check(crs, x, a, c1, r1) || !Self::check(crs, x, a, c2, r2) || c1 == c2 {
+            return None;
+        }
+        let mut bits: Vec<bool> = Vec::new();
+        let mut t1: Vec<C1::Scalar> = Vec::new();
+        let mut t2: Vec<C2::Scalar> = Vec::new();
+        for ((e0, z), (e0_prime, z_prime)) in r1.iter().zip(r2.iter()) {
+            let e = [*e0, *c1 ^ e0];
+            let e_prime = [*e0_prime, *c2 ^ e0_prime];
+            let k = if e[0] != e_prime[0] { 0 } else { 1 };
+            // e[k] and e_prime[k] are smaller than both orders, so the difference is not zero
+            let d1 = C1::Scalar::from_u128(e[k]) - C1::Scalar::from_u128(e_prime[k]);
+            let d2 = C2::Scalar::from_u128(e[k]) - C2::Scalar::from_u128(e_prime[k]);
+            bits.push(k == 1);
+            t1.push((z[k].0 - z_prime[k].0) * d1.invert().unwrap());
+            t2.push((z[k].1 - z_prime[k].1) * d2.invert().unwrap());
+        }
+
+        Some(Witness::new(
+            from_bits::<C1>(&bits),
+            weighted_scalar_sum(&t1),
+            weighted_scalar_sum(&t2),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k256::Secp256k1;
+    use p256::NistP256;
+    use sha2::Sha256;
+
+    type C1 = Secp256k1;
+    type C2 = NistP256;
+    type Scalar1 = <C1 as CurveArithmetic>::Scalar;
+    type Scalar2 = <C2 as CurveArithmetic>::Scalar;
+    type Nizk = CrossGroupDLEQ<C1, C2, Sha256, Sha256>;
+
+    // a secret of bit_length() bits, its blindings and the statement
+    fn instance(crs: &Crs<C1, C2>) -> (Statement<C1, C2>, Witness<C1, C2>) {
+        let n = bit_length::<C1, C2>();
+        let bits: Vec<bool> = (0..n).map(|_| OsRng.next_u32() & 1 == 1).collect();
+        let s1 = from_bits::<C1>(&bits);
+        let s2 = from_bits::<C2>(&bits);
+        let t1 = Scalar1::random(&mut OsRng);
+        let t2 = Scalar2::random(&mut OsRng);
+        let statement = Statement::new(crs.g1 * s1 + crs.h1 * t1, crs.g2 * s2 + crs.h2 * t2);
+
+        (statement, Witness::new(s1, t1, t2))
+    }
+
+    // A prover that commits to the digits d1 in C1 and d2 in C2 instead of the same bits in both
+    // groups, and answers every ring as if the member of the digit in C1 was the real one
+    fn proof_with_digits(
+        crs: &Crs<C1, C2>,
+        d1: &[Scalar1],
+        d2: &[Scalar2],
+        t1: &Scalar1,
+        t2: &Scalar2,
+    ) -> (Statement<C1, C2>, Proof<C1, C2>) {
+        let n = d1.len();
+        let r1 = blindings(t1, n);
+        let r2 = blindings(t2, n);
+        let mut a: Vec<BitCommitment<C1, C2>> = Vec::with_capacity(n);
+        let mut st = Vec::with_capacity(n);
+        for i in 0..n {
+            let c1 = crs.g1 * d1[i] + crs.h1 * r1[i];
+            let c2 = crs.g2 * d2[i] + crs.h2 * r2[i];
+            let keys = ring_keys(&crs.g1, &crs.g2, &c1, &c2);
+            let real = (d1[i] == Scalar1::ONE) as usize;
+            let u = (Scalar1::random(&mut OsRng), Scalar2::random(&mut OsRng));
+            let e_sim = random_challenge();
+            let z_sim = (Scalar1::random(&mut OsRng), Scalar2::random(&mut OsRng));
+            let mut ring = [(crs.h1 * u.0, crs.h2 * u.1); 2];
+            ring[1 - real] = ring_commitment(&crs.h1, &crs.h2, &keys[1 - real], e_sim, &z_sim);
+            a.push((c1, c2, ring));
+            st.push((real, u, e_sim, z_sim));
+        }
+        let x = Statement::new(
+            crs.g1 * weighted_scalar_sum(d1) + crs.h1 * t1,
+            crs.g2 * weighted_scalar_sum(d2) + crs.h2 * t2,
+        );
+        let c = Nizk::compute_challenge(crs, &x, &a);
+        let r = st
+            .iter()
+            .zip(r1.iter().zip(r2.iter()))
+            .map(|((real, u, e_sim, z_sim), (r1, r2))| {
+                let e = c ^ e_sim;
+                let mut z = [*z_sim; 2];
+                z[*real] = (
+                    u.0 + Scalar1::from_u128(e) * r1,
+                    u.1 + Scalar2::from_u128(e) * r2,
+                );
+                (if *real == 0 { e } else { *e_sim }, z)
+            })
+            .collect();
+
+        (x, Proof { a, r })
+    }
+
+    // the digits of s in both groups
+    fn digits(bits: &[bool]) -> (Vec<Scalar1>, Vec<Scalar2>) {
+        let d1 = bits.iter().map(|b| Scalar1::from(*b as u64)).collect();
+        let d2 = bits.iter().map(|b| Scalar2::from(*b as u64)).collect();
+
+        (d1, d2)
+    }
+
+    #[test]
+    fn cross_group_dleq() {
+        let crs = Nizk::crs_gen();
+        let (x, w) = instance(&crs);
+        assert!(Witness::R(&crs, &w, &x));
+        let proof = Nizk::prove(&crs, &x, &w);
+        assert!(Nizk::verify(&crs, &x, &proof));
+
+        // P2 commits to another value
+        let other = Statement::new(x.p1, x.p2 + crs.g2);
+        assert!(!Nizk::verify(&crs, &other, &proof));
+
+        let n = bit_length::<C1, C2>();
+        assert!(transfer_scalar::<C1, C2>(&w.s()).is_some());
+        assert!(to_bits::<C1>(&-Scalar1::ONE, n).is_none());
+    }
+
+    #[test]
+    fn cross_group_dleq_encoding() {
+        let crs = Nizk::crs_gen();
+        assert!(Crs::<C1, C2>::from_byte_vector(&crs.to_byte_vector())
+            .is_some_and(|decoded| decoded.to_byte_vector() == crs.to_byte_vector()));
+        let (x, w) = instance(&crs);
+        let proof = Nizk::prove(&crs, &x, &w);
+        let n = bit_length::<C1, C2>();
+
+        let bytes = proof.to_byte_vector();
+        let mut reader = ByteReader::new(&bytes);
+        let a = read_commitment::<C1, C2>(&mut reader, n, true).unwrap();
+        let r = read_response::<C1, C2>(&mut reader, n).unwrap();
+        assert!(reader.is_empty());
+        let decoded = Proof { a, r };
+        assert_eq!(decoded.to_byte_vector(), bytes);
+        assert!(Nizk::verify(&crs, &x, &decoded));
+
+        // without the commitments of the rings, which are recomputed from the challenge
+        let mut bytes = bits_to_byte_vector::<C1, C2>(&proof.a);
+        bytes.append(&mut response_to_byte_vector::<C1, C2>(&proof.r));
+        let mut reader = ByteReader::new(&bytes);
+        let mut a = read_commitment::<C1, C2>(&mut reader, n, false).unwrap();
+        let r = read_response::<C1, C2>(&mut reader, n).unwrap();
+        let c = Nizk::compute_challenge(&crs, &x, &proof.a);
+        ring_commitments(&crs, &mut a, c, &r);
+        assert!(Nizk::verify(&crs, &x, &Proof { a, r }));
+    }
+
+    #[test]
+    fn cross_group_dleq_different_values() {
+        let crs = Nizk::crs_gen();
+        let n = bit_length::<C1, C2>();
+        let bits: Vec<bool> = (0..n).map(|_| OsRng.next_u32() & 1 == 1).collect();
+        let t1 = Scalar1::random(&mut OsRng);
+        let t2 = Scalar2::random(&mut OsRng);
+
+        // the same bits in both groups: the cheating prover is honest
+        let (d1, d2) = digits(&bits);
+        let (x, proof) = proof_with_digits(&crs, &d1, &d2, &t1, &t2);
+        assert!(Nizk::verify(&crs, &x, &proof));
+
+        // a bit differs between both groups
+        let mut flipped = bits.clone();
+        flipped[7] = !flipped[7];
+        let (_, d2_flipped) = digits(&flipped);
+        let (x, proof) = proof_with_digits(&crs, &d1, &d2_flipped, &t1, &t2);
+        assert!(!Nizk::verify(&crs, &x, &proof));
+
+        // P1 hides s = q2 + s2 and P2 hides s mod q2 = s2
+        let q2_minus_one = to_bits::<C2>(&-Scalar2::ONE, n + 1).unwrap();
+        let q2 = from_bits::<C1>(&q2_minus_one) + Scalar1::ONE;
+        let mut d1_wrapped = d1.clone();
+        d1_wrapped[0] += q2;
+        assert_eq!(
+            weighted_scalar_sum(&d1_wrapped),
+            weighted_scalar_sum(&d1) + q2
+        );
+        let (x, proof) = proof_with_digits(&crs, &d1_wrapped, &d2, &t1, &t2);
+        assert!(!Nizk::verify(&crs, &x, &proof));
+    }
+
+    #[test]
+    fn cross_group_dleq_tampered() {
+        let crs = Nizk::crs_gen();
+        let (x, w) = instance(&crs);
+        let proof = Nizk::prove(&crs, &x, &w);
+        let verify = |a, r| Nizk::verify(&crs, &x, &Proof { a, r });
+
+        // a response of a ring
+        let mut r = proof.r.clone();
+        r[3].1[1].0 += Scalar1::ONE;
+        assert!(!verify(proof.a.clone(), r));
+        let mut r = proof.r.clone();
+        r[3].1[0].1 += Scalar2::ONE;
+        assert!(!verify(proof.a.clone(), r));
+
+        // the split of the challenge between the members of a ring
+        let mut r = proof.r.clone();
+        r[5].0 ^= 1;
+        assert!(!verify(proof.a.clone(), r));
+
+        // a bit commitment, with the sum of the commitments unchanged
+        let mut a = proof.a.clone();
+        a[0].0 += crs.h1 + crs.h1;
+        a[1].0 -= crs.h1;
+        assert_eq!(commitment_sum::<C1, C2>(&a), (x.p1, x.p2));
+        assert!(!verify(a, proof.r.clone()));
+
+        assert!(verify(proof.a, proof.r));
+    }
+}
@@ -0,0 +1,319 @@
+use crate::bls_signatures::hash_to_g2;
+use crate::commitment_scheme::CommitmentScheme;
+use crate::hash_commitment::{HashCommitment, SALT_BYTES};
+use crate::utils::{point_to_byte_vector, ByteReader};
+use bls12_381::G2Projective;
+use digest::Digest;
+use elliptic_curve::group::cofactor::CofactorGroup;
+use elliptic_curve::hash2curve::{ExpandMsgXmd, GroupDigest};
+use elliptic_curve::point::DecompressPoint;
+use elliptic_curve::CurveArithmetic;
+use rand_core::{OsRng, RngCore};
+use sha2::Sha256;
+use std::marker::PhantomData;
+
+// Generation of common reference strings made of independent generators of a curve.
+//
+// - hash_to_curve_generator derives a generator deterministically from a domain string with the
+//   hash_to_curve of RFC 9380 (random oracle variant, expand_message_xmd with SHA-256). Anyone can
+//   recompute the generators from the domain, and nobody knows their discrete logarithms with
+//   respect to the generator of the curve or to each other. hash_to_g2_generator does the same
+//   in the group G2 of BLS12-381.
+// - CoinTossing lets several parties agree on a random seed that none of them could bias. Each
+//   party commits to a random seed with a HashCommitment, and the seeds are opened once all the
+//   commitments have been exchanged. The joint seed is then used as the domain of
+//   hash_to_curve_generator.
+// - generators_to_byte_vector and generators_from_byte_vector serialize the generators, so the
+//   parties can compare the CRS they use.
+
+// domain separation tag of the hash_to_curve calls of this crate
+const HASH_TO_CURVE_DST: &[u8] = b"MPCP_RUST-CRS-V01-CS01-with-expand_message_xmd:SHA-256_RO_";
+
+pub const SEED_BYTES: usize = 32;
+
+// Derive the generator number index of the domain. Different (domain, index) pairs give
+// independent generators.
+pub fn hash_to_curve_generator<C>(domain: &[u8], index: u64) -> C::ProjectivePoint
+where
+    C: GroupDigest,
+    C::ProjectivePoint: CofactorGroup,
+{
+    let msg = [domain, &index.to_be_bytes()].concat();
+
+    C::hash_from_bytes::<ExpandMsgXmd<Sha256>>(&[&msg], &[HASH_TO_CURVE_DST])
+        .expect("the length of the domain separation tag is valid")
+}
+
+// same as hash_to_curve_generator, for the group G2 of BLS12-381
+pub fn hash_to_g2_generator(domain: &[u8], index: u64) -> G2Projective {
+    let msg = [domain, &index.to_be_bytes()].concat();
+
+    hash_to_g2(HASH_TO_CURVE_DST, &msg)
+}
+
+pub fn generators_to_byte_vector<C: CurveArithmetic>(generators: &[C::ProjectivePoint]) -> Vec<u8> {
+    let mut v: Vec<u8> = Vec::new();
+    for generator in generators {
+        v.append(&mut point_to_byte_vector::<C>(generator));
+    }
+
+    v
+}
+
+// inverse of generators_to_byte_vector, returns None if the bytes are not exactly n points
+pub fn generators_from_byte_vector<C>(bytes: &[u8], n: usize) -> Option<Vec<C::ProjectivePoint>>
+where
+    C: CurveArithmetic,
+    C::AffinePoint: DecompressPoint<C>,
+{
+    let mut reader = ByteReader::new(bytes);
+    let generators = (0..n)
+        .map(|_| reader.read_point::<C>())
+        .collect::<Option<Vec<_>>>()?;
+
+    reader.is_empty().then_some(generators)
+}
+
+// One of the parties of a coin tossing among any number of parties, which are numbered with
+// distinct indices. The protocol is:
+// 1. every party creates a CoinTossing with its index and sends its commitment to the other
+//    parties,
+// 2. once a party has received the commitments of all the other parties, it sends its opening,
+// 3. every party calls joint_seed with the index, the commitment and the opening of every other
+//    party. It fails if an opening does not open the corresponding commitment, or if an index or
+//    a commitment is repeated or is the one of the party, and otherwise all the parties obtain
+//    the same joint seed, the XOR of all the seeds.
+// A party cannot choose its seed as a function of the seeds of the other parties because it is
+// committed before seeing them, so the joint seed is uniform if at least one party is honest.
+// The index of a party is part of the tag of its commitment, so a malicious party cannot send
+// back the commitment and the opening of an honest party as its own, which would cancel the
+// seed of the honest party in the XOR.
+pub struct CoinTossing<H>
+where
+    H: Digest,
+{
+    index: u64,
+    seed: [u8; SEED_BYTES],
+    salt: [u8; SALT_BYTES],
+    commitment: Vec<u8>,
+    _hash_marker: PhantomData<H>,
+}
+
+// the seed of a party and the salt of the hash commitment to it
+#[derive(Clone, Copy)]
+pub struct CoinTossingOpening {
+    pub seed: [u8; SEED_BYTES],
+    pub salt: [u8; SALT_BYTES],
+}
+
+impl<H> CoinTossing<H>
+where
+    H: Digest,
+{
+    pub fn new(index: u64) -> Self {
+        let mut seed = [0u8; SEED_BYTES];
+        OsRng.fill_bytes(&mut seed);
+        let (commitment, salt) = HashCommitment::<H>::commit(&Self::tag(index), &seed.to_vec());
+
+        Self {
+            index,
+            seed,
+            salt,
+            commitment,
+            _hash_marker: PhantomData,
+        }
+    }
+
+    pub fn commitment(&self) -> Vec<u8> {
+        self.commitment.clone()
+    }
+
+    pub fn opening(&self) -> CoinTossingOpening {
+        CoinTossingOpening {
+            seed: self.seed,
+            salt: self.salt,
+        }
+    }
+
+    pub fn index(&self) -> u64 {
+        self.index
+    }
+
+    // peers holds the index, the commitment and the opening of every other party, in any order
+    pub fn joint_seed(
+        &self,
+        peers: &[(u64, Vec<u8>, CoinTossingOpening)],
+    ) -> Option<[u8; SEED_BYTES]> {
+        let mut seed = self.seed;
+        let mut indices = vec![self.index];
+        let mut commitments = vec![&self.commitment];
+        for (index, commitment, opening) in peers {
+            if indices.contains(index) || commitments.contains(&commitment) {
+                return None;
+            }
+            indices.push(*index);
+            commitments.push(commitment);
+            if !HashCommitment::<H>::verify(
+                &Self::tag(*index),
+                commitment,
+                &opening.seed.to_vec(),
+                &opening.salt,
+            ) {
+                return None;
+            }
+            for (b, peer_b) in seed.iter_mut().zip(opening.seed) {
+                *b ^= peer_b;
+            }
+        }
+
+        Some(seed)
+    }
+
+    fn tag(index: u64) -> Vec<u8> {
+        [b"crs_coin_tossing".as_slice(), &index.to_be_bytes()].concat()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::por_schnorr_signature::Crs;
+    use elliptic_curve::Group;
+    use k256::Secp256k1;
+    use p256::NistP256;
+
+    type C = Secp256k1;
+    type Point = <C as CurveArithmetic>::ProjectivePoint;
+
+    #[test]
+    fn hash_to_curve_generators() {
+        let h0 = hash_to_curve_generator::<C>(b"domain", 0);
+        assert!(h0 == hash_to_curve_generator::<C>(b"domain", 0));
+        assert!(h0 != hash_to_curve_generator::<C>(b"domain", 1));
+        assert!(h0 != hash_to_curve_generator::<C>(b"other domain", 0));
+        assert!(h0 != Point::GENERATOR);
+        assert!(!bool::from(h0.is_identity()));
+
+        let h = hash_to_curve_generator::<NistP256>(b"domain", 0);
+        assert!(!bool::from(h.is_identity()));
+
+        let h2 = hash_to_g2_generator(b"domain", 0);
+        assert!(h2 == hash_to_g2_generator(b"domain", 0));
+        assert!(h2 != hash_to_g2_generator(b"domain", 1));
+        assert!(h2 != G2Projective::generator());
+        assert!(!bool::from(h2.is_identity()));
+    }
+
+    #[test]
+    fn generators_serialization() {
+        let generators: Vec<Point> = (0..3)
+            .map(|i| hash_to_curve_generator::<C>(b"domain", i))
+            .collect();
+        let bytes = generators_to_byte_vector::<C>(&generators);
+
+        assert!(generators_from_byte_vector::<C>(&bytes, 3).unwrap() == generators);
+        assert!(generators_from_byte_vector::<C>(&bytes, 2).is_none());
+        assert!(generators_from_byte_vector::<C>(&bytes[1..], 3).is_none());
+    }
+
+    #[test]
+    fn coin_tossing() {
+        let alice = CoinTossing::<Sha256>::new(0);
+        let bob = CoinTossing::<Sha256>::new(1);
+        let (commitment_a, commitment_b) = (alice.commitment(), bob.commitment());
+
+        let seed_a = alice
+            .joint_seed(&[(1, commitment_b.clone(), bob.opening())])
+            .unwrap();
+        let seed_b = bob
+            .joint_seed(&[(0, commitment_a, alice.opening())])
+            .unwrap();
+        assert_eq!(seed_a, seed_b);
+
+        // bob opens a seed different from the committed one
+        let mut cheating_opening = bob.opening();
+        cheating_opening.seed[0] ^= 1;
+        assert!(alice
+            .joint_seed(&[(1, commitment_b.clone(), cheating_opening)])
+            .is_none());
+
+        // or the committed seed with a different salt
+        let mut cheating_opening = bob.opening();
+        cheating_opening.salt[0] ^= 1;
+        assert!(alice
+            .joint_seed(&[(1, commitment_b.clone(), cheating_opening)])
+            .is_none());
+
+        // or claims the index of another party
+        assert!(alice
+            .joint_seed(&[(2, commitment_b, bob.opening())])
+            .is_none());
+    }
+
+    #[test]
+    fn coin_tossing_echo() {
+        let alice = CoinTossing::<Sha256>::new(0);
+        let bob = CoinTossing::<Sha256>::new(1);
+        let carol = CoinTossing::<Sha256>::new(2);
+        let echo =
+            |party: &CoinTossing<Sha256>, index| (index, party.commitment(), party.opening());
+
+        // mallory, with index 1, sends back the commitment and the opening of alice, which would
+        // make the joint seed zero
+        assert!(alice.joint_seed(&[echo(&alice, 1)]).is_none());
+        assert!(alice.joint_seed(&[echo(&alice, 0)]).is_none());
+        // or the ones of carol twice, which would cancel the seed of carol
+        let peers = [echo(&bob, 1), echo(&carol, 2), echo(&carol, 3)];
+        assert!(alice.joint_seed(&peers).is_none());
+        let peers = [echo(&bob, 1), echo(&carol, 2), echo(&carol, 2)];
+        assert!(alice.joint_seed(&peers).is_none());
+
+        assert!(alice
+            .joint_seed(&[echo(&bob, 1), echo(&carol, 2)])
+            .is_some());
+    }
+
+    #[test]
+    fn multi_party_coin_tossing() {
+        let parties: Vec<CoinTossing<Sha256>> = (0..4).map(CoinTossing::new).collect();
+        let messages: Vec<(u64, Vec<u8>, CoinTossingOpening)> = parties
+            .iter()
+            .map(|party| (party.index(), party.commitment(), party.opening()))
+            .collect();
+
+        let seeds: Vec<[u8; SEED_BYTES]> = parties
+            .iter()
+            .enumerate()
+            .map(|(i, party)| {
+                let mut peers = messages.clone();
+                peers.remove(i);
+                // the order in which the openings are received does not matter
+                peers.reverse();
+                party.joint_seed(&peers).unwrap()
+            })
+            .collect();
+        assert!(seeds.iter().all(|seed| *seed == seeds[0]));
+    }
+
+    #[test]
+    fn coin_tossing_crs() {
+        let alice = CoinTossing::<Sha256>::new(0);
+        let bob = CoinTossing::<Sha256>::new(1);
+        let seed_a = alice
+            .joint_seed(&[(1, bob.commitment(), bob.opening())])
+            .unwrap();
+        let seed_b = bob
+            .joint_seed(&[(0, alice.commitment(), alice.opening())])
+            .unwrap();
+
+        // both parties compare the serialization of the CRS they derived
+        let crs_a = Crs::<C>::from_seed(&seed_a).to_byte_vector();
+        let crs_b = Crs::<C>::from_seed(&seed_b).to_byte_vector();
+        assert_eq!(crs_a, crs_b);
+        assert_ne!(crs_a, Crs::<C>::hash_to_curve().to_byte_vector());
+        assert_eq!(
+            Crs::<C>::from_byte_vector(&crs_a).unwrap().to_byte_vector(),
+            crs_a
+        );
+    }
+}
@@ -0,0 +1,309 @@
+use crate::hard_relation::HardRelation;
+use crate::nizk::{EncodableProof, ProofEncoding, NIZK};
+use crate::sigma_protocol::{SigmaProtocol, SpecialSoundness};
+use crate::utils::{point_to_byte_vector, proj, scalar_to_byte_vector, ByteReader};
+use digest::Digest;
+use elliptic_curve::ff::Field;
+use elliptic_curve::ops::Reduce;
+use elliptic_curve::point::DecompressPoint;
+use elliptic_curve::scalar::NonZeroScalar;
+use elliptic_curve::CurveArithmetic;
+use rand_core::OsRng;
+use std::marker::PhantomData;
+
+// Chaum-Pedersen proof of equality of discrete logarithms: the prover knows w such that
+// h1 = g1^w and h2 = g2^w. It is used to prove that a decryption share a^s is computed with the
+// secret s of a verification key g^s, or that a cyphertext is a re-encryption of another one.
+pub struct DLEQProof<C, H>
+where
+    C: CurveArithmetic,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+{
+    _curve_marker: PhantomData<C>,
+    _hash_marker: PhantomData<H>,
+}
+
+pub struct Witness<C>
+where
+    C: CurveArithmetic,
+{
+    w: C::Scalar,
+}
+
+impl<C> Witness<C>
+where
+    C: CurveArithmetic,
+{
+    pub fn new(w: C::Scalar) -> Self {
+        Self { w }
+    }
+
+    pub fn w(&self) -> C::Scalar {
+        self.w
+    }
+}
+
+pub struct Statement<C>
+where
+    C: CurveArithmetic,
+{
+    g1: C::ProjectivePoint,
+    h1: C::ProjectivePoint,
+    g2: C::ProjectivePoint,
+    h2: C::ProjectivePoint,
+}
+
+impl<C> Statement<C>
+where
+    C: CurveArithmetic,
+{
+    pub fn new(
+        g1: C::ProjectivePoint,
+        h1: C::ProjectivePoint,
+        g2: C::ProjectivePoint,
+        h2: C::ProjectivePoint,
+    ) -> Self {
+        Self { g1, h1, g2, h2 }
+    }
+
+    pub fn to_byte_vector(&self) -> Vec<u8> {
+        let mut v: Vec<u8> = Vec::new();
+        v.append(&mut point_to_byte_vector::<C>(&self.g1));
+        v.append(&mut point_to_byte_vector::<C>(&self.h1));
+        v.append(&mut point_to_byte_vector::<C>(&self.g2));
+        v.append(&mut point_to_byte_vector::<C>(&self.h2));
+
+        v
+    }
+}
+
+// Relation_DLEQ = {(g1, h1, g2, h2; w) | h1 = g1^w && h2 = g2^w}
+// The public parameters are the bases (g1, g2).
+impl<C> HardRelation<Statement<C>, Witness<C>> for Witness<C>
+where
+    C: CurveArithmetic,
+{
+    type PP = (C::ProjectivePoint, C::ProjectivePoint);
+
+    fn R(_pp: &Self::PP, w: &Witness<C>, x: &Statement<C>) -> bool {
+        x.h1 == x.g1 * w.w && x.h2 == x.g2 * w.w
+    }
+    // From a Witness w, compute a Statement s such that R(w, s) == true
+    fn statement(pp: &Self::PP, w: &Witness<C>) -> Statement<C> {
+        let (g1, g2) = *pp;
+
+        Statement::new(g1, g1 * w.w, g2, g2 * w.w)
+    }
+
+    fn gen(pp: &Self::PP) -> (Witness<C>, Statement<C>) {
+        let w = Witness::new(*NonZeroScalar::<C>::random(&mut OsRng).as_ref());
+        let x = Self::statement(pp, &w);
+
+        (w, x)
+    }
+}
+
+pub struct Proof<C>
+where
+    C: CurveArithmetic,
+{
+    a: (C::ProjectivePoint, C::ProjectivePoint),
+    r: C::Scalar,
+}
+
+impl<C> Proof<C>
+where
+    C: CurveArithmetic,
+{
+    pub fn to_byte_vector(&self) -> Vec<u8> {
+        let mut v: Vec<u8> = Vec::new();
+        let (a1, a2) = self.a;
+        v.append(&mut point_to_byte_vector::<C>(&a1));
+        v.append(&mut point_to_byte_vector::<C>(&a2));
+        v.append(&mut scalar_to_byte_vector::<C>(&self.r));
+
+        v
+    }
+}
+
+fn compute_challenge<C, H>(
+    a: (C::ProjectivePoint, C::ProjectivePoint),
+    x: &Statement<C>,
+) -> C::Scalar
+where
+    C: CurveArithmetic,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+{
+    let (a1, a2) = a;
+
+    let hasher = H::new();
+    <C::Scalar as Reduce<C::Uint>>::reduce_bytes(
+        &hasher
+            .chain_update(proj::<C>(&x.g1))
+            .chain_update(proj::<C>(&x.h1))
+            .chain_update(proj::<C>(&x.g2))
+            .chain_update(proj::<C>(&x.h2))
+            .chain_update(proj::<C>(&a1))
+            .chain_update(proj::<C>(&a2))
+            .finalize(),
+    )
+}
+
+impl<C, H> NIZK for DLEQProof<C, H>
+where
+    C: CurveArithmetic,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+{
+    type CRS = ();
+    type Statement = Statement<C>;
+    type Witness = Witness<C>;
+    type Proof = Proof<C>;
+
+    fn crs_gen() -> Self::CRS {}
+
+    fn prove(crs: &Self::CRS, x: &Self::Statement, w: &Self::Witness) -> Self::Proof {
+        let (a, u) = Self::commit(crs, x, w);
+        let c = compute_challenge::<C, H>(a, x);
+        let r = Self::respond(crs, x, w, &u, &c);
+
+        Proof::<C> { a, r }
+    }
+
+    fn verify(crs: &Self::CRS, x: &Self::Statement, p: &Self::Proof) -> bool {
+        let c = compute_challenge::<C, H>(p.a, x);
+
+        Self::check(crs, x, &p.a, &c, &p.r)
+    }
+}
+
+impl<C, H> SigmaProtocol for DLEQProof<C, H>
+where
+    C: CurveArithmetic,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+{
+    type Commitment = (C::ProjectivePoint, C::ProjectivePoint);
+    type State = C::Scalar;
+    type Challenge = C::Scalar;
+    type Response = C::Scalar;
+
+    fn commit(
+        _crs: &Self::CRS,
+        x: &Self::Statement,
+        _w: &Self::Witness,
+    ) -> (Self::Commitment, Self::State) {
+        let nz_u = NonZeroScalar::<C>::random(&mut OsRng);
+        let u = nz_u.as_ref();
+
+        ((x.g1 * u, x.g2 * u), *u)
+    }
+
+    fn respond(
+        _crs: &Self::CRS,
+        _x: &Self::Statement,
+        w: &Self::Witness,
+        st: &Self::State,
+        c: &Self::Challenge,
+    ) -> Self::Response {
+        *st + w.w * c
+    }
+
+    fn check(
+        _crs: &Self::CRS,
+        x: &Self::Statement,
+        a: &Self::Commitment,
+        c: &Self::Challenge,
+        r: &Self::Response,
+    ) -> bool {
+        let (a1, a2) = *a;
+
+        x.g1 * r == a1 + x.h1 * c && x.g2 * r == a2 + x.h2 * c
+    }
+
+    fn simulate(
+        _crs: &Self::CRS,
+        x: &Self::Statement,
+        c: &Self::Challenge,
+    ) -> (Self::Commitment, Self::Response) {
+        let r = C::Scalar::random(&mut OsRng);
+
+        ((x.g1 * r - x.h1 * c, x.g2 * r - x.h2 * c), r)
+    }
+}
+
+impl<C, H> SpecialSoundness for DLEQProof<C, H>
+where
+    C: CurveArithmetic,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+{
+    // w = (r1 - r2) / (c1 - c2)
+    fn extract(
+        crs: &Self::CRS,
+        x: &Self::Statement,
+        a: &Self::Commitment,
+        c1: &Self::Challenge,
+        r1: &Self::Response,
+        c2: &Self::Challenge,
+        r2: &Self::Response,
+    ) -> Option<Self::Witness> {
+        if !Self::check(crs, x, a, c1, r1) || !Self::check(crs, x, a, c2, r2) {
+            return None;
+        }
+        let inv: Option<C::Scalar> = (*c1 - *c2).invert().into();
+
+        Some(Witness::new((*r1 - *r2) * inv?))
+    }
+}
+
+impl<C, H> EncodableProof for DLEQProof<C, H>
+where
+    C: CurveArithmetic,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+    C::AffinePoint: DecompressPoint<C>,
+{
+    fn encode_proof(
+        _crs: &Self::CRS,
+        x: &Self::Statement,
+        p: &Self::Proof,
+        encoding: ProofEncoding,
+    ) -> Vec<u8> {
+        match encoding {
+            ProofEncoding::Commitments => p.to_byte_vector(),
+            ProofEncoding::ChallengeResponse => {
+                let c = compute_challenge::<C, H>(p.a, x);
+                let mut v: Vec<u8> = Vec::new();
+                v.append(&mut scalar_to_byte_vector::<C>(&c));
+                v.append(&mut scalar_to_byte_vector::<C>(&p.r));
+
+                v
+            }
+        }
+    }
+
+    fn decode_proof(
+        _crs: &Self::CRS,
+        x: &Self::Statement,
+        bytes: &[u8],
+        encoding: ProofEncoding,
+    ) -> Option<Self::Proof> {
+        let mut reader = ByteReader::new(bytes);
+        let p = match encoding {
+            ProofEncoding::Commitments => {
+                let a1 = reader.read_point::<C>()?;
+                let a2 = reader.read_point::<C>()?;
+                let r = reader.read_scalar::<C>()?;
+                Proof::<C> { a: (a1, a2), r }
+            }
+            ProofEncoding::ChallengeResponse => {
+                let c = reader.read_scalar::<C>()?;
+                let r = reader.read_scalar::<C>()?;
+                let a = (x.g1 * r - x.h1 * c, x.g2 * r - x.h2 * c);
+                if compute_challenge::<C, H>(a, x) != c {
+                    return None;
+                }
+                Proof::<C> { a, r }
+            }
+        };
+
+        reader.is_empty().then_some(p)
+    }
+}
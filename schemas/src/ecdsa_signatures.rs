@@ -1,5 +1,6 @@
 use crate::hard_relation::HardRelation;
 use crate::signature_scheme::SignatureScheme;
+use crate::utils::scalar_to_byte_vector;
 use digest::Digest;
 use elliptic_curve::ff::Field;
 use elliptic_curve::ops::Invert;
@@ -12,12 +13,25 @@ use elliptic_curve::Group;
 use rand_core::OsRng;
 use std::marker::PhantomData;
 
+// sig is r, the x coordinate of the point R = g^k, and proof is s = k^-1 (H(m) + r sk)
 pub struct ECDSASignature<C>
 where
     C: CurveArithmetic,
 {
-    sig: NonZeroScalar<C>,
-    proof: NonZeroScalar<C>,
+    pub sig: NonZeroScalar<C>,
+    pub proof: NonZeroScalar<C>,
+}
+
+impl<C> ECDSASignature<C>
+where
+    C: CurveArithmetic,
+{
+    pub fn to_byte_vector(&self) -> Vec<u8> {
+        let mut v: Vec<u8> = Vec::new();
+        v.append(&mut scalar_to_byte_vector::<C>(self.sig.as_ref()));
+        v.append(&mut scalar_to_byte_vector::<C>(self.proof.as_ref()));
+        v
+    }
 }
 
 pub struct ECDSA<C, H>
@@ -29,6 +43,37 @@ where
     _hash_marker: PhantomData<H>,
 }
 
+impl<C, H> ECDSA<C, H>
+where
+    C: CurveArithmetic,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+{
+    // The point R = g^(H(m) s^-1) pk^(r s^-1) of a signature, whose x coordinate is r if the
+    // signature is valid. It allows to check the signature with the equation R^s = g^H(m) pk^r,
+    // which is linear in s.
+    pub fn nonce_point(
+        pk: &C::ProjectivePoint,
+        msg: &str,
+        sig: &ECDSASignature<C>,
+    ) -> C::ProjectivePoint {
+        let h = Self::hash(msg);
+        let g = C::ProjectivePoint::generator();
+
+        let s1 = sig.proof.invert();
+        let s1 = s1.as_ref();
+        let sig = sig.sig.as_ref();
+
+        g * (h * s1) + (*pk) * ((*sig) * (*s1))
+    }
+
+    pub fn hash(msg: &str) -> C::Scalar {
+        let hasher = H::new();
+        let bytes = hasher.chain_update(msg).finalize();
+
+        <C::Scalar as Reduce<C::Uint>>::reduce_bytes(&bytes)
+    }
+}
+
 impl<C, H> SignatureScheme for ECDSA<C, H>
 where
     C: CurveArithmetic,
@@ -43,9 +88,7 @@ where
     }
 
     fn sign(sk: &Self::SK, msg: &str) -> Self::Signature {
-        let hasher = H::new();
-        let bytes = hasher.chain_update(msg).finalize();
-        let h = <C::Scalar as Reduce<C::Uint>>::reduce_bytes(&bytes);
+        let h = Self::hash(msg);
 
         let g = C::ProjectivePoint::generator();
 
@@ -70,16 +113,8 @@ where
     }
 
     fn verify(pk: &Self::PK, msg: &str, sig: &Self::Signature) -> bool {
-        let hasher = H::new();
-        let bytes = hasher.chain_update(msg).finalize();
-        let h = <C::Scalar as Reduce<C::Uint>>::reduce_bytes(&bytes);
-
-        let g = C::ProjectivePoint::generator();
-
-        let s1 = sig.proof.invert();
-        let s1 = s1.as_ref();
+        let R = Self::nonce_point(pk, msg, sig);
         let sig = sig.sig.as_ref();
-        let R = g * (h * s1) + (*pk) * ((*sig) * (*s1));
         let affine_R: AffinePoint<C> = R.into();
         // affine_R is very unlikely to be 0 because it is computed from R and:
         //      - g is not the identity
@@ -0,0 +1,163 @@
+use crate::hard_relation::HardRelation;
+use crate::public_key_encryption_scheme::PublicKeyEncryptionScheme;
+use crate::utils::{point_to_byte_vector, ByteReader};
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use elliptic_curve::point::DecompressPoint;
+use elliptic_curve::scalar::NonZeroScalar;
+use elliptic_curve::{CurveArithmetic, Group};
+use hkdf::Hkdf;
+use rand_core::OsRng;
+use sha2::Sha256;
+use std::marker::PhantomData;
+
+// ECIES: hybrid encryption of byte strings of any length under an ElGamal public key pk = g^sk.
+// The sender samples an ephemeral key pair (r, g^r), derives a symmetric key from the Diffie-
+// Hellman point pk^r with HKDF-SHA256 and encrypts the message with ChaCha20-Poly1305. The
+// receiver recomputes the Diffie-Hellman point as (g^r)^sk. Every encryption uses a fresh
+// ephemeral key, hence a fresh symmetric key, so the nonce of the AEAD can be fixed.
+pub struct ECIES<C>
+where
+    C: CurveArithmetic,
+{
+    _curve_marker: PhantomData<C>,
+}
+
+pub struct ECIESCypherText<C>
+where
+    C: CurveArithmetic,
+{
+    pub ephemeral_pk: C::ProjectivePoint,
+    pub ct: Vec<u8>,
+}
+
+impl<C> ECIESCypherText<C>
+where
+    C: CurveArithmetic,
+{
+    pub fn to_byte_vector(&self) -> Vec<u8> {
+        let mut v: Vec<u8> = Vec::new();
+        v.append(&mut point_to_byte_vector::<C>(&self.ephemeral_pk));
+        v.extend_from_slice(&self.ct);
+
+        v
+    }
+
+    pub fn from_byte_vector(bytes: &[u8]) -> Option<Self>
+    where
+        C::AffinePoint: DecompressPoint<C>,
+    {
+        let mut reader = ByteReader::new(bytes);
+        let ephemeral_pk = reader.read_point::<C>()?;
+        // the rest of the bytes are the cyphertext of the AEAD
+        let ct = reader.read_rest();
+
+        Some(Self {
+            ephemeral_pk,
+            ct: ct.to_vec(),
+        })
+    }
+}
+
+impl<C> ECIES<C>
+where
+    C: CurveArithmetic,
+{
+    // The key is bound to both the Diffie-Hellman point and the ephemeral public key.
+    fn key(dh: &C::ProjectivePoint, ephemeral_pk: &C::ProjectivePoint) -> ChaCha20Poly1305 {
+        let hkdf = Hkdf::<Sha256>::new(
+            Some(&point_to_byte_vector::<C>(ephemeral_pk)),
+            &point_to_byte_vector::<C>(dh),
+        );
+        let mut key = Key::default();
+        hkdf.expand(b"ecies_chacha20poly1305_key", &mut key)
+            .expect("32 bytes is a valid length for HKDF-SHA256");
+
+        ChaCha20Poly1305::new(&key)
+    }
+}
+
+impl<C> PublicKeyEncryptionScheme for ECIES<C>
+where
+    C: CurveArithmetic,
+{
+    type PK = C::ProjectivePoint;
+    type SK = NonZeroScalar<C>;
+    type Message = Vec<u8>;
+    type CypherText = ECIESCypherText<C>;
+    type Randomness = C::Scalar;
+
+    fn gen() -> (Self::SK, Self::PK) {
+        Self::SK::gen(&C::ProjectivePoint::generator())
+    }
+
+    fn enc(pk: &Self::PK, msg: &Self::Message) -> (Self::CypherText, Self::Randomness) {
+        let nz_r = NonZeroScalar::<C>::random(&mut OsRng);
+        let r = nz_r.as_ref();
+        let ephemeral_pk = C::ProjectivePoint::generator() * r;
+        let cipher = Self::key(&(*pk * r), &ephemeral_pk);
+        let ct = cipher
+            .encrypt(&Nonce::default(), msg.as_slice())
+            .expect("the message is shorter than the maximum length of ChaCha20-Poly1305");
+
+        (ECIESCypherText { ephemeral_pk, ct }, *r)
+    }
+
+    fn dec(sk: &Self::SK, ct: &Self::CypherText) -> Option<Self::Message> {
+        let cipher = Self::key(&(ct.ephemeral_pk * sk.as_ref()), &ct.ephemeral_pk);
+
+        cipher.decrypt(&Nonce::default(), ct.ct.as_slice()).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k256::Secp256k1;
+    use p256::NistP256;
+
+    type C = Secp256k1;
+
+    #[test]
+    fn ecies() {
+        let (sk, pk) = ECIES::<C>::gen();
+        let msg =
+            b"Lorem ipsum dolor sit amet, consectetur adipiscing elit, sed do eiusmod tempor \
+            incididunt ut labore et dolore magna aliqua"
+                .to_vec();
+        let (ct, _) = ECIES::<C>::enc(&pk, &msg);
+        assert_eq!(ECIES::<C>::dec(&sk, &ct).unwrap(), msg);
+
+        // the cyphertext is the ephemeral public key, the message and the 16 byte tag
+        let bytes = ct.to_byte_vector();
+        assert_eq!(bytes.len(), 33 + msg.len() + 16);
+        let decoded = ECIESCypherText::<C>::from_byte_vector(&bytes).unwrap();
+        assert_eq!(ECIES::<C>::dec(&sk, &decoded).unwrap(), msg);
+
+        let (other_sk, _) = ECIES::<C>::gen();
+        assert!(ECIES::<C>::dec(&other_sk, &ct).is_none());
+
+        let (sk, pk) = ECIES::<NistP256>::gen();
+        let (ct, _) = ECIES::<NistP256>::enc(&pk, &Vec::new());
+        assert_eq!(ECIES::<NistP256>::dec(&sk, &ct).unwrap(), Vec::<u8>::new());
+    }
+
+    #[test]
+    fn ecies_tampering() {
+        let (sk, pk) = ECIES::<C>::gen();
+        let (ct, _) = ECIES::<C>::enc(&pk, &b"message".to_vec());
+
+        let mut tampered = ECIESCypherText::<C> {
+            ephemeral_pk: ct.ephemeral_pk,
+            ct: ct.ct.clone(),
+        };
+        tampered.ct[0] ^= 1;
+        assert!(ECIES::<C>::dec(&sk, &tampered).is_none());
+
+        let tampered = ECIESCypherText::<C> {
+            ephemeral_pk: ct.ephemeral_pk + <C as CurveArithmetic>::ProjectivePoint::GENERATOR,
+            ct: ct.ct,
+        };
+        assert!(ECIES::<C>::dec(&sk, &tampered).is_none());
+    }
+}
@@ -1,9 +1,13 @@
 use crate::hard_relation::HardRelation;
 use crate::public_key_encryption_scheme::PublicKeyEncryptionScheme;
+use crate::utils::{point_from_byte_vector, point_to_byte_vector};
+use elliptic_curve::point::DecompressPoint;
 use elliptic_curve::scalar::NonZeroScalar;
 use elliptic_curve::CurveArithmetic;
+use elliptic_curve::FieldBytes;
 use elliptic_curve::Group;
 use rand_core::OsRng;
+use std::collections::HashMap;
 use std::marker::PhantomData;
 
 pub struct ElGamal<C>
@@ -35,11 +39,321 @@ where
 
         ((g * y, s + msg), *y)
     }
-    fn dec(sk: &Self::SK, ct: &Self::CypherText) -> Self::Message {
+    fn dec(sk: &Self::SK, ct: &Self::CypherText) -> Option<Self::Message> {
         let (a, b) = ct;
         let sk = sk.as_ref();
         let msg = *b - *a * *sk;
 
-        msg
+        Some(msg)
+    }
+}
+
+impl<C> ElGamal<C>
+where
+    C: CurveArithmetic,
+{
+    // ct * Enc(pk, 1; r), an encryption of the same message with randomness y + r
+    pub fn rerandomize_with(
+        pk: &C::ProjectivePoint,
+        ct: &(C::ProjectivePoint, C::ProjectivePoint),
+        r: &C::Scalar,
+    ) -> (C::ProjectivePoint, C::ProjectivePoint) {
+        let g = C::ProjectivePoint::generator();
+
+        (ct.0 + g * r, ct.1 + *pk * r)
+    }
+
+    // fresh encryption of the same message, unlinkable to ct without the secret key. It returns
+    // the randomness added to the one of ct, which is the witness of a ReEncryptionProof.
+    pub fn rerandomize(
+        pk: &C::ProjectivePoint,
+        ct: &(C::ProjectivePoint, C::ProjectivePoint),
+    ) -> ((C::ProjectivePoint, C::ProjectivePoint), C::Scalar) {
+        let nz_r = NonZeroScalar::<C>::random(&mut OsRng);
+        let r = nz_r.as_ref();
+
+        (Self::rerandomize_with(pk, ct, r), *r)
+    }
+}
+
+// Encoding of short byte strings as points, so that they can be encrypted with ElGamal. The x
+// coordinate of the point of msg is the length of msg (one byte), msg, zero padding and a
+// counter (the last byte), which is increased until x is the abscissa of a point of the curve.
+// Since the length is at most 30 on the 32 byte fields of the curves we use, x is always smaller
+// than the modulus of the field.
+impl<C> ElGamal<C>
+where
+    C: CurveArithmetic,
+    C::AffinePoint: DecompressPoint<C>,
+{
+    pub fn max_message_len() -> usize {
+        FieldBytes::<C>::default().len() - 2
+    }
+
+    // None if msg is longer than max_message_len
+    pub fn encode_message(msg: &[u8]) -> Option<C::ProjectivePoint> {
+        if msg.len() > Self::max_message_len() {
+            return None;
+        }
+        let mut bytes = vec![0u8; FieldBytes::<C>::default().len() + 1];
+        bytes[0] = msg.len() as u8;
+        bytes[1..=msg.len()].copy_from_slice(msg);
+
+        let counter = bytes.len() - 2;
+        (0..=u8::MAX).find_map(|i| {
+            bytes[counter] = i;
+            point_from_byte_vector::<C>(&bytes)
+        })
+    }
+
+    // inverse of encode_message, None if point is not the encoding of a message
+    pub fn decode_message(point: &C::ProjectivePoint) -> Option<Vec<u8>> {
+        let bytes = point_to_byte_vector::<C>(point);
+        let len = bytes[0] as usize;
+        if len > Self::max_message_len() || bytes[len + 1..bytes.len() - 2].iter().any(|b| *b != 0)
+        {
+            return None;
+        }
+
+        Some(bytes[1..=len].to_vec())
+    }
+}
+
+// Exponential ElGamal: the message m is a small integer encrypted as the group element g^m, so
+// the scheme is additively homomorphic: the product of the encryptions of m1 and m2 is an
+// encryption of m1 + m2 and the power k of the encryption of m is an encryption of k * m.
+// Decryption recovers g^m and then m with a baby-step giant-step search, which is only feasible
+// if m is smaller than a known bound. PublicKeyEncryptionScheme::dec uses DEFAULT_BOUND and
+// dec_bounded lets the caller choose the bound, both return None for larger messages.
+pub struct ExponentialElGamal<C>
+where
+    C: CurveArithmetic,
+{
+    _curve_marker: PhantomData<C>,
+}
+
+pub const DEFAULT_BOUND: u64 = 1 << 24;
+
+impl<C> ExponentialElGamal<C>
+where
+    C: CurveArithmetic,
+{
+    pub fn enc_with(
+        pk: &C::ProjectivePoint,
+        msg: u64,
+        r: &C::Scalar,
+    ) -> (C::ProjectivePoint, C::ProjectivePoint) {
+        let g = C::ProjectivePoint::generator();
+
+        (g * r, *pk * r + g * C::Scalar::from(msg))
+    }
+
+    // returns None if the message is not smaller than the bound of the table
+    pub fn dec_bounded(
+        sk: &NonZeroScalar<C>,
+        ct: &(C::ProjectivePoint, C::ProjectivePoint),
+        table: &BabyStepGiantStep<C>,
+    ) -> Option<u64> {
+        let g_m = ElGamal::<C>::dec(sk, ct)?;
+
+        table.dlog(&g_m)
+    }
+
+    // encryption of m1 + m2 from encryptions of m1 and m2
+    pub fn add(
+        ct1: &(C::ProjectivePoint, C::ProjectivePoint),
+        ct2: &(C::ProjectivePoint, C::ProjectivePoint),
+    ) -> (C::ProjectivePoint, C::ProjectivePoint) {
+        (ct1.0 + ct2.0, ct1.1 + ct2.1)
+    }
+
+    // encryption of k * m from an encryption of m
+    pub fn mul(
+        ct: &(C::ProjectivePoint, C::ProjectivePoint),
+        k: u64,
+    ) -> (C::ProjectivePoint, C::ProjectivePoint) {
+        let k = C::Scalar::from(k);
+
+        (ct.0 * k, ct.1 * k)
+    }
+
+    // fresh encryption of the same message, unlinkable to ct. It returns the randomness added to
+    // the one of ct.
+    pub fn rerandomize(
+        pk: &C::ProjectivePoint,
+        ct: &(C::ProjectivePoint, C::ProjectivePoint),
+    ) -> ((C::ProjectivePoint, C::ProjectivePoint), C::Scalar) {
+        ElGamal::<C>::rerandomize(pk, ct)
+    }
+}
+
+impl<C> PublicKeyEncryptionScheme for ExponentialElGamal<C>
+where
+    C: CurveArithmetic,
+{
+    type PK = C::ProjectivePoint;
+    type SK = NonZeroScalar<C>;
+    type Message = u64;
+    type CypherText = (C::ProjectivePoint, C::ProjectivePoint);
+    type Randomness = C::Scalar;
+
+    fn gen() -> (Self::SK, Self::PK) {
+        ElGamal::<C>::gen()
+    }
+
+    fn enc(pk: &Self::PK, msg: &Self::Message) -> (Self::CypherText, Self::Randomness) {
+        let nz_r = NonZeroScalar::<C>::random(&mut OsRng);
+        let r = nz_r.as_ref();
+
+        (Self::enc_with(pk, *msg, r), *r)
+    }
+    fn dec(sk: &Self::SK, ct: &Self::CypherText) -> Option<Self::Message> {
+        let table = BabyStepGiantStep::<C>::new(DEFAULT_BOUND);
+
+        Self::dec_bounded(sk, ct, &table)
+    }
+}
+
+// Baby-step giant-step search of the discrete logarithm m in [0, bound) of g^m. With
+// n = ceil(sqrt(bound)), the table stores the baby steps g^j for 0 < j < n, and the search
+// computes the giant steps g^m / g^(i n) until one of them is in the table, so both the table
+// and the search take O(sqrt(bound)) group operations. The table only depends on the bound, so
+// it can be computed once and used for many decryptions.
+pub struct BabyStepGiantStep<C>
+where
+    C: CurveArithmetic,
+{
+    bound: u64,
+    n: u64,
+    // the identity (j = 0) is not in the table, its encoding is checked separately
+    baby_steps: HashMap<Vec<u8>, u64>,
+    giant_step: C::ProjectivePoint,
+}
+
+impl<C> BabyStepGiantStep<C>
+where
+    C: CurveArithmetic,
+{
+    pub fn new(bound: u64) -> Self {
+        let n = (bound as f64).sqrt().ceil() as u64;
+        let g = C::ProjectivePoint::generator();
+        let mut baby_steps = HashMap::new();
+        let mut point = C::ProjectivePoint::identity();
+        for j in 1..n {
+            point += g;
+            baby_steps.insert(point_to_byte_vector::<C>(&point), j);
+        }
+
+        Self {
+            bound,
+            n,
+            baby_steps,
+            giant_step: -(g * C::Scalar::from(n)),
+        }
+    }
+
+    pub fn bound(&self) -> u64 {
+        self.bound
+    }
+
+    // m such that point = g^m, or None if there is no such m smaller than the bound
+    pub fn dlog(&self, point: &C::ProjectivePoint) -> Option<u64> {
+        let mut point = *point;
+        for i in 0..self.n {
+            let j = if bool::from(point.is_identity()) {
+                Some(0)
+            } else {
+                self.baby_steps
+                    .get(&point_to_byte_vector::<C>(&point))
+                    .copied()
+            };
+            if let Some(j) = j {
+                let m = i * self.n + j;
+                return (m < self.bound).then_some(m);
+            }
+            point += self.giant_step;
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k256::Secp256k1;
+    use p256::NistP256;
+
+    type C = Secp256k1;
+    type Point = <C as CurveArithmetic>::ProjectivePoint;
+    type Scalar = <C as CurveArithmetic>::Scalar;
+    type ExpElGamal = ExponentialElGamal<C>;
+
+    #[test]
+    fn message_encoding() {
+        let (sk, pk) = ElGamal::<C>::gen();
+        for msg in [&b""[..], b"hello", &[0xff; 30]] {
+            let point = ElGamal::<C>::encode_message(msg).unwrap();
+            let (ct, _) = ElGamal::<C>::enc(&pk, &point);
+            let decrypted = ElGamal::<C>::dec(&sk, &ct).unwrap();
+            assert_eq!(ElGamal::<C>::decode_message(&decrypted).unwrap(), msg);
+        }
+        assert!(ElGamal::<C>::encode_message(&[0; 31]).is_none());
+        assert!(ElGamal::<NistP256>::encode_message(b"hello").is_some());
+        assert!(ElGamal::<C>::decode_message(&(Point::GENERATOR * Scalar::from(7u64))).is_none());
+    }
+
+    #[test]
+    fn baby_step_giant_step() {
+        let g = Point::GENERATOR;
+        let table = BabyStepGiantStep::<C>::new(1000);
+
+        for m in [0, 1, 31, 32, 33, 500, 999] {
+            assert_eq!(table.dlog(&(g * Scalar::from(m))), Some(m));
+        }
+        assert_eq!(table.dlog(&(g * Scalar::from(1000u64))), None);
+        assert_eq!(table.dlog(&(g * Scalar::from(5000u64))), None);
+        assert_eq!(table.dlog(&(-g)), None);
+    }
+
+    #[test]
+    fn exponential_elgamal() {
+        let (sk, pk) = ExpElGamal::gen();
+        let (ct, _) = ExpElGamal::enc(&pk, &1234);
+        assert_eq!(ExpElGamal::dec(&sk, &ct), Some(1234));
+
+        let table = BabyStepGiantStep::<C>::new(100);
+        assert_eq!(ExpElGamal::dec_bounded(&sk, &ct, &table), None);
+        let (ct, _) = ExpElGamal::enc(&pk, &99);
+        assert_eq!(ExpElGamal::dec_bounded(&sk, &ct, &table), Some(99));
+    }
+
+    #[test]
+    fn exponential_elgamal_homomorphism() {
+        let (sk, pk) = ExpElGamal::gen();
+        let table = BabyStepGiantStep::<C>::new(1 << 16);
+
+        // tally of the votes 0, 1, 1, 0, 1
+        let tally = [0, 1, 1, 0, 1]
+            .iter()
+            .map(|v| ExpElGamal::enc(&pk, v).0)
+            .reduce(|acc, ct| ExpElGamal::add(&acc, &ct))
+            .unwrap();
+        assert_eq!(ExpElGamal::dec_bounded(&sk, &tally, &table), Some(3));
+
+        // aggregate price of 7 items of price 120 and 3 items of price 45
+        let (ct1, _) = ExpElGamal::enc(&pk, &120);
+        let (ct2, _) = ExpElGamal::enc(&pk, &45);
+        let total = ExpElGamal::add(&ExpElGamal::mul(&ct1, 7), &ExpElGamal::mul(&ct2, 3));
+        assert_eq!(ExpElGamal::dec_bounded(&sk, &total, &table), Some(975));
+
+        let (ct, r) = ExpElGamal::enc(&pk, &42);
+        let (rerandomized, r2) = ExpElGamal::rerandomize(&pk, &ct);
+        assert!(rerandomized != ct);
+        assert!(rerandomized == ExpElGamal::enc_with(&pk, 42, &(r + r2)));
+        assert_eq!(
+            ExpElGamal::dec_bounded(&sk, &rerandomized, &table),
+            Some(42)
+        );
     }
 }
@@ -0,0 +1,81 @@
+use crate::commitment_scheme::CommitmentScheme;
+use digest::Digest;
+use rand_core::{OsRng, RngCore};
+use std::marker::PhantomData;
+
+// Commitments to byte strings with a hash function: commit(msg; salt) = H(tag || salt || msg).
+// The public parameter is the tag, which separates the commitments of the different uses of the
+// scheme. It is prefixed with its length so that no pair (tag, salt) is a prefix of another one.
+// The scheme is binding if H is collision resistant and hiding if H behaves as a random oracle,
+// thanks to the uniform salt.
+
+pub const SALT_BYTES: usize = 32;
+
+pub struct HashCommitment<H>
+where
+    H: Digest,
+{
+    _hash_marker: PhantomData<H>,
+}
+
+impl<H> CommitmentScheme for HashCommitment<H>
+where
+    H: Digest,
+{
+    type PP = Vec<u8>;
+    type Message = Vec<u8>;
+    type Randomness = [u8; SALT_BYTES];
+    type Commitment = Vec<u8>;
+
+    fn commit(pp: &Self::PP, msg: &Self::Message) -> (Self::Commitment, Self::Randomness) {
+        let mut salt = [0u8; SALT_BYTES];
+        OsRng.fill_bytes(&mut salt);
+
+        (Self::commit_with(pp, msg, &salt), salt)
+    }
+
+    fn commit_with(pp: &Self::PP, msg: &Self::Message, r: &Self::Randomness) -> Self::Commitment {
+        H::new()
+            .chain_update((pp.len() as u64).to_be_bytes())
+            .chain_update(pp)
+            .chain_update(r)
+            .chain_update(msg)
+            .finalize()
+            .to_vec()
+    }
+
+    fn verify(
+        pp: &Self::PP,
+        c: &Self::Commitment,
+        msg: &Self::Message,
+        r: &Self::Randomness,
+    ) -> bool {
+        Self::commit_with(pp, msg, r) == *c
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sha2::Sha256;
+
+    type Com = HashCommitment<Sha256>;
+
+    #[test]
+    fn hash_commitment() {
+        let tag = b"tag".to_vec();
+        let msg = b"message".to_vec();
+        let (c, salt) = Com::commit(&tag, &msg);
+
+        assert!(Com::verify(&tag, &c, &msg, &salt));
+        assert!(!Com::verify(&tag, &c, &b"massage".to_vec(), &salt));
+        assert!(!Com::verify(&b"other tag".to_vec(), &c, &msg, &salt));
+        let mut other_salt = salt;
+        other_salt[0] ^= 1;
+        assert!(!Com::verify(&tag, &c, &msg, &other_salt));
+
+        // the same message is committed to different values
+        let (c2, _) = Com::commit(&tag, &msg);
+        assert_ne!(c, c2);
+    }
+}
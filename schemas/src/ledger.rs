@@ -0,0 +1,590 @@
+use crate::adaptor_signatures::AdaptorSignatureScheme;
+use crate::signature_scheme::SignatureScheme;
+use crate::utils::point_to_byte_vector;
+use elliptic_curve::CurveArithmetic;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::marker::PhantomData;
+
+// An in-process ledger to run the selling protocols against. It keeps a UTXO set: every output
+// holds an amount and can be spent through any of its branches, where a branch requires a
+// signature under each of its keys and, optionally, that a number of blocks have been mined since
+// the output was confirmed. Transactions are signed on the hex encoding of their id, which does
+// not cover the signatures, so that the ids of a chain of transactions are known before any of
+// them is signed. Submitted transactions wait in the mempool until the next block is mined.
+
+type Point<C> = <C as CurveArithmetic>::ProjectivePoint;
+
+pub type TxId = [u8; 32];
+
+// A simulated clock, counting the ticks (for instance blocks) of the ledger that both parties
+// observe. Timelocks are measured with it.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Clock {
+    now: u64,
+}
+
+impl Clock {
+    pub fn new() -> Self {
+        Self { now: 0 }
+    }
+
+    pub fn now(&self) -> u64 {
+        self.now
+    }
+
+    pub fn advance(&mut self, ticks: u64) {
+        self.now += ticks;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LedgerError {
+    // The transaction has no inputs, or not one witness per input
+    Malformed,
+    // An input refers to an output that does not exist
+    UnknownOutput,
+    // An input refers to an output spent on the ledger, in the mempool or twice in the transaction
+    AlreadySpent,
+    // A witness selects a branch that the output does not have
+    InvalidBranch,
+    // A witness selects a branch whose timelock has not expired yet
+    TimelockNotExpired,
+    // A witness does not hold one valid signature per key of its branch
+    InvalidSignature,
+    // The outputs of the transaction are worth more than its inputs
+    InsufficientFunds,
+}
+
+impl fmt::Display for LedgerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let description = match self {
+            LedgerError::Malformed => "the transaction is malformed",
+            LedgerError::UnknownOutput => "an input refers to an unknown output",
+            LedgerError::AlreadySpent => "an input refers to a spent output",
+            LedgerError::InvalidBranch => "a witness selects an unknown branch",
+            LedgerError::TimelockNotExpired => "the timelock of a branch has not expired yet",
+            LedgerError::InvalidSignature => "a signature of a witness is not valid",
+            LedgerError::InsufficientFunds => "the outputs are worth more than the inputs",
+        };
+
+        write!(f, "{}", description)
+    }
+}
+
+impl std::error::Error for LedgerError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct OutPoint {
+    pub txid: TxId,
+    pub index: u32,
+}
+
+impl OutPoint {
+    pub fn new(txid: TxId, index: u32) -> Self {
+        Self { txid, index }
+    }
+
+    fn to_byte_vector(self) -> Vec<u8> {
+        let mut v: Vec<u8> = self.txid.to_vec();
+        v.extend_from_slice(&self.index.to_be_bytes());
+
+        v
+    }
+}
+
+// A key of a branch, together with the scheme its signatures are verified with: a plain
+// signature, or a signature adapted from a pre-signature.
+#[derive(Debug, Clone, Copy)]
+pub enum Key<C>
+where
+    C: CurveArithmetic,
+{
+    Signature(Point<C>),
+    Adaptor(Point<C>),
+}
+
+#[derive(Debug, Clone)]
+pub struct Branch<C>
+where
+    C: CurveArithmetic,
+{
+    pub keys: Vec<Key<C>>,
+    // number of blocks to wait after the output is confirmed, 0 for no timelock
+    pub timelock: u64,
+}
+
+impl<C> Branch<C>
+where
+    C: CurveArithmetic,
+{
+    pub fn new(keys: Vec<Key<C>>, timelock: u64) -> Self {
+        Self { keys, timelock }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Output<C>
+where
+    C: CurveArithmetic,
+{
+    pub amount: u64,
+    pub branches: Vec<Branch<C>>,
+}
+
+impl<C> Output<C>
+where
+    C: CurveArithmetic,
+{
+    pub fn new(amount: u64, branches: Vec<Branch<C>>) -> Self {
+        Self { amount, branches }
+    }
+
+    // An output spendable by a plain signature under pk
+    pub fn to_key(amount: u64, pk: Point<C>) -> Self {
+        Self::new(amount, vec![Branch::new(vec![Key::Signature(pk)], 0)])
+    }
+
+    fn to_byte_vector(&self) -> Vec<u8> {
+        let mut v: Vec<u8> = Vec::new();
+        v.extend_from_slice(&self.amount.to_be_bytes());
+        v.extend_from_slice(&(self.branches.len() as u32).to_be_bytes());
+        for branch in self.branches.iter() {
+            v.extend_from_slice(&branch.timelock.to_be_bytes());
+            v.extend_from_slice(&(branch.keys.len() as u32).to_be_bytes());
+            for key in branch.keys.iter() {
+                let (tag, pk) = match key {
+                    Key::Signature(pk) => (0u8, pk),
+                    Key::Adaptor(pk) => (1u8, pk),
+                };
+                v.push(tag);
+                v.append(&mut point_to_byte_vector::<C>(pk));
+            }
+        }
+
+        v
+    }
+}
+
+// The branch of the spent output and one signature per key of the branch, in the same order
+#[derive(Debug, Clone)]
+pub struct Witness<Sig> {
+    pub branch: usize,
+    pub signatures: Vec<Sig>,
+}
+
+impl<Sig> Witness<Sig> {
+    pub fn new(branch: usize, signatures: Vec<Sig>) -> Self {
+        Self { branch, signatures }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Transaction<C, Sig>
+where
+    C: CurveArithmetic,
+{
+    pub inputs: Vec<OutPoint>,
+    pub outputs: Vec<Output<C>>,
+    pub witnesses: Vec<Witness<Sig>>,
+}
+
+impl<C, Sig> Transaction<C, Sig>
+where
+    C: CurveArithmetic,
+{
+    // An unsigned transaction: the witnesses are added once it is signed
+    pub fn new(inputs: Vec<OutPoint>, outputs: Vec<Output<C>>) -> Self {
+        Self {
+            inputs,
+            outputs,
+            witnesses: Vec::new(),
+        }
+    }
+
+    pub fn txid(&self) -> TxId {
+        let mut hasher = Sha256::new().chain_update(b"ledger_transaction");
+        hasher.update((self.inputs.len() as u32).to_be_bytes());
+        for input in self.inputs.iter() {
+            hasher.update(input.to_byte_vector());
+        }
+        hasher.update((self.outputs.len() as u32).to_be_bytes());
+        for output in self.outputs.iter() {
+            hasher.update(output.to_byte_vector());
+        }
+
+        hasher.finalize().into()
+    }
+
+    // The message signed by the witnesses
+    pub fn message(&self) -> String {
+        self.txid().iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    pub fn outpoint(&self, index: u32) -> OutPoint {
+        OutPoint::new(self.txid(), index)
+    }
+}
+
+pub struct Block<C, Sig>
+where
+    C: CurveArithmetic,
+{
+    pub height: u64,
+    pub transactions: Vec<Transaction<C, Sig>>,
+}
+
+struct Utxo<C>
+where
+    C: CurveArithmetic,
+{
+    output: Output<C>,
+    height: u64,
+}
+
+// S verifies the signatures under Key::Signature and A the ones under Key::Adaptor
+pub struct Ledger<C, S, A>
+where
+    C: CurveArithmetic,
+    S: SignatureScheme<PK = Point<C>>,
+    A: AdaptorSignatureScheme<PK = Point<C>, Signature = S::Signature>,
+{
+    clock: Clock,
+    blocks: Vec<Block<C, S::Signature>>,
+    mempool: Vec<Transaction<C, S::Signature>>,
+    utxos: HashMap<OutPoint, Utxo<C>>,
+    // where each confirmed transaction is, as (height, position in the block)
+    index: HashMap<TxId, (u64, usize)>,
+    // the confirmed transaction that spends each spent output
+    spenders: HashMap<OutPoint, TxId>,
+    minted: u64,
+    _adaptor_marker: PhantomData<A>,
+}
+
+impl<C, S, A> Default for Ledger<C, S, A>
+where
+    C: CurveArithmetic,
+    S: SignatureScheme<PK = Point<C>>,
+    A: AdaptorSignatureScheme<PK = Point<C>, Signature = S::Signature>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C, S, A> Ledger<C, S, A>
+where
+    C: CurveArithmetic,
+    S: SignatureScheme<PK = Point<C>>,
+    A: AdaptorSignatureScheme<PK = Point<C>, Signature = S::Signature>,
+{
+    pub fn new() -> Self {
+        Self {
+            clock: Clock::new(),
+            blocks: Vec::new(),
+            mempool: Vec::new(),
+            utxos: HashMap::new(),
+            index: HashMap::new(),
+            spenders: HashMap::new(),
+            minted: 0,
+            _adaptor_marker: PhantomData,
+        }
+    }
+
+    // The number of blocks mined so far
+    pub fn height(&self) -> u64 {
+        self.clock.now()
+    }
+
+    pub fn clock(&self) -> &Clock {
+        &self.clock
+    }
+
+    // Creates an output out of thin air, confirmed at the current height, to fund the parties
+    pub fn mint(&mut self, output: Output<C>) -> OutPoint {
+        let txid: TxId = Sha256::new()
+            .chain_update(b"ledger_mint")
+            .chain_update(self.minted.to_be_bytes())
+            .finalize()
+            .into();
+        self.minted += 1;
+        let outpoint = OutPoint::new(txid, 0);
+        self.utxos.insert(
+            outpoint,
+            Utxo {
+                output,
+                height: self.height(),
+            },
+        );
+
+        outpoint
+    }
+
+    // Checks the transaction against the UTXO set and the mempool, and adds it to the mempool so
+    // that it is confirmed by the next block
+    pub fn submit(&mut self, tx: Transaction<C, S::Signature>) -> Result<TxId, LedgerError> {
+        if tx.inputs.is_empty() || tx.inputs.len() != tx.witnesses.len() {
+            return Err(LedgerError::Malformed);
+        }
+        let in_mempool: HashSet<&OutPoint> = self
+            .mempool
+            .iter()
+            .flat_map(|tx| tx.inputs.iter())
+            .collect();
+        let mut in_tx: HashSet<&OutPoint> = HashSet::new();
+        let msg = tx.message();
+        let mut amount: u64 = 0;
+        for (input, witness) in tx.inputs.iter().zip(tx.witnesses.iter()) {
+            if self.spenders.contains_key(input) || in_mempool.contains(input) {
+                return Err(LedgerError::AlreadySpent);
+            }
+            if !in_tx.insert(input) {
+                return Err(LedgerError::AlreadySpent);
+            }
+            let utxo = self.utxos.get(input).ok_or(LedgerError::UnknownOutput)?;
+            let branch = utxo
+                .output
+                .branches
+                .get(witness.branch)
+                .ok_or(LedgerError::InvalidBranch)?;
+            // the transaction is confirmed in the block at height self.height() + 1
+            if self.height() + 1 < utxo.height.saturating_add(branch.timelock) {
+                return Err(LedgerError::TimelockNotExpired);
+            }
+            if branch.keys.len() != witness.signatures.len() {
+                return Err(LedgerError::InvalidSignature);
+            }
+            let valid =
+                branch
+                    .keys
+                    .iter()
+                    .zip(witness.signatures.iter())
+                    .all(|(key, sig)| match key {
+                        Key::Signature(pk) => S::verify(pk, &msg, sig),
+                        Key::Adaptor(pk) => A::verify(pk, &msg, sig),
+                    });
+            if !valid {
+                return Err(LedgerError::InvalidSignature);
+            }
+            amount = amount.saturating_add(utxo.output.amount);
+        }
+        let spent = tx
+            .outputs
+            .iter()
+            .try_fold(0u64, |acc, output| acc.checked_add(output.amount))
+            .ok_or(LedgerError::InsufficientFunds)?;
+        if spent > amount {
+            return Err(LedgerError::InsufficientFunds);
+        }
+        let txid = tx.txid();
+        self.mempool.push(tx);
+
+        Ok(txid)
+    }
+
+    // Confirms the transactions of the mempool in a new block, and returns its height
+    pub fn mine(&mut self) -> u64 {
+        self.clock.advance(1);
+        let height = self.height();
+        let transactions: Vec<Transaction<C, S::Signature>> = self.mempool.drain(..).collect();
+        for (position, tx) in transactions.iter().enumerate() {
+            let txid = tx.txid();
+            for input in tx.inputs.iter() {
+                self.utxos.remove(input);
+                self.spenders.insert(*input, txid);
+            }
+            for (i, output) in tx.outputs.iter().enumerate() {
+                self.utxos.insert(
+                    OutPoint::new(txid, i as u32),
+                    Utxo {
+                        output: output.clone(),
+                        height,
+                    },
+                );
+            }
+            self.index.insert(txid, (height, position));
+        }
+        self.blocks.push(Block {
+            height,
+            transactions,
+        });
+
+        height
+    }
+
+    pub fn block(&self, height: u64) -> Option<&Block<C, S::Signature>> {
+        let i = usize::try_from(height.checked_sub(1)?).ok()?;
+
+        self.blocks.get(i)
+    }
+
+    // The confirmed transaction with id txid
+    pub fn transaction(&self, txid: &TxId) -> Option<&Transaction<C, S::Signature>> {
+        let (height, position) = self.index.get(txid)?;
+
+        self.block(*height)?.transactions.get(*position)
+    }
+
+    // The height of the block that confirmed the transaction with id txid
+    pub fn confirmation_height(&self, txid: &TxId) -> Option<u64> {
+        self.index.get(txid).map(|(height, _)| *height)
+    }
+
+    // The output at outpoint, if it is confirmed and unspent
+    pub fn unspent_output(&self, outpoint: &OutPoint) -> Option<&Output<C>> {
+        self.utxos.get(outpoint).map(|utxo| &utxo.output)
+    }
+
+    // The confirmed transaction that spends the output at outpoint
+    pub fn spender(&self, outpoint: &OutPoint) -> Option<&Transaction<C, S::Signature>> {
+        self.transaction(self.spenders.get(outpoint)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hard_relation::HardRelation;
+    use crate::schnorr_adaptor_signatures::SchnorrAdaptorSignature;
+    use crate::schnorr_signatures::{SchnorrSignature, SchnorrSignatureScheme};
+    use elliptic_curve::scalar::NonZeroScalar;
+    use k256::Secp256k1;
+
+    type C = Secp256k1;
+    type Sig = SchnorrSignatureScheme<C, Sha256>;
+    type ASig = SchnorrAdaptorSignature<C, Sha256>;
+    type TestLedger = Ledger<C, Sig, ASig>;
+    type Tx = Transaction<C, SchnorrSignature<C>>;
+
+    const TIMEOUT: u64 = 10;
+
+    #[test]
+    fn ledger_transfer() {
+        let mut ledger = TestLedger::new();
+        let (sk_a, pk_a) = Sig::gen();
+        let (_, pk_b) = Sig::gen();
+        let coins = ledger.mint(Output::to_key(100, pk_a));
+
+        let mut tx: Tx = Tx::new(vec![coins], vec![Output::to_key(101, pk_b)]);
+        tx.witnesses = vec![Witness::new(0, vec![Sig::sign(&sk_a, &tx.message())])];
+        assert_eq!(ledger.submit(tx), Err(LedgerError::InsufficientFunds));
+
+        let mut tx: Tx = Tx::new(vec![coins], vec![Output::to_key(90, pk_b)]);
+        let (sk_c, _) = Sig::gen();
+        tx.witnesses = vec![Witness::new(0, vec![Sig::sign(&sk_c, &tx.message())])];
+        assert_eq!(
+            ledger.submit(tx.clone()),
+            Err(LedgerError::InvalidSignature)
+        );
+        tx.witnesses = vec![Witness::new(0, vec![Sig::sign(&sk_a, &tx.message())])];
+        let txid = ledger.submit(tx.clone()).unwrap();
+        assert_eq!(ledger.submit(tx.clone()), Err(LedgerError::AlreadySpent));
+        assert!(ledger.transaction(&txid).is_none());
+
+        assert_eq!(ledger.mine(), 1);
+        assert_eq!(ledger.confirmation_height(&txid), Some(1));
+        assert!(ledger.unspent_output(&coins).is_none());
+        assert_eq!(ledger.unspent_output(&tx.outpoint(0)).unwrap().amount, 90);
+        assert_eq!(ledger.spender(&coins).unwrap().txid(), txid);
+        assert_eq!(ledger.block(1).unwrap().transactions.len(), 1);
+        assert_eq!(ledger.submit(tx), Err(LedgerError::AlreadySpent));
+
+        let mut tx: Tx = Tx::new(vec![OutPoint::new(txid, 1)], vec![Output::to_key(1, pk_a)]);
+        tx.witnesses = vec![Witness::new(0, Vec::new())];
+        assert_eq!(ledger.submit(tx), Err(LedgerError::UnknownOutput));
+    }
+
+    #[test]
+    fn ledger_two_of_two_and_timelock() {
+        let mut ledger = TestLedger::new();
+        let (sk_a, pk_a) = Sig::gen();
+        let (sk_b, pk_b) = ASig::gen();
+        let (sk_s, pk_s) = Sig::gen();
+
+        // (pk_b && pk_s) || (pk_a + TIMEOUT)
+        let lock = Output::new(
+            100,
+            vec![
+                Branch::new(vec![Key::Adaptor(pk_b), Key::Signature(pk_s)], 0),
+                Branch::new(vec![Key::Signature(pk_a)], TIMEOUT),
+            ],
+        );
+        let coins = ledger.mint(lock);
+        ledger.mine();
+
+        let tx_pay: Tx = Tx::new(vec![coins], vec![Output::to_key(100, pk_s)]);
+        let tx_recover: Tx = Tx::new(vec![coins], vec![Output::to_key(100, pk_a)]);
+
+        // the recover branch is timelocked
+        let mut tx = tx_recover.clone();
+        tx.witnesses = vec![Witness::new(1, vec![Sig::sign(&sk_a, &tx.message())])];
+        assert_eq!(
+            ledger.submit(tx.clone()),
+            Err(LedgerError::TimelockNotExpired)
+        );
+        let mut tx = tx_recover.clone();
+        tx.witnesses = vec![Witness::new(2, Vec::new())];
+        assert_eq!(ledger.submit(tx), Err(LedgerError::InvalidBranch));
+
+        // the pay branch needs both signatures, the one of pk_b adapted from a pre-signature
+        let (w, x) = NonZeroScalar::<C>::gen(&Point::<C>::GENERATOR);
+        let pre_signature = ASig::pre_sign(&sk_b, &tx_pay.message(), &x);
+        let adapted = ASig::adapt(&pk_b, &pre_signature, &w);
+        let mut tx = tx_pay.clone();
+        tx.witnesses = vec![Witness::new(0, vec![adapted.clone()])];
+        assert_eq!(ledger.submit(tx), Err(LedgerError::InvalidSignature));
+        let mut tx = tx_pay.clone();
+        tx.witnesses = vec![Witness::new(
+            0,
+            vec![pre_signature.clone(), Sig::sign(&sk_s, &tx.message())],
+        )];
+        assert_eq!(ledger.submit(tx), Err(LedgerError::InvalidSignature));
+        let mut tx = tx_pay.clone();
+        tx.witnesses = vec![Witness::new(
+            0,
+            vec![adapted, Sig::sign(&sk_s, &tx.message())],
+        )];
+        ledger.submit(tx).unwrap();
+        ledger.mine();
+
+        // the witness is extracted from the published transaction
+        let published = ledger.spender(&coins).unwrap();
+        let signature = &published.witnesses[0].signatures[0];
+        let extracted_w = ASig::extract(&pk_b, &pre_signature, signature).unwrap();
+        assert!(Point::<C>::GENERATOR * *extracted_w == x);
+    }
+
+    #[test]
+    fn ledger_refund() {
+        let mut ledger = TestLedger::new();
+        let (sk_a, pk_a) = Sig::gen();
+        let (_, pk_b) = ASig::gen();
+        let (_, pk_s) = Sig::gen();
+        let lock = Output::new(
+            100,
+            vec![
+                Branch::new(vec![Key::Adaptor(pk_b), Key::Signature(pk_s)], 0),
+                Branch::new(vec![Key::Signature(pk_a)], TIMEOUT),
+            ],
+        );
+        let coins = ledger.mint(lock);
+        let mut tx_recover: Tx = Tx::new(vec![coins], vec![Output::to_key(100, pk_a)]);
+        tx_recover.witnesses = vec![Witness::new(
+            1,
+            vec![Sig::sign(&sk_a, &tx_recover.message())],
+        )];
+
+        // the output is confirmed at height 0, so tx_recover can go in the block at TIMEOUT
+        for _ in 1..TIMEOUT {
+            assert_eq!(
+                ledger.submit(tx_recover.clone()),
+                Err(LedgerError::TimelockNotExpired)
+            );
+            ledger.mine();
+        }
+        let txid = ledger.submit(tx_recover).unwrap();
+        ledger.mine();
+        assert_eq!(ledger.confirmation_height(&txid), Some(TIMEOUT));
+    }
+}
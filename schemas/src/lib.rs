@@ -1,22 +1,44 @@
 pub mod adaptor_signatures;
+pub mod aead_encryption;
+pub mod bip340;
+pub mod bls_signatures;
+pub mod bulletproofs;
+pub mod commitment_scheme;
+pub mod cross_group_dleq;
+pub mod crs;
+pub mod dleq;
 pub mod ecdsa_signatures;
+pub mod ecies;
 pub mod hard_relation;
+pub mod hash_commitment;
 pub mod identification_scheme;
+pub mod ledger;
 pub mod nizk;
 pub mod pedersen_commitment;
 pub mod proof_f;
 pub mod proof_phi;
-// pub mod proof_star;
+pub mod proof_star;
+pub mod protocol_message;
 pub mod elgamal;
 pub mod one_time_pad;
+pub mod pok_bls_signature;
+pub mod pok_ecdsa_signature;
 pub mod pok_schnorr_signature;
 pub mod por_schnorr_signature;
 pub mod public_key_encryption_scheme;
+pub mod reencryption_proof;
 pub mod schnorr_adaptor_signatures;
 pub mod schnorr_signatures;
+pub mod selling_protocol;
 pub mod sigma_proof;
+pub mod sigma_protocol;
+pub mod shuffle_proof;
 pub mod signature_scheme;
 pub mod symmetric_encryption;
+pub mod taproot;
+pub mod taproot_protocol;
+pub mod threshold_elgamal;
+pub mod transport;
 pub mod utils;
 
 pub fn add(left: usize, right: usize) -> usize {
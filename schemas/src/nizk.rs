@@ -10,3 +10,155 @@ pub trait NIZK {
     fn prove(crs: &Self::CRS, x: &Self::Statement, w: &Self::Witness) -> Self::Proof;
     fn verify(crs: &Self::CRS, x: &Self::Statement, p: &Self::Proof) -> bool;
 }
+
+// Serialization formats for the proofs of sigma protocols made non-interactive with Fiat-Shamir.
+// - Commitments: the commitments a and the responses r are sent. This is the format of the
+//   to_byte_vector functions of the proofs.
+// - ChallengeResponse: the challenge c and the responses r are sent. The verifier recomputes the
+//   commitments from the verification equations and checks that they hash to c. Since every
+//   commitment is a point and the challenge is a single scalar, the proof is about half the size.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProofEncoding {
+    Commitments,
+    ChallengeResponse,
+}
+
+pub trait EncodableProof: NIZK {
+    fn encode_proof(
+        crs: &Self::CRS,
+        x: &Self::Statement,
+        p: &Self::Proof,
+        encoding: ProofEncoding,
+    ) -> Vec<u8>;
+    // returns None if the bytes are malformed or, for the ChallengeResponse encoding, if the
+    // recomputed commitments do not hash to the challenge
+    fn decode_proof(
+        crs: &Self::CRS,
+        x: &Self::Statement,
+        bytes: &[u8],
+        encoding: ProofEncoding,
+    ) -> Option<Self::Proof>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dleq::DLEQProof;
+    use crate::ecdsa_signatures::ECDSA;
+    use crate::hard_relation::HardRelation;
+    use crate::pok_ecdsa_signature::PoKECDSASignature;
+    use crate::pok_schnorr_signature::PoKSchnorrSignature;
+    use crate::por_schnorr_signature::PoRSchnorrSignature;
+    use crate::schnorr_signatures::SchnorrSignatureScheme;
+    use crate::sigma_proof::DLogSigmaProof;
+    use crate::signature_scheme::SignatureScheme;
+    use elliptic_curve::scalar::NonZeroScalar;
+    use elliptic_curve::CurveArithmetic;
+    use k256::Secp256k1;
+    use sha2::Sha256;
+
+    type C = Secp256k1;
+    type Point = <C as CurveArithmetic>::ProjectivePoint;
+    type Sig = SchnorrSignatureScheme<C, Sha256>;
+
+    // encodes p with both encodings, checks the sizes and that both decode to a valid proof
+    fn round_trip<N: EncodableProof>(crs: &N::CRS, x: &N::Statement, p: &N::Proof) -> Vec<usize> {
+        let encodings = [ProofEncoding::Commitments, ProofEncoding::ChallengeResponse];
+        encodings
+            .iter()
+            .map(|encoding| {
+                let bytes = N::encode_proof(crs, x, p, *encoding);
+                let decoded = N::decode_proof(crs, x, &bytes, *encoding).unwrap();
+                assert!(N::verify(crs, x, &decoded));
+                assert_eq!(N::encode_proof(crs, x, &decoded, *encoding), bytes);
+
+                let mut tampered = bytes.clone();
+                tampered[1] ^= 1;
+                if let Some(tampered) = N::decode_proof(crs, x, &tampered, *encoding) {
+                    assert!(!N::verify(crs, x, &tampered));
+                }
+                assert!(N::decode_proof(crs, x, &bytes[1..], *encoding).is_none());
+
+                bytes.len()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn dlog_proof_encodings() {
+        type Nizk = DLogSigmaProof<C, Sha256>;
+        let crs = Nizk::crs_gen();
+        let (w, x) = NonZeroScalar::<C>::gen(&crs);
+        let p = Nizk::prove(&crs, &x, &w);
+
+        assert_eq!(round_trip::<Nizk>(&crs, &x, &p), vec![65, 64]);
+    }
+
+    #[test]
+    fn dleq_proof_encodings() {
+        use crate::dleq::{Statement, Witness};
+        type Nizk = DLEQProof<C, Sha256>;
+        let g = Point::GENERATOR;
+        let (w, h) = NonZeroScalar::<C>::gen(&g);
+        let (_, g2) = NonZeroScalar::<C>::gen(&g);
+        let statement = Statement::<C>::new(g, h, g2, g2 * *w);
+        let p = Nizk::prove(&(), &statement, &Witness::<C>::new(*w));
+
+        assert_eq!(round_trip::<Nizk>(&(), &statement, &p), vec![98, 64]);
+    }
+
+    #[test]
+    fn pok_schnorr_signature_encodings() {
+        use crate::pok_schnorr_signature::{Statement, Witness};
+        type Nizk = PoKSchnorrSignature<C, Sha256>;
+        let g = Point::GENERATOR;
+        let (sk, pk) = Sig::gen();
+        let (w, x) = NonZeroScalar::<C>::gen(&g);
+        let msg = "message";
+        let signature = Sig::sign(&sk, msg);
+        let ct = signature.sig + *w;
+        let statement =
+            Statement::<C, Sha256>::new(g * signature.sig, x, pk, signature.proof, ct, msg.into());
+        let witness = Witness::<C>::new(signature.sig, *w);
+        let p = Nizk::prove(&(), &statement, &witness);
+
+        assert_eq!(round_trip::<Nizk>(&(), &statement, &p), vec![130, 96]);
+    }
+
+    #[test]
+    fn pok_ecdsa_signature_encodings() {
+        use crate::pok_ecdsa_signature::{Statement, Witness};
+        type Nizk = PoKECDSASignature<C, Sha256>;
+        type Ecdsa = ECDSA<C, Sha256>;
+        let g = Point::GENERATOR;
+        let (sk, pk) = Ecdsa::gen();
+        let (w, x) = NonZeroScalar::<C>::gen(&g);
+        let msg = "message";
+        let signature = Ecdsa::sign(&sk, msg);
+        let nonce_point = Ecdsa::nonce_point(&pk, msg, &signature);
+        let ct = *signature.proof + *w;
+        let statement = Statement::<C, Sha256>::new(x, pk, nonce_point, ct, msg.into());
+        let witness = Witness::<C>::new(*signature.proof, *w);
+        let p = Nizk::prove(&(), &statement, &witness);
+
+        assert_eq!(round_trip::<Nizk>(&(), &statement, &p), vec![98, 64]);
+    }
+
+    #[test]
+    fn por_schnorr_signature_encodings() {
+        use crate::por_schnorr_signature::{Statement, Witness};
+        type Nizk = PoRSchnorrSignature<C, Sha256>;
+        let g = Point::GENERATOR;
+        let (sk, pk) = Sig::gen();
+        let (w, x) = NonZeroScalar::<C>::gen(&g);
+        let msg = "message";
+        let signature = Sig::sign(&sk, msg);
+        let statement =
+            Statement::<C, Sha256>::new(x, pk, g * signature.sig, signature.proof, msg.into());
+        let witness = Witness::<C>::new(signature.sig, *w);
+        let crs = Nizk::crs_gen();
+        let p = Nizk::prove(&crs, &statement, &witness);
+
+        assert_eq!(round_trip::<Nizk>(&crs, &statement, &p), vec![259, 160]);
+    }
+}
@@ -21,7 +21,7 @@ where
     fn enc(key: &Self::Key, msg: &Self::Message) -> Self::CypherText {
         *msg + *key
     }
-    fn dec(key: &Self::Key, ct: &Self::CypherText) -> Self::Message {
-        *ct - *key
+    fn dec(key: &Self::Key, ct: &Self::CypherText) -> Option<Self::Message> {
+        Some(*ct - *key)
     }
 }
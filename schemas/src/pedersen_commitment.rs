@@ -0,0 +1,298 @@
+use crate::commitment_scheme::{CommitmentScheme, HomomorphicCommitmentScheme};
+use crate::crs::{generators_from_byte_vector, generators_to_byte_vector, hash_to_curve_generator};
+use elliptic_curve::ff::Field;
+use elliptic_curve::group::cofactor::CofactorGroup;
+use elliptic_curve::hash2curve::GroupDigest;
+use elliptic_curve::point::DecompressPoint;
+use elliptic_curve::{CurveArithmetic, Group};
+use rand_core::OsRng;
+use std::marker::PhantomData;
+
+// Pedersen commitments over a curve C: commit(v; r) = g^v * h^r.
+// The generator h must be independent of g (nobody can know log_g(h)), otherwise the
+// commitment is not binding. That is why h is derived with hash_to_curve instead of being
+// computed as g^k for some random k.
+pub struct PedersenGens<C>
+where
+    C: CurveArithmetic,
+{
+    pub g: C::ProjectivePoint,
+    pub h: C::ProjectivePoint,
+}
+
+impl<C> PedersenGens<C>
+where
+    C: CurveArithmetic,
+{
+    pub fn new(g: C::ProjectivePoint, h: C::ProjectivePoint) -> Self {
+        Self { g, h }
+    }
+
+    // g is the generator of the curve and h is a nothing-up-my-sleeve point derived from a fixed
+    // domain string.
+    pub fn nums() -> Self
+    where
+        C: GroupDigest,
+        C::ProjectivePoint: CofactorGroup,
+    {
+        let g = C::ProjectivePoint::generator();
+        let h = hash_to_curve_generator::<C>(b"pedersen_commitment_h", 0);
+
+        Self { g, h }
+    }
+
+    pub fn to_byte_vector(&self) -> Vec<u8> {
+        generators_to_byte_vector::<C>(&[self.g, self.h])
+    }
+
+    pub fn from_byte_vector(bytes: &[u8]) -> Option<Self>
+    where
+        C::AffinePoint: DecompressPoint<C>,
+    {
+        let generators = generators_from_byte_vector::<C>(bytes, 2)?;
+
+        Some(Self::new(generators[0], generators[1]))
+    }
+
+    pub fn commit(&self, value: &C::Scalar, blinding: &C::Scalar) -> C::ProjectivePoint {
+        self.g * value + self.h * blinding
+    }
+}
+
+// Pedersen commitments to vectors of scalars: commit(v_1, ..., v_n; r) = g_1^v_1 ... g_n^v_n * h^r.
+// As for PedersenGens, nobody may know a discrete logarithm relation between the generators.
+pub struct PedersenVectorGens<C>
+where
+    C: CurveArithmetic,
+{
+    pub g: Vec<C::ProjectivePoint>,
+    pub h: C::ProjectivePoint,
+}
+
+impl<C> PedersenVectorGens<C>
+where
+    C: CurveArithmetic,
+{
+    pub fn new(g: Vec<C::ProjectivePoint>, h: C::ProjectivePoint) -> Self {
+        Self { g, h }
+    }
+
+    // n nothing-up-my-sleeve generators g_i and the h of PedersenGens::nums
+    pub fn nums(n: usize) -> Self
+    where
+        C: GroupDigest,
+        C::ProjectivePoint: CofactorGroup,
+    {
+        let g = (0..n)
+            .map(|i| hash_to_curve_generator::<C>(b"pedersen_commitment_g", i as u64))
+            .collect();
+        let h = PedersenGens::<C>::nums().h;
+
+        Self { g, h }
+    }
+
+    pub fn len(&self) -> usize {
+        self.g.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.g.is_empty()
+    }
+
+    pub fn to_byte_vector(&self) -> Vec<u8> {
+        let mut generators = self.g.clone();
+        generators.push(self.h);
+
+        generators_to_byte_vector::<C>(&generators)
+    }
+
+    // inverse of to_byte_vector for n generators g_i
+    pub fn from_byte_vector(bytes: &[u8], n: usize) -> Option<Self>
+    where
+        C::AffinePoint: DecompressPoint<C>,
+    {
+        let mut g = generators_from_byte_vector::<C>(bytes, n + 1)?;
+        let h = g.pop()?;
+
+        Some(Self::new(g, h))
+    }
+
+    // values must have as many elements as there are generators g_i
+    pub fn commit(&self, values: &[C::Scalar], blinding: &C::Scalar) -> C::ProjectivePoint {
+        assert_eq!(values.len(), self.g.len());
+
+        self.g
+            .iter()
+            .zip(values)
+            .fold(self.h * blinding, |acc, (g, v)| acc + *g * v)
+    }
+}
+
+pub struct Pedersen<C>
+where
+    C: CurveArithmetic,
+{
+    _curve_marker: PhantomData<C>,
+}
+
+impl<C> CommitmentScheme for Pedersen<C>
+where
+    C: CurveArithmetic,
+{
+    type PP = PedersenGens<C>;
+    type Message = C::Scalar;
+    type Randomness = C::Scalar;
+    type Commitment = C::ProjectivePoint;
+
+    fn commit(pp: &Self::PP, msg: &Self::Message) -> (Self::Commitment, Self::Randomness) {
+        let r = C::Scalar::random(&mut OsRng);
+
+        (pp.commit(msg, &r), r)
+    }
+
+    fn commit_with(pp: &Self::PP, msg: &Self::Message, r: &Self::Randomness) -> Self::Commitment {
+        pp.commit(msg, r)
+    }
+
+    fn verify(
+        pp: &Self::PP,
+        c: &Self::Commitment,
+        msg: &Self::Message,
+        r: &Self::Randomness,
+    ) -> bool {
+        pp.commit(msg, r) == *c
+    }
+}
+
+impl<C> HomomorphicCommitmentScheme for Pedersen<C>
+where
+    C: CurveArithmetic,
+{
+    type Scalar = C::Scalar;
+
+    fn add(c1: &Self::Commitment, c2: &Self::Commitment) -> Self::Commitment {
+        *c1 + *c2
+    }
+
+    fn mul(c: &Self::Commitment, k: &Self::Scalar) -> Self::Commitment {
+        *c * k
+    }
+}
+
+pub struct PedersenVector<C>
+where
+    C: CurveArithmetic,
+{
+    _curve_marker: PhantomData<C>,
+}
+
+impl<C> CommitmentScheme for PedersenVector<C>
+where
+    C: CurveArithmetic,
+{
+    type PP = PedersenVectorGens<C>;
+    type Message = Vec<C::Scalar>;
+    type Randomness = C::Scalar;
+    type Commitment = C::ProjectivePoint;
+
+    fn commit(pp: &Self::PP, msg: &Self::Message) -> (Self::Commitment, Self::Randomness) {
+        let r = C::Scalar::random(&mut OsRng);
+
+        (pp.commit(msg, &r), r)
+    }
+
+    fn commit_with(pp: &Self::PP, msg: &Self::Message, r: &Self::Randomness) -> Self::Commitment {
+        pp.commit(msg, r)
+    }
+
+    fn verify(
+        pp: &Self::PP,
+        c: &Self::Commitment,
+        msg: &Self::Message,
+        r: &Self::Randomness,
+    ) -> bool {
+        msg.len() == pp.len() && pp.commit(msg, r) == *c
+    }
+}
+
+impl<C> HomomorphicCommitmentScheme for PedersenVector<C>
+where
+    C: CurveArithmetic,
+{
+    type Scalar = C::Scalar;
+
+    fn add(c1: &Self::Commitment, c2: &Self::Commitment) -> Self::Commitment {
+        *c1 + *c2
+    }
+
+    fn mul(c: &Self::Commitment, k: &Self::Scalar) -> Self::Commitment {
+        *c * k
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k256::Secp256k1;
+
+    type C = Secp256k1;
+    type Scalar = <C as CurveArithmetic>::Scalar;
+
+    #[test]
+    fn pedersen_commitment() {
+        let pp = PedersenGens::<C>::nums();
+        let v = Scalar::random(&mut OsRng);
+        let (c, r) = Pedersen::<C>::commit(&pp, &v);
+
+        assert!(Pedersen::<C>::verify(&pp, &c, &v, &r));
+        assert!(!Pedersen::<C>::verify(&pp, &c, &(v + Scalar::ONE), &r));
+        assert!(!Pedersen::<C>::verify(&pp, &c, &v, &(r + Scalar::ONE)));
+        assert!(pp.g != pp.h);
+        assert!(
+            PedersenGens::<C>::from_byte_vector(&pp.to_byte_vector())
+                .unwrap()
+                .h
+                == pp.h
+        );
+    }
+
+    #[test]
+    fn pedersen_commitment_homomorphism() {
+        let pp = PedersenGens::<C>::nums();
+        let (v1, v2, k) = (
+            Scalar::random(&mut OsRng),
+            Scalar::random(&mut OsRng),
+            Scalar::random(&mut OsRng),
+        );
+        let (c1, r1) = Pedersen::<C>::commit(&pp, &v1);
+        let (c2, r2) = Pedersen::<C>::commit(&pp, &v2);
+
+        let c = Pedersen::<C>::add(&c1, &c2);
+        assert!(Pedersen::<C>::verify(&pp, &c, &(v1 + v2), &(r1 + r2)));
+        let c = Pedersen::<C>::mul(&c1, &k);
+        assert!(Pedersen::<C>::verify(&pp, &c, &(v1 * k), &(r1 * k)));
+    }
+
+    #[test]
+    fn pedersen_vector_commitment() {
+        let pp = PedersenVectorGens::<C>::nums(4);
+        let v: Vec<Scalar> = (0..4).map(|_| Scalar::random(&mut OsRng)).collect();
+        let (c, r) = PedersenVector::<C>::commit(&pp, &v);
+
+        assert!(PedersenVector::<C>::verify(&pp, &c, &v, &r));
+        let mut w = v.clone();
+        w.swap(0, 1);
+        assert!(!PedersenVector::<C>::verify(&pp, &c, &w, &r));
+        assert!(!PedersenVector::<C>::verify(&pp, &c, &v[..3].to_vec(), &r));
+
+        let (c2, r2) = PedersenVector::<C>::commit(&pp, &w);
+        let sum: Vec<Scalar> = v.iter().zip(&w).map(|(a, b)| *a + b).collect();
+        let c = PedersenVector::<C>::add(&c, &c2);
+        assert!(PedersenVector::<C>::verify(&pp, &c, &sum, &(r + r2)));
+
+        let bytes = pp.to_byte_vector();
+        let decoded = PedersenVectorGens::<C>::from_byte_vector(&bytes, 4).unwrap();
+        assert!(decoded.g == pp.g && decoded.h == pp.h);
+        assert!(PedersenVectorGens::<C>::from_byte_vector(&bytes, 3).is_none());
+    }
+}
@@ -1,16 +1,41 @@
+use crate::bls_signatures::{
+    hash_to_g1, BLSSignature, BLSSignatureScheme, SIGNATURE_DST,
+};
+use crate::cross_group_dleq::{
+    self, blindings, challenge_from_digest, random_challenge, random_points, ring_commitment,
+    ring_keys, to_bits, weighted_scalar_sum, weighted_sum,
+};
+use crate::crs::{hash_to_curve_generator, hash_to_g2_generator};
 use crate::hard_relation::HardRelation;
 use crate::nizk::NIZK;
-use crate::utils::{point_to_byte_vector, proj, scalar_to_byte_vector};
-use digest::{Digest, KeyInit};
-use elliptic_curve::ops::Reduce;
-use elliptic_curve::point::AffineCoordinates;
+use crate::sigma_protocol::{SigmaProtocol, SpecialSoundness};
+use crate::signature_scheme::SignatureScheme;
+use crate::utils::{point_to_byte_vector, scalar_to_byte_vector};
+use bls12_381::{pairing, G1Affine, G1Projective, G2Affine, G2Projective, Scalar};
+use digest::Digest;
+use elliptic_curve::ff::{Field, PrimeField};
+use elliptic_curve::group::cofactor::CofactorGroup;
+use elliptic_curve::hash2curve::GroupDigest;
+use elliptic_curve::point::DecompressPoint;
 use elliptic_curve::scalar::NonZeroScalar;
-use elliptic_curve::FieldBytes;
 use elliptic_curve::{CurveArithmetic, Group};
 use rand_core::OsRng;
 use std::marker::PhantomData;
 
-pub struct PoKSchnorrSignature<C, H>
+// Proof of knowledge of a BLS signature sig on a public message, encrypted under the statement
+// x = g^w of an adaptor signature over the curve C. The seller publishes
+//     ct = sig * g1^w (in G1) and y = g2^w (in G2),
+// so the buyer decrypts sig = ct / g1^w once the payment reveals w. Anybody can check with the
+// pairing that e(ct, g2) == e(H(m), pk) * e(g1, y), which means that ct / g1^w is a valid signature
+// if y = g2^w. Computing g1^w from y is hard in BLS12-381 (there is no efficient map from G2 to
+// G1), so y does not reveal the signature.
+//
+// It remains to prove that x and y have the same discrete logarithm w. The orders of C and
+// BLS12-381 are different, so w is decomposed in bits as in cross_group_dleq: every bit is
+// committed in both groups, C_i = g^b_i * h^r_i and D_i = g2^b_i * h2^s_i, with blindings such
+// that sum 2^i r_i = sum 2^i s_i = 0, and a ring with two members proves that both commitments
+// hide the same bit. The verifier checks that sum 2^i C_i = x and sum 2^i D_i = y.
+pub struct PoKBLSSignature<C, H>
 where
     C: CurveArithmetic,
     H: Digest<OutputSize = C::FieldBytesSize>,
@@ -19,11 +44,90 @@ where
     _hash_marker: PhantomData<H>,
 }
 
+// number of bits n of w: 2^n is smaller than the orders of C and BLS12-381
+pub fn bit_length<C: CurveArithmetic>() -> usize {
+    let bits = std::cmp::min(C::Scalar::NUM_BITS, Scalar::NUM_BITS);
+
+    (bits - 1) as usize
+}
+
+// the integer w < 2^n as a scalar of BLS12-381
+pub fn transfer_scalar<C: CurveArithmetic>(w: &C::Scalar) -> Option<Scalar> {
+    let bits = to_bits::<C>(w, bit_length::<C>())?;
+
+    Some(bits_to_scalar(&bits))
+}
+
+fn bits_to_scalar(bits: &[bool]) -> Scalar {
+    let bits: Vec<Scalar> = bits
+        .iter()
+        .map(|bit| if *bit { Scalar::ONE } else { Scalar::ZERO })
+        .collect();
+
+    weighted_scalar_sum(&bits)
+}
+
+// Witness and statement of an adaptor signature with w < 2^n, so that w can be used to encrypt a
+// BLS signature. It is a replacement of NonZeroScalar::gen.
+pub fn gen_witness<C: CurveArithmetic>(
+    g: &C::ProjectivePoint,
+) -> (NonZeroScalar<C>, C::ProjectivePoint) {
+    loop {
+        let bits: Vec<bool> = (0..bit_length::<C>())
+            .map(|_| bool::from(C::Scalar::random(&mut OsRng).is_odd()))
+            .collect();
+        let w = cross_group_dleq::from_bits::<C>(&bits);
+        let w: Option<NonZeroScalar<C>> = NonZeroScalar::new(w).into();
+        if let Some(w) = w {
+            return (w, *g * w.as_ref());
+        }
+    }
+}
+
+// (ct, y) = (sig * g1^w, g2^w), or None if w is too big to be transferred to BLS12-381
+pub fn encrypt<C: CurveArithmetic>(
+    sig: &BLSSignature,
+    w: &C::Scalar,
+) -> Option<(G1Projective, G2Projective)> {
+    let w = transfer_scalar::<C>(w)?;
+
+    Some((
+        sig.sig + G1Projective::generator() * w,
+        G2Projective::generator() * w,
+    ))
+}
+
+pub fn decrypt<C: CurveArithmetic>(ct: &G1Projective, w: &C::Scalar) -> Option<BLSSignature> {
+    let w = transfer_scalar::<C>(w)?;
+
+    Some(BLSSignature {
+        sig: ct - G1Projective::generator() * w,
+    })
+}
+
+fn g1_to_byte_vector(point: &G1Projective) -> Vec<u8> {
+    G1Affine::from(point).to_compressed().to_vec()
+}
+
+fn g2_to_byte_vector(point: &G2Projective) -> Vec<u8> {
+    G2Affine::from(point).to_compressed().to_vec()
+}
+
+pub struct Crs<C>
+where
+    C: CurveArithmetic,
+{
+    pub g: C::ProjectivePoint,
+    pub h: C::ProjectivePoint,
+    pub g2: G2Projective,
+    pub h2: G2Projective,
+}
+
 pub struct Witness<C>
 where
     C: CurveArithmetic,
 {
-    sig: C::Scalar,
+    sig: G1Projective,
     w: C::Scalar,
 }
 
@@ -31,89 +135,116 @@ impl<C> Witness<C>
 where
     C: CurveArithmetic,
 {
-    pub fn new(sig: C::Scalar, w: C::Scalar) -> Self {
+    pub fn new(sig: G1Projective, w: C::Scalar) -> Self {
         Self { sig, w }
     }
 }
 
-pub struct Statement<C, H>
+pub struct Statement<C>
 where
     C: CurveArithmetic,
-    H: Digest<OutputSize = C::FieldBytesSize>,
 {
-    gs: C::ProjectivePoint,
     x: C::ProjectivePoint,
-    pk: C::ProjectivePoint,
-    e: C::Scalar,
-    ct: C::Scalar,
+    y: G2Projective,
+    ct: G1Projective,
+    pk: G2Projective,
     msg: String,
-    _hash_marker: PhantomData<H>,
 }
 
-impl<C, H> Statement<C, H>
+impl<C> Statement<C>
 where
     C: CurveArithmetic,
-    H: Digest<OutputSize = C::FieldBytesSize>,
 {
     pub fn to_byte_vector(&self) -> Vec<u8> {
         let mut v: Vec<u8> = Vec::new();
-        unimplemented!("TODO: implement to_byte_vector for pk_schnorr_signature::Statement");
+        v.append(&mut point_to_byte_vector::<C>(&self.x));
+        v.append(&mut g2_to_byte_vector(&self.y));
+        v.append(&mut g1_to_byte_vector(&self.ct));
+        v.append(&mut g2_to_byte_vector(&self.pk));
+        let clone_msg = self.msg.clone();
+        v.append(&mut clone_msg.into_bytes());
+
+        v
     }
 
     pub fn new(
-        gs: C::ProjectivePoint,
         x: C::ProjectivePoint,
-        pk: C::ProjectivePoint,
-        e: C::Scalar,
-        ct: C::Scalar,
+        y: G2Projective,
+        ct: G1Projective,
+        pk: G2Projective,
         msg: String,
     ) -> Self {
-        Self {
-            gs,
-            x,
-            pk,
-            e,
-            ct,
-            msg,
-            _hash_marker: PhantomData,
-        }
+        Self { x, y, ct, pk, msg }
+    }
+
+    pub fn ct(&self) -> G1Projective {
+        self.ct
+    }
+
+    // e(ct, g2) == e(H(m), pk) * e(g1, y), the part of the relation that needs no proof
+    fn check_encryption(&self) -> bool {
+        let h = hash_to_g1(SIGNATURE_DST, self.msg.as_bytes());
+        let g1 = G1Affine::generator();
+        let g2 = G2Affine::generator();
+
+        pairing(&self.ct.into(), &g2)
+            == pairing(&h.into(), &self.pk.into()) + pairing(&g1, &self.y.into())
     }
 }
 
-impl<C, H> HardRelation<Statement<C, H>, Witness<C>> for Witness<C>
+impl<C> HardRelation<Statement<C>, Witness<C>> for Witness<C>
 where
     C: CurveArithmetic,
-    H: Digest<OutputSize = C::FieldBytesSize>,
 {
-    fn R(w: &Witness<C>, x: &Statement<C, H>) -> bool {
-        let g = C::ProjectivePoint::generator();
-        let rv = x.gs + x.pk * x.e;
-        let proj_rv = proj::<C>(&rv);
-        let hasher = H::new();
-        let ev = <C::Scalar as Reduce<C::Uint>>::reduce_bytes(
-            &hasher
-                .chain_update(&proj_rv)
-                .chain_update(x.msg.as_str())
-                .finalize(),
-        );
-        x.x == g * w.w && x.gs == g * w.sig && g * x.ct == x.x + x.gs && ev == x.e
+    type PP = Crs<C>;
+
+    fn R(pp: &Self::PP, w: &Witness<C>, x: &Statement<C>) -> bool {
+        let w_bls = match transfer_scalar::<C>(&w.w) {
+            Some(w_bls) => w_bls,
+            None => return false,
+        };
+        let signature = BLSSignature { sig: w.sig };
+
+        x.x == pp.g * w.w
+            && x.y == pp.g2 * w_bls
+            && x.ct == w.sig + G1Projective::generator() * w_bls
+            && BLSSignatureScheme::verify(&x.pk, &x.msg, &signature)
     }
     // From a Witness w, compute a Statement s such that R(w, s) == true
-    fn statement(w: &Witness<C>) -> Statement<C, H> {
+    fn statement(_pp: &Self::PP, _w: &Witness<C>) -> Statement<C> {
         unimplemented!("This function should never be called!");
     }
 
-    fn gen() -> (Witness<C>, Statement<C, H>) {
+    fn gen(_pp: &Self::PP) -> (Witness<C>, Statement<C>) {
         unimplemented!("This function should never be called!");
     }
 }
 
+// (C_i, D_i, [(R1, R2) of the member 0 of the ring, (R1, R2) of the member 1])
+pub type BitCommitment<C> = (
+    <C as CurveArithmetic>::ProjectivePoint,
+    G2Projective,
+    [(<C as CurveArithmetic>::ProjectivePoint, G2Projective); 2],
+);
+
+// (challenge of the member 0 of the ring, [(z1, z2) of the member 0, (z1, z2) of the member 1])
+pub type BitResponse<C> = (u128, [(<C as CurveArithmetic>::Scalar, Scalar); 2]);
+
+// (b_i, (r_i, s_i), nonces of the real member, challenge and responses of the simulated one)
+type BitState<C> = (
+    bool,
+    (<C as CurveArithmetic>::Scalar, Scalar),
+    (<C as CurveArithmetic>::Scalar, Scalar),
+    u128,
+    (<C as CurveArithmetic>::Scalar, Scalar),
+);
+
 pub struct Proof<C>
 where
     C: CurveArithmetic,
 {
-    a: (C::ProjectivePoint, C::ProjectivePoint),
-    r: (C::Scalar, C::Scalar),
+    a: Vec<BitCommitment<C>>,
+    r: Vec<BitResponse<C>>,
 }
 
 impl<C> Proof<C>
@@ -122,97 +253,249 @@ where
 {
     pub fn to_byte_vector(&self) -> Vec<u8> {
         let mut v: Vec<u8> = Vec::new();
-        unimplemented!("TODO: implement to_byte_vector for pk_schnorr_signature::Statement");
+        for (c1, c2, ring) in self.a.iter() {
+            v.append(&mut point_to_byte_vector::<C>(c1));
+            v.append(&mut g2_to_byte_vector(c2));
+            for (r1, r2) in ring.iter() {
+                v.append(&mut point_to_byte_vector::<C>(r1));
+                v.append(&mut g2_to_byte_vector(r2));
+            }
+        }
+        for (e0, z) in self.r.iter() {
+            v.extend_from_slice(&e0.to_be_bytes());
+            for (z1, z2) in z.iter() {
+                v.append(&mut scalar_to_byte_vector::<C>(z1));
+                v.extend_from_slice(&z2.to_bytes());
+            }
+        }
+
+        v
     }
 }
 
-fn compute_challenge<C, H>(
-    a: (C::ProjectivePoint, C::ProjectivePoint),
-    x: &Statement<C, H>,
-) -> C::Scalar
+fn compute_challenge<C, H>(crs: &Crs<C>, x: &Statement<C>, a: &[BitCommitment<C>]) -> u128
 where
     C: CurveArithmetic,
     H: Digest<OutputSize = C::FieldBytesSize>,
 {
-    let (a1, a2) = a;
-
-    let proj_a1 = proj::<C>(&a1);
-    let proj_a2 = proj::<C>(&a2);
-    let proj_gs = proj::<C>(&x.gs);
-    let proj_x = proj::<C>(&x.x);
-    let proj_pk = proj::<C>(&x.pk);
-    let e = Into::<<C::AffinePoint as AffineCoordinates>::FieldRepr>::into(x.e);
-    let ct = Into::<<C::AffinePoint as AffineCoordinates>::FieldRepr>::into(x.ct);
-
-    let hasher = H::new();
-    let c = <C::Scalar as Reduce<C::Uint>>::reduce_bytes(
-        &hasher
-            .chain_update(proj_a1)
-            .chain_update(proj_a2)
-            .chain_update(proj_gs)
-            .chain_update(proj_x)
-            .chain_update(proj_pk)
-            .chain_update(e)
-            .chain_update(ct)
-            .chain_update(x.msg.as_str())
-            .finalize(),
-    );
-
-    c
-}
-
-impl<C, H> NIZK for PoKSchnorrSignature<C, H>
+    let hasher = H::new()
+        .chain_update(point_to_byte_vector::<C>(&crs.g))
+        .chain_update(point_to_byte_vector::<C>(&crs.h))
+        .chain_update(g2_to_byte_vector(&crs.g2))
+        .chain_update(g2_to_byte_vector(&crs.h2))
+        .chain_update(x.to_byte_vector());
+    let hasher = a.iter().fold(hasher, |hasher, (c1, c2, ring)| {
+        ring.iter().fold(
+            hasher
+                .chain_update(point_to_byte_vector::<C>(c1))
+                .chain_update(g2_to_byte_vector(c2)),
+            |hasher, (r1, r2)| {
+                hasher
+                    .chain_update(point_to_byte_vector::<C>(r1))
+                    .chain_update(g2_to_byte_vector(r2))
+            },
+        )
+    });
+
+    challenge_from_digest(&hasher.finalize())
+}
+
+impl<C, H> NIZK for PoKBLSSignature<C, H>
 where
     C: CurveArithmetic,
     H: Digest<OutputSize = C::FieldBytesSize>,
+    C::AffinePoint: DecompressPoint<C>,
+    C: GroupDigest,
+    C::ProjectivePoint: CofactorGroup,
 {
-    type CRS = ();
-    type Statement = Statement<C, H>;
+    type CRS = Crs<C>;
+    type Statement = Statement<C>;
     type Witness = Witness<C>;
     type Proof = Proof<C>;
 
     fn crs_gen() -> Self::CRS {
-        ()
+        Crs {
+            g: C::ProjectivePoint::generator(),
+            h: hash_to_curve_generator::<C>(b"pok_bls_signature_h", 0),
+            g2: G2Projective::generator(),
+            h2: hash_to_g2_generator(b"pok_bls_signature_h", 0),
+        }
     }
 
     fn prove(crs: &Self::CRS, x: &Self::Statement, w: &Self::Witness) -> Self::Proof {
-        let nz_u1 = NonZeroScalar::<C>::random(&mut OsRng);
-        let nz_u2 = NonZeroScalar::<C>::random(&mut OsRng);
-        let u1 = nz_u1.as_ref();
-        let u2 = nz_u2.as_ref();
-        let g = C::ProjectivePoint::generator();
-        let a1 = g * u1;
-        let a2 = g * u2;
-
-        let a = (a1, a2);
-        let c = compute_challenge::<C, H>(a, x);
-
-        let r1 = *u1 + w.sig * c;
-        let r2 = *u2 + w.w * c;
-        let r = (r1, r2);
+        let (a, st) = Self::commit(crs, x, w);
+        let c = compute_challenge::<C, H>(crs, x, &a);
+        let r = Self::respond(crs, x, w, &st, &c);
 
         Proof::<C> { a, r }
     }
 
-    #[rustfmt::skip]
     fn verify(crs: &Self::CRS, x: &Self::Statement, p: &Self::Proof) -> bool {
-        let (a1, a2) = p.a;
-        let (r1, r2) = p.r;
-        let g = C::ProjectivePoint::generator();
-        let rv = x.gs + x.pk * x.e;
-        let proj_rv = proj::<C>(&rv);
-        let hasher = H::new();
-        let ev = <C::Scalar as Reduce<C::Uint>>::reduce_bytes(
-            &hasher
-                .chain_update(&proj_rv)
-                .chain_update(x.msg.as_str())
-                .finalize(),
-        );
-        let c = compute_challenge::<C, H>(p.a, x);
-
-        g * x.ct == x.x + x.gs 
-            && ev == x.e 
-            && g * r1 == a1 + x.gs * c 
-            && g * r2 == a2 + x.x * c
+        let c = compute_challenge::<C, H>(crs, x, &p.a);
+
+        Self::check(crs, x, &p.a, &c, &p.r)
+    }
+}
+
+impl<C, H> SigmaProtocol for PoKBLSSignature<C, H>
+where
+    C: CurveArithmetic,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+    C::AffinePoint: DecompressPoint<C>,
+    C: GroupDigest,
+    C::ProjectivePoint: CofactorGroup,
+{
+    type Commitment = Vec<BitCommitment<C>>;
+    type State = Vec<BitState<C>>;
+    type Challenge = u128;
+    type Response = Vec<BitResponse<C>>;
+
+    fn commit(
+        crs: &Self::CRS,
+        _x: &Self::Statement,
+        w: &Self::Witness,
+    ) -> (Self::Commitment, Self::State) {
+        let n = bit_length::<C>();
+        let bits = to_bits::<C>(&w.w, n).expect("the witness does not fit in the bit length");
+        let r1 = blindings(&C::Scalar::ZERO, n);
+        let r2 = blindings(&Scalar::ZERO, n);
+
+        bits.iter()
+            .zip(r1.iter().zip(r2.iter()))
+            .map(|(bit, (r1, r2))| {
+                let (c1, c2) = if *bit {
+                    (crs.g + crs.h * r1, crs.g2 + crs.h2 * r2)
+                } else {
+                    (crs.h * r1, crs.h2 * r2)
+                };
+                let keys = ring_keys(&crs.g, &crs.g2, &c1, &c2);
+                let real = *bit as usize;
+
+                let u1 = *NonZeroScalar::<C>::random(&mut OsRng).as_ref();
+                let u2 = Scalar::random(&mut OsRng);
+                let e_sim = random_challenge();
+                let z_sim = (C::Scalar::random(&mut OsRng), Scalar::random(&mut OsRng));
+
+                let mut ring = [(crs.h * u1, crs.h2 * u2); 2];
+                ring[1 - real] =
+                    ring_commitment(&crs.h, &crs.h2, &keys[1 - real], e_sim, &z_sim);
+
+                ((c1, c2, ring), (*bit, (*r1, *r2), (u1, u2), e_sim, z_sim))
+            })
+            .unzip()
+    }
+
+    fn respond(
+        _crs: &Self::CRS,
+        _x: &Self::Statement,
+        _w: &Self::Witness,
+        st: &Self::State,
+        c: &Self::Challenge,
+    ) -> Self::Response {
+        st.iter()
+            .map(|(bit, (r1, r2), (u1, u2), e_sim, z_sim)| {
+                let real = *bit as usize;
+                let e = *c ^ e_sim;
+                let z = (
+                    *u1 + C::Scalar::from_u128(e) * r1,
+                    *u2 + Scalar::from_u128(e) * r2,
+                );
+
+                let mut responses = [*z_sim; 2];
+                responses[real] = z;
+                let e0 = if real == 0 { e } else { *e_sim };
+
+                (e0, responses)
+            })
+            .collect()
+    }
+
+    fn check(
+        crs: &Self::CRS,
+        x: &Self::Statement,
+        a: &Self::Commitment,
+        c: &Self::Challenge,
+        r: &Self::Response,
+    ) -> bool {
+        let n = bit_length::<C>();
+        if a.len() != n || r.len() != n {
+            return false;
+        }
+        let c1: Vec<C::ProjectivePoint> = a.iter().map(|bit| bit.0).collect();
+        let c2: Vec<G2Projective> = a.iter().map(|bit| bit.1).collect();
+        if weighted_sum(&c1) != x.x || weighted_sum(&c2) != x.y {
+            return false;
+        }
+
+        x.check_encryption()
+            && a.iter().zip(r.iter()).all(|((c1, c2, ring), (e0, z))| {
+                let keys = ring_keys(&crs.g, &crs.g2, c1, c2);
+                let e = [*e0, *c ^ e0];
+                (0..2).all(|k| {
+                    ring_commitment(&crs.h, &crs.h2, &keys[k], e[k], &z[k]) == ring[k]
+                })
+            })
+    }
+
+    fn simulate(
+        crs: &Self::CRS,
+        x: &Self::Statement,
+        c: &Self::Challenge,
+    ) -> (Self::Commitment, Self::Response) {
+        let n = bit_length::<C>();
+        let c1 = random_points(&crs.h, &x.x, n);
+        let c2 = random_points(&crs.h2, &x.y, n);
+
+        c1.iter()
+            .zip(c2.iter())
+            .map(|(c1, c2)| {
+                let keys = ring_keys(&crs.g, &crs.g2, c1, c2);
+                let e0 = random_challenge();
+                let e = [e0, *c ^ e0];
+                let z = [
+                    (C::Scalar::random(&mut OsRng), Scalar::random(&mut OsRng)),
+                    (C::Scalar::random(&mut OsRng), Scalar::random(&mut OsRng)),
+                ];
+                let ring = [
+                    ring_commitment(&crs.h, &crs.h2, &keys[0], e[0], &z[0]),
+                    ring_commitment(&crs.h, &crs.h2, &keys[1], e[1], &z[1]),
+                ];
+
+                ((*c1, *c2, ring), (e0, z))
+            })
+            .unzip()
+    }
+}
+
+// The bits of w are extracted as in cross_group_dleq, and the signature is decrypted with w.
+impl<C, H> SpecialSoundness for PoKBLSSignature<C, H>
+where
+    C: CurveArithmetic,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+    C::AffinePoint: DecompressPoint<C>,
+    C: GroupDigest,
+    C::ProjectivePoint: CofactorGroup,
+{
+    fn extract(
+        crs: &Self::CRS,
+        x: &Self::Statement,
+        a: &Self::Commitment,
+        c1: &Self::Challenge,
+        r1: &Self::Response,
+        c2: &Self::Challenge,
+        r2: &Self::Response,
+    ) -> Option<Self::Witness> {
+        if !Self::check(crs, x, a, c1, r1) || !Self::check(crs, x, a, c2, r2) || c1 == c2 {
+            return None;
+        }
+        let bits: Vec<bool> = r1
+            .iter()
+            .zip(r2.iter())
+            .map(|((e0, _), (e0_prime, _))| e0 == e0_prime)
+            .collect();
+        let w = cross_group_dleq::from_bits::<C>(&bits);
+        let signature = decrypt::<C>(&x.ct, &w)?;
+
+        Some(Witness::new(signature.sig, w))
     }
 }
@@ -0,0 +1,271 @@
+use crate::dleq::{self, DLEQProof};
+use crate::hard_relation::HardRelation;
+use crate::nizk::{EncodableProof, ProofEncoding, NIZK};
+use crate::sigma_protocol::{SigmaProtocol, SpecialSoundness};
+use crate::utils::{point_to_byte_vector, scalar_to_byte_vector};
+use digest::Digest;
+use elliptic_curve::ff::Field;
+use elliptic_curve::ops::Reduce;
+use elliptic_curve::point::{AffineCoordinates, DecompressPoint};
+use elliptic_curve::AffinePoint;
+use elliptic_curve::{CurveArithmetic, Group};
+use std::marker::PhantomData;
+
+// Proof of knowledge of an ECDSA signature (r, s) on msg under pk, whose s is encrypted with the
+// witness w of the adaptor statement x = g^w as ct = s + w.
+//
+// The nonce point R of the signature (see ECDSA::nonce_point) is public. The signature is valid
+// iff r is the x coordinate of R and R^s = g^H(m) pk^r =: Z, an equation that is linear in s.
+// Since s = ct - w, it becomes R^ct / Z = R^w, so the proof is a DLEQProof of equality of the
+// discrete logarithms of x in base g and R^ct / Z in base R. Its challenge binds pk, ct and msg
+// through R^ct / Z.
+pub struct PoKECDSASignature<C, H>
+where
+    C: CurveArithmetic,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+{
+    _curve_marker: PhantomData<C>,
+    _hash_marker: PhantomData<H>,
+}
+
+pub struct Witness<C>
+where
+    C: CurveArithmetic,
+{
+    s: C::Scalar,
+    w: C::Scalar,
+}
+
+impl<C> Witness<C>
+where
+    C: CurveArithmetic,
+{
+    pub fn new(s: C::Scalar, w: C::Scalar) -> Self {
+        Self { s, w }
+    }
+}
+
+pub struct Statement<C, H>
+where
+    C: CurveArithmetic,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+{
+    x: C::ProjectivePoint,
+    pk: C::ProjectivePoint,
+    nonce_point: C::ProjectivePoint,
+    ct: C::Scalar,
+    msg: String,
+    _hash_marker: PhantomData<H>,
+}
+
+impl<C, H> Statement<C, H>
+where
+    C: CurveArithmetic,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+{
+    pub fn to_byte_vector(&self) -> Vec<u8> {
+        let mut v: Vec<u8> = Vec::new();
+        v.append(&mut point_to_byte_vector::<C>(&self.x));
+        v.append(&mut point_to_byte_vector::<C>(&self.pk));
+        v.append(&mut point_to_byte_vector::<C>(&self.nonce_point));
+        v.append(&mut scalar_to_byte_vector::<C>(&self.ct));
+        let clone_msg = self.msg.clone();
+        v.append(&mut clone_msg.into_bytes());
+
+        v
+    }
+
+    pub fn new(
+        x: C::ProjectivePoint,
+        pk: C::ProjectivePoint,
+        nonce_point: C::ProjectivePoint,
+        ct: C::Scalar,
+        msg: String,
+    ) -> Self {
+        Self {
+            x,
+            pk,
+            nonce_point,
+            ct,
+            msg,
+            _hash_marker: PhantomData,
+        }
+    }
+
+    // r, the x coordinate of the nonce point, which is the first half of the signature
+    pub fn r(&self) -> C::Scalar {
+        let affine: AffinePoint<C> = self.nonce_point.into();
+
+        <C::Scalar as Reduce<C::Uint>>::reduce_bytes(&affine.x())
+    }
+
+    // Z = g^H(m) pk^r
+    fn z(&self) -> C::ProjectivePoint {
+        let hasher = H::new();
+        let h = <C::Scalar as Reduce<C::Uint>>::reduce_bytes(
+            &hasher.chain_update(self.msg.as_str()).finalize(),
+        );
+
+        C::ProjectivePoint::generator() * h + self.pk * self.r()
+    }
+
+    // R^ct / Z, which is R^w if ct encrypts a valid signature
+    fn y(&self) -> C::ProjectivePoint {
+        self.nonce_point * self.ct - self.z()
+    }
+
+    // (g, x, R, R^ct / Z)
+    fn dleq_statement(&self) -> dleq::Statement<C> {
+        dleq::Statement::new(
+            C::ProjectivePoint::generator(),
+            self.x,
+            self.nonce_point,
+            self.y(),
+        )
+    }
+}
+
+impl<C, H> HardRelation<Statement<C, H>, Witness<C>> for Witness<C>
+where
+    C: CurveArithmetic,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+{
+    type PP = C::ProjectivePoint;
+
+    fn R(pp: &Self::PP, w: &Witness<C>, x: &Statement<C, H>) -> bool {
+        let g = *pp;
+
+        x.x == g * w.w
+            && x.ct == w.s + w.w
+            && !bool::from(w.s.is_zero())
+            && !bool::from(x.r().is_zero())
+            && x.nonce_point * w.s == x.z()
+    }
+    // From a Witness w, compute a Statement s such that R(w, s) == true
+    fn statement(_pp: &Self::PP, _w: &Witness<C>) -> Statement<C, H> {
+        unimplemented!("This function should never be called!");
+    }
+
+    fn gen(_pp: &Self::PP) -> (Witness<C>, Statement<C, H>) {
+        unimplemented!("This function should never be called!");
+    }
+}
+
+pub type Proof<C> = dleq::Proof<C>;
+
+impl<C, H> NIZK for PoKECDSASignature<C, H>
+where
+    C: CurveArithmetic,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+{
+    type CRS = ();
+    type Statement = Statement<C, H>;
+    type Witness = Witness<C>;
+    type Proof = Proof<C>;
+
+    fn crs_gen() -> Self::CRS {}
+
+    fn prove(_crs: &Self::CRS, x: &Self::Statement, w: &Self::Witness) -> Self::Proof {
+        DLEQProof::<C, H>::prove(&(), &x.dleq_statement(), &dleq::Witness::new(w.w))
+    }
+
+    fn verify(_crs: &Self::CRS, x: &Self::Statement, p: &Self::Proof) -> bool {
+        !bool::from(x.r().is_zero()) && DLEQProof::<C, H>::verify(&(), &x.dleq_statement(), p)
+    }
+}
+
+impl<C, H> SigmaProtocol for PoKECDSASignature<C, H>
+where
+    C: CurveArithmetic,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+{
+    type Commitment = (C::ProjectivePoint, C::ProjectivePoint);
+    type State = C::Scalar;
+    type Challenge = C::Scalar;
+    type Response = C::Scalar;
+
+    fn commit(
+        _crs: &Self::CRS,
+        x: &Self::Statement,
+        w: &Self::Witness,
+    ) -> (Self::Commitment, Self::State) {
+        DLEQProof::<C, H>::commit(&(), &x.dleq_statement(), &dleq::Witness::new(w.w))
+    }
+
+    fn respond(
+        _crs: &Self::CRS,
+        x: &Self::Statement,
+        w: &Self::Witness,
+        st: &Self::State,
+        c: &Self::Challenge,
+    ) -> Self::Response {
+        DLEQProof::<C, H>::respond(&(), &x.dleq_statement(), &dleq::Witness::new(w.w), st, c)
+    }
+
+    fn check(
+        _crs: &Self::CRS,
+        x: &Self::Statement,
+        a: &Self::Commitment,
+        c: &Self::Challenge,
+        r: &Self::Response,
+    ) -> bool {
+        !bool::from(x.r().is_zero()) && DLEQProof::<C, H>::check(&(), &x.dleq_statement(), a, c, r)
+    }
+
+    fn simulate(
+        _crs: &Self::CRS,
+        x: &Self::Statement,
+        c: &Self::Challenge,
+    ) -> (Self::Commitment, Self::Response) {
+        DLEQProof::<C, H>::simulate(&(), &x.dleq_statement(), c)
+    }
+}
+
+impl<C, H> SpecialSoundness for PoKECDSASignature<C, H>
+where
+    C: CurveArithmetic,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+{
+    // the witness w of the DLEQProof, and s = ct - w
+    fn extract(
+        _crs: &Self::CRS,
+        x: &Self::Statement,
+        a: &Self::Commitment,
+        c1: &Self::Challenge,
+        r1: &Self::Response,
+        c2: &Self::Challenge,
+        r2: &Self::Response,
+    ) -> Option<Self::Witness> {
+        if bool::from(x.r().is_zero()) {
+            return None;
+        }
+        let w = DLEQProof::<C, H>::extract(&(), &x.dleq_statement(), a, c1, r1, c2, r2)?.w();
+
+        Some(Witness::new(x.ct - w, w))
+    }
+}
+
+impl<C, H> EncodableProof for PoKECDSASignature<C, H>
+where
+    C: CurveArithmetic,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+    C::AffinePoint: DecompressPoint<C>,
+{
+    fn encode_proof(
+        _crs: &Self::CRS,
+        x: &Self::Statement,
+        p: &Self::Proof,
+        encoding: ProofEncoding,
+    ) -> Vec<u8> {
+        DLEQProof::<C, H>::encode_proof(&(), &x.dleq_statement(), p, encoding)
+    }
+
+    fn decode_proof(
+        _crs: &Self::CRS,
+        x: &Self::Statement,
+        bytes: &[u8],
+        encoding: ProofEncoding,
+    ) -> Option<Self::Proof> {
+        DLEQProof::<C, H>::decode_proof(&(), &x.dleq_statement(), bytes, encoding)
+    }
+}
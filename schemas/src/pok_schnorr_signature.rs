@@ -1,9 +1,11 @@
 use crate::hard_relation::HardRelation;
-use crate::nizk::NIZK;
-use crate::utils::{point_to_byte_vector, proj, scalar_to_byte_vector};
+use crate::nizk::{EncodableProof, ProofEncoding, NIZK};
+use crate::sigma_protocol::{SigmaProtocol, SpecialSoundness};
+use crate::utils::{point_to_byte_vector, proj, scalar_to_byte_vector, ByteReader};
 use digest::{Digest, KeyInit};
+use elliptic_curve::ff::Field;
 use elliptic_curve::ops::Reduce;
-use elliptic_curve::point::AffineCoordinates;
+use elliptic_curve::point::{AffineCoordinates, DecompressPoint};
 use elliptic_curve::scalar::NonZeroScalar;
 use elliptic_curve::FieldBytes;
 use elliptic_curve::{CurveArithmetic, Group};
@@ -193,6 +195,35 @@ where
     }
 
     fn prove(crs: &Self::CRS, x: &Self::Statement, w: &Self::Witness) -> Self::Proof {
+        let (a, u) = Self::commit(crs, x, w);
+        let c = compute_challenge::<C, H>(a, x);
+        let r = Self::respond(crs, x, w, &u, &c);
+
+        Proof::<C> { a, r }
+    }
+
+    fn verify(crs: &Self::CRS, x: &Self::Statement, p: &Self::Proof) -> bool {
+        let c = compute_challenge::<C, H>(p.a, x);
+
+        Self::check(crs, x, &p.a, &c, &p.r)
+    }
+}
+
+impl<C, H> SigmaProtocol for PoKSchnorrSignature<C, H>
+where
+    C: CurveArithmetic,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+{
+    type Commitment = (C::ProjectivePoint, C::ProjectivePoint);
+    type State = (C::Scalar, C::Scalar);
+    type Challenge = C::Scalar;
+    type Response = (C::Scalar, C::Scalar);
+
+    fn commit(
+        _crs: &Self::CRS,
+        _x: &Self::Statement,
+        _w: &Self::Witness,
+    ) -> (Self::Commitment, Self::State) {
         let nz_u1 = NonZeroScalar::<C>::random(&mut OsRng);
         let nz_u2 = NonZeroScalar::<C>::random(&mut OsRng);
         let u1 = nz_u1.as_ref();
@@ -201,20 +232,33 @@ where
         let a1 = g * u1;
         let a2 = g * u2;
 
-        let a = (a1, a2);
-        let c = compute_challenge::<C, H>(a, x);
+        ((a1, a2), (*u1, *u2))
+    }
 
-        let r1 = *u1 + w.sig * c;
-        let r2 = *u2 + w.w * c;
-        let r = (r1, r2);
+    fn respond(
+        _crs: &Self::CRS,
+        _x: &Self::Statement,
+        w: &Self::Witness,
+        st: &Self::State,
+        c: &Self::Challenge,
+    ) -> Self::Response {
+        let (u1, u2) = *st;
+        let r1 = u1 + w.sig * c;
+        let r2 = u2 + w.w * c;
 
-        Proof::<C> { a, r }
+        (r1, r2)
     }
 
     #[rustfmt::skip]
-    fn verify(crs: &Self::CRS, x: &Self::Statement, p: &Self::Proof) -> bool {
-        let (a1, a2) = p.a;
-        let (r1, r2) = p.r;
+    fn check(
+        _crs: &Self::CRS,
+        x: &Self::Statement,
+        a: &Self::Commitment,
+        c: &Self::Challenge,
+        r: &Self::Response,
+    ) -> bool {
+        let (a1, a2) = *a;
+        let (r1, r2) = *r;
         let g = C::ProjectivePoint::generator();
         let rv = x.gs + x.pk * x.e;
         let proj_rv = proj::<C>(&rv);
@@ -225,11 +269,112 @@ where
                 .chain_update(x.msg.as_str())
                 .finalize(),
         );
-        let c = compute_challenge::<C, H>(p.a, x);
 
-        g * x.ct == x.x + x.gs 
-            && ev == x.e 
-            && g * r1 == a1 + x.gs * c 
+        g * x.ct == x.x + x.gs
+            && ev == x.e
+            && g * r1 == a1 + x.gs * c
             && g * r2 == a2 + x.x * c
     }
+
+    fn simulate(
+        _crs: &Self::CRS,
+        x: &Self::Statement,
+        c: &Self::Challenge,
+    ) -> (Self::Commitment, Self::Response) {
+        let r1 = C::Scalar::random(&mut OsRng);
+        let r2 = C::Scalar::random(&mut OsRng);
+        let g = C::ProjectivePoint::generator();
+        let a1 = g * r1 - x.gs * c;
+        let a2 = g * r2 - x.x * c;
+
+        ((a1, a2), (r1, r2))
+    }
+}
+
+impl<C, H> SpecialSoundness for PoKSchnorrSignature<C, H>
+where
+    C: CurveArithmetic,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+{
+    fn extract(
+        crs: &Self::CRS,
+        x: &Self::Statement,
+        a: &Self::Commitment,
+        c1: &Self::Challenge,
+        r1: &Self::Response,
+        c2: &Self::Challenge,
+        r2: &Self::Response,
+    ) -> Option<Self::Witness> {
+        if !Self::check(crs, x, a, c1, r1) || !Self::check(crs, x, a, c2, r2) {
+            return None;
+        }
+        let inv: Option<C::Scalar> = (*c1 - *c2).invert().into();
+        let inv = inv?;
+        let (r1_sig, r1_w) = *r1;
+        let (r2_sig, r2_w) = *r2;
+
+        Some(Witness::new((r1_sig - r2_sig) * inv, (r1_w - r2_w) * inv))
+    }
+}
+
+impl<C, H> EncodableProof for PoKSchnorrSignature<C, H>
+where
+    C: CurveArithmetic,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+    C::AffinePoint: DecompressPoint<C>,
+{
+    fn encode_proof(
+        _crs: &Self::CRS,
+        x: &Self::Statement,
+        p: &Self::Proof,
+        encoding: ProofEncoding,
+    ) -> Vec<u8> {
+        match encoding {
+            ProofEncoding::Commitments => p.to_byte_vector(),
+            ProofEncoding::ChallengeResponse => {
+                let (r1, r2) = p.r;
+                let c = compute_challenge::<C, H>(p.a, x);
+                let mut v: Vec<u8> = Vec::new();
+                v.append(&mut scalar_to_byte_vector::<C>(&c));
+                v.append(&mut scalar_to_byte_vector::<C>(&r1));
+                v.append(&mut scalar_to_byte_vector::<C>(&r2));
+
+                v
+            }
+        }
+    }
+
+    fn decode_proof(
+        _crs: &Self::CRS,
+        x: &Self::Statement,
+        bytes: &[u8],
+        encoding: ProofEncoding,
+    ) -> Option<Self::Proof> {
+        let mut reader = ByteReader::new(bytes);
+        let p = match encoding {
+            ProofEncoding::Commitments => {
+                let a1 = reader.read_point::<C>()?;
+                let a2 = reader.read_point::<C>()?;
+                let r1 = reader.read_scalar::<C>()?;
+                let r2 = reader.read_scalar::<C>()?;
+                Proof::<C> {
+                    a: (a1, a2),
+                    r: (r1, r2),
+                }
+            }
+            ProofEncoding::ChallengeResponse => {
+                let c = reader.read_scalar::<C>()?;
+                let r1 = reader.read_scalar::<C>()?;
+                let r2 = reader.read_scalar::<C>()?;
+                let g = C::ProjectivePoint::generator();
+                let a = (g * r1 - x.gs * c, g * r2 - x.x * c);
+                if compute_challenge::<C, H>(a, x) != c {
+                    return None;
+                }
+                Proof::<C> { a, r: (r1, r2) }
+            }
+        };
+
+        reader.is_empty().then_some(p)
+    }
 }
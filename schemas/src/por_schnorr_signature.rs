@@ -1,17 +1,21 @@
+use crate::crs::{generators_from_byte_vector, generators_to_byte_vector, hash_to_curve_generator};
 use crate::hard_relation::HardRelation;
-use crate::nizk::NIZK;
-use crate::utils::{point_to_byte_vector, proj, scalar_to_byte_vector};
+use crate::nizk::{EncodableProof, ProofEncoding, NIZK};
+use crate::sigma_protocol::{SigmaProtocol, SpecialSoundness};
+use crate::utils::{point_to_byte_vector, proj, scalar_to_byte_vector, ByteReader};
 use digest::{Digest, KeyInit};
+use elliptic_curve::ff::Field;
 use elliptic_curve::ops::Reduce;
-use elliptic_curve::point::AffineCoordinates;
+use elliptic_curve::point::{AffineCoordinates, DecompressPoint};
 use elliptic_curve::scalar::NonZeroScalar;
+use elliptic_curve::group::cofactor::CofactorGroup;
+use elliptic_curve::hash2curve::GroupDigest;
 use elliptic_curve::FieldBytes;
 use elliptic_curve::{CurveArithmetic, Group};
 use rand_core::OsRng;
 use std::marker::PhantomData;
 use crate::schnorr_signatures::{SchnorrSignatureScheme, SchnorrSignature};
 use crate::signature_scheme::SignatureScheme;
-use std::time::Instant;
 
 pub struct PoRSchnorrSignature<C, H>
 where
@@ -39,6 +43,8 @@ where
     }
 }
 
+// g is the generator of the curve, used by the Schnorr signatures, and h an independent generator.
+// Nobody may know log_g(h), otherwise the branch over h could be proven without w.
 pub struct Crs<C>
 where 
     C: CurveArithmetic 
@@ -47,6 +53,52 @@ where
     h: C::ProjectivePoint
 }
 
+impl<C> Crs<C>
+where
+    C: CurveArithmetic,
+{
+    pub fn new(g: C::ProjectivePoint, h: C::ProjectivePoint) -> Self {
+        Self { g, h }
+    }
+
+    // h is derived with hash_to_curve from a fixed domain, so anyone can recompute it
+    pub fn hash_to_curve() -> Self
+    where
+        C: GroupDigest,
+        C::ProjectivePoint: CofactorGroup,
+    {
+        Self::from_seed(&[])
+    }
+
+    // h is derived from a seed agreed by both parties, for instance the joint seed of a
+    // crs::CoinTossing, so that neither of them has to trust a fixed domain
+    pub fn from_seed(seed: &[u8]) -> Self
+    where
+        C: GroupDigest,
+        C::ProjectivePoint: CofactorGroup,
+    {
+        let domain = [b"por_schnorr_signature_h".as_slice(), seed].concat();
+
+        Self {
+            g: C::ProjectivePoint::generator(),
+            h: hash_to_curve_generator::<C>(&domain, 0),
+        }
+    }
+
+    pub fn to_byte_vector(&self) -> Vec<u8> {
+        generators_to_byte_vector::<C>(&[self.g, self.h])
+    }
+
+    pub fn from_byte_vector(bytes: &[u8]) -> Option<Self>
+    where
+        C::AffinePoint: DecompressPoint<C>,
+    {
+        let generators = generators_from_byte_vector::<C>(bytes, 2)?;
+
+        Some(Self::new(generators[0], generators[1]))
+    }
+}
+
 pub struct Statement<C, H>
 where
     C: CurveArithmetic,
@@ -208,7 +260,8 @@ where
 
 impl<C, H> NIZK for PoRSchnorrSignature<C, H>
 where
-    C: CurveArithmetic,
+    C: GroupDigest,
+    C::ProjectivePoint: CofactorGroup,
     H: Digest<OutputSize = C::FieldBytesSize>,
 {
     type CRS = Crs<C>;
@@ -217,36 +270,45 @@ where
     type Proof = Proof<C>;
 
     fn crs_gen() -> Self::CRS {
-        crate::debug_print!("DIFFIE-HELMAN for interactive crs gen for generators g and h");
-        // both parties:
-        let g = C::ProjectivePoint::generator();
-        // Party A:
-        let start = Instant::now();
-        let nz_alpha = NonZeroScalar::<C>::random(&mut OsRng);
-        let alpha : C::Scalar = *nz_alpha;
-        let h1 = g * alpha;
-        let duration = start.elapsed();
-        crate::debug_print!("Diffie-Helman first phase: {} ms", duration.as_millis());
-        crate::debug_print!("transmited {} bytes", point_to_byte_vector::<C>(&h1).len());
-        // Send h1 to party A
-        // Party B:
-        let start = Instant::now();
-        let nz_beta = NonZeroScalar::<C>::random(&mut OsRng);
-        let beta : C::Scalar = *nz_beta;
-        let h = h1* beta;
-        let duration = start.elapsed();
-        crate::debug_print!("Diffie-Helman second phase: {} ms", duration.as_millis());
-        crate::debug_print!("transmited {} bytes", point_to_byte_vector::<C>(&h).len());
-        // Send h to  party A
-        // both parties:
-        let crs = Crs::<C> {
-            g, h
-        };
-        
-        crs
+        Crs::hash_to_curve()
     }
 
     fn prove(crs: &Self::CRS, x: &Self::Statement, w: &Self::Witness) -> Self::Proof {
+        let (a, st) = Self::commit(crs, x, w);
+        let c = compute_challenge::<C, H>(a, x);
+        let (r, c) = Self::respond(crs, x, w, &st, &c);
+
+        Proof::<C> {
+            a, r, c
+        }
+    }
+
+    fn verify(crs: &Self::CRS, x: &Self::Statement, p: &Self::Proof) -> bool {
+        let c = compute_challenge::<C, H>(p.a, x);
+
+        Self::check(crs, x, &p.a, &c, &(p.r, p.c))
+    }
+}
+
+// OR composition: the prover runs the branch it has a witness for and simulates the other one
+// with a challenge chosen in advance. The challenge c of the verifier is split as c = c1 + c2,
+// where c1 is the challenge of the branch over g and c2 the one of the branch over h.
+impl<C, H> SigmaProtocol for PoRSchnorrSignature<C, H>
+where
+    C: GroupDigest,
+    C::ProjectivePoint: CofactorGroup,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+{
+    type Commitment = (C::ProjectivePoint, C::ProjectivePoint, C::ProjectivePoint);
+    type State = ((C::Scalar, C::Scalar, C::Scalar), C::Scalar);
+    type Challenge = C::Scalar;
+    type Response = ((C::Scalar, C::Scalar, C::Scalar), (C::Scalar, C::Scalar));
+
+    fn commit(
+        crs: &Self::CRS,
+        x: &Self::Statement,
+        w: &Self::Witness,
+    ) -> (Self::Commitment, Self::State) {
         let nz_u_g = NonZeroScalar::<C>::random(&mut OsRng);
         let nz_u_h = NonZeroScalar::<C>::random(&mut OsRng);
         let nz_u_sig = NonZeroScalar::<C>::random(&mut OsRng);
@@ -259,10 +321,31 @@ where
         let a_g : C::ProjectivePoint;
         let a_sig : C::ProjectivePoint;
         let a_h : C::ProjectivePoint;
-        let a : (C::ProjectivePoint, C::ProjectivePoint, C::ProjectivePoint);
+        // challenge of the simulated branch
+        let c_sim : C::Scalar = *NonZeroScalar::<C>::random(&mut OsRng);
+
+        if crs.g * w.w == x.x {
+            a_g = g* u_g;
+            a_sig = g* u_sig;
+            a_h = x.x * (-c_sim) + h*u_h;
+        } else {
+            a_g = x.x*(-c_sim) + g* u_g;
+            a_sig = x.gs*(-c_sim) + g*u_sig;
+            a_h = h * u_h;
+        }
+
+        ((a_g, a_sig, a_h), ((*u_g, *u_sig, *u_h), c_sim))
+    }
+
+    fn respond(
+        crs: &Self::CRS,
+        x: &Self::Statement,
+        w: &Self::Witness,
+        st: &Self::State,
+        c: &Self::Challenge,
+    ) -> Self::Response {
+        let ((u_g, u_sig, u_h), c_sim) = *st;
 
-        // TODO change compute challenge
-        let c : C::Scalar;
         let c1: C::Scalar;
         let c2: C::Scalar;
 
@@ -271,50 +354,34 @@ where
         let r_h : C::Scalar;
 
         if crs.g * w.w == x.x {
-            let nz_c2 = NonZeroScalar::<C>::random(&mut OsRng);
-            c2 = *nz_c2;
-
-            a_g = g* u_g;
-            a_sig = g* u_sig;
-            a_h = x.x * (-c2) + h*u_h;
-
-            a = (a_g, a_sig, a_h);
-            c = compute_challenge::<C, H>(a, x);
-            c1 = c - c2;
+            c2 = c_sim;
+            c1 = *c - c2;
 
             r_g = c1*w.w + u_g;
             r_sig = c1*w.sig_proof + u_sig;
-            r_h = *u_h;
+            r_h = u_h;
         } else {
-            let nz_c1 = NonZeroScalar::<C>::random(&mut OsRng);
-            c1 = *nz_c1;
-
-            a_g = x.x*(-c1) + g* u_g;
-            a_sig = x.gs*(-c1) + g*u_sig;
-            a_h = h * u_h;
+            c1 = c_sim;
+            c2 = *c - c1;
 
-            a = (a_g, a_sig, a_h);
-            c = compute_challenge::<C, H>(a, x);
-            c2 = c - c1;
-
-            r_g = *u_g;
-            r_sig = *u_sig;
+            r_g = u_g;
+            r_sig = u_sig;
             r_h = c2*w.w + u_h;
         }
 
-        let c = (c1, c2);
-        let r = (r_g, r_sig, r_h);
-
-        Proof::<C> {
-            a, r, c
-        }
+        ((r_g, r_sig, r_h), (c1, c2))
     }
 
     #[rustfmt::skip]
-    fn verify(crs: &Self::CRS, x: &Self::Statement, p: &Self::Proof) -> bool {
-        let (a_g, a_sig, a_h) = p.a;
-        let (r_g, r_sig, r_h) = p.r;
-        let (c1, c2) = p.c;
+    fn check(
+        crs: &Self::CRS,
+        x: &Self::Statement,
+        a: &Self::Commitment,
+        c: &Self::Challenge,
+        r: &Self::Response,
+    ) -> bool {
+        let (a_g, a_sig, a_h) = *a;
+        let ((r_g, r_sig, r_h), (c1, c2)) = *r;
         let g = crs.g;
         let h = crs.h;
         let rv = x.gs + x.pk * x.e;
@@ -326,13 +393,140 @@ where
                 .chain_update(x.msg.as_str())
                 .finalize(),
         );
-        let c = compute_challenge::<C, H>(p.a, x);
 
-        c == c1 + c2
-        && ev == x.e 
+        *c == c1 + c2
+        && ev == x.e
         && g * r_sig == a_sig + x.gs * c1
         && g * r_g == a_g + x.x * c1
         && h * r_h == a_h + x.x * c2
     }
+
+    fn simulate(
+        crs: &Self::CRS,
+        x: &Self::Statement,
+        c: &Self::Challenge,
+    ) -> (Self::Commitment, Self::Response) {
+        let c1 = C::Scalar::random(&mut OsRng);
+        let c2 = *c - c1;
+        let r_g = C::Scalar::random(&mut OsRng);
+        let r_sig = C::Scalar::random(&mut OsRng);
+        let r_h = C::Scalar::random(&mut OsRng);
+
+        let a_g = crs.g * r_g - x.x * c1;
+        let a_sig = crs.g * r_sig - x.gs * c1;
+        let a_h = crs.h * r_h - x.x * c2;
+
+        ((a_g, a_sig, a_h), ((r_g, r_sig, r_h), (c1, c2)))
+    }
+}
+
+// Since c1 + c2 differs in both transcripts, the challenge of at least one of the branches
+// differs too, and we extract from that branch. The branch over h only gives w, so the extracted
+// witness has no signature in that case.
+impl<C, H> SpecialSoundness for PoRSchnorrSignature<C, H>
+where
+    C: GroupDigest,
+    C::ProjectivePoint: CofactorGroup,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+{
+    fn extract(
+        crs: &Self::CRS,
+        x: &Self::Statement,
+        a: &Self::Commitment,
+        c1: &Self::Challenge,
+        r1: &Self::Response,
+        c2: &Self::Challenge,
+        r2: &Self::Response,
+    ) -> Option<Self::Witness> {
+        if !Self::check(crs, x, a, c1, r1) || !Self::check(crs, x, a, c2, r2) || c1 == c2 {
+            return None;
+        }
+        let ((r1_g, r1_sig, r1_h), (c1_g, c1_h)) = *r1;
+        let ((r2_g, r2_sig, r2_h), (c2_g, c2_h)) = *r2;
+
+        if c1_g != c2_g {
+            let inv: Option<C::Scalar> = (c1_g - c2_g).invert().into();
+            let inv = inv?;
+            Some(Witness::new((r1_sig - r2_sig) * inv, (r1_g - r2_g) * inv))
+        } else {
+            let inv: Option<C::Scalar> = (c1_h - c2_h).invert().into();
+            Some(Witness::new(C::Scalar::ZERO, (r1_h - r2_h) * inv?))
+        }
+    }
 }
 
+impl<C, H> EncodableProof for PoRSchnorrSignature<C, H>
+where
+    C: GroupDigest,
+    C::ProjectivePoint: CofactorGroup,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+    C::AffinePoint: DecompressPoint<C>,
+{
+    fn encode_proof(
+        _crs: &Self::CRS,
+        _x: &Self::Statement,
+        p: &Self::Proof,
+        encoding: ProofEncoding,
+    ) -> Vec<u8> {
+        match encoding {
+            ProofEncoding::Commitments => p.to_byte_vector(),
+            // the challenges c1 and c2 are already part of the proof, so we only drop a
+            ProofEncoding::ChallengeResponse => {
+                let (r_g, r_sig, r_h) = p.r;
+                let (c1, c2) = p.c;
+                let mut v: Vec<u8> = Vec::new();
+                v.append(&mut scalar_to_byte_vector::<C>(&r_g));
+                v.append(&mut scalar_to_byte_vector::<C>(&r_sig));
+                v.append(&mut scalar_to_byte_vector::<C>(&r_h));
+                v.append(&mut scalar_to_byte_vector::<C>(&c1));
+                v.append(&mut scalar_to_byte_vector::<C>(&c2));
+
+                v
+            }
+        }
+    }
+
+    fn decode_proof(
+        crs: &Self::CRS,
+        x: &Self::Statement,
+        bytes: &[u8],
+        encoding: ProofEncoding,
+    ) -> Option<Self::Proof> {
+        let mut reader = ByteReader::new(bytes);
+        let a = match encoding {
+            ProofEncoding::Commitments => {
+                let a_g = reader.read_point::<C>()?;
+                let a_sig = reader.read_point::<C>()?;
+                let a_h = reader.read_point::<C>()?;
+                Some((a_g, a_sig, a_h))
+            }
+            ProofEncoding::ChallengeResponse => None,
+        };
+        let r_g = reader.read_scalar::<C>()?;
+        let r_sig = reader.read_scalar::<C>()?;
+        let r_h = reader.read_scalar::<C>()?;
+        let c1 = reader.read_scalar::<C>()?;
+        let c2 = reader.read_scalar::<C>()?;
+
+        let a = match a {
+            Some(a) => a,
+            None => {
+                let a_g = crs.g * r_g - x.x * c1;
+                let a_sig = crs.g * r_sig - x.gs * c1;
+                let a_h = crs.h * r_h - x.x * c2;
+                let a = (a_g, a_sig, a_h);
+                if compute_challenge::<C, H>(a, x) != c1 + c2 {
+                    return None;
+                }
+                a
+            }
+        };
+        let p = Proof::<C> {
+            a,
+            r: (r_g, r_sig, r_h),
+            c: (c1, c2),
+        };
+
+        reader.is_empty().then_some(p)
+    }
+}
@@ -1,9 +1,13 @@
+use crate::commitment_scheme::{CommitmentScheme, HomomorphicCommitmentScheme};
 use crate::hard_relation::HardRelation;
-use crate::nizk::NIZK;
-use crate::utils::proj;
+use crate::nizk::{EncodableProof, ProofEncoding, NIZK};
+use crate::pedersen_commitment::{Pedersen, PedersenGens};
+use crate::sigma_protocol::{SigmaProtocol, SpecialSoundness};
+use crate::utils::{point_to_byte_vector, proj, scalar_to_byte_vector, ByteReader};
 use digest::Digest;
 use elliptic_curve::ff::Field;
 use elliptic_curve::ops::Reduce;
+use elliptic_curve::point::DecompressPoint;
 use elliptic_curve::scalar::NonZeroScalar;
 use elliptic_curve::{CurveArithmetic, Group};
 use rand_core::OsRng;
@@ -30,17 +34,19 @@ impl<C> Witness<C>
 where
     C: CurveArithmetic,
 {
-    fn new(s: C::Scalar, e: C::Scalar) -> Self {
+    pub fn new(s: C::Scalar, e: C::Scalar) -> Self {
         Witness { s, e }
     }
 }
 
+// c_s is a Pedersen commitment to s with blinding e whose generators are swapped with respect to
+// PedersenGens::nums: s goes with h and e with g, so c_s = g^e * h^s.
 pub struct Statement<C>
 where
     C: CurveArithmetic,
 {
     g: C::ProjectivePoint,
-    h: C::ProjectivePoint,
+    pedersen: PedersenGens<C>,
     x: C::ProjectivePoint,
     c_s: C::ProjectivePoint,
 }
@@ -49,13 +55,18 @@ impl<C> Statement<C>
 where
     C: CurveArithmetic,
 {
-    fn new(
+    pub fn new(
         g: C::ProjectivePoint,
         h: C::ProjectivePoint,
         x: C::ProjectivePoint,
         c_s: C::ProjectivePoint,
     ) -> Self {
-        Statement { g, h, x, c_s }
+        Statement {
+            g,
+            pedersen: PedersenGens::new(h, g),
+            x,
+            c_s,
+        }
     }
 }
 
@@ -69,7 +80,7 @@ where
     type PP = C::ProjectivePoint;
 
     fn R(pp: &Self::PP, w: &Witness<C>, x: &Statement<C>) -> bool {
-        x.x == x.g * w.s && x.c_s == x.g * w.e + x.h * w.s
+        x.x == x.g * w.s && Pedersen::<C>::verify(&x.pedersen, &x.c_s, &w.s, &w.e)
     }
 
     fn statement(pp: &Self::PP, w: &Witness<C>) -> Statement<C> {
@@ -92,6 +103,36 @@ where
     }
 }
 
+fn compute_challenge<C, H>(
+    a: (C::ProjectivePoint, C::ProjectivePoint),
+    x: &Statement<C>,
+) -> C::Scalar
+where
+    C: CurveArithmetic,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+{
+    let (a0, a1) = a;
+
+    let proj_g = proj::<C>(&x.g);
+    let proj_h = proj::<C>(&x.pedersen.g);
+    let proj_c_s = proj::<C>(&x.c_s);
+    let proj_x = proj::<C>(&x.x);
+    let proj_a0 = proj::<C>(&a0);
+    let proj_a1 = proj::<C>(&a1);
+
+    let hasher = H::new();
+    <C::Scalar as Reduce<C::Uint>>::reduce_bytes(
+        &hasher
+            .chain_update(proj_g)
+            .chain_update(proj_h)
+            .chain_update(proj_c_s)
+            .chain_update(proj_x)
+            .chain_update(proj_a0)
+            .chain_update(proj_a1)
+            .finalize(),
+    )
+}
+
 impl<C, H> NIZK for SigmaProofF<C, H>
 where
     C: CurveArithmetic,
@@ -101,8 +142,8 @@ where
     type Statement = Statement<C>;
     type Witness = Witness<C>;
     type Proof = (
-        (C::ProjectivePoint, C::ProjectivePoint, C::ProjectivePoint),
-        (C::Scalar, C::Scalar, C::Scalar),
+        (C::ProjectivePoint, C::ProjectivePoint),
+        (C::Scalar, C::Scalar),
     );
 
     fn crs_gen() -> Self::CRS {
@@ -110,77 +151,180 @@ where
     }
 
     fn prove(crs: &Self::CRS, x: &Self::Statement, w: &Self::Witness) -> Self::Proof {
-        let _u1 = NonZeroScalar::<C>::random(&mut OsRng);
-        let _u2 = NonZeroScalar::<C>::random(&mut OsRng);
-        let u1 = _u1.as_ref();
-        let u2 = _u2.as_ref();
-
-        let g = x.g;
-        let h = x.h;
-
-        let a0 = g * u1;
-        let a1 = g * u1;
-        let a2 = h * u2;
-        let a = (a0, a1, a2);
-
-        let proj_g = proj::<C>(&g);
-        let proj_h = proj::<C>(&h);
-        let proj_c_s = proj::<C>(&x.c_s);
-        let proj_x = proj::<C>(&x.x);
-        let proj_a0 = proj::<C>(&a0);
-        let proj_a1 = proj::<C>(&a1);
-        let proj_a2 = proj::<C>(&a2);
-
-        let hasher = H::new();
-        let c = <C::Scalar as Reduce<C::Uint>>::reduce_bytes(
-            &hasher
-                .chain_update(proj_g)
-                .chain_update(proj_h)
-                .chain_update(proj_c_s)
-                .chain_update(proj_x)
-                .chain_update(proj_a0)
-                .chain_update(proj_a1)
-                .chain_update(proj_a2)
-                .finalize(),
-        );
-
-        let r0 = *u1 + c * w.s;
-        let r1 = *u1 + c * w.e;
-        let r2 = *u2 + c * w.s;
-        let r = (r0, r1, r2);
+        let (a, st) = Self::commit(crs, x, w);
+        let c = compute_challenge::<C, H>(a, x);
+        let r = Self::respond(crs, x, w, &st, &c);
 
         (a, r)
     }
 
     fn verify(crs: &Self::CRS, x: &Self::Statement, p: &Self::Proof) -> bool {
         let (a, r) = *p;
-        let (a0, a1, a2) = a;
-        let (r0, r1, r2) = r;
-
-        let g = x.g;
-        let h = x.h;
-
-        let proj_g = proj::<C>(&g);
-        let proj_h = proj::<C>(&h);
-        let proj_c_s = proj::<C>(&x.c_s);
-        let proj_x = proj::<C>(&x.x);
-        let proj_a0 = proj::<C>(&a0);
-        let proj_a1 = proj::<C>(&a1);
-        let proj_a2 = proj::<C>(&a2);
-
-        let hasher = H::new();
-        let c = <C::Scalar as Reduce<C::Uint>>::reduce_bytes(
-            &hasher
-                .chain_update(proj_g)
-                .chain_update(proj_h)
-                .chain_update(proj_c_s)
-                .chain_update(proj_x)
-                .chain_update(proj_a0)
-                .chain_update(proj_a1)
-                .chain_update(proj_a2)
-                .finalize(),
-        );
-
-        (g * r0 == a0 + x.x * c) && (g * r1 + h * r2 == a1 + a2 + x.c_s * c)
+
+        let c = compute_challenge::<C, H>(a, x);
+
+        Self::check(crs, x, &a, &c, &r)
+    }
+}
+
+// The same nonce u_s is used in both commitments, so the responses prove that the s in x = g^s is
+// the same s committed in c_s = g^e * h^s.
+impl<C, H> SigmaProtocol for SigmaProofF<C, H>
+where
+    C: CurveArithmetic,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+{
+    type Commitment = (C::ProjectivePoint, C::ProjectivePoint);
+    type State = (C::Scalar, C::Scalar);
+    type Challenge = C::Scalar;
+    type Response = (C::Scalar, C::Scalar);
+
+    fn commit(
+        _crs: &Self::CRS,
+        x: &Self::Statement,
+        _w: &Self::Witness,
+    ) -> (Self::Commitment, Self::State) {
+        let u_s = *NonZeroScalar::<C>::random(&mut OsRng).as_ref();
+        let u_e = *NonZeroScalar::<C>::random(&mut OsRng).as_ref();
+
+        let a0 = x.g * u_s;
+        let a1 = Pedersen::<C>::commit_with(&x.pedersen, &u_s, &u_e);
+
+        ((a0, a1), (u_s, u_e))
+    }
+
+    fn respond(
+        _crs: &Self::CRS,
+        _x: &Self::Statement,
+        w: &Self::Witness,
+        st: &Self::State,
+        c: &Self::Challenge,
+    ) -> Self::Response {
+        let (u_s, u_e) = *st;
+
+        let r_s = u_s + *c * w.s;
+        let r_e = u_e + *c * w.e;
+
+        (r_s, r_e)
+    }
+
+    fn check(
+        _crs: &Self::CRS,
+        x: &Self::Statement,
+        a: &Self::Commitment,
+        c: &Self::Challenge,
+        r: &Self::Response,
+    ) -> bool {
+        let (a0, a1) = *a;
+        let (r_s, r_e) = *r;
+        let c_s_c = Pedersen::<C>::mul(&x.c_s, c);
+
+        (x.g * r_s == a0 + x.x * c)
+            && Pedersen::<C>::verify(&x.pedersen, &Pedersen::<C>::add(&a1, &c_s_c), &r_s, &r_e)
+    }
+
+    fn simulate(
+        _crs: &Self::CRS,
+        x: &Self::Statement,
+        c: &Self::Challenge,
+    ) -> (Self::Commitment, Self::Response) {
+        let r_s = C::Scalar::random(&mut OsRng);
+        let r_e = C::Scalar::random(&mut OsRng);
+
+        let a0 = x.g * r_s - x.x * c;
+        let a1 = Pedersen::<C>::commit_with(&x.pedersen, &r_s, &r_e) - x.c_s * c;
+
+        ((a0, a1), (r_s, r_e))
+    }
+}
+
+impl<C, H> SpecialSoundness for SigmaProofF<C, H>
+where
+    C: CurveArithmetic,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+{
+    fn extract(
+        crs: &Self::CRS,
+        x: &Self::Statement,
+        a: &Self::Commitment,
+        c1: &Self::Challenge,
+        r1: &Self::Response,
+        c2: &Self::Challenge,
+        r2: &Self::Response,
+    ) -> Option<Self::Witness> {
+        if !Self::check(crs, x, a, c1, r1) || !Self::check(crs, x, a, c2, r2) {
+            return None;
+        }
+        let inv: Option<C::Scalar> = (*c1 - *c2).invert().into();
+        let inv = inv?;
+        let (r_s1, r_e1) = *r1;
+        let (r_s2, r_e2) = *r2;
+
+        Some(Witness::new((r_s1 - r_s2) * inv, (r_e1 - r_e2) * inv))
+    }
+}
+
+impl<C, H> EncodableProof for SigmaProofF<C, H>
+where
+    C: CurveArithmetic,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+    C::AffinePoint: DecompressPoint<C>,
+{
+    fn encode_proof(
+        _crs: &Self::CRS,
+        x: &Self::Statement,
+        p: &Self::Proof,
+        encoding: ProofEncoding,
+    ) -> Vec<u8> {
+        let (a, r) = *p;
+        let (a0, a1) = a;
+        let (r_s, r_e) = r;
+        let mut v: Vec<u8> = Vec::new();
+        match encoding {
+            ProofEncoding::Commitments => {
+                v.append(&mut point_to_byte_vector::<C>(&a0));
+                v.append(&mut point_to_byte_vector::<C>(&a1));
+            }
+            ProofEncoding::ChallengeResponse => {
+                let c = compute_challenge::<C, H>(a, x);
+                v.append(&mut scalar_to_byte_vector::<C>(&c));
+            }
+        }
+        v.append(&mut scalar_to_byte_vector::<C>(&r_s));
+        v.append(&mut scalar_to_byte_vector::<C>(&r_e));
+
+        v
+    }
+
+    fn decode_proof(
+        _crs: &Self::CRS,
+        x: &Self::Statement,
+        bytes: &[u8],
+        encoding: ProofEncoding,
+    ) -> Option<Self::Proof> {
+        let mut reader = ByteReader::new(bytes);
+        let p = match encoding {
+            ProofEncoding::Commitments => {
+                let a0 = reader.read_point::<C>()?;
+                let a1 = reader.read_point::<C>()?;
+                let r_s = reader.read_scalar::<C>()?;
+                let r_e = reader.read_scalar::<C>()?;
+                ((a0, a1), (r_s, r_e))
+            }
+            ProofEncoding::ChallengeResponse => {
+                let c = reader.read_scalar::<C>()?;
+                let r_s = reader.read_scalar::<C>()?;
+                let r_e = reader.read_scalar::<C>()?;
+                let a0 = x.g * r_s - x.x * c;
+                let a1 = Pedersen::<C>::commit_with(&x.pedersen, &r_s, &r_e) - x.c_s * c;
+                let a = (a0, a1);
+                if compute_challenge::<C, H>(a, x) != c {
+                    return None;
+                }
+                (a, (r_s, r_e))
+            }
+        };
+
+        reader.is_empty().then_some(p)
     }
 }
@@ -1,16 +1,25 @@
+use crate::cross_group_dleq::{self, BitCommitment, BitResponse, CrossGroupDLEQ, Crs};
 use crate::hard_relation::HardRelation;
-use crate::nizk::NIZK;
-use crate::utils::{point_to_byte_vector, proj, scalar_to_byte_vector};
+use crate::nizk::{EncodableProof, ProofEncoding, NIZK};
+use crate::sigma_protocol::{SigmaProtocol, SpecialSoundness};
+use crate::utils::{point_to_byte_vector, proj, scalar_to_byte_vector, ByteReader};
 use digest::Digest;
-use elliptic_curve::ops::Reduce;
-use elliptic_curve::point::AffineCoordinates;
+use elliptic_curve::ff::{Field, PrimeField};
+use elliptic_curve::group::cofactor::CofactorGroup;
+use elliptic_curve::hash2curve::GroupDigest;
+use elliptic_curve::point::{AffineCoordinates, DecompressPoint};
 use elliptic_curve::scalar::NonZeroScalar;
 use elliptic_curve::AffinePoint;
+use elliptic_curve::CurveArithmetic;
 use elliptic_curve::FieldBytes;
-use elliptic_curve::{CurveArithmetic, Group};
 use rand_core::OsRng;
 use std::marker::PhantomData;
 
+// The proof has two parts sharing the same challenge:
+// - a CrossGroupDLEQ proof that P1 = g1^s * h1^t and point_2 = g2^s for the same s. P1 is not
+//   sent, it is the sum of the bit commitments of the DLEQ proof.
+// - a sigma proof over C1 of knowledge of y, w and t such that A = g1^y, x = g1^w and
+//   B / P1 = x^y / h1^t, so the point encrypted in (A, B) is B / x^y = P1 / h1^t = g1^s.
 pub struct SigmaProofPhi<C1, C2, H1, H2>
 where
     C1: CurveArithmetic,
@@ -38,6 +47,7 @@ impl<C> Witness<C>
 where
     C: CurveArithmetic,
 {
+    // s must be smaller than 2^cross_group_dleq::bit_length() and point = g1^s
     pub fn new(s: C::Scalar, w: C::Scalar, y: C::Scalar, point: C::ProjectivePoint) -> Self {
         Witness { s, w, y, point }
     }
@@ -65,8 +75,6 @@ where
     C1: CurveArithmetic,
     C2: CurveArithmetic,
 {
-    g1: C1::ProjectivePoint,
-    g2: C2::ProjectivePoint,
     point_2: C2::ProjectivePoint,
     x: C1::ProjectivePoint,
     ct: (C1::ProjectivePoint, C1::ProjectivePoint),
@@ -78,19 +86,11 @@ where
     C2: CurveArithmetic,
 {
     pub fn new(
-        g1: C1::ProjectivePoint,
-        g2: C2::ProjectivePoint,
         point_2: C2::ProjectivePoint,
         x: C1::ProjectivePoint,
         ct: (C1::ProjectivePoint, C1::ProjectivePoint),
     ) -> Self {
-        Statement {
-            g1,
-            g2,
-            point_2,
-            x,
-            ct,
-        }
+        Statement { point_2, x, ct }
     }
 
     pub fn to_byte_vector(&self) -> Vec<u8> {
@@ -105,47 +105,36 @@ where
     }
 }
 
-fn scalar_transformation<C1, C2>(s1: &C1::Scalar) -> C2::Scalar
-where
-    C1: CurveArithmetic,
-    C2: CurveArithmetic,
-    <C2 as elliptic_curve::Curve>::Uint: From<<C1 as CurveArithmetic>::Scalar>,
-{
-    <C2::Scalar as Reduce<C2::Uint>>::reduce((*s1).into())
-}
-
-fn phi<C1, C2>(s: &C1::Scalar) -> C2::ProjectivePoint
-where
-    C1: CurveArithmetic,
-    C2: CurveArithmetic,
-    <C2 as elliptic_curve::Curve>::Uint: From<<C1 as CurveArithmetic>::Scalar>,
-{
-    let s2 = scalar_transformation::<C1, C2>(&s);
-    C2::ProjectivePoint::generator() * s2
-}
-
+// Relation_phi = {(point_2, x, (A, B); s, w, y, point) | A = g1^y &&
+//                                                       B = x^y * point &&
+//                                                       x = g1^w &&
+//                                                       point = g1^s &&
+//                                                       point_2 = g2^s}
+// where s is an integer smaller than 2^n, so it has the same value in both groups.
 impl<C1, C2> HardRelation<Statement<C1, C2>, Witness<C1>> for Witness<C1>
 where
     C1: CurveArithmetic,
     C2: CurveArithmetic,
-    <C2 as elliptic_curve::Curve>::Uint: From<<C1 as CurveArithmetic>::Scalar>,
 {
-    type PP = (C1::ProjectivePoint, C2::ProjectivePoint);
+    type PP = Crs<C1, C2>;
     fn R(pp: &Self::PP, w: &Witness<C1>, x: &Statement<C1, C2>) -> bool {
-        let (A, B) = x.ct;
-
-        A == x.g1 * w.y
-            && B == x.x * w.y + w.point
-            && x.x == x.g1 * w.w
-            && x.point_2 == x.g2 * scalar_transformation::<C1, C2>(&w.s)
-            && x.point_2 == phi::<C1, C2>(&w.s)
+        let (a, b) = x.ct;
+
+        a == pp.g1 * w.y
+            && b == x.x * w.y + w.point
+            && x.x == pp.g1 * w.w
+            && w.point == pp.g1 * w.s
+            && match cross_group_dleq::transfer_scalar::<C1, C2>(&w.s) {
+                Some(s2) => x.point_2 == pp.g2 * s2,
+                None => false,
+            }
     }
 
-    fn statement(pp: &Self::PP, w: &Witness<C1>) -> Statement<C1, C2> {
+    fn statement(_pp: &Self::PP, _w: &Witness<C1>) -> Statement<C1, C2> {
         unimplemented!("This function should never be called!");
     }
 
-    fn gen(pp: &Self::PP) -> (Witness<C1>, Statement<C1, C2>) {
+    fn gen(_pp: &Self::PP) -> (Witness<C1>, Statement<C1, C2>) {
         unimplemented!("This function should never be called!");
     }
 }
@@ -156,73 +145,89 @@ where
     C2: CurveArithmetic,
     H2: Digest<OutputSize = C2::FieldBytesSize>,
     H1: Digest<OutputSize = C1::FieldBytesSize>,
-    <C2 as elliptic_curve::Curve>::Uint: From<<C1 as CurveArithmetic>::Scalar>,
 {
     fn compute_challenge(
-        a: (
-            C1::ProjectivePoint,
-            C1::ProjectivePoint,
-            C1::ProjectivePoint,
-            C2::ProjectivePoint,
-            C2::ProjectivePoint,
-        ),
+        crs: &Crs<C1, C2>,
+        a: &PhiCommitment<C1, C2>,
         x: &Statement<C1, C2>,
-    ) -> (C1::Scalar, C2::Scalar) {
-        let (a1, a2, a3, a4, a5) = a;
+    ) -> u128 {
+        let (a_dleq, (a1, a2, a3)) = a;
         let (ct_a, ct_b) = x.ct;
-        let proj_g1 = proj::<C1>(&x.g1);
-        let proj_g2 = proj::<C2>(&x.g2);
         let proj_point_2 = proj::<C2>(&x.point_2);
         let proj_x = proj::<C1>(&x.x);
         let proj_ct_a = proj::<C1>(&ct_a);
         let proj_ct_b = proj::<C1>(&ct_b);
-        let proj_a1 = proj::<C1>(&a1);
-        let proj_a2 = proj::<C1>(&a2);
-        let proj_a3 = proj::<C1>(&a3);
-        let proj_a4 = proj::<C2>(&a4);
-        let proj_a5 = proj::<C2>(&a5);
-
-        let hasher = H1::new();
-        let c1 = <C1::Scalar as Reduce<C1::Uint>>::reduce_bytes(
+        let proj_a1 = proj::<C1>(a1);
+        let proj_a2 = proj::<C1>(a2);
+        let proj_a3 = proj::<C1>(a3);
+
+        let hasher = crs
+            .chain_to(H1::new())
+            .chain_update(proj_point_2)
+            .chain_update(proj_x)
+            .chain_update(proj_ct_a)
+            .chain_update(proj_ct_b);
+        let hasher = CrossGroupDLEQ::<C1, C2, H1, H2>::chain_commitment(hasher, a_dleq);
+
+        cross_group_dleq::challenge_from_digest(
             &hasher
-                .chain_update(proj_g1)
-                .chain_update(proj_g2)
-                .chain_update(proj_point_2)
-                .chain_update(proj_x)
-                .chain_update(proj_ct_a)
-                .chain_update(proj_ct_b)
                 .chain_update(proj_a1)
                 .chain_update(proj_a2)
                 .chain_update(proj_a3)
-                .chain_update(proj_a4)
-                .chain_update(proj_a5)
                 .finalize(),
-        );
-        let c2 = scalar_transformation::<C1, C2>(&c1);
+        )
+    }
+
+    // commitments of the sigma proof over C1 that pass the verification for the challenge c
+    fn linear_commitment(
+        crs: &Crs<C1, C2>,
+        x: &Statement<C1, C2>,
+        p1: &C1::ProjectivePoint,
+        c: &C1::Scalar,
+        r: &(C1::Scalar, C1::Scalar, C1::Scalar),
+    ) -> (
+        C1::ProjectivePoint,
+        C1::ProjectivePoint,
+        C1::ProjectivePoint,
+    ) {
+        let (r_y, r_w, r_t) = *r;
+        let (ct_a, ct_b) = x.ct;
 
-        (c1, c2)
+        let a1 = crs.g1 * r_y - ct_a * c;
+        let a2 = crs.g1 * r_w - x.x * c;
+        let a3 = x.x * r_y - crs.h1 * r_t - (ct_b - p1) * c;
+
+        (a1, a2, a3)
     }
 }
 
+// (commitments of the DLEQ proof, (a1, a2, a3) of the sigma proof over C1)
+pub type PhiCommitment<C1, C2> = (
+    Vec<BitCommitment<C1, C2>>,
+    (
+        <C1 as CurveArithmetic>::ProjectivePoint,
+        <C1 as CurveArithmetic>::ProjectivePoint,
+        <C1 as CurveArithmetic>::ProjectivePoint,
+    ),
+);
+
+// (responses of the DLEQ proof, (r_y, r_w, r_t) of the sigma proof over C1)
+pub type PhiResponse<C1, C2> = (
+    Vec<BitResponse<C1, C2>>,
+    (
+        <C1 as CurveArithmetic>::Scalar,
+        <C1 as CurveArithmetic>::Scalar,
+        <C1 as CurveArithmetic>::Scalar,
+    ),
+);
+
 pub struct Proof<C1, C2>
 where
     C1: CurveArithmetic,
     C2: CurveArithmetic,
 {
-    a: (
-        C1::ProjectivePoint,
-        C1::ProjectivePoint,
-        C1::ProjectivePoint,
-        C2::ProjectivePoint,
-        C2::ProjectivePoint,
-    ),
-    r: (
-        C1::ProjectivePoint,
-        C1::Scalar,
-        C1::Scalar,
-        C1::Scalar,
-        C2::Scalar,
-    ),
+    a: PhiCommitment<C1, C2>,
+    r: PhiResponse<C1, C2>,
 }
 
 impl<C1, C2> Proof<C1, C2>
@@ -231,20 +236,23 @@ where
     C2: CurveArithmetic,
 {
     pub fn to_byte_vector(&self) -> Vec<u8> {
-        let (a1, a2, a3, a4, a5) = self.a;
-        let (r1, r2, r3, r4, r5) = self.r;
+        let (a_dleq, (a1, a2, a3)) = &self.a;
+        let (r_dleq, (r_y, r_w, r_t)) = &self.r;
         let mut v: Vec<u8> = Vec::new();
 
-        v.append(&mut point_to_byte_vector::<C1>(&a1));
-        v.append(&mut point_to_byte_vector::<C1>(&a2));
-        v.append(&mut point_to_byte_vector::<C1>(&a3));
-        v.append(&mut point_to_byte_vector::<C2>(&a4));
-        v.append(&mut point_to_byte_vector::<C2>(&a5));
-        v.append(&mut point_to_byte_vector::<C1>(&r1));
-        v.append(&mut scalar_to_byte_vector::<C1>(&r2));
-        v.append(&mut scalar_to_byte_vector::<C1>(&r3));
-        v.append(&mut scalar_to_byte_vector::<C1>(&r4));
-        v.append(&mut scalar_to_byte_vector::<C2>(&r5));
+        v.append(&mut cross_group_dleq::bits_to_byte_vector::<C1, C2>(a_dleq));
+        v.append(&mut cross_group_dleq::rings_to_byte_vector::<C1, C2>(
+            a_dleq,
+        ));
+        v.append(&mut point_to_byte_vector::<C1>(a1));
+        v.append(&mut point_to_byte_vector::<C1>(a2));
+        v.append(&mut point_to_byte_vector::<C1>(a3));
+        v.append(&mut cross_group_dleq::response_to_byte_vector::<C1, C2>(
+            r_dleq,
+        ));
+        v.append(&mut scalar_to_byte_vector::<C1>(r_y));
+        v.append(&mut scalar_to_byte_vector::<C1>(r_w));
+        v.append(&mut scalar_to_byte_vector::<C1>(r_t));
 
         v
     }
@@ -256,63 +264,277 @@ where
     C2: CurveArithmetic,
     H2: Digest<OutputSize = C2::FieldBytesSize>,
     H1: Digest<OutputSize = C1::FieldBytesSize>,
-    <C2 as elliptic_curve::Curve>::Uint: From<<C1 as CurveArithmetic>::Scalar>,
+    C1::AffinePoint: DecompressPoint<C1>,
+    C2::AffinePoint: DecompressPoint<C2>,
+    C1: GroupDigest,
+    C1::ProjectivePoint: CofactorGroup,
+    C2: GroupDigest,
+    C2::ProjectivePoint: CofactorGroup,
 {
-    type CRS = C1::ProjectivePoint;
+    type CRS = Crs<C1, C2>;
     type Statement = Statement<C1, C2>;
     type Witness = Witness<C1>;
     type Proof = Proof<C1, C2>;
 
     fn crs_gen() -> Self::CRS {
-        Self::CRS::generator()
+        Crs::nums()
     }
 
     fn prove(crs: &Self::CRS, x: &Self::Statement, w: &Self::Witness) -> Self::Proof {
-        let _u1 = NonZeroScalar::<C1>::random(&mut OsRng);
-        let _u2 = NonZeroScalar::<C1>::random(&mut OsRng);
-        let _u3 = NonZeroScalar::<C1>::random(&mut OsRng);
-        let _u4 = _u1; //NonZeroScalar::<C1>::random(&mut OsRng);
-        let _u5 = NonZeroScalar::<C2>::random(&mut OsRng);
-
-        let u1 = _u1.as_ref();
-        let u2 = _u2.as_ref();
-        let u3 = _u3.as_ref();
-        let u4 = _u4.as_ref();
-        let u5 = _u5.as_ref();
-
-        let a1 = x.x * (*u1);
-        let a2 = x.g1 * (*u2);
-        let a3 = x.g1 * (*u3);
-        let a4 = phi::<C1, C2>(u4);
-        let a5 = x.g2 * (*u5);
-        let a = (a1, a2, a3, a4, a5);
-
-        let (c1, _) = Self::compute_challenge(a, x);
-
-        let r1 = w.point * c1 + x.x * (*u1 - *u2);
-        let r2 = *u2 + c1 * w.y;
-        let r3 = *u3 + c1 * w.w;
-        let aux = w.s * c1;
-        let s_c1 = scalar_transformation::<C1, C2>(&aux);
-        let r4 = (*u4) + w.s * c1;
-        let r5 = *u5 + s_c1;
-        let r = (r1, r2, r3, r4, r5);
+        let (a, st) = Self::commit(crs, x, w);
+
+        let c = Self::compute_challenge(crs, &a, x);
+
+        let r = Self::respond(crs, x, w, &st, &c);
 
         Proof::<C1, C2> { a, r }
     }
 
     fn verify(crs: &Self::CRS, x: &Self::Statement, p: &Self::Proof) -> bool {
-        let (a1, a2, a3, a4, a5) = p.a;
-        let (r1, r2, r3, r4, r5) = p.r;
+        let c = Self::compute_challenge(crs, &p.a, x);
+
+        Self::check(crs, x, &p.a, &c, &p.r)
+    }
+}
 
-        let (c1, c2) = Self::compute_challenge(p.a, x);
+impl<C1, C2, H1, H2> SigmaProtocol for SigmaProofPhi<C1, C2, H1, H2>
+where
+    C1: CurveArithmetic,
+    C2: CurveArithmetic,
+    H2: Digest<OutputSize = C2::FieldBytesSize>,
+    H1: Digest<OutputSize = C1::FieldBytesSize>,
+    C1::AffinePoint: DecompressPoint<C1>,
+    C2::AffinePoint: DecompressPoint<C2>,
+    C1: GroupDigest,
+    C1::ProjectivePoint: CofactorGroup,
+    C2: GroupDigest,
+    C2::ProjectivePoint: CofactorGroup,
+{
+    type Commitment = PhiCommitment<C1, C2>;
+    // state of the DLEQ proof, nonces (u_y, u_w, u_t) and the blinding t of P1
+    type State = (
+        <CrossGroupDLEQ<C1, C2, H1, H2> as SigmaProtocol>::State,
+        (C1::Scalar, C1::Scalar, C1::Scalar),
+        C1::Scalar,
+    );
+    type Challenge = u128;
+    type Response = PhiResponse<C1, C2>;
+
+    fn commit(
+        crs: &Self::CRS,
+        x: &Self::Statement,
+        w: &Self::Witness,
+    ) -> (Self::Commitment, Self::State) {
+        let t = C1::Scalar::random(&mut OsRng);
+        let dleq_x = cross_group_dleq::Statement::new(crs.g1 * w.s + crs.h1 * t, x.point_2);
+        let dleq_w = cross_group_dleq::Witness::new(w.s, t, C2::Scalar::ZERO);
+        let (a_dleq, st_dleq) = CrossGroupDLEQ::<C1, C2, H1, H2>::commit(crs, &dleq_x, &dleq_w);
+
+        let u_y = *NonZeroScalar::<C1>::random(&mut OsRng).as_ref();
+        let u_w = *NonZeroScalar::<C1>::random(&mut OsRng).as_ref();
+        let u_t = *NonZeroScalar::<C1>::random(&mut OsRng).as_ref();
+
+        let a1 = crs.g1 * u_y;
+        let a2 = crs.g1 * u_w;
+        let a3 = x.x * u_y - crs.h1 * u_t;
+
+        ((a_dleq, (a1, a2, a3)), (st_dleq, (u_y, u_w, u_t), t))
+    }
 
-        let (ct_a, ct_b) = x.ct;
+    fn respond(
+        crs: &Self::CRS,
+        x: &Self::Statement,
+        w: &Self::Witness,
+        st: &Self::State,
+        c: &Self::Challenge,
+    ) -> Self::Response {
+        let (st_dleq, (u_y, u_w, u_t), t) = st;
+        let dleq_x = cross_group_dleq::Statement::new(crs.g1 * w.s + crs.h1 * t, x.point_2);
+        let dleq_w = cross_group_dleq::Witness::new(w.s, *t, C2::Scalar::ZERO);
+        let r_dleq = CrossGroupDLEQ::<C1, C2, H1, H2>::respond(crs, &dleq_x, &dleq_w, st_dleq, c);
+
+        let c1 = C1::Scalar::from_u128(*c);
+        let r_y = *u_y + c1 * w.y;
+        let r_w = *u_w + c1 * w.w;
+        let r_t = *u_t + c1 * t;
+
+        (r_dleq, (r_y, r_w, r_t))
+    }
+
+    fn check(
+        crs: &Self::CRS,
+        x: &Self::Statement,
+        a: &Self::Commitment,
+        c: &Self::Challenge,
+        r: &Self::Response,
+    ) -> bool {
+        let (a_dleq, a_linear) = a;
+        let (r_dleq, r_linear) = r;
+        let (p1, _) = cross_group_dleq::commitment_sum::<C1, C2>(a_dleq);
+        let dleq_x = cross_group_dleq::Statement::new(p1, x.point_2);
+        let c1 = C1::Scalar::from_u128(*c);
+
+        CrossGroupDLEQ::<C1, C2, H1, H2>::check(crs, &dleq_x, a_dleq, c, r_dleq)
+            && Self::linear_commitment(crs, x, &p1, &c1, r_linear) == *a_linear
+    }
+
+    fn simulate(
+        crs: &Self::CRS,
+        x: &Self::Statement,
+        c: &Self::Challenge,
+    ) -> (Self::Commitment, Self::Response) {
+        // P1 is a uniformly random point, as the Pedersen commitment of a real proof
+        let p1 = crs.h1 * C1::Scalar::random(&mut OsRng);
+        let dleq_x = cross_group_dleq::Statement::new(p1, x.point_2);
+        let (a_dleq, r_dleq) = CrossGroupDLEQ::<C1, C2, H1, H2>::simulate(crs, &dleq_x, c);
+
+        let r_linear = (
+            C1::Scalar::random(&mut OsRng),
+            C1::Scalar::random(&mut OsRng),
+            C1::Scalar::random(&mut OsRng),
+        );
+        let a_linear = Self::linear_commitment(crs, x, &p1, &C1::Scalar::from_u128(*c), &r_linear);
+
+        ((a_dleq, a_linear), (r_dleq, r_linear))
+    }
+}
+
+// s is extracted by the extractor of the DLEQ proof, and y and w as in a Schnorr proof.
+impl<C1, C2, H1, H2> SpecialSoundness for SigmaProofPhi<C1, C2, H1, H2>
+where
+    C1: CurveArithmetic,
+    C2: CurveArithmetic,
+    H2: Digest<OutputSize = C2::FieldBytesSize>,
+    H1: Digest<OutputSize = C1::FieldBytesSize>,
+    C1::AffinePoint: DecompressPoint<C1>,
+    C2::AffinePoint: DecompressPoint<C2>,
+    C1: GroupDigest,
+    C1::ProjectivePoint: CofactorGroup,
+    C2: GroupDigest,
+    C2::ProjectivePoint: CofactorGroup,
+{
+    fn extract(
+        crs: &Self::CRS,
+        x: &Self::Statement,
+        a: &Self::Commitment,
+        c1: &Self::Challenge,
+        r1: &Self::Response,
+        c2: &Self::Challenge,
+        r2: &Self::Response,
+    ) -> Option<Self::Witness> {
+        if !Self::check(crs, x, a, c1, r1) || !Self::check(crs, x, a, c2, r2) {
+            return None;
+        }
+        let (p1, _) = cross_group_dleq::commitment_sum::<C1, C2>(&a.0);
+        let dleq_x = cross_group_dleq::Statement::new(p1, x.point_2);
+        let dleq_w =
+            CrossGroupDLEQ::<C1, C2, H1, H2>::extract(crs, &dleq_x, &a.0, c1, &r1.0, c2, &r2.0)?;
+
+        let d = C1::Scalar::from_u128(*c1) - C1::Scalar::from_u128(*c2);
+        let inv: Option<C1::Scalar> = d.invert().into();
+        let inv = inv?;
+        let (r1_y, r1_w, _) = r1.1;
+        let (r2_y, r2_w, _) = r2.1;
+
+        let s = dleq_w.s();
+        let y = (r1_y - r2_y) * inv;
+        let w = (r1_w - r2_w) * inv;
+
+        Some(Witness::new(s, w, y, crs.g1 * s))
+    }
+}
+
+impl<C1, C2, H1, H2> EncodableProof for SigmaProofPhi<C1, C2, H1, H2>
+where
+    C1: CurveArithmetic,
+    C2: CurveArithmetic,
+    H2: Digest<OutputSize = C2::FieldBytesSize>,
+    H1: Digest<OutputSize = C1::FieldBytesSize>,
+    C1::AffinePoint: DecompressPoint<C1>,
+    C2::AffinePoint: DecompressPoint<C2>,
+    C1: GroupDigest,
+    C1::ProjectivePoint: CofactorGroup,
+    C2: GroupDigest,
+    C2::ProjectivePoint: CofactorGroup,
+{
+    fn encode_proof(
+        crs: &Self::CRS,
+        x: &Self::Statement,
+        p: &Self::Proof,
+        encoding: ProofEncoding,
+    ) -> Vec<u8> {
+        match encoding {
+            ProofEncoding::Commitments => p.to_byte_vector(),
+            // the commitments of the rings and of the sigma proof over C1 are dropped, but not
+            // the bit commitments
+            ProofEncoding::ChallengeResponse => {
+                let (r_dleq, (r_y, r_w, r_t)) = &p.r;
+                let c = Self::compute_challenge(crs, &p.a, x);
+                let mut v: Vec<u8> = Vec::new();
+                v.extend_from_slice(&c.to_be_bytes());
+                v.append(&mut cross_group_dleq::bits_to_byte_vector::<C1, C2>(&p.a.0));
+                v.append(&mut cross_group_dleq::response_to_byte_vector::<C1, C2>(
+                    r_dleq,
+                ));
+                v.append(&mut scalar_to_byte_vector::<C1>(r_y));
+                v.append(&mut scalar_to_byte_vector::<C1>(r_w));
+                v.append(&mut scalar_to_byte_vector::<C1>(r_t));
+
+                v
+            }
+        }
+    }
 
-        a1 + ct_b * c1 == r1 + x.x * r2
-            && a2 + ct_a * c1 == x.g1 * r2
-            && a3 + x.x * c1 == x.g1 * r3
-            && phi::<C1, C2>(&r4) == a4 + x.point_2 * c2
-            && x.g2 * r5 == a5 + x.point_2 * c2
+    fn decode_proof(
+        crs: &Self::CRS,
+        x: &Self::Statement,
+        bytes: &[u8],
+        encoding: ProofEncoding,
+    ) -> Option<Self::Proof> {
+        let n = cross_group_dleq::bit_length::<C1, C2>();
+        let mut reader = ByteReader::new(bytes);
+        let p = match encoding {
+            ProofEncoding::Commitments => {
+                let a_dleq = cross_group_dleq::read_commitment::<C1, C2>(&mut reader, n, true)?;
+                let a1 = reader.read_point::<C1>()?;
+                let a2 = reader.read_point::<C1>()?;
+                let a3 = reader.read_point::<C1>()?;
+                let r_dleq = cross_group_dleq::read_response::<C1, C2>(&mut reader, n)?;
+                let r_y = reader.read_scalar::<C1>()?;
+                let r_w = reader.read_scalar::<C1>()?;
+                let r_t = reader.read_scalar::<C1>()?;
+                Proof::<C1, C2> {
+                    a: (a_dleq, (a1, a2, a3)),
+                    r: (r_dleq, (r_y, r_w, r_t)),
+                }
+            }
+            ProofEncoding::ChallengeResponse => {
+                let c = cross_group_dleq::challenge_from_digest(
+                    reader.read_bytes(cross_group_dleq::CHALLENGE_BYTES)?,
+                );
+                let mut a_dleq =
+                    cross_group_dleq::read_commitment::<C1, C2>(&mut reader, n, false)?;
+                let r_dleq = cross_group_dleq::read_response::<C1, C2>(&mut reader, n)?;
+                let r_y = reader.read_scalar::<C1>()?;
+                let r_w = reader.read_scalar::<C1>()?;
+                let r_t = reader.read_scalar::<C1>()?;
+                let r_linear = (r_y, r_w, r_t);
+
+                cross_group_dleq::ring_commitments::<C1, C2>(crs, &mut a_dleq, c, &r_dleq);
+                let (p1, _) = cross_group_dleq::commitment_sum::<C1, C2>(&a_dleq);
+                let a_linear =
+                    Self::linear_commitment(crs, x, &p1, &C1::Scalar::from_u128(c), &r_linear);
+                let a = (a_dleq, a_linear);
+                if Self::compute_challenge(crs, &a, x) != c {
+                    return None;
+                }
+                Proof::<C1, C2> {
+                    a,
+                    r: (r_dleq, r_linear),
+                }
+            }
+        };
+
+        reader.is_empty().then_some(p)
     }
 }
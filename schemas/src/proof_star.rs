@@ -1,9 +1,13 @@
-use crate::nizk::NIZK;
-use crate::utils::proj;
-use crate::HardRelation;
+use crate::elgamal::ElGamal;
+use crate::hard_relation::HardRelation;
+use crate::nizk::{EncodableProof, ProofEncoding, NIZK};
+use crate::public_key_encryption_scheme::PublicKeyEncryptionScheme;
+use crate::sigma_protocol::SigmaProtocol;
+use crate::utils::{point_to_byte_vector, proj, scalar_to_byte_vector, ByteReader};
 use digest::Digest;
 use elliptic_curve::ff::Field;
 use elliptic_curve::ops::Reduce;
+use elliptic_curve::point::DecompressPoint;
 use elliptic_curve::scalar::NonZeroScalar;
 use elliptic_curve::{CurveArithmetic, Group};
 use rand_core::OsRng;
@@ -32,7 +36,9 @@ impl<C> Witness<C>
 where
     C: CurveArithmetic,
 {
-    fn new(s: C::Scalar, e: C::Scalar, y: C::Scalar, w: C::Scalar) -> Self {
+    // s is the committed secret, e the randomness of the commitment, y the randomness of the
+    // encryption and w the witness of the adaptor statement x (which is the decryption key).
+    pub fn new(s: C::Scalar, e: C::Scalar, y: C::Scalar, w: C::Scalar) -> Self {
         Witness { s, e, y, w }
     }
 }
@@ -41,7 +47,6 @@ pub struct Statement<C>
 where
     C: CurveArithmetic,
 {
-    g: C::ProjectivePoint,
     h: C::ProjectivePoint,
     c_s: C::ProjectivePoint,
     y: C::ProjectivePoint,
@@ -54,17 +59,17 @@ impl<C> Statement<C>
 where
     C: CurveArithmetic,
 {
-    fn new(
-        g: C::ProjectivePoint,
+    // ct is the ElGamal encryption of g^s under pk, as returned by ElGamal::enc, where g is the
+    // generator of the CRS
+    pub fn new(
         h: C::ProjectivePoint,
         c_s: C::ProjectivePoint,
-        y: C::ProjectivePoint,
-        m: C::ProjectivePoint,
+        ct: <ElGamal<C> as PublicKeyEncryptionScheme>::CypherText,
         pk: C::ProjectivePoint,
         x: C::ProjectivePoint,
     ) -> Self {
+        let (y, m) = ct;
         Statement {
-            g,
             h,
             c_s,
             y,
@@ -73,6 +78,22 @@ where
             x,
         }
     }
+
+    pub fn ct(&self) -> <ElGamal<C> as PublicKeyEncryptionScheme>::CypherText {
+        (self.y, self.m)
+    }
+
+    pub fn to_byte_vector(&self) -> Vec<u8> {
+        let mut v: Vec<u8> = Vec::new();
+        v.append(&mut point_to_byte_vector::<C>(&self.h));
+        v.append(&mut point_to_byte_vector::<C>(&self.c_s));
+        v.append(&mut point_to_byte_vector::<C>(&self.y));
+        v.append(&mut point_to_byte_vector::<C>(&self.m));
+        v.append(&mut point_to_byte_vector::<C>(&self.pk));
+        v.append(&mut point_to_byte_vector::<C>(&self.x));
+
+        v
+    }
 }
 
 // Relation* = {(c_s, x, h, ct; w, s) | c_s = commit(s) &
@@ -80,50 +101,115 @@ where
 //                                      ct = enc(pk, s) &
 //                                      pk = g^w}
 // in our case, we have:
-// Relation* = {(c_s, x, h, (Y, M), pk; s, y, w) | c_s = g^e * h^s &
-//                                                 Y = g^y &
-//                                                 M = s * pk^y &
-//                                                 x = g^w &
-//                                                 pk = g^w}
+// Relation* = {(c_s, x, h, (Y, M), pk; s, e, y, w) | c_s = g^e * h^s &
+//                                                    Y = g^y &
+//                                                    M = g^s * pk^y &
+//                                                    x = g^w &
+//                                                    pk = g^w}
+// where g is the generator given by the CRS, not by the prover. Only g^s is encrypted, so the
+// buyer of a secret sold with this proof obtains g^s, not s.
 impl<C> HardRelation<Statement<C>, Witness<C>> for Witness<C>
 where
     C: CurveArithmetic,
 {
-    fn R(w: &Witness<C>, x: &Statement<C>) -> bool {
-        (x.c_s == x.g * w.e + x.h * w.s)
-            && (x.y == x.g * w.y)
-            && (x.m == w.s + x.pk * w.y)
-            && (x.x == x.g * w.w)
-            && (x.pk == x.g * w.w)
-    }
-
-    fn statement(w: &Witness<C>) -> Statement<C> {
-        //let g = C::ProjectivePoint::generator();
-        //let h = C::ProjectivePoint::random(&mut OsRng);
-        //let c_s = g * w.e + h * w.s;
-        //let y = g * w.y;
-        //let pk = g * w.w;
-        //let m = w.s + pk * w.y;
-        //let x = g * w.w;
-        //Statement::<C>::new(g, h, c_s, y, m, pk, x)
-        unimplemented!("This function should never be called!");
+    type PP = C::ProjectivePoint;
+
+    fn R(g: &Self::PP, w: &Witness<C>, x: &Statement<C>) -> bool {
+        (x.c_s == *g * w.e + x.h * w.s)
+            && (x.y == *g * w.y)
+            && (x.m == *g * w.s + x.pk * w.y)
+            && (x.x == *g * w.w)
+            && (x.pk == *g * w.w)
     }
 
-    fn gen() -> (Witness<C>, Statement<C>) {
-        //let s = C::Scalar::random(&mut OsRng);
-        //let e = C::Scalar::random(&mut OsRng);
-        //let y = C::Scalar::random(&mut OsRng);
-        //let w = C::Scalar::random(&mut OsRng);
-        //let witness = Witness::<C>::new(s, e, y, w);
-        //let statement = Self::statement(&witness);
+    fn statement(_pp: &Self::PP, _w: &Witness<C>) -> Statement<C> {
+        unimplemented!("This function should never be called!");
+    }
 
-        //(witness, statement)
+    fn gen(_pp: &Self::PP) -> (Witness<C>, Statement<C>) {
         unimplemented!("This function should never be called!");
     }
 }
 
-type Point<C: CurveArithmetic> = C::ProjectivePoint;
-type Scalar<C: CurveArithmetic> = C::Scalar;
+pub struct Proof<C>
+where
+    C: CurveArithmetic,
+{
+    a: (
+        C::ProjectivePoint,
+        C::ProjectivePoint,
+        C::ProjectivePoint,
+        C::ProjectivePoint,
+    ),
+    r: (C::Scalar, C::Scalar, C::Scalar, C::Scalar),
+}
+
+impl<C> Proof<C>
+where
+    C: CurveArithmetic,
+{
+    pub fn to_byte_vector(&self) -> Vec<u8> {
+        let (a_c, a_y, a_m, a_w) = self.a;
+        let (r_e, r_s, r_y, r_w) = self.r;
+        let mut v: Vec<u8> = Vec::new();
+        v.append(&mut point_to_byte_vector::<C>(&a_c));
+        v.append(&mut point_to_byte_vector::<C>(&a_y));
+        v.append(&mut point_to_byte_vector::<C>(&a_m));
+        v.append(&mut point_to_byte_vector::<C>(&a_w));
+        v.append(&mut scalar_to_byte_vector::<C>(&r_e));
+        v.append(&mut scalar_to_byte_vector::<C>(&r_s));
+        v.append(&mut scalar_to_byte_vector::<C>(&r_y));
+        v.append(&mut scalar_to_byte_vector::<C>(&r_w));
+
+        v
+    }
+}
+
+fn compute_challenge<C, H>(
+    g: &C::ProjectivePoint,
+    a: (
+        C::ProjectivePoint,
+        C::ProjectivePoint,
+        C::ProjectivePoint,
+        C::ProjectivePoint,
+    ),
+    x: &Statement<C>,
+) -> C::Scalar
+where
+    C: CurveArithmetic,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+{
+    let (a_c, a_y, a_m, a_w) = a;
+
+    let proj_g = proj::<C>(g);
+    let proj_h = proj::<C>(&x.h);
+    let proj_c_s = proj::<C>(&x.c_s);
+    let proj_y = proj::<C>(&x.y);
+    let proj_m = proj::<C>(&x.m);
+    let proj_pk = proj::<C>(&x.pk);
+    let proj_x = proj::<C>(&x.x);
+    let proj_a_c = proj::<C>(&a_c);
+    let proj_a_y = proj::<C>(&a_y);
+    let proj_a_m = proj::<C>(&a_m);
+    let proj_a_w = proj::<C>(&a_w);
+
+    let hasher = H::new();
+    <C::Scalar as Reduce<C::Uint>>::reduce_bytes(
+        &hasher
+            .chain_update(proj_g)
+            .chain_update(proj_h)
+            .chain_update(proj_c_s)
+            .chain_update(proj_y)
+            .chain_update(proj_m)
+            .chain_update(proj_pk)
+            .chain_update(proj_x)
+            .chain_update(proj_a_c)
+            .chain_update(proj_a_y)
+            .chain_update(proj_a_m)
+            .chain_update(proj_a_w)
+            .finalize(),
+    )
+}
 
 impl<C, H> NIZK for SigmaProofStar<C, H>
 where
@@ -133,112 +219,256 @@ where
     type CRS = C::ProjectivePoint;
     type Statement = Statement<C>;
     type Witness = Witness<C>;
-    type Proof = (
-        (Point<C>, Point<C>, Point<C>, Point<C>, Point<C>),
-        (Scalar<C>, Scalar<C>, Scalar<C>, Scalar<C>, Scalar<C>),
-    );
+    type Proof = Proof<C>;
 
     fn crs_gen() -> Self::CRS {
         Self::CRS::generator()
     }
 
     fn prove(crs: &Self::CRS, x: &Self::Statement, w: &Self::Witness) -> Self::Proof {
-        let _u_11 = NonZeroScalar::<C>::random(&mut OsRng);
-        let _u_12 = NonZeroScalar::<C>::random(&mut OsRng);
-        let _u_32 = NonZeroScalar::<C>::random(&mut OsRng);
-        let _u_2 = NonZeroScalar::<C>::random(&mut OsRng);
-        let u_11 = _u_11.as_ref();
-        let u_12 = _u_12.as_ref();
-        let u_32 = _u_32.as_ref();
-        let u_2 = _u_2.as_ref();
-
-        let g = x.g;
-        let h = x.h;
+        let (a, u) = Self::commit(crs, x, w);
+
+        let c = compute_challenge::<C, H>(crs, a, x);
 
-        let a_11 = g * u_11;
-        let a_12 = h * u_12;
-        let a_31 = u_11 + x.pk * u_11;
-        let a_32 = g * u_32;
-        let a_2 = g * u_2;
-        let a = (a_11, a_12, a_31, a_32, a_2);
-
-        let proj_g = proj::<C>(&g);
-        let proj_h = proj::<C>(&h);
-        let proj_c_s = proj::<C>(&x.c_s);
-        let proj_y = proj::<C>(&x.y);
-        let proj_m = proj::<C>(&x.m);
-        let proj_x = proj::<C>(&x.x);
-        let proj_a_11 = proj::<C>(&a_11);
-        let proj_a_12 = proj::<C>(&a_12);
-        let proj_a_31 = proj::<C>(&a_31);
-        let proj_a_32 = proj::<C>(&a_32);
-        let proj_a_2 = proj::<C>(&a_2);
-
-        let hasher = H::new();
-        let c = <C::Scalar as Reduce<C::Uint>>::reduce_bytes(
-            &hasher
-                .chain_update(proj_g)
-                .chain_update(proj_h)
-                .chain_update(proj_c_s)
-                .chain_update(proj_x)
-                .chain_update(proj_a_11)
-                .chain_update(proj_a_12)
-                .chain_update(proj_a_31)
-                .chain_update(proj_a_32)
-                .chain_update(proj_a_2)
-                .finalize(),
-        );
-
-        let r_11 = u_11 + c * w.e;
-        let r_12 = u_12 + c * w.s;
-        let r_31 = u_11 + c * w.s;
-        let r_32 = u_32 + c * w.y;
-        let r_2 = u_2 + c * w.w;
-
-        let r = (r_11, r_12, r_31, r_32, r_2);
-
-        (a, r)
+        let r = Self::respond(crs, x, w, &u, &c);
+
+        Proof::<C> { a, r }
     }
 
     fn verify(crs: &Self::CRS, x: &Self::Statement, p: &Self::Proof) -> bool {
-        let (a, r) = *p;
-        let (a_11, a_12, a_31, a_32, a_2) = a;
-        let (r_11, r_12, r_31, r_32, r_2) = r;
+        let c = compute_challenge::<C, H>(crs, p.a, x);
+
+        Self::check(crs, x, &p.a, &c, &p.r)
+    }
+}
+
+impl<C, H> SigmaProtocol for SigmaProofStar<C, H>
+where
+    C: CurveArithmetic,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+{
+    type Commitment = (
+        C::ProjectivePoint,
+        C::ProjectivePoint,
+        C::ProjectivePoint,
+        C::ProjectivePoint,
+    );
+    type State = (C::Scalar, C::Scalar, C::Scalar, C::Scalar);
+    type Challenge = C::Scalar;
+    type Response = (C::Scalar, C::Scalar, C::Scalar, C::Scalar);
 
-        let g = x.g;
+    fn commit(
+        crs: &Self::CRS,
+        x: &Self::Statement,
+        _w: &Self::Witness,
+    ) -> (Self::Commitment, Self::State) {
+        let nz_u_e = NonZeroScalar::<C>::random(&mut OsRng);
+        let nz_u_s = NonZeroScalar::<C>::random(&mut OsRng);
+        let nz_u_y = NonZeroScalar::<C>::random(&mut OsRng);
+        let nz_u_w = NonZeroScalar::<C>::random(&mut OsRng);
+        let u_e = nz_u_e.as_ref();
+        let u_s = nz_u_s.as_ref();
+        let u_y = nz_u_y.as_ref();
+        let u_w = nz_u_w.as_ref();
+
+        let g = *crs;
+        let h = x.h;
+
+        // the same u_s is used for the commitment and the ciphertext, which links both to s
+        let a_c = g * u_e + h * u_s;
+        let a_y = g * u_y;
+        let a_m = g * u_s + x.pk * u_y;
+        let a_w = g * u_w;
+
+        ((a_c, a_y, a_m, a_w), (*u_e, *u_s, *u_y, *u_w))
+    }
+
+    fn respond(
+        _crs: &Self::CRS,
+        _x: &Self::Statement,
+        w: &Self::Witness,
+        st: &Self::State,
+        c: &Self::Challenge,
+    ) -> Self::Response {
+        let (u_e, u_s, u_y, u_w) = *st;
+
+        let r_e = u_e + *c * w.e;
+        let r_s = u_s + *c * w.s;
+        let r_y = u_y + *c * w.y;
+        let r_w = u_w + *c * w.w;
+
+        (r_e, r_s, r_y, r_w)
+    }
+
+    fn check(
+        crs: &Self::CRS,
+        x: &Self::Statement,
+        a: &Self::Commitment,
+        c: &Self::Challenge,
+        r: &Self::Response,
+    ) -> bool {
+        let (a_c, a_y, a_m, a_w) = *a;
+        let (r_e, r_s, r_y, r_w) = *r;
+
+        let g = *crs;
         let h = x.h;
 
-        let proj_g = proj::<C>(&g);
-        let proj_h = proj::<C>(&h);
-        let proj_c_s = proj::<C>(&x.c_s);
-        let proj_y = proj::<C>(&x.y);
-        let proj_m = proj::<C>(&x.m);
-        let proj_x = proj::<C>(&x.x);
-        let proj_a_11 = proj::<C>(&a_11);
-        let proj_a_12 = proj::<C>(&a_12);
-        let proj_a_31 = proj::<C>(&a_31);
-        let proj_a_32 = proj::<C>(&a_32);
-        let proj_a_2 = proj::<C>(&a_2);
-
-        let hasher = H::new();
-        let c = <C::Scalar as Reduce<C::Uint>>::reduce_bytes(
-            &hasher
-                .chain_update(proj_g)
-                .chain_update(proj_h)
-                .chain_update(proj_c_s)
-                .chain_update(proj_x)
-                .chain_update(proj_a_11)
-                .chain_update(proj_a_12)
-                .chain_update(proj_a_31)
-                .chain_update(proj_a_32)
-                .chain_update(proj_a_2)
-                .finalize(),
-        );
-
-        (g * r_2 == a_2 + x.x * c)
-            && (g * r_2 == a_2 + x.pk * c)
-            && (g * r_11 + h * r_12 == a_11 + a_12 + x.c_s * c)
-            && (a_31 + x.m * c == r_31 + x.pk * c)
-            && (a_32 + x.y * c == g * r_32)
+        (g * r_e + h * r_s == a_c + x.c_s * c)
+            && (g * r_y == a_y + x.y * c)
+            && (g * r_s + x.pk * r_y == a_m + x.m * c)
+            && (g * r_w == a_w + x.x * c)
+            && (g * r_w == a_w + x.pk * c)
+    }
+
+    fn simulate(
+        crs: &Self::CRS,
+        x: &Self::Statement,
+        c: &Self::Challenge,
+    ) -> (Self::Commitment, Self::Response) {
+        let g = *crs;
+        let r_e = C::Scalar::random(&mut OsRng);
+        let r_s = C::Scalar::random(&mut OsRng);
+        let r_y = C::Scalar::random(&mut OsRng);
+        let r_w = C::Scalar::random(&mut OsRng);
+
+        let a_c = g * r_e + x.h * r_s - x.c_s * c;
+        let a_y = g * r_y - x.y * c;
+        let a_m = g * r_s + x.pk * r_y - x.m * c;
+        let a_w = g * r_w - x.x * c;
+
+        ((a_c, a_y, a_m, a_w), (r_e, r_s, r_y, r_w))
+    }
+}
+
+impl<C, H> EncodableProof for SigmaProofStar<C, H>
+where
+    C: CurveArithmetic,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+    C::AffinePoint: DecompressPoint<C>,
+{
+    fn encode_proof(
+        crs: &Self::CRS,
+        x: &Self::Statement,
+        p: &Self::Proof,
+        encoding: ProofEncoding,
+    ) -> Vec<u8> {
+        match encoding {
+            ProofEncoding::Commitments => p.to_byte_vector(),
+            ProofEncoding::ChallengeResponse => {
+                let (r_e, r_s, r_y, r_w) = p.r;
+                let c = compute_challenge::<C, H>(crs, p.a, x);
+                let mut v: Vec<u8> = Vec::new();
+                v.append(&mut scalar_to_byte_vector::<C>(&c));
+                v.append(&mut scalar_to_byte_vector::<C>(&r_e));
+                v.append(&mut scalar_to_byte_vector::<C>(&r_s));
+                v.append(&mut scalar_to_byte_vector::<C>(&r_y));
+                v.append(&mut scalar_to_byte_vector::<C>(&r_w));
+
+                v
+            }
+        }
+    }
+
+    fn decode_proof(
+        crs: &Self::CRS,
+        x: &Self::Statement,
+        bytes: &[u8],
+        encoding: ProofEncoding,
+    ) -> Option<Self::Proof> {
+        let mut reader = ByteReader::new(bytes);
+        let p = match encoding {
+            ProofEncoding::Commitments => {
+                let a_c = reader.read_point::<C>()?;
+                let a_y = reader.read_point::<C>()?;
+                let a_m = reader.read_point::<C>()?;
+                let a_w = reader.read_point::<C>()?;
+                let r_e = reader.read_scalar::<C>()?;
+                let r_s = reader.read_scalar::<C>()?;
+                let r_y = reader.read_scalar::<C>()?;
+                let r_w = reader.read_scalar::<C>()?;
+                Proof::<C> {
+                    a: (a_c, a_y, a_m, a_w),
+                    r: (r_e, r_s, r_y, r_w),
+                }
+            }
+            ProofEncoding::ChallengeResponse => {
+                let c = reader.read_scalar::<C>()?;
+                let r_e = reader.read_scalar::<C>()?;
+                let r_s = reader.read_scalar::<C>()?;
+                let r_y = reader.read_scalar::<C>()?;
+                let r_w = reader.read_scalar::<C>()?;
+                let a_c = *crs * r_e + x.h * r_s - x.c_s * c;
+                let a_y = *crs * r_y - x.y * c;
+                let a_m = *crs * r_s + x.pk * r_y - x.m * c;
+                let a_w = *crs * r_w - x.x * c;
+                let a = (a_c, a_y, a_m, a_w);
+                if compute_challenge::<C, H>(crs, a, x) != c {
+                    return None;
+                }
+                Proof::<C> {
+                    a,
+                    r: (r_e, r_s, r_y, r_w),
+                }
+            }
+        };
+
+        reader.is_empty().then_some(p)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pedersen_commitment::PedersenGens;
+    use k256::Secp256k1;
+    use sha2::Sha256;
+
+    type C = Secp256k1;
+    type Point = <C as CurveArithmetic>::ProjectivePoint;
+    type Scalar = <C as CurveArithmetic>::Scalar;
+    type Nizk = SigmaProofStar<C, Sha256>;
+
+    // the statement of a secret s sold under x, with ct encrypting g^s, or g^other if given
+    fn instance(other: Option<Scalar>) -> (Statement<C>, Witness<C>) {
+        let g = Point::GENERATOR;
+        let h = PedersenGens::<C>::nums().h;
+        let s = Scalar::random(&mut OsRng);
+        let e = Scalar::random(&mut OsRng);
+        let (w, x) = NonZeroScalar::<C>::gen(&g);
+        let (ct, y) = ElGamal::<C>::enc(&x, &(g * other.unwrap_or(s)));
+        let statement = Statement::<C>::new(h, g * e + h * s, ct, x, x);
+        let witness = Witness::<C>::new(s, e, y, *w);
+
+        (statement, witness)
+    }
+
+    #[test]
+    fn proof_star() {
+        let crs = Nizk::crs_gen();
+        let (statement, witness) = instance(None);
+        assert!(Witness::<C>::R(&crs, &witness, &statement));
+        let proof = Nizk::prove(&crs, &statement, &witness);
+        assert!(Nizk::verify(&crs, &statement, &proof));
+
+        for encoding in [ProofEncoding::Commitments, ProofEncoding::ChallengeResponse] {
+            let bytes = Nizk::encode_proof(&crs, &statement, &proof, encoding);
+            let decoded = Nizk::decode_proof(&crs, &statement, &bytes, encoding).unwrap();
+            assert!(Nizk::verify(&crs, &statement, &decoded));
+        }
+
+        // the base of the proof is the generator of the CRS, not one chosen by the prover
+        let other_crs = crs * Scalar::from(2u64);
+        assert!(!Nizk::verify(&other_crs, &statement, &proof));
+    }
+
+    #[test]
+    fn proof_star_other_secret() {
+        // ct encrypts g^s' for an s' other than the committed s
+        let crs = Nizk::crs_gen();
+        let (statement, witness) = instance(Some(Scalar::random(&mut OsRng)));
+        assert!(!Witness::<C>::R(&crs, &witness, &statement));
+        let proof = Nizk::prove(&crs, &statement, &witness);
+
+        assert!(!Nizk::verify(&crs, &statement, &proof));
     }
 }
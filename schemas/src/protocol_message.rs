@@ -0,0 +1,748 @@
+use crate::bip340;
+use crate::schnorr_signatures::SchnorrSignature;
+use crate::selling_protocol::{
+    Goods, LockMessage, PaymentMessage, PreSignatureMessage, RefundMessage, SellingError, Terms,
+};
+use crate::utils::{point_to_byte_vector, scalar_to_byte_vector, ByteReader};
+use digest::Digest;
+use elliptic_curve::point::DecompressPoint;
+use elliptic_curve::{CurveArithmetic, FieldBytes};
+use std::fmt;
+
+// The messages that the seller and the buyer of the selling protocol exchange, and their
+// canonical binary encoding, so that parties built independently can talk to each other.
+//
+// Every message is encoded as
+//
+//   version (u16) || type (u8) || body
+//
+// with integers in big-endian, points as the x coordinate followed by one byte with the parity
+// of y, and scalars as their canonical big-endian encoding, both of the length of the field of
+// the curve. The bodies are:
+//
+//   0x00 Hello             curve (u8) || hash (u8) || goods (u8) || n (u8) || n versions (u16)
+//   0x01 Accept            curve (u8) || hash (u8) || goods (u8) || version (u16)
+//   0x02 Keys              n (u8) || n points
+//   0x03 Offer             statement (point) || proof (the rest of the message)
+//   0x04 PreSignature      pre-signature (2 scalars)
+//   0x05 Lock              signature of tx_lock (2 scalars)
+//   0x06 Payment           signature of tx_pay (2 scalars) || signature of the seller (2 scalars)
+//   0x07 Refund            signature of tx_recover (2 scalars)
+//   0x08 Abort             reason (u8)
+//   0x09 Nonce             nonce of the 2-of-2 signature of tx_pay (point)
+//   0x0a PartialSignature  partial signature of tx_pay (scalar)
+//   0x0b TaprootLock       BIP-340 signature of tx_lock (64 bytes)
+//   0x0c TaprootPayment    BIP-340 signature of tx_pay (64 bytes)
+//
+// The buyer opens with Hello, listing the versions it supports, its ciphersuite and the goods it
+// buys, and the seller answers with Accept and the highest version both support, or with Abort if
+// there is none or the ciphersuites or the goods differ. All the following messages carry the
+// negotiated version. The encodings of Hello, Accept and Abort are the same in every version, so
+// that parties with no version in common can still tell each other.
+//
+// The proof of an offer is encoded by the Goods: the PoKSchnorrSignature of SignatureGoods, the
+// PoRSchnorrSignature of ServiceGoods or the number of signatures (u16), the encrypted signatures
+// and the DLogSigmaProof of BatchSignatureGoods, with the values of its statement other than x.
+//
+// The messages 0x09 to 0x0c are the ones of schemas::taproot_protocol, which replace PreSignature,
+// Lock and Payment when the transactions are the Taproot ones of schemas::taproot.
+
+pub const PROTOCOL_VERSION: u16 = 1;
+pub const SUPPORTED_VERSIONS: [u16; 1] = [PROTOCOL_VERSION];
+
+type Point<C> = <C as CurveArithmetic>::ProjectivePoint;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CurveId {
+    Secp256k1 = 0x01,
+    NistP256 = 0x02,
+    BrainpoolP256r1 = 0x03,
+    NistP384 = 0x04,
+}
+
+impl CurveId {
+    fn from_u8(id: u8) -> Option<Self> {
+        match id {
+            0x01 => Some(CurveId::Secp256k1),
+            0x02 => Some(CurveId::NistP256),
+            0x03 => Some(CurveId::BrainpoolP256r1),
+            0x04 => Some(CurveId::NistP384),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashId {
+    Sha256 = 0x01,
+    Sha384 = 0x02,
+}
+
+impl HashId {
+    fn from_u8(id: u8) -> Option<Self> {
+        match id {
+            0x01 => Some(HashId::Sha256),
+            0x02 => Some(HashId::Sha384),
+            _ => None,
+        }
+    }
+}
+
+// The curve and the hash function that instantiate the schemes of the protocol
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ciphersuite {
+    pub curve: CurveId,
+    pub hash: HashId,
+}
+
+impl Ciphersuite {
+    pub fn new(curve: CurveId, hash: HashId) -> Self {
+        Self { curve, hash }
+    }
+
+    fn to_byte_vector(self) -> Vec<u8> {
+        vec![self.curve as u8, self.hash as u8]
+    }
+
+    fn read(reader: &mut ByteReader) -> Option<Self> {
+        let bytes = reader.read_bytes(2)?;
+
+        Some(Self::new(
+            CurveId::from_u8(bytes[0])?,
+            HashId::from_u8(bytes[1])?,
+        ))
+    }
+}
+
+// What is sold, so that a buyer of some goods does not go on with a seller of other ones
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GoodsId {
+    Signature = 0x01,
+    Service = 0x02,
+    BatchSignature = 0x03,
+}
+
+impl GoodsId {
+    fn read(reader: &mut ByteReader) -> Option<Self> {
+        match reader.read_bytes(1)?[0] {
+            0x01 => Some(GoodsId::Signature),
+            0x02 => Some(GoodsId::Service),
+            0x03 => Some(GoodsId::BatchSignature),
+            _ => None,
+        }
+    }
+}
+
+// Why a party stops the protocol
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AbortReason {
+    UnsupportedVersion = 0x01,
+    UnsupportedCiphersuite = 0x02,
+    MalformedMessage = 0x03,
+    UnexpectedMessage = 0x04,
+    InvalidOffer = 0x05,
+    InvalidPreSignature = 0x06,
+    InvalidLock = 0x07,
+    InvalidPayment = 0x08,
+    TimelockExpired = 0x09,
+    InvalidPartialSignature = 0x0a,
+    UnsupportedGoods = 0x0b,
+    UnknownNotary = 0x0c,
+    Other = 0xff,
+}
+
+impl AbortReason {
+    fn from_u8(reason: u8) -> Self {
+        match reason {
+            0x01 => AbortReason::UnsupportedVersion,
+            0x02 => AbortReason::UnsupportedCiphersuite,
+            0x03 => AbortReason::MalformedMessage,
+            0x04 => AbortReason::UnexpectedMessage,
+            0x05 => AbortReason::InvalidOffer,
+            0x06 => AbortReason::InvalidPreSignature,
+            0x07 => AbortReason::InvalidLock,
+            0x08 => AbortReason::InvalidPayment,
+            0x09 => AbortReason::TimelockExpired,
+            0x0a => AbortReason::InvalidPartialSignature,
+            0x0b => AbortReason::UnsupportedGoods,
+            0x0c => AbortReason::UnknownNotary,
+            // reasons of later versions
+            _ => AbortReason::Other,
+        }
+    }
+}
+
+impl From<SellingError> for AbortReason {
+    fn from(error: SellingError) -> Self {
+        match error {
+            SellingError::UnexpectedMessage => AbortReason::UnexpectedMessage,
+            SellingError::InvalidOffer => AbortReason::InvalidOffer,
+            SellingError::InvalidPreSignature => AbortReason::InvalidPreSignature,
+            SellingError::InvalidLock => AbortReason::InvalidLock,
+            SellingError::InvalidPayment | SellingError::ExtractionFailed => {
+                AbortReason::InvalidPayment
+            }
+            SellingError::TimelockExpired => AbortReason::TimelockExpired,
+            SellingError::MalformedMessage => AbortReason::MalformedMessage,
+            SellingError::InvalidPartialSignature => AbortReason::InvalidPartialSignature,
+            SellingError::UnknownNotary => AbortReason::UnknownNotary,
+            SellingError::InvalidNotarySignature | SellingError::TimelockNotExpired => {
+                AbortReason::Other
+            }
+        }
+    }
+}
+
+impl fmt::Display for AbortReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let description = match self {
+            AbortReason::UnsupportedVersion => "no protocol version in common",
+            AbortReason::UnsupportedCiphersuite => "the ciphersuites differ",
+            AbortReason::MalformedMessage => "malformed message",
+            AbortReason::UnexpectedMessage => "unexpected message",
+            AbortReason::InvalidOffer => "invalid offer",
+            AbortReason::InvalidPreSignature => "invalid pre-signature",
+            AbortReason::InvalidLock => "invalid signature of tx_lock",
+            AbortReason::InvalidPayment => "invalid payment",
+            AbortReason::TimelockExpired => "the timelock of tx_lock has expired",
+            AbortReason::InvalidPartialSignature => "invalid partial signature of tx_pay",
+            AbortReason::UnsupportedGoods => "the goods differ",
+            AbortReason::UnknownNotary => "the notary is not the one of the buyer",
+            AbortReason::Other => "aborted",
+        };
+
+        write!(f, "{}", description)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageError {
+    // The body does not decode, is truncated or has trailing bytes
+    Malformed,
+    UnknownType(u8),
+    UnsupportedVersion(u16),
+    // A list of the message has more items than its count of one byte holds
+    TooManyItems(usize),
+}
+
+impl fmt::Display for MessageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MessageError::Malformed => write!(f, "malformed message"),
+            MessageError::UnknownType(kind) => write!(f, "unknown message type 0x{:02x}", kind),
+            MessageError::UnsupportedVersion(version) => {
+                write!(f, "unsupported protocol version {}", version)
+            }
+            MessageError::TooManyItems(n) => write!(f, "{} items do not fit in one message", n),
+        }
+    }
+}
+
+impl std::error::Error for MessageError {}
+
+// The highest version in both lists
+pub fn negotiate_version(ours: &[u16], theirs: &[u16]) -> Option<u16> {
+    ours.iter().filter(|v| theirs.contains(v)).max().copied()
+}
+
+pub enum ProtocolMessage<C>
+where
+    C: CurveArithmetic,
+{
+    Hello {
+        versions: Vec<u16>,
+        ciphersuite: Ciphersuite,
+        goods: GoodsId,
+    },
+    Accept {
+        version: u16,
+        ciphersuite: Ciphersuite,
+        goods: GoodsId,
+    },
+    // The public keys of a party that the transactions pay to or are signed with
+    Keys(Vec<Point<C>>),
+    Offer {
+        statement: Point<C>,
+        proof: Vec<u8>,
+    },
+    PreSignature(PreSignatureMessage<C>),
+    Lock(LockMessage<C>),
+    // The witnesses of tx_pay: the adapted signature of the buyer and the signature of the seller
+    Payment {
+        payment: PaymentMessage<C>,
+        signature_seller: SchnorrSignature<C>,
+    },
+    Refund(RefundMessage<C>),
+    Abort(AbortReason),
+    Nonce(Point<C>),
+    PartialSignature(C::Scalar),
+    TaprootLock(bip340::Signature),
+    TaprootPayment(bip340::Signature),
+}
+
+impl<C> ProtocolMessage<C>
+where
+    C: CurveArithmetic,
+{
+    const HELLO: u8 = 0x00;
+    const ACCEPT: u8 = 0x01;
+    const KEYS: u8 = 0x02;
+    const OFFER: u8 = 0x03;
+    const PRE_SIGNATURE: u8 = 0x04;
+    const LOCK: u8 = 0x05;
+    const PAYMENT: u8 = 0x06;
+    const REFUND: u8 = 0x07;
+    const ABORT: u8 = 0x08;
+    const NONCE: u8 = 0x09;
+    const PARTIAL_SIGNATURE: u8 = 0x0a;
+    const TAPROOT_LOCK: u8 = 0x0b;
+    const TAPROOT_PAYMENT: u8 = 0x0c;
+
+    // The Offer message of an offer of the goods G. The encoding of the offer starts with its
+    // statement, and the rest is the proof.
+    pub fn offer<H, G>(offer: &G::Offer) -> Self
+    where
+        H: Digest<OutputSize = C::FieldBytesSize>,
+        G: Goods<C, H>,
+    {
+        let statement = G::statement(offer);
+        let bytes = G::offer_to_byte_vector(offer);
+        let point_len = FieldBytes::<C>::default().len() + 1;
+        debug_assert_eq!(bytes[..point_len], point_to_byte_vector::<C>(&statement));
+
+        ProtocolMessage::Offer {
+            statement,
+            proof: bytes[point_len..].to_vec(),
+        }
+    }
+
+    // The offer of the goods G in an Offer message, for the offer of the terms
+    pub fn decode_offer<H, G>(
+        terms: &Terms<C>,
+        params: &G::Params,
+        statement: &Point<C>,
+        proof: &[u8],
+    ) -> Option<G::Offer>
+    where
+        C::AffinePoint: DecompressPoint<C>,
+        H: Digest<OutputSize = C::FieldBytesSize>,
+        G: Goods<C, H>,
+    {
+        let mut bytes = point_to_byte_vector::<C>(statement);
+        bytes.extend_from_slice(proof);
+
+        G::offer_from_byte_vector(terms, params, &bytes)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ProtocolMessage::Hello { .. } => "Hello",
+            ProtocolMessage::Accept { .. } => "Accept",
+            ProtocolMessage::Keys(_) => "Keys",
+            ProtocolMessage::Offer { .. } => "Offer",
+            ProtocolMessage::PreSignature(_) => "PreSignature",
+            ProtocolMessage::Lock(_) => "Lock",
+            ProtocolMessage::Payment { .. } => "Payment",
+            ProtocolMessage::Refund(_) => "Refund",
+            ProtocolMessage::Abort(_) => "Abort",
+            ProtocolMessage::Nonce(_) => "Nonce",
+            ProtocolMessage::PartialSignature(_) => "PartialSignature",
+            ProtocolMessage::TaprootLock(_) => "TaprootLock",
+            ProtocolMessage::TaprootPayment(_) => "TaprootPayment",
+        }
+    }
+
+    // Fails if a list does not fit in its count
+    pub fn to_byte_vector(&self, version: u16) -> Result<Vec<u8>, MessageError> {
+        let count = |n: usize| u8::try_from(n).map_err(|_| MessageError::TooManyItems(n));
+        let mut v: Vec<u8> = version.to_be_bytes().to_vec();
+        match self {
+            ProtocolMessage::Hello {
+                versions,
+                ciphersuite,
+                goods,
+            } => {
+                v.push(Self::HELLO);
+                v.append(&mut ciphersuite.to_byte_vector());
+                v.push(*goods as u8);
+                v.push(count(versions.len())?);
+                for version in versions {
+                    v.extend(version.to_be_bytes());
+                }
+            }
+            ProtocolMessage::Accept {
+                version,
+                ciphersuite,
+                goods,
+            } => {
+                v.push(Self::ACCEPT);
+                v.append(&mut ciphersuite.to_byte_vector());
+                v.push(*goods as u8);
+                v.extend(version.to_be_bytes());
+            }
+            ProtocolMessage::Keys(keys) => {
+                v.push(Self::KEYS);
+                v.push(count(keys.len())?);
+                for key in keys {
+                    v.append(&mut point_to_byte_vector::<C>(key));
+                }
+            }
+            ProtocolMessage::Offer { statement, proof } => {
+                v.push(Self::OFFER);
+                v.append(&mut point_to_byte_vector::<C>(statement));
+                v.extend_from_slice(proof);
+            }
+            ProtocolMessage::PreSignature(msg) => {
+                v.push(Self::PRE_SIGNATURE);
+                v.append(&mut msg.to_byte_vector());
+            }
+            ProtocolMessage::Lock(msg) => {
+                v.push(Self::LOCK);
+                v.append(&mut msg.to_byte_vector());
+            }
+            ProtocolMessage::Payment {
+                payment,
+                signature_seller,
+            } => {
+                v.push(Self::PAYMENT);
+                v.append(&mut payment.to_byte_vector());
+                v.append(&mut signature_seller.to_byte_vector());
+            }
+            ProtocolMessage::Refund(msg) => {
+                v.push(Self::REFUND);
+                v.append(&mut msg.to_byte_vector());
+            }
+            ProtocolMessage::Abort(reason) => {
+                v.push(Self::ABORT);
+                v.push(*reason as u8);
+            }
+            ProtocolMessage::Nonce(nonce) => {
+                v.push(Self::NONCE);
+                v.append(&mut point_to_byte_vector::<C>(nonce));
+            }
+            ProtocolMessage::PartialSignature(partial_signature) => {
+                v.push(Self::PARTIAL_SIGNATURE);
+                v.append(&mut scalar_to_byte_vector::<C>(partial_signature));
+            }
+            ProtocolMessage::TaprootLock(signature) => {
+                v.push(Self::TAPROOT_LOCK);
+                v.extend_from_slice(signature);
+            }
+            ProtocolMessage::TaprootPayment(signature) => {
+                v.push(Self::TAPROOT_PAYMENT);
+                v.extend_from_slice(signature);
+            }
+        }
+
+        Ok(v)
+    }
+
+    // The version of the message and the message
+    pub fn from_byte_vector(bytes: &[u8]) -> Result<(u16, Self), MessageError>
+    where
+        C::AffinePoint: DecompressPoint<C>,
+    {
+        let mut reader = ByteReader::new(bytes);
+        let header = reader.read_bytes(3).ok_or(MessageError::Malformed)?;
+        let version = u16::from_be_bytes([header[0], header[1]]);
+        let kind = header[2];
+        if !matches!(kind, Self::HELLO | Self::ACCEPT | Self::ABORT)
+            && !SUPPORTED_VERSIONS.contains(&version)
+        {
+            return Err(MessageError::UnsupportedVersion(version));
+        }
+        let signature = |bytes: &[u8]| SchnorrSignature::<C>::from_byte_vector(bytes);
+        let scalar_len = FieldBytes::<C>::default().len();
+
+        let message = match kind {
+            Self::HELLO => {
+                let ciphersuite = Ciphersuite::read(&mut reader);
+                let goods = GoodsId::read(&mut reader);
+                let n = reader.read_bytes(1).map(|n| n[0] as usize);
+                let versions = n.and_then(|n| reader.read_bytes(2 * n)).map(|versions| {
+                    versions
+                        .chunks(2)
+                        .map(|v| u16::from_be_bytes([v[0], v[1]]))
+                        .collect()
+                });
+                ciphersuite
+                    .zip(goods)
+                    .zip(versions)
+                    .map(|((ciphersuite, goods), versions)| ProtocolMessage::Hello {
+                        versions,
+                        ciphersuite,
+                        goods,
+                    })
+            }
+            Self::ACCEPT => {
+                let ciphersuite = Ciphersuite::read(&mut reader);
+                let goods = GoodsId::read(&mut reader);
+                let version = reader
+                    .read_bytes(2)
+                    .map(|v| u16::from_be_bytes([v[0], v[1]]));
+                ciphersuite
+                    .zip(goods)
+                    .zip(version)
+                    .map(|((ciphersuite, goods), version)| ProtocolMessage::Accept {
+                        version,
+                        ciphersuite,
+                        goods,
+                    })
+            }
+            Self::KEYS => reader.read_bytes(1).and_then(|n| {
+                let keys: Option<Vec<Point<C>>> =
+                    (0..n[0]).map(|_| reader.read_point::<C>()).collect();
+                keys.map(ProtocolMessage::Keys)
+            }),
+            Self::OFFER => reader
+                .read_point::<C>()
+                .map(|statement| ProtocolMessage::Offer {
+                    statement,
+                    proof: reader.read_rest().to_vec(),
+                }),
+            Self::PRE_SIGNATURE => PreSignatureMessage::from_byte_vector(reader.read_rest())
+                .map(ProtocolMessage::PreSignature),
+            Self::LOCK => {
+                LockMessage::from_byte_vector(reader.read_rest()).map(ProtocolMessage::Lock)
+            }
+            Self::PAYMENT => {
+                let payment = reader
+                    .read_bytes(2 * scalar_len)
+                    .and_then(PaymentMessage::from_byte_vector);
+                let signature_seller = signature(reader.read_rest());
+                payment
+                    .zip(signature_seller)
+                    .map(|(payment, signature_seller)| ProtocolMessage::Payment {
+                        payment,
+                        signature_seller,
+                    })
+            }
+            Self::REFUND => {
+                RefundMessage::from_byte_vector(reader.read_rest()).map(ProtocolMessage::Refund)
+            }
+            Self::ABORT => reader
+                .read_bytes(1)
+                .map(|reason| ProtocolMessage::Abort(AbortReason::from_u8(reason[0]))),
+            Self::NONCE => reader.read_point::<C>().map(ProtocolMessage::Nonce),
+            Self::PARTIAL_SIGNATURE => reader
+                .read_scalar::<C>()
+                .map(ProtocolMessage::PartialSignature),
+            Self::TAPROOT_LOCK => reader
+                .read_bytes(64)
+                .and_then(|signature| signature.try_into().ok())
+                .map(ProtocolMessage::TaprootLock),
+            Self::TAPROOT_PAYMENT => reader
+                .read_bytes(64)
+                .and_then(|signature| signature.try_into().ok())
+                .map(ProtocolMessage::TaprootPayment),
+            _ => return Err(MessageError::UnknownType(kind)),
+        };
+
+        match message {
+            Some(message) if reader.is_empty() => Ok((version, message)),
+            _ => Err(MessageError::Malformed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adaptor_signatures::AdaptorSignatureScheme;
+    use crate::hard_relation::HardRelation;
+    use crate::schnorr_adaptor_signatures::SchnorrAdaptorSignature;
+    use crate::schnorr_signatures::SchnorrSignatureScheme;
+    use crate::selling_protocol::{BatchSignatureGoods, ServiceGoods, SignatureGoods};
+    use crate::signature_scheme::SignatureScheme;
+    use elliptic_curve::scalar::NonZeroScalar;
+    use k256::Secp256k1;
+    use sha2::Sha256;
+
+    type C = Secp256k1;
+
+    fn roundtrip(message: &ProtocolMessage<C>) {
+        let bytes = message.to_byte_vector(PROTOCOL_VERSION).unwrap();
+        let (version, decoded) = ProtocolMessage::<C>::from_byte_vector(&bytes).unwrap();
+        assert_eq!(version, PROTOCOL_VERSION);
+        assert_eq!(decoded.name(), message.name());
+        assert_eq!(decoded.to_byte_vector(PROTOCOL_VERSION).unwrap(), bytes);
+        // truncated and with trailing bytes, except for the proof of an offer, which is opaque here
+        if !matches!(message, ProtocolMessage::Offer { .. }) {
+            let mut longer = bytes.clone();
+            longer.push(0);
+            for malformed in [&bytes[..bytes.len() - 1], &longer[..]] {
+                assert_eq!(
+                    ProtocolMessage::<C>::from_byte_vector(malformed).err(),
+                    Some(MessageError::Malformed)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn protocol_messages() {
+        let ciphersuite = Ciphersuite::new(CurveId::Secp256k1, HashId::Sha256);
+        let (sk, pk) = SchnorrSignatureScheme::<C, Sha256>::gen();
+        let signature = || SchnorrSignatureScheme::<C, Sha256>::sign(&sk, "tx");
+        let (_, x) = NonZeroScalar::<C>::gen(&Point::<C>::GENERATOR);
+
+        let messages = vec![
+            ProtocolMessage::Hello {
+                versions: vec![1, 2],
+                ciphersuite,
+                goods: GoodsId::Signature,
+            },
+            ProtocolMessage::Accept {
+                version: 1,
+                ciphersuite,
+                goods: GoodsId::BatchSignature,
+            },
+            ProtocolMessage::Keys(vec![pk, x, pk]),
+            ProtocolMessage::Offer {
+                statement: x,
+                proof: vec![1, 2, 3],
+            },
+            ProtocolMessage::PreSignature(PreSignatureMessage {
+                pre_signature: SchnorrAdaptorSignature::<C, Sha256>::pre_sign(&sk, "tx", &x),
+            }),
+            ProtocolMessage::Lock(LockMessage {
+                signature: signature(),
+            }),
+            ProtocolMessage::Payment {
+                payment: PaymentMessage {
+                    signature: signature(),
+                },
+                signature_seller: signature(),
+            },
+            ProtocolMessage::Refund(RefundMessage {
+                signature: signature(),
+            }),
+            ProtocolMessage::Abort(AbortReason::InvalidOffer),
+            ProtocolMessage::Nonce(x),
+            ProtocolMessage::PartialSignature(*sk),
+            ProtocolMessage::TaprootLock([1; 64]),
+            ProtocolMessage::TaprootPayment([2; 64]),
+        ];
+        for message in &messages {
+            roundtrip(message);
+        }
+
+        // the layout of the handshake
+        assert_eq!(
+            messages[0].to_byte_vector(PROTOCOL_VERSION).unwrap(),
+            vec![0x00, 0x01, 0x00, 0x01, 0x01, 0x01, 0x02, 0x00, 0x01, 0x00, 0x02]
+        );
+        assert_eq!(
+            messages[1].to_byte_vector(PROTOCOL_VERSION).unwrap(),
+            vec![0x00, 0x01, 0x01, 0x01, 0x01, 0x03, 0x00, 0x01]
+        );
+        assert_eq!(
+            messages[8].to_byte_vector(PROTOCOL_VERSION).unwrap(),
+            vec![0x00, 0x01, 0x08, 0x05]
+        );
+        // messages of an unknown version, except for the handshake, are rejected
+        let mut lock = messages[5].to_byte_vector(PROTOCOL_VERSION).unwrap();
+        lock[1] = 0x02;
+        assert_eq!(
+            ProtocolMessage::<C>::from_byte_vector(&lock).err(),
+            Some(MessageError::UnsupportedVersion(2))
+        );
+        let (version, _) =
+            ProtocolMessage::<C>::from_byte_vector(&messages[0].to_byte_vector(7).unwrap())
+                .unwrap();
+        assert_eq!(version, 7);
+        assert_eq!(
+            ProtocolMessage::<C>::from_byte_vector(&[0x00, 0x01, 0x0d]).err(),
+            Some(MessageError::UnknownType(0x0d))
+        );
+        // an unknown ciphersuite or unknown goods are malformed
+        for accept in [
+            [0x00, 0x01, 0x01, 0x07, 0x01, 0x01, 0x00, 0x01],
+            [0x00, 0x01, 0x01, 0x01, 0x01, 0x04, 0x00, 0x01],
+        ] {
+            assert_eq!(
+                ProtocolMessage::<C>::from_byte_vector(&accept).err(),
+                Some(MessageError::Malformed)
+            );
+        }
+        // the counts of the lists are one byte
+        let hello = ProtocolMessage::<C>::Hello {
+            versions: vec![1; 256],
+            ciphersuite,
+            goods: GoodsId::Service,
+        };
+        assert_eq!(
+            hello.to_byte_vector(PROTOCOL_VERSION).err(),
+            Some(MessageError::TooManyItems(256))
+        );
+        roundtrip(&ProtocolMessage::Keys(vec![pk; 255]));
+        assert_eq!(
+            ProtocolMessage::<C>::Keys(vec![pk; 256])
+                .to_byte_vector(PROTOCOL_VERSION)
+                .err(),
+            Some(MessageError::TooManyItems(256))
+        );
+    }
+
+    #[test]
+    fn protocol_offers() {
+        fn check<G: Goods<C, Sha256>>(terms: &Terms<C>, params: &G::Params, secret: &G::Secret) {
+            let (w, x) = NonZeroScalar::<C>::gen(&Point::<C>::GENERATOR);
+            let offer = G::offer(terms, params, secret, &w, &x);
+
+            let bytes = ProtocolMessage::<C>::offer::<Sha256, G>(&offer)
+                .to_byte_vector(PROTOCOL_VERSION)
+                .unwrap();
+            let Ok((_, ProtocolMessage::Offer { statement, proof })) =
+                ProtocolMessage::<C>::from_byte_vector(&bytes)
+            else {
+                panic!("not an offer");
+            };
+            assert!(statement == x);
+            let decoded =
+                ProtocolMessage::<C>::decode_offer::<Sha256, G>(terms, params, &statement, &proof)
+                    .unwrap();
+            assert!(G::verify_offer(terms, params, &decoded));
+
+            // the proof without its first or its last byte, or with a trailing byte
+            let over_long = [proof.as_slice(), &[0]].concat();
+            for proof in [&proof[1..], &proof[..proof.len() - 1], &over_long] {
+                assert!(ProtocolMessage::<C>::decode_offer::<Sha256, G>(
+                    terms, params, &statement, proof
+                )
+                .is_none());
+            }
+        }
+
+        let (sk_notary, pk_notary) = SchnorrSignatureScheme::<C, Sha256>::gen();
+        let msg = String::from("Lorem ipsum dolor sit amet");
+        let terms = Terms {
+            pk_notary,
+            msg: msg.clone(),
+            pk_lock: pk_notary,
+            pk_buyer: pk_notary,
+            tx_lock: String::new(),
+            tx_pay: String::new(),
+            tx_recover: String::new(),
+            timeout: 0,
+        };
+        let signature = SchnorrSignatureScheme::<C, Sha256>::sign(&sk_notary, &msg);
+        check::<SignatureGoods<C, Sha256>>(&terms, &(), &signature);
+        check::<ServiceGoods<C, Sha256>>(&terms, &(), &signature);
+
+        let msgs: Vec<String> = (0..3).map(|i| format!("message {i}")).collect();
+        let signatures = msgs
+            .iter()
+            .map(|msg| SchnorrSignatureScheme::<C, Sha256>::sign(&sk_notary, msg))
+            .collect();
+        check::<BatchSignatureGoods<C, Sha256>>(&terms, &msgs, &signatures);
+    }
+
+    #[test]
+    fn version_negotiation() {
+        assert_eq!(negotiate_version(&[1, 2, 3], &[2, 3, 4]), Some(3));
+        assert_eq!(negotiate_version(&SUPPORTED_VERSIONS, &[1]), Some(1));
+        assert_eq!(negotiate_version(&[1], &[2]), None);
+        assert_eq!(negotiate_version(&[1], &[]), None);
+    }
+}
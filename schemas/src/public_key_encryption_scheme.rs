@@ -7,5 +7,6 @@ pub trait PublicKeyEncryptionScheme {
 
     fn gen() -> (Self::SK, Self::PK);
     fn enc(pk: &Self::PK, msg: &Self::Message) -> (Self::CypherText, Self::Randomness);
-    fn dec(sk: &Self::SK, ct: &Self::CypherText) -> Self::Message;
+    // None if ct is not a valid cyphertext under the public key of sk
+    fn dec(sk: &Self::SK, ct: &Self::CypherText) -> Option<Self::Message>;
 }
@@ -0,0 +1,175 @@
+use crate::dleq::{self, DLEQProof};
+use crate::elgamal::ElGamal;
+use crate::hard_relation::HardRelation;
+use crate::nizk::NIZK;
+use crate::public_key_encryption_scheme::PublicKeyEncryptionScheme;
+use crate::utils::point_to_byte_vector;
+use digest::Digest;
+use elliptic_curve::ff::Field;
+use elliptic_curve::{CurveArithmetic, Group};
+use rand_core::OsRng;
+use std::marker::PhantomData;
+
+type CypherText<C> = <ElGamal<C> as PublicKeyEncryptionScheme>::CypherText;
+
+// Proof that the ElGamal cyphertext ct2 is a re-encryption of ct under pk, that is
+// ct2 = ElGamal::rerandomize_with(pk, ct, r), without revealing r. An intermediary can then pass
+// on ct2 and convince the next party that the message has not changed, without learning it.
+// With ct = (a, b) and ct2 = (a2, b2), this is a proof of equality of the discrete logarithms of
+// a2 / a in base g and of b2 / b in base pk.
+pub struct ReEncryptionProof<C, H>
+where
+    C: CurveArithmetic,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+{
+    _curve_marker: PhantomData<C>,
+    _hash_marker: PhantomData<H>,
+}
+
+pub struct Witness<C>
+where
+    C: CurveArithmetic,
+{
+    r: C::Scalar,
+}
+
+impl<C> Witness<C>
+where
+    C: CurveArithmetic,
+{
+    pub fn new(r: C::Scalar) -> Self {
+        Self { r }
+    }
+}
+
+pub struct Statement<C>
+where
+    C: CurveArithmetic,
+{
+    pk: C::ProjectivePoint,
+    ct: CypherText<C>,
+    ct2: CypherText<C>,
+}
+
+impl<C> Statement<C>
+where
+    C: CurveArithmetic,
+{
+    pub fn new(pk: C::ProjectivePoint, ct: CypherText<C>, ct2: CypherText<C>) -> Self {
+        Self { pk, ct, ct2 }
+    }
+
+    pub fn to_byte_vector(&self) -> Vec<u8> {
+        let mut v: Vec<u8> = Vec::new();
+        v.append(&mut point_to_byte_vector::<C>(&self.pk));
+        for point in [self.ct.0, self.ct.1, self.ct2.0, self.ct2.1] {
+            v.append(&mut point_to_byte_vector::<C>(&point));
+        }
+
+        v
+    }
+
+    fn dleq_statement(&self) -> dleq::Statement<C> {
+        dleq::Statement::new(
+            C::ProjectivePoint::generator(),
+            self.ct2.0 - self.ct.0,
+            self.pk,
+            self.ct2.1 - self.ct.1,
+        )
+    }
+}
+
+// Relation_reenc = {(pk, ct, ct2; r) | ct2 = ct * Enc(pk, 1; r)}
+// The public parameters are the public key pk and the cyphertext ct that is re-encrypted.
+impl<C> HardRelation<Statement<C>, Witness<C>> for Witness<C>
+where
+    C: CurveArithmetic,
+{
+    type PP = (C::ProjectivePoint, CypherText<C>);
+
+    fn R(_pp: &Self::PP, w: &Witness<C>, x: &Statement<C>) -> bool {
+        x.ct2 == ElGamal::<C>::rerandomize_with(&x.pk, &x.ct, &w.r)
+    }
+    // From a Witness w, compute a Statement s such that R(w, s) == true
+    fn statement(pp: &Self::PP, w: &Witness<C>) -> Statement<C> {
+        let (pk, ct) = *pp;
+
+        Statement::new(pk, ct, ElGamal::<C>::rerandomize_with(&pk, &ct, &w.r))
+    }
+
+    fn gen(pp: &Self::PP) -> (Witness<C>, Statement<C>) {
+        let w = Witness::new(C::Scalar::random(&mut OsRng));
+        let x = Self::statement(pp, &w);
+
+        (w, x)
+    }
+}
+
+impl<C, H> NIZK for ReEncryptionProof<C, H>
+where
+    C: CurveArithmetic,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+{
+    type CRS = ();
+    type Statement = Statement<C>;
+    type Witness = Witness<C>;
+    type Proof = dleq::Proof<C>;
+
+    fn crs_gen() -> Self::CRS {}
+
+    fn prove(_crs: &Self::CRS, x: &Self::Statement, w: &Self::Witness) -> Self::Proof {
+        DLEQProof::<C, H>::prove(&(), &x.dleq_statement(), &dleq::Witness::new(w.r))
+    }
+
+    fn verify(_crs: &Self::CRS, x: &Self::Statement, p: &Self::Proof) -> bool {
+        DLEQProof::<C, H>::verify(&(), &x.dleq_statement(), p)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k256::Secp256k1;
+    use sha2::Sha256;
+
+    type C = Secp256k1;
+    type Point = <C as CurveArithmetic>::ProjectivePoint;
+    type Scalar = <C as CurveArithmetic>::Scalar;
+    type Nizk = ReEncryptionProof<C, Sha256>;
+
+    #[test]
+    fn reencryption_proof() {
+        let (sk, pk) = ElGamal::<C>::gen();
+        let msg = Point::GENERATOR * Scalar::random(&mut OsRng);
+        let (ct, _) = ElGamal::<C>::enc(&pk, &msg);
+        let (ct2, r) = ElGamal::<C>::rerandomize(&pk, &ct);
+        assert!(ct2 != ct);
+        assert_eq!(ElGamal::<C>::dec(&sk, &ct2).unwrap(), msg);
+
+        let statement = Statement::<C>::new(pk, ct, ct2);
+        let witness = Witness::<C>::new(r);
+        assert!(Witness::<C>::R(&(pk, ct), &witness, &statement));
+        let proof = Nizk::prove(&(), &statement, &witness);
+        assert!(Nizk::verify(&(), &statement, &proof));
+
+        // ct3 re-encrypts ct with a different message
+        let ct3 = (ct2.0, ct2.1 + Point::GENERATOR);
+        let statement = Statement::<C>::new(pk, ct, ct3);
+        assert!(!Nizk::verify(&(), &statement, &proof));
+        let proof = Nizk::prove(&(), &statement, &witness);
+        assert!(!Nizk::verify(&(), &statement, &proof));
+    }
+
+    #[test]
+    fn reencryption_proof_gen() {
+        let (sk, pk) = ElGamal::<C>::gen();
+        let msg = Point::GENERATOR * Scalar::random(&mut OsRng);
+        let (ct, _) = ElGamal::<C>::enc(&pk, &msg);
+
+        let (witness, statement) = Witness::<C>::gen(&(pk, ct));
+        assert!(Witness::<C>::R(&(pk, ct), &witness, &statement));
+        assert_eq!(ElGamal::<C>::dec(&sk, &statement.ct2).unwrap(), msg);
+        let proof = Nizk::prove(&(), &statement, &witness);
+        assert!(Nizk::verify(&(), &statement, &proof));
+    }
+}
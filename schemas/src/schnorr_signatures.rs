@@ -1,6 +1,6 @@
 use crate::hard_relation::HardRelation;
 use crate::signature_scheme::SignatureScheme;
-use crate::utils::{point_to_byte_vector, proj, scalar_to_byte_vector};
+use crate::utils::{point_to_byte_vector, proj, scalar_to_byte_vector, ByteReader};
 use digest::Digest;
 use elliptic_curve::ops::Reduce;
 use elliptic_curve::scalar::NonZeroScalar;
@@ -10,7 +10,7 @@ use rand_core::OsRng;
 use std::marker::PhantomData;
 
 // The type representing SchnorrSignatures over an elliptic curve C
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct SchnorrSignature<C>
 where
     C: CurveArithmetic,
@@ -29,6 +29,15 @@ where
         v.append(&mut scalar_to_byte_vector::<C>(&self.sig));
         v
     }
+
+    // inverse of to_byte_vector
+    pub fn from_byte_vector(bytes: &[u8]) -> Option<Self> {
+        let mut reader = ByteReader::new(bytes);
+        let proof = reader.read_scalar::<C>()?;
+        let sig = reader.read_scalar::<C>()?;
+
+        reader.is_empty().then_some(Self { proof, sig })
+    }
 }
 
 pub struct SchnorrSignatureScheme<C, H>
@@ -0,0 +1,1838 @@
+use crate::adaptor_signatures::AdaptorSignatureScheme;
+use crate::hard_relation::HardRelation;
+use crate::ledger::Clock;
+use crate::nizk::{EncodableProof, ProofEncoding, NIZK};
+use crate::one_time_pad::OneTimePad;
+use crate::pok_schnorr_signature::{self, PoKSchnorrSignature};
+use crate::por_schnorr_signature::{self, PoRSchnorrSignature};
+use crate::protocol_message::GoodsId;
+use crate::schnorr_adaptor_signatures::SchnorrAdaptorSignature;
+use crate::schnorr_signatures::{SchnorrSignature, SchnorrSignatureScheme};
+use crate::sigma_proof::DLogSigmaProof;
+use crate::signature_scheme::SignatureScheme;
+use crate::symmetric_encryption::SymmetricEncryptionScheme;
+use crate::utils::{point_to_byte_vector, proj, scalar_to_byte_vector, ByteReader};
+use digest::Digest;
+use elliptic_curve::group::cofactor::CofactorGroup;
+use elliptic_curve::hash2curve::GroupDigest;
+use elliptic_curve::ops::Reduce;
+use elliptic_curve::point::DecompressPoint;
+use elliptic_curve::scalar::NonZeroScalar;
+use elliptic_curve::{CurveArithmetic, Group};
+use std::fmt;
+use std::marker::PhantomData;
+
+// The selling-signature protocol as a pair of state machines, so that an application can embed
+// the protocol instead of running it inline. The Seller owns a Schnorr signature of the notary on
+// msg and sells it (or, in the service variant, the witness of a proof of representation of it,
+// and with BatchSignatureGoods, the signatures on several messages for one offer and one payment)
+// to the Buyer, who pays with a Schnorr adaptor signature on tx_pay:
+//
+//   Seller::offer                   -> Offer          (seller to buyer)
+//   Buyer::receive_offer            -> PreSignature   (buyer to seller)
+//   Buyer::lock                     -> Lock           (buyer publishes tx_lock on the ledger)
+//   Buyer::confirm_lock                               (tx_lock is confirmed)
+//   Seller::receive_lock                              (seller reads tx_lock from the ledger)
+//   Seller::receive_pre_signature   -> Payment        (seller publishes tx_pay on the ledger)
+//   Buyer::receive_payment          -> the goods      (buyer reads tx_pay from the ledger)
+//
+// The output of tx_lock can be spent either by tx_pay, with the signatures of the buyer and the
+// seller, or by tx_recover with the signature of the buyer alone once terms.timeout ticks of the
+// Clock have passed since the block that confirmed tx_lock. Both roles count the timeout from that
+// height, whenever they read it, and the seller stops PAYMENT_MARGIN ticks before the timeout so
+// that tx_pay is confirmed first. If the seller aborts instead, the buyer gets its coins back
+// with Buyer::refund.
+//
+// Every step checks the messages it receives and returns a SellingError instead of panicking. A
+// failed step leaves the role in the state it was, so it can still process a valid message, and
+// when the message was invalid because of the other party, the role keeps it as Evidence. Once
+// tx_lock is published, a buyer that gets no valid payment can always exit with Buyer::refund.
+
+type Point<C> = <C as CurveArithmetic>::ProjectivePoint;
+type ASig<C, H> = SchnorrAdaptorSignature<C, H>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SellingError {
+    // The message is not the one expected in the current state of the role
+    UnexpectedMessage,
+    // The signature of the notary held by the seller does not verify
+    InvalidNotarySignature,
+    // The proof of the offer does not verify against the terms of the buyer
+    InvalidOffer,
+    // The pre-signature of the buyer does not pre-verify on tx_pay
+    InvalidPreSignature,
+    // The payment signature does not verify on tx_pay
+    InvalidPayment,
+    // The witness extracted from the payment does not open the goods of the offer
+    ExtractionFailed,
+    // The signature of tx_lock does not verify
+    InvalidLock,
+    // The seller can no longer publish tx_pay, since the buyer may have spent tx_lock
+    TimelockExpired,
+    // The buyer cannot publish tx_recover yet
+    TimelockNotExpired,
+    // The message does not decode
+    MalformedMessage,
+    // A partial signature of tx_pay does not verify (Taproot transactions)
+    InvalidPartialSignature,
+    // The seller announces another notary than the one the buyer knows
+    UnknownNotary,
+}
+
+impl fmt::Display for SellingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let description = match self {
+            SellingError::UnexpectedMessage => "unexpected message in the current state",
+            SellingError::InvalidNotarySignature => "the signature of the notary is not valid",
+            SellingError::InvalidOffer => "the proof of the offer is not valid",
+            SellingError::InvalidPreSignature => "the pre-signature is not valid",
+            SellingError::InvalidPayment => "the payment signature is not valid",
+            SellingError::ExtractionFailed => "the goods cannot be recovered from the payment",
+            SellingError::InvalidLock => "the signature of tx_lock is not valid",
+            SellingError::TimelockExpired => "the timelock of tx_lock has expired",
+            SellingError::TimelockNotExpired => "the timelock of tx_lock has not expired yet",
+            SellingError::MalformedMessage => "the message is malformed",
+            SellingError::InvalidPartialSignature => "the partial signature is not valid",
+            SellingError::UnknownNotary => "the notary is not the one of the buyer",
+        };
+
+        write!(f, "{}", description)
+    }
+}
+
+impl std::error::Error for SellingError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Party {
+    Seller,
+    Buyer,
+}
+
+impl fmt::Display for Party {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Party::Seller => write!(f, "seller"),
+            Party::Buyer => write!(f, "buyer"),
+        }
+    }
+}
+
+// A message of the other party that a role rejected, with its encoding, so that the application
+// can log or report who deviated from the protocol and how. The messages are not authenticated,
+// so this is a record for the honest party, not a proof that convinces a third party.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Evidence {
+    pub culprit: Party,
+    pub error: SellingError,
+    pub message: Vec<u8>,
+}
+
+impl Evidence {
+    pub fn new(culprit: Party, error: SellingError, message: Vec<u8>) -> Self {
+        Self {
+            culprit,
+            error,
+            message,
+        }
+    }
+}
+
+impl fmt::Display for Evidence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} (message ", self.culprit, self.error)?;
+        for byte in &self.message {
+            write!(f, "{:02x}", byte)?;
+        }
+        write!(f, ")")
+    }
+}
+
+// How many ticks before the deadline of tx_recover the seller stops completing tx_pay
+pub const PAYMENT_MARGIN: u64 = 6;
+
+// What both parties agree on before running the protocol: the notary and the message whose
+// signature is sold, the key of the buyer that funds tx_lock, the key of the buyer that signs
+// tx_pay and tx_recover, the messages signed for the transactions (Transaction::message when
+// they are ledger::Transaction) and the timelock of tx_recover.
+#[derive(Clone)]
+pub struct Terms<C>
+where
+    C: CurveArithmetic,
+{
+    pub pk_notary: Point<C>,
+    pub msg: String,
+    pub pk_lock: Point<C>,
+    pub pk_buyer: Point<C>,
+    pub tx_lock: String,
+    pub tx_pay: String,
+    pub tx_recover: String,
+    pub timeout: u64,
+}
+
+impl<C> Terms<C>
+where
+    C: CurveArithmetic,
+{
+    // tx_recover can be published from this time on, given the height at which tx_lock was
+    // confirmed
+    pub fn deadline(&self, locked_at: u64) -> u64 {
+        locked_at.saturating_add(self.timeout)
+    }
+
+    // The seller only completes tx_pay before this time, PAYMENT_MARGIN ticks before the deadline,
+    // so that tx_pay is confirmed before the buyer can publish tx_recover
+    pub fn payment_deadline(&self, locked_at: u64) -> u64 {
+        self.deadline(locked_at).saturating_sub(PAYMENT_MARGIN)
+    }
+}
+
+// What is sold: which secret of the seller the goods are made of, how the seller proves, in the
+// offer, that the witness of the adaptor statement x unlocks the goods, and how the buyer recovers
+// the goods with the witness.
+pub trait Goods<C, H>
+where
+    C: CurveArithmetic,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+{
+    // What the parties agree on about these goods besides the terms, passed to every method
+    // like the PP of a HardRelation: the messages of BatchSignatureGoods, nothing for the others
+    type Params;
+    type Secret;
+    type Offer;
+    type Output;
+
+    // How Hello and Accept name the goods
+    const ID: GoodsId;
+
+    // Whether the secret is the one of the terms, checked by Seller::new
+    fn verify_secret(terms: &Terms<C>, params: &Self::Params, secret: &Self::Secret) -> bool;
+
+    fn offer(
+        terms: &Terms<C>,
+        params: &Self::Params,
+        secret: &Self::Secret,
+        w: &NonZeroScalar<C>,
+        x: &Point<C>,
+    ) -> Self::Offer;
+
+    // The statement of the adaptor signature
+    fn statement(offer: &Self::Offer) -> Point<C>;
+
+    // starts with the encoding of the statement
+    fn offer_to_byte_vector(offer: &Self::Offer) -> Vec<u8>;
+
+    // inverse of offer_to_byte_vector, for the offer of the terms
+    fn offer_from_byte_vector(
+        terms: &Terms<C>,
+        params: &Self::Params,
+        bytes: &[u8],
+    ) -> Option<Self::Offer>
+    where
+        C::AffinePoint: DecompressPoint<C>;
+
+    fn verify_offer(terms: &Terms<C>, params: &Self::Params, offer: &Self::Offer) -> bool;
+
+    fn recover(
+        terms: &Terms<C>,
+        params: &Self::Params,
+        offer: &Self::Offer,
+        w: &NonZeroScalar<C>,
+    ) -> Option<Self::Output>;
+}
+
+// The signature of the notary is sold: it is encrypted with the witness, and the offer proves
+// knowledge of the signature and the witness with PoKSchnorrSignature.
+pub struct SignatureGoods<C, H>
+where
+    C: CurveArithmetic,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+{
+    _curve_marker: PhantomData<C>,
+    _hash_marker: PhantomData<H>,
+}
+
+pub struct SignatureOffer<C>
+where
+    C: CurveArithmetic,
+{
+    pub x: Point<C>,
+    pub gs: Point<C>,
+    pub e: C::Scalar,
+    pub ct: C::Scalar,
+    pub proof: pok_schnorr_signature::Proof<C>,
+}
+
+impl<C> SignatureOffer<C>
+where
+    C: CurveArithmetic,
+{
+    pub fn to_byte_vector(&self) -> Vec<u8> {
+        let mut v: Vec<u8> = Vec::new();
+        v.append(&mut point_to_byte_vector::<C>(&self.x));
+        v.append(&mut point_to_byte_vector::<C>(&self.gs));
+        v.append(&mut scalar_to_byte_vector::<C>(&self.e));
+        v.append(&mut scalar_to_byte_vector::<C>(&self.ct));
+        v.append(&mut self.proof.to_byte_vector());
+
+        v
+    }
+}
+
+impl<C, H> SignatureGoods<C, H>
+where
+    C: CurveArithmetic,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+{
+    fn nizk_statement(
+        terms: &Terms<C>,
+        offer: &SignatureOffer<C>,
+    ) -> pok_schnorr_signature::Statement<C, H> {
+        pok_schnorr_signature::Statement::new(
+            offer.gs,
+            offer.x,
+            terms.pk_notary,
+            offer.e,
+            offer.ct,
+            terms.msg.clone(),
+        )
+    }
+}
+
+impl<C, H> Goods<C, H> for SignatureGoods<C, H>
+where
+    C: CurveArithmetic,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+{
+    type Params = ();
+    type Secret = SchnorrSignature<C>;
+    type Offer = SignatureOffer<C>;
+    type Output = SchnorrSignature<C>;
+
+    const ID: GoodsId = GoodsId::Signature;
+
+    fn verify_secret(terms: &Terms<C>, _params: &(), signature: &Self::Secret) -> bool {
+        SchnorrSignatureScheme::<C, H>::verify(&terms.pk_notary, &terms.msg, signature)
+    }
+
+    fn offer(
+        terms: &Terms<C>,
+        _params: &(),
+        signature: &SchnorrSignature<C>,
+        w: &NonZeroScalar<C>,
+        x: &Point<C>,
+    ) -> Self::Offer {
+        let gs = Point::<C>::generator() * signature.sig;
+        let ct = OneTimePad::<C::Scalar>::enc(w, &signature.sig);
+        let statement = pok_schnorr_signature::Statement::<C, H>::new(
+            gs,
+            *x,
+            terms.pk_notary,
+            signature.proof,
+            ct,
+            terms.msg.clone(),
+        );
+        let witness = pok_schnorr_signature::Witness::new(signature.sig, **w);
+        let proof = PoKSchnorrSignature::<C, H>::prove(&(), &statement, &witness);
+
+        SignatureOffer {
+            x: *x,
+            gs,
+            e: signature.proof,
+            ct,
+            proof,
+        }
+    }
+
+    fn statement(offer: &Self::Offer) -> Point<C> {
+        offer.x
+    }
+
+    fn offer_to_byte_vector(offer: &Self::Offer) -> Vec<u8> {
+        offer.to_byte_vector()
+    }
+
+    fn offer_from_byte_vector(terms: &Terms<C>, _params: &(), bytes: &[u8]) -> Option<Self::Offer>
+    where
+        C::AffinePoint: DecompressPoint<C>,
+    {
+        let mut reader = ByteReader::new(bytes);
+        let x = reader.read_point::<C>()?;
+        let gs = reader.read_point::<C>()?;
+        let e = reader.read_scalar::<C>()?;
+        let ct = reader.read_scalar::<C>()?;
+        let statement = pok_schnorr_signature::Statement::<C, H>::new(
+            gs,
+            x,
+            terms.pk_notary,
+            e,
+            ct,
+            terms.msg.clone(),
+        );
+        let proof = PoKSchnorrSignature::<C, H>::decode_proof(
+            &(),
+            &statement,
+            reader.read_rest(),
+            ProofEncoding::Commitments,
+        )?;
+
+        Some(SignatureOffer {
+            x,
+            gs,
+            e,
+            ct,
+            proof,
+        })
+    }
+
+    fn verify_offer(terms: &Terms<C>, _params: &(), offer: &Self::Offer) -> bool {
+        PoKSchnorrSignature::<C, H>::verify(&(), &Self::nizk_statement(terms, offer), &offer.proof)
+    }
+
+    fn recover(
+        terms: &Terms<C>,
+        _params: &(),
+        offer: &Self::Offer,
+        w: &NonZeroScalar<C>,
+    ) -> Option<Self::Output> {
+        let signature = SchnorrSignature {
+            proof: offer.e,
+            sig: OneTimePad::<C::Scalar>::dec(w, &offer.ct)?,
+        };
+
+        SchnorrSignatureScheme::<C, H>::verify(&terms.pk_notary, &terms.msg, &signature)
+            .then_some(signature)
+    }
+}
+
+// A service is sold: the offer proves with PoRSchnorrSignature that the statement x is a
+// re-randomization of the signature of the notary, and the buyer gets the witness of x.
+pub struct ServiceGoods<C, H>
+where
+    C: CurveArithmetic,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+{
+    _curve_marker: PhantomData<C>,
+    _hash_marker: PhantomData<H>,
+}
+
+pub struct ServiceOffer<C>
+where
+    C: CurveArithmetic,
+{
+    pub x: Point<C>,
+    pub gs: Point<C>,
+    pub e: C::Scalar,
+    pub proof: por_schnorr_signature::Proof<C>,
+}
+
+impl<C> ServiceOffer<C>
+where
+    C: CurveArithmetic,
+{
+    pub fn to_byte_vector(&self) -> Vec<u8> {
+        let mut v: Vec<u8> = Vec::new();
+        v.append(&mut point_to_byte_vector::<C>(&self.x));
+        v.append(&mut point_to_byte_vector::<C>(&self.gs));
+        v.append(&mut scalar_to_byte_vector::<C>(&self.e));
+        v.append(&mut self.proof.to_byte_vector());
+
+        v
+    }
+}
+
+impl<C, H> ServiceGoods<C, H>
+where
+    C: CurveArithmetic,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+{
+    fn nizk_statement(
+        terms: &Terms<C>,
+        offer: &ServiceOffer<C>,
+    ) -> por_schnorr_signature::Statement<C, H> {
+        por_schnorr_signature::Statement::new(
+            offer.x,
+            terms.pk_notary,
+            offer.gs,
+            offer.e,
+            terms.msg.clone(),
+        )
+    }
+}
+
+impl<C, H> Goods<C, H> for ServiceGoods<C, H>
+where
+    C: GroupDigest,
+    C::ProjectivePoint: CofactorGroup,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+{
+    type Params = ();
+    type Secret = SchnorrSignature<C>;
+    type Offer = ServiceOffer<C>;
+    type Output = NonZeroScalar<C>;
+
+    const ID: GoodsId = GoodsId::Service;
+
+    fn verify_secret(terms: &Terms<C>, _params: &(), signature: &Self::Secret) -> bool {
+        SchnorrSignatureScheme::<C, H>::verify(&terms.pk_notary, &terms.msg, signature)
+    }
+
+    fn offer(
+        terms: &Terms<C>,
+        _params: &(),
+        signature: &SchnorrSignature<C>,
+        w: &NonZeroScalar<C>,
+        x: &Point<C>,
+    ) -> Self::Offer {
+        let gs = Point::<C>::generator() * signature.sig;
+        let statement = por_schnorr_signature::Statement::<C, H>::new(
+            *x,
+            terms.pk_notary,
+            gs,
+            signature.proof,
+            terms.msg.clone(),
+        );
+        let witness = por_schnorr_signature::Witness::new(signature.sig, **w);
+        let crs = PoRSchnorrSignature::<C, H>::crs_gen();
+        let proof = PoRSchnorrSignature::<C, H>::prove(&crs, &statement, &witness);
+
+        ServiceOffer {
+            x: *x,
+            gs,
+            e: signature.proof,
+            proof,
+        }
+    }
+
+    fn statement(offer: &Self::Offer) -> Point<C> {
+        offer.x
+    }
+
+    fn offer_to_byte_vector(offer: &Self::Offer) -> Vec<u8> {
+        offer.to_byte_vector()
+    }
+
+    fn offer_from_byte_vector(terms: &Terms<C>, _params: &(), bytes: &[u8]) -> Option<Self::Offer>
+    where
+        C::AffinePoint: DecompressPoint<C>,
+    {
+        let mut reader = ByteReader::new(bytes);
+        let x = reader.read_point::<C>()?;
+        let gs = reader.read_point::<C>()?;
+        let e = reader.read_scalar::<C>()?;
+        let statement = por_schnorr_signature::Statement::<C, H>::new(
+            x,
+            terms.pk_notary,
+            gs,
+            e,
+            terms.msg.clone(),
+        );
+        let crs = PoRSchnorrSignature::<C, H>::crs_gen();
+        let proof = PoRSchnorrSignature::<C, H>::decode_proof(
+            &crs,
+            &statement,
+            reader.read_rest(),
+            ProofEncoding::Commitments,
+        )?;
+
+        Some(ServiceOffer { x, gs, e, proof })
+    }
+
+    fn verify_offer(terms: &Terms<C>, _params: &(), offer: &Self::Offer) -> bool {
+        let crs = PoRSchnorrSignature::<C, H>::crs_gen();
+
+        PoRSchnorrSignature::<C, H>::verify(&crs, &Self::nizk_statement(terms, offer), &offer.proof)
+    }
+
+    fn recover(
+        _terms: &Terms<C>,
+        _params: &(),
+        offer: &Self::Offer,
+        w: &NonZeroScalar<C>,
+    ) -> Option<Self::Output> {
+        (Point::<C>::generator() * **w == offer.x).then_some(*w)
+    }
+}
+
+// The largest batch, whose size fits in the two bytes of n in the offer
+pub const MAX_BATCH: usize = u16::MAX as usize;
+
+// Several signatures of the notary, one on each message of the params, are sold for one
+// payment: they are all encrypted with the same witness w of x, ct_i = sig_i + w, so that
+// ct_i * G - x is the commitment gs_i of sig_i and the buyer checks each item against its
+// message without a proof per item. The offer proves knowledge of w with one DLogSigmaProof.
+// Since ct_i - ct_j = sig_i - sig_j, a buyer who knows one signature of the batch learns the
+// others: the items are bought together or not at all.
+pub struct BatchSignatureGoods<C, H>
+where
+    C: CurveArithmetic,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+{
+    _curve_marker: PhantomData<C>,
+    _hash_marker: PhantomData<H>,
+}
+
+pub struct BatchSignatureOffer<C>
+where
+    C: CurveArithmetic,
+{
+    pub x: Point<C>,
+    // The challenge and the encrypted response of the signature on each message
+    pub items: Vec<(C::Scalar, C::Scalar)>,
+    pub proof: (Point<C>, C::Scalar),
+}
+
+impl<C> BatchSignatureOffer<C>
+where
+    C: CurveArithmetic,
+{
+    // x || n (u16) || n items || proof. The offers of BatchSignatureGoods::offer have at most
+    // MAX_BATCH items.
+    pub fn to_byte_vector(&self) -> Vec<u8> {
+        let n = u16::try_from(self.items.len()).expect("at most MAX_BATCH items");
+        let mut v: Vec<u8> = Vec::new();
+        v.append(&mut point_to_byte_vector::<C>(&self.x));
+        v.extend(n.to_be_bytes());
+        for (e, ct) in &self.items {
+            v.append(&mut scalar_to_byte_vector::<C>(e));
+            v.append(&mut scalar_to_byte_vector::<C>(ct));
+        }
+        v.append(&mut point_to_byte_vector::<C>(&self.proof.0));
+        v.append(&mut scalar_to_byte_vector::<C>(&self.proof.1));
+
+        v
+    }
+}
+
+impl<C, H> Goods<C, H> for BatchSignatureGoods<C, H>
+where
+    C: CurveArithmetic,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+{
+    // the messages of the batch
+    type Params = Vec<String>;
+    type Secret = Vec<SchnorrSignature<C>>;
+    type Offer = BatchSignatureOffer<C>;
+    type Output = Vec<SchnorrSignature<C>>;
+
+    const ID: GoodsId = GoodsId::BatchSignature;
+
+    fn verify_secret(terms: &Terms<C>, msgs: &Vec<String>, signatures: &Self::Secret) -> bool {
+        !msgs.is_empty()
+            && msgs.len() <= MAX_BATCH
+            && signatures.len() == msgs.len()
+            && msgs.iter().zip(signatures).all(|(msg, signature)| {
+                SchnorrSignatureScheme::<C, H>::verify(&terms.pk_notary, msg, signature)
+            })
+    }
+
+    fn offer(
+        _terms: &Terms<C>,
+        _msgs: &Vec<String>,
+        signatures: &Self::Secret,
+        w: &NonZeroScalar<C>,
+        x: &Point<C>,
+    ) -> Self::Offer {
+        let items = signatures
+            .iter()
+            .map(|signature| {
+                (
+                    signature.proof,
+                    OneTimePad::<C::Scalar>::enc(w, &signature.sig),
+                )
+            })
+            .collect();
+        let crs = DLogSigmaProof::<C, H>::crs_gen();
+        let proof = DLogSigmaProof::<C, H>::prove(&crs, x, w);
+
+        BatchSignatureOffer {
+            x: *x,
+            items,
+            proof,
+        }
+    }
+
+    fn statement(offer: &Self::Offer) -> Point<C> {
+        offer.x
+    }
+
+    fn offer_to_byte_vector(offer: &Self::Offer) -> Vec<u8> {
+        offer.to_byte_vector()
+    }
+
+    // n is read from the offer, and checked against the params by verify_offer
+    fn offer_from_byte_vector(
+        _terms: &Terms<C>,
+        _msgs: &Vec<String>,
+        bytes: &[u8],
+    ) -> Option<Self::Offer>
+    where
+        C::AffinePoint: DecompressPoint<C>,
+    {
+        let mut reader = ByteReader::new(bytes);
+        let x = reader.read_point::<C>()?;
+        let n = reader.read_bytes(2)?;
+        let n = u16::from_be_bytes([n[0], n[1]]);
+        let mut items = Vec::with_capacity(n as usize);
+        for _ in 0..n {
+            let e = reader.read_scalar::<C>()?;
+            let ct = reader.read_scalar::<C>()?;
+            items.push((e, ct));
+        }
+        let crs = DLogSigmaProof::<C, H>::crs_gen();
+        let proof = DLogSigmaProof::<C, H>::decode_proof(
+            &crs,
+            &x,
+            reader.read_rest(),
+            ProofEncoding::Commitments,
+        )?;
+
+        Some(BatchSignatureOffer { x, items, proof })
+    }
+
+    fn verify_offer(terms: &Terms<C>, msgs: &Vec<String>, offer: &Self::Offer) -> bool {
+        let g = Point::<C>::generator();
+        let crs = DLogSigmaProof::<C, H>::crs_gen();
+
+        !msgs.is_empty()
+            && offer.items.len() == msgs.len()
+            && DLogSigmaProof::<C, H>::verify(&crs, &offer.x, &offer.proof)
+            && msgs.iter().zip(&offer.items).all(|(msg, (e, ct))| {
+                // the Schnorr verification of the signature, with gs_i in place of sig_i * G
+                let r = g * *ct - offer.x + terms.pk_notary * *e;
+                let hasher = H::new();
+                *e == <C::Scalar as Reduce<C::Uint>>::reduce_bytes(
+                    &hasher
+                        .chain_update(proj::<C>(&r))
+                        .chain_update(msg)
+                        .finalize(),
+                )
+            })
+    }
+
+    fn recover(
+        terms: &Terms<C>,
+        msgs: &Vec<String>,
+        offer: &Self::Offer,
+        w: &NonZeroScalar<C>,
+    ) -> Option<Self::Output> {
+        msgs.iter()
+            .zip(&offer.items)
+            .map(|(msg, (e, ct))| {
+                let signature = SchnorrSignature {
+                    proof: *e,
+                    sig: OneTimePad::<C::Scalar>::dec(w, ct)?,
+                };
+
+                SchnorrSignatureScheme::<C, H>::verify(&terms.pk_notary, msg, &signature)
+                    .then_some(signature)
+            })
+            .collect()
+    }
+}
+
+// Sent by the buyer to the seller once the offer is verified
+pub struct PreSignatureMessage<C>
+where
+    C: CurveArithmetic,
+{
+    pub pre_signature: SchnorrSignature<C>,
+}
+
+impl<C> PreSignatureMessage<C>
+where
+    C: CurveArithmetic,
+{
+    pub fn to_byte_vector(&self) -> Vec<u8> {
+        self.pre_signature.to_byte_vector()
+    }
+
+    pub fn from_byte_vector(bytes: &[u8]) -> Option<Self> {
+        Some(Self {
+            pre_signature: SchnorrSignature::from_byte_vector(bytes)?,
+        })
+    }
+}
+
+// The signature of tx_lock published by the buyer, and read from the ledger by the seller
+pub struct LockMessage<C>
+where
+    C: CurveArithmetic,
+{
+    pub signature: SchnorrSignature<C>,
+}
+
+impl<C> LockMessage<C>
+where
+    C: CurveArithmetic,
+{
+    pub fn to_byte_vector(&self) -> Vec<u8> {
+        self.signature.to_byte_vector()
+    }
+
+    pub fn from_byte_vector(bytes: &[u8]) -> Option<Self> {
+        Some(Self {
+            signature: SchnorrSignature::from_byte_vector(bytes)?,
+        })
+    }
+}
+
+// The signature of tx_recover published by the buyer once the timelock has expired
+pub struct RefundMessage<C>
+where
+    C: CurveArithmetic,
+{
+    pub signature: SchnorrSignature<C>,
+}
+
+impl<C> RefundMessage<C>
+where
+    C: CurveArithmetic,
+{
+    pub fn to_byte_vector(&self) -> Vec<u8> {
+        self.signature.to_byte_vector()
+    }
+
+    pub fn from_byte_vector(bytes: &[u8]) -> Option<Self> {
+        Some(Self {
+            signature: SchnorrSignature::from_byte_vector(bytes)?,
+        })
+    }
+}
+
+// The signature of tx_pay published by the seller, and read from the ledger by the buyer
+pub struct PaymentMessage<C>
+where
+    C: CurveArithmetic,
+{
+    pub signature: SchnorrSignature<C>,
+}
+
+impl<C> PaymentMessage<C>
+where
+    C: CurveArithmetic,
+{
+    pub fn to_byte_vector(&self) -> Vec<u8> {
+        self.signature.to_byte_vector()
+    }
+
+    pub fn from_byte_vector(bytes: &[u8]) -> Option<Self> {
+        Some(Self {
+            signature: SchnorrSignature::from_byte_vector(bytes)?,
+        })
+    }
+}
+
+pub enum SellerState<C, S>
+where
+    C: CurveArithmetic,
+{
+    // Holds the secret of the goods (the signature of the notary), waiting to make an offer
+    Ready {
+        secret: S,
+    },
+    // The offer is sent, waiting for tx_lock to be published
+    Offered {
+        w: NonZeroScalar<C>,
+        x: Point<C>,
+    },
+    // tx_lock is confirmed, waiting for the pre-signature of the buyer
+    Locked {
+        w: NonZeroScalar<C>,
+        x: Point<C>,
+        locked_at: u64,
+    },
+    // The payment is published, so the buyer can recover the goods
+    Paid,
+}
+
+pub struct Seller<C, H, G>
+where
+    C: CurveArithmetic,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+    G: Goods<C, H>,
+{
+    terms: Terms<C>,
+    params: G::Params,
+    state: SellerState<C, G::Secret>,
+    evidence: Vec<Evidence>,
+    _hash_marker: PhantomData<H>,
+    _goods_marker: PhantomData<G>,
+}
+
+impl<C, H, G> Seller<C, H, G>
+where
+    C: CurveArithmetic,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+    G: Goods<C, H>,
+{
+    pub fn new(
+        terms: Terms<C>,
+        params: G::Params,
+        secret: G::Secret,
+    ) -> Result<Self, SellingError> {
+        if !G::verify_secret(&terms, &params, &secret) {
+            return Err(SellingError::InvalidNotarySignature);
+        }
+
+        Ok(Self {
+            terms,
+            params,
+            state: SellerState::Ready { secret },
+            evidence: vec![],
+            _hash_marker: PhantomData,
+            _goods_marker: PhantomData,
+        })
+    }
+
+    pub fn state(&self) -> &SellerState<C, G::Secret> {
+        &self.state
+    }
+
+    // The messages of the buyer rejected so far
+    pub fn evidence(&self) -> &[Evidence] {
+        &self.evidence
+    }
+
+    fn blame(&mut self, error: SellingError, message: Vec<u8>) -> SellingError {
+        self.evidence
+            .push(Evidence::new(Party::Buyer, error, message));
+
+        error
+    }
+
+    pub fn offer(&mut self) -> Result<G::Offer, SellingError> {
+        let SellerState::Ready { secret } = &self.state else {
+            return Err(SellingError::UnexpectedMessage);
+        };
+        let (w, x) = NonZeroScalar::<C>::gen(&Point::<C>::generator());
+        let offer = G::offer(&self.terms, &self.params, secret, &w, &x);
+        self.state = SellerState::Offered { w, x };
+
+        Ok(offer)
+    }
+
+    // confirmed_at is the height of the block that confirmed tx_lock, not the time the seller
+    // reads it, so that the seller and the buyer count the timelock from the same point
+    pub fn receive_lock(
+        &mut self,
+        msg: &LockMessage<C>,
+        confirmed_at: u64,
+    ) -> Result<(), SellingError> {
+        let SellerState::Offered { w, x } = &self.state else {
+            return Err(SellingError::UnexpectedMessage);
+        };
+        if !SchnorrSignatureScheme::<C, H>::verify(
+            &self.terms.pk_lock,
+            &self.terms.tx_lock,
+            &msg.signature,
+        ) {
+            return Err(self.blame(SellingError::InvalidLock, msg.to_byte_vector()));
+        }
+        self.state = SellerState::Locked {
+            w: *w,
+            x: *x,
+            locked_at: confirmed_at,
+        };
+
+        Ok(())
+    }
+
+    pub fn receive_pre_signature(
+        &mut self,
+        msg: &PreSignatureMessage<C>,
+        clock: &Clock,
+    ) -> Result<PaymentMessage<C>, SellingError> {
+        let SellerState::Locked { w, x, locked_at } = &self.state else {
+            return Err(SellingError::UnexpectedMessage);
+        };
+        if clock.now() >= self.terms.payment_deadline(*locked_at) {
+            return Err(SellingError::TimelockExpired);
+        }
+        let pk_buyer = &self.terms.pk_buyer;
+        let tx_pay = &self.terms.tx_pay;
+        if !ASig::<C, H>::pre_verify(pk_buyer, tx_pay, x, &msg.pre_signature) {
+            return Err(self.blame(SellingError::InvalidPreSignature, msg.to_byte_vector()));
+        }
+        let signature = ASig::<C, H>::adapt(pk_buyer, &msg.pre_signature, w);
+        if !ASig::<C, H>::verify(pk_buyer, tx_pay, &signature) {
+            return Err(self.blame(SellingError::InvalidPreSignature, msg.to_byte_vector()));
+        }
+        self.state = SellerState::Paid;
+
+        Ok(PaymentMessage { signature })
+    }
+}
+
+pub enum BuyerState<C, O>
+where
+    C: CurveArithmetic,
+{
+    // Waiting for the offer of the seller
+    Ready,
+    // The pre-signature is sent, waiting to publish tx_lock
+    PreSigned {
+        offer: O,
+        pre_signature: SchnorrSignature<C>,
+    },
+    // tx_lock is signed, waiting for it to be confirmed
+    Locking {
+        offer: O,
+        pre_signature: SchnorrSignature<C>,
+    },
+    // tx_lock is confirmed, waiting for the payment to appear on the ledger
+    Locked {
+        offer: O,
+        pre_signature: SchnorrSignature<C>,
+        locked_at: u64,
+    },
+    // The goods have been recovered from the payment
+    Done,
+    // The seller did not publish the payment in time, and tx_recover is published
+    Refunded,
+}
+
+pub struct Buyer<C, H, G>
+where
+    C: CurveArithmetic,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+    G: Goods<C, H>,
+{
+    terms: Terms<C>,
+    params: G::Params,
+    sk_lock: NonZeroScalar<C>,
+    sk: NonZeroScalar<C>,
+    state: BuyerState<C, G::Offer>,
+    evidence: Vec<Evidence>,
+    _hash_marker: PhantomData<H>,
+}
+
+impl<C, H, G> Buyer<C, H, G>
+where
+    C: CurveArithmetic,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+    G: Goods<C, H>,
+{
+    // sk_lock and sk are the signing keys of terms.pk_lock and terms.pk_buyer
+    pub fn new(
+        terms: Terms<C>,
+        params: G::Params,
+        sk_lock: NonZeroScalar<C>,
+        sk: NonZeroScalar<C>,
+    ) -> Self {
+        Self {
+            terms,
+            params,
+            sk_lock,
+            sk,
+            state: BuyerState::Ready,
+            evidence: vec![],
+            _hash_marker: PhantomData,
+        }
+    }
+
+    pub fn state(&self) -> &BuyerState<C, G::Offer> {
+        &self.state
+    }
+
+    // The messages of the seller rejected so far
+    pub fn evidence(&self) -> &[Evidence] {
+        &self.evidence
+    }
+
+    fn blame(&mut self, error: SellingError, message: Vec<u8>) -> SellingError {
+        self.evidence
+            .push(Evidence::new(Party::Seller, error, message));
+
+        error
+    }
+
+    pub fn receive_offer(
+        &mut self,
+        offer: G::Offer,
+    ) -> Result<PreSignatureMessage<C>, SellingError> {
+        let BuyerState::Ready = &self.state else {
+            return Err(SellingError::UnexpectedMessage);
+        };
+        if !G::verify_offer(&self.terms, &self.params, &offer) {
+            let bytes = G::offer_to_byte_vector(&offer);
+            return Err(self.blame(SellingError::InvalidOffer, bytes));
+        }
+        let x = G::statement(&offer);
+        let pre_signature = ASig::<C, H>::pre_sign(&self.sk, &self.terms.tx_pay, &x);
+        let msg = PreSignatureMessage {
+            pre_signature: pre_signature.clone(),
+        };
+        self.state = BuyerState::PreSigned {
+            offer,
+            pre_signature,
+        };
+
+        Ok(msg)
+    }
+
+    pub fn lock(&mut self) -> Result<LockMessage<C>, SellingError> {
+        match std::mem::replace(&mut self.state, BuyerState::Ready) {
+            BuyerState::PreSigned {
+                offer,
+                pre_signature,
+            } => {
+                let signature =
+                    SchnorrSignatureScheme::<C, H>::sign(&self.sk_lock, &self.terms.tx_lock);
+                self.state = BuyerState::Locking {
+                    offer,
+                    pre_signature,
+                };
+
+                Ok(LockMessage { signature })
+            }
+            state => {
+                self.state = state;
+                Err(SellingError::UnexpectedMessage)
+            }
+        }
+    }
+
+    // confirmed_at is the height of the block that confirmed tx_lock, from which the timelock of
+    // tx_recover counts
+    pub fn confirm_lock(&mut self, confirmed_at: u64) -> Result<(), SellingError> {
+        match std::mem::replace(&mut self.state, BuyerState::Ready) {
+            BuyerState::Locking {
+                offer,
+                pre_signature,
+            } => {
+                self.state = BuyerState::Locked {
+                    offer,
+                    pre_signature,
+                    locked_at: confirmed_at,
+                };
+
+                Ok(())
+            }
+            state => {
+                self.state = state;
+                Err(SellingError::UnexpectedMessage)
+            }
+        }
+    }
+
+    pub fn refund(&mut self, clock: &Clock) -> Result<RefundMessage<C>, SellingError> {
+        let BuyerState::Locked { locked_at, .. } = &self.state else {
+            return Err(SellingError::UnexpectedMessage);
+        };
+        if clock.now() < self.terms.deadline(*locked_at) {
+            return Err(SellingError::TimelockNotExpired);
+        }
+        let signature = SchnorrSignatureScheme::<C, H>::sign(&self.sk, &self.terms.tx_recover);
+        self.state = BuyerState::Refunded;
+
+        Ok(RefundMessage { signature })
+    }
+
+    pub fn receive_payment(&mut self, msg: &PaymentMessage<C>) -> Result<G::Output, SellingError> {
+        let BuyerState::Locked {
+            offer,
+            pre_signature,
+            ..
+        } = &self.state
+        else {
+            return Err(SellingError::UnexpectedMessage);
+        };
+        let pk_buyer = &self.terms.pk_buyer;
+        if !ASig::<C, H>::verify(pk_buyer, &self.terms.tx_pay, &msg.signature) {
+            return Err(self.blame(SellingError::InvalidPayment, msg.to_byte_vector()));
+        }
+        let w: Option<NonZeroScalar<C>> =
+            ASig::<C, H>::extract(pk_buyer, pre_signature, &msg.signature).into();
+        let Some(goods) = w.and_then(|w| G::recover(&self.terms, &self.params, offer, &w)) else {
+            return Err(self.blame(SellingError::ExtractionFailed, msg.to_byte_vector()));
+        };
+        self.state = BuyerState::Done;
+
+        Ok(goods)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ledger::{Branch, Key, Ledger, LedgerError, OutPoint, Output, Transaction, Witness};
+    use crate::transport::{MemoryTransport, Transport};
+    use k256::Secp256k1;
+    use sha2::Sha256;
+    use std::thread;
+
+    type C = Secp256k1;
+
+    const TIMEOUT: u64 = 144;
+
+    // the terms, the secret of the seller and the keys of the buyer
+    type Setup<S> = (Terms<C>, S, NonZeroScalar<C>, NonZeroScalar<C>);
+
+    fn terms() -> Setup<SchnorrSignature<C>> {
+        let (sk_notary, pk_notary) = SchnorrSignatureScheme::<C, Sha256>::gen();
+        let (sk_lock, pk_lock) = SchnorrSignatureScheme::<C, Sha256>::gen();
+        let (sk_buyer, pk_buyer) = ASig::<C, Sha256>::gen();
+        let msg = String::from("Lorem ipsum dolor sit amet");
+        let signature = SchnorrSignatureScheme::<C, Sha256>::sign(&sk_notary, &msg);
+        let terms = Terms {
+            pk_notary,
+            msg,
+            pk_lock,
+            pk_buyer,
+            tx_lock: String::from("(alpha, pk_b_1) -> (alpha, (pk_b_2 && pk_s) || (pk_b_2 + t))"),
+            tx_pay: String::from("(alpha, (pk_b_2 && pk_s) || (pk_b_2 + t)) -> (alpha, pk_s_2)"),
+            tx_recover: String::from(
+                "(alpha, (pk_b_2 && pk_s) || (pk_b_2 + t)) -> (alpha, pk_b_3)",
+            ),
+            timeout: TIMEOUT,
+        };
+
+        (terms, signature, sk_lock, sk_buyer)
+    }
+
+    #[test]
+    fn selling_signature() {
+        type G = SignatureGoods<C, Sha256>;
+        let (terms, signature, sk_lock, sk_buyer) = terms();
+        let sig = signature.sig;
+        let mut clock = Clock::new();
+        let mut seller = Seller::<C, Sha256, G>::new(terms.clone(), (), signature).unwrap();
+        let mut buyer = Buyer::<C, Sha256, G>::new(terms, (), sk_lock, sk_buyer);
+
+        let offer = seller.offer().unwrap();
+        assert_eq!(seller.offer().err(), Some(SellingError::UnexpectedMessage));
+        let pre_signature = buyer.receive_offer(offer).unwrap();
+        let lock = buyer.lock().unwrap();
+        // tx_lock is confirmed at height 1, and the seller pays just before its margin
+        clock.advance(1);
+        buyer.confirm_lock(clock.now()).unwrap();
+        seller.receive_lock(&lock, clock.now()).unwrap();
+        clock.advance(TIMEOUT - PAYMENT_MARGIN - 1);
+        let payment = seller
+            .receive_pre_signature(&pre_signature, &clock)
+            .unwrap();
+        assert!(matches!(seller.state(), SellerState::Paid));
+        let extracted_signature = buyer.receive_payment(&payment).unwrap();
+        assert!(matches!(buyer.state(), BuyerState::Done));
+
+        assert!(extracted_signature.sig == sig);
+    }
+
+    #[test]
+    fn selling_service() {
+        type G = ServiceGoods<C, Sha256>;
+        let (terms, signature, sk_lock, sk_buyer) = terms();
+        let clock = Clock::new();
+        let mut seller = Seller::<C, Sha256, G>::new(terms.clone(), (), signature).unwrap();
+        let mut buyer = Buyer::<C, Sha256, G>::new(terms, (), sk_lock, sk_buyer);
+
+        let offer = seller.offer().unwrap();
+        let x = offer.x;
+        let pre_signature = buyer.receive_offer(offer).unwrap();
+        let lock = buyer.lock().unwrap();
+        buyer.confirm_lock(clock.now()).unwrap();
+        seller.receive_lock(&lock, clock.now()).unwrap();
+        let payment = seller
+            .receive_pre_signature(&pre_signature, &clock)
+            .unwrap();
+        let w = buyer.receive_payment(&payment).unwrap();
+
+        assert!(Point::<C>::GENERATOR * *w == x);
+    }
+
+    // the seller runs in its own thread and both roles only see the bytes of the messages
+    fn selling_over_transport<G>(
+        params: G::Params,
+        (terms, secret, sk_lock, sk_buyer): Setup<G::Secret>,
+    ) where
+        G: Goods<C, Sha256>,
+        G::Params: Clone + Send + 'static,
+        G::Secret: Send + 'static,
+        G::Offer: Send,
+    {
+        let (mut seller_end, mut buyer_end) = MemoryTransport::pair();
+        let seller_terms = terms.clone();
+        let seller_params = params.clone();
+        let seller = thread::spawn(move || {
+            let clock = Clock::new();
+            let mut seller =
+                Seller::<C, Sha256, G>::new(seller_terms, seller_params, secret).unwrap();
+            let offer = seller.offer().unwrap();
+            seller_end.send(&G::offer_to_byte_vector(&offer)).unwrap();
+            let lock = LockMessage::from_byte_vector(&seller_end.receive().unwrap()).unwrap();
+            seller.receive_lock(&lock, clock.now()).unwrap();
+            let pre_signature =
+                PreSignatureMessage::from_byte_vector(&seller_end.receive().unwrap()).unwrap();
+            let payment = seller
+                .receive_pre_signature(&pre_signature, &clock)
+                .unwrap();
+            seller_end.send(&payment.to_byte_vector()).unwrap();
+        });
+
+        let clock = Clock::new();
+        let bytes = buyer_end.receive().unwrap();
+        assert!(G::offer_from_byte_vector(&terms, &params, &bytes[1..]).is_none());
+        assert!(G::offer_from_byte_vector(&terms, &params, &bytes[..bytes.len() - 1]).is_none());
+        let offer = G::offer_from_byte_vector(&terms, &params, &bytes).unwrap();
+        let mut buyer = Buyer::<C, Sha256, G>::new(terms, params, sk_lock, sk_buyer);
+        assert_eq!(G::offer_to_byte_vector(&offer), bytes);
+        let pre_signature = buyer.receive_offer(offer).unwrap();
+        buyer_end
+            .send(&buyer.lock().unwrap().to_byte_vector())
+            .unwrap();
+        buyer.confirm_lock(clock.now()).unwrap();
+        buyer_end.send(&pre_signature.to_byte_vector()).unwrap();
+        let payment = PaymentMessage::from_byte_vector(&buyer_end.receive().unwrap()).unwrap();
+        assert!(buyer.receive_payment(&payment).is_ok());
+        seller.join().unwrap();
+        assert_eq!(buyer_end.bytes_sent(), 2 * (4 + 64));
+    }
+
+    #[test]
+    fn selling_signature_over_transport() {
+        selling_over_transport::<SignatureGoods<C, Sha256>>((), terms());
+    }
+
+    #[test]
+    fn selling_service_over_transport() {
+        selling_over_transport::<ServiceGoods<C, Sha256>>((), terms());
+    }
+
+    // n messages, and the terms of a batch of them with the signatures of the notary
+    fn batch_terms(n: usize) -> (Vec<String>, Setup<Vec<SchnorrSignature<C>>>) {
+        let (mut terms, _, sk_lock, sk_buyer) = terms();
+        let (sk_notary, pk_notary) = SchnorrSignatureScheme::<C, Sha256>::gen();
+        terms.pk_notary = pk_notary;
+        let msgs: Vec<String> = (0..n).map(|i| format!("{} {}", terms.msg, i)).collect();
+        let signatures = msgs
+            .iter()
+            .map(|msg| SchnorrSignatureScheme::<C, Sha256>::sign(&sk_notary, msg))
+            .collect();
+
+        (msgs, (terms, signatures, sk_lock, sk_buyer))
+    }
+
+    #[test]
+    fn selling_batch() {
+        type G = BatchSignatureGoods<C, Sha256>;
+        const N: usize = 8;
+        let (single_terms, signature, _, _) = terms();
+        let single_offer =
+            Seller::<C, Sha256, SignatureGoods<C, Sha256>>::new(single_terms, (), signature)
+                .unwrap()
+                .offer()
+                .unwrap();
+        let (msgs, (terms, signatures, sk_lock, sk_buyer)) = batch_terms(N);
+        let clock = Clock::new();
+        let mut seller =
+            Seller::<C, Sha256, G>::new(terms.clone(), msgs.clone(), signatures.clone()).unwrap();
+        let mut buyer = Buyer::<C, Sha256, G>::new(terms, msgs, sk_lock, sk_buyer);
+
+        let offer = seller.offer().unwrap();
+        // one statement, the number of signatures, one proof and two scalars per signature,
+        // instead of N offers
+        let bytes = G::offer_to_byte_vector(&offer);
+        assert_eq!(bytes.len(), 33 + 2 + N * 64 + 33 + 32);
+        assert_eq!(bytes[33..35], (N as u16).to_be_bytes());
+        assert!(bytes.len() < N * single_offer.to_byte_vector().len());
+        let pre_signature = buyer.receive_offer(offer).unwrap();
+        let lock = buyer.lock().unwrap();
+        buyer.confirm_lock(clock.now()).unwrap();
+        seller.receive_lock(&lock, clock.now()).unwrap();
+        let payment = seller
+            .receive_pre_signature(&pre_signature, &clock)
+            .unwrap();
+        let extracted_signatures = buyer.receive_payment(&payment).unwrap();
+
+        assert_eq!(extracted_signatures.len(), N);
+        for (extracted, signature) in extracted_signatures.iter().zip(&signatures) {
+            assert!(extracted.proof == signature.proof && extracted.sig == signature.sig);
+        }
+    }
+
+    #[test]
+    fn selling_batch_errors() {
+        type G = BatchSignatureGoods<C, Sha256>;
+        let (msgs, (terms, signatures, sk_lock, sk_buyer)) = batch_terms(3);
+        let mut swapped = signatures.clone();
+        swapped.swap(0, 1);
+        for secret in [vec![], signatures[..2].to_vec(), swapped] {
+            assert_eq!(
+                Seller::<C, Sha256, G>::new(terms.clone(), msgs.clone(), secret).err(),
+                Some(SellingError::InvalidNotarySignature)
+            );
+        }
+        assert_eq!(
+            Seller::<C, Sha256, G>::new(terms.clone(), vec![], vec![]).err(),
+            Some(SellingError::InvalidNotarySignature)
+        );
+        let mut seller =
+            Seller::<C, Sha256, G>::new(terms.clone(), msgs.clone(), signatures).unwrap();
+        let mut buyer = Buyer::<C, Sha256, G>::new(terms.clone(), msgs.clone(), sk_lock, sk_buyer);
+        let offer = seller.offer().unwrap();
+        let bytes = G::offer_to_byte_vector(&offer);
+
+        // an item encrypted with another witness than the one of x
+        let mut tampered = G::offer_from_byte_vector(&terms, &msgs, &bytes).unwrap();
+        tampered.items[2].1 += k256::Scalar::ONE;
+        assert_eq!(
+            buyer.receive_offer(tampered).err(),
+            Some(SellingError::InvalidOffer)
+        );
+        // the offer of a smaller batch, and a count that disagrees with the items
+        let mut truncated = G::offer_from_byte_vector(&terms, &msgs, &bytes).unwrap();
+        truncated.items.pop();
+        assert_eq!(
+            buyer.receive_offer(truncated).err(),
+            Some(SellingError::InvalidOffer)
+        );
+        let mut miscounted = bytes.clone();
+        miscounted[34] -= 1;
+        assert!(G::offer_from_byte_vector(&terms, &msgs, &miscounted).is_none());
+        assert_eq!(buyer.evidence().len(), 2);
+        // a buyer who agreed on a smaller batch
+        let mut smaller_buyer =
+            Buyer::<C, Sha256, G>::new(terms.clone(), msgs[..2].to_vec(), sk_lock, sk_buyer);
+        assert_eq!(
+            smaller_buyer
+                .receive_offer(G::offer_from_byte_vector(&terms, &msgs, &bytes).unwrap())
+                .err(),
+            Some(SellingError::InvalidOffer)
+        );
+        assert!(matches!(buyer.state(), BuyerState::Ready));
+
+        assert!(buyer.receive_offer(offer).is_ok());
+    }
+
+    #[test]
+    fn selling_batch_over_transport() {
+        let (msgs, terms) = batch_terms(4);
+        selling_over_transport::<BatchSignatureGoods<C, Sha256>>(msgs, terms);
+    }
+
+    #[test]
+    fn selling_errors() {
+        type G = SignatureGoods<C, Sha256>;
+        let (terms, signature, sk_lock, sk_buyer) = terms();
+        let clock = Clock::new();
+        let forged = SchnorrSignature {
+            proof: signature.proof,
+            sig: signature.sig + signature.sig,
+        };
+        assert_eq!(
+            Seller::<C, Sha256, G>::new(terms.clone(), (), forged).err(),
+            Some(SellingError::InvalidNotarySignature)
+        );
+
+        let mut seller = Seller::<C, Sha256, G>::new(terms.clone(), (), signature).unwrap();
+        let mut buyer = Buyer::<C, Sha256, G>::new(terms.clone(), (), sk_lock, sk_buyer);
+
+        // an offer on a message other than the one of the terms of the buyer
+        let other_terms = Terms {
+            msg: String::from("another message"),
+            ..terms.clone()
+        };
+        let other_signature = SchnorrSignature {
+            proof: Default::default(),
+            sig: Default::default(),
+        };
+        let (w, x) = NonZeroScalar::<C>::gen(&Point::<C>::GENERATOR);
+        let bad_offer = G::offer(&other_terms, &(), &other_signature, &w, &x);
+        assert_eq!(
+            buyer.receive_offer(bad_offer).err(),
+            Some(SellingError::InvalidOffer)
+        );
+        assert_eq!(buyer.lock().err(), Some(SellingError::UnexpectedMessage));
+
+        let offer = seller.offer().unwrap();
+        let pre_signature = buyer.receive_offer(offer).unwrap();
+
+        // the seller does not accept the pre-signature before tx_lock is published, nor a lock
+        // signed by another key
+        assert_eq!(
+            seller.receive_pre_signature(&pre_signature, &clock).err(),
+            Some(SellingError::UnexpectedMessage)
+        );
+        let bad_lock = LockMessage {
+            signature: SchnorrSignatureScheme::<C, Sha256>::sign(&w, &terms.tx_lock),
+        };
+        assert_eq!(
+            seller.receive_lock(&bad_lock, clock.now()).err(),
+            Some(SellingError::InvalidLock)
+        );
+        let lock = buyer.lock().unwrap();
+        buyer.confirm_lock(clock.now()).unwrap();
+        seller.receive_lock(&lock, clock.now()).unwrap();
+
+        // a pre-signature under another key is rejected, and the seller can still proceed
+        let (sk_other, _) = ASig::<C, Sha256>::gen();
+        let bad_pre_signature = PreSignatureMessage {
+            pre_signature: ASig::<C, Sha256>::pre_sign(&sk_other, &terms.tx_pay, &x),
+        };
+        assert_eq!(
+            seller
+                .receive_pre_signature(&bad_pre_signature, &clock)
+                .err(),
+            Some(SellingError::InvalidPreSignature)
+        );
+        let payment = seller
+            .receive_pre_signature(&pre_signature, &clock)
+            .unwrap();
+
+        let bad_payment = PaymentMessage {
+            signature: SchnorrSignature {
+                proof: payment.signature.proof,
+                sig: payment.signature.sig + payment.signature.sig,
+            },
+        };
+        assert_eq!(
+            buyer.receive_payment(&bad_payment).err(),
+            Some(SellingError::InvalidPayment)
+        );
+        assert!(buyer.receive_payment(&payment).is_ok());
+        assert_eq!(
+            buyer.receive_payment(&payment).err(),
+            Some(SellingError::UnexpectedMessage)
+        );
+        assert_eq!(
+            buyer.refund(&clock).err(),
+            Some(SellingError::UnexpectedMessage)
+        );
+    }
+
+    #[test]
+    fn selling_refund() {
+        type G = SignatureGoods<C, Sha256>;
+        let (terms, signature, sk_lock, sk_buyer) = terms();
+        let mut clock = Clock::new();
+        clock.advance(10);
+        let mut seller = Seller::<C, Sha256, G>::new(terms.clone(), (), signature).unwrap();
+        let mut buyer = Buyer::<C, Sha256, G>::new(terms.clone(), (), sk_lock, sk_buyer);
+
+        let offer = seller.offer().unwrap();
+        let pre_signature = buyer.receive_offer(offer).unwrap();
+        // nothing to refund before tx_lock is published
+        assert_eq!(
+            buyer.refund(&clock).err(),
+            Some(SellingError::UnexpectedMessage)
+        );
+        let lock = buyer.lock().unwrap();
+        buyer.confirm_lock(clock.now()).unwrap();
+        seller.receive_lock(&lock, clock.now()).unwrap();
+
+        // the seller aborts, and the buyer has to wait for the timelock
+        clock.advance(TIMEOUT - 1);
+        assert_eq!(
+            buyer.refund(&clock).err(),
+            Some(SellingError::TimelockNotExpired)
+        );
+        clock.advance(1);
+        let refund = buyer.refund(&clock).unwrap();
+        assert!(matches!(buyer.state(), BuyerState::Refunded));
+        assert!(SchnorrSignatureScheme::<C, Sha256>::verify(
+            &terms.pk_buyer,
+            &terms.tx_recover,
+            &refund.signature
+        ));
+
+        // once the timelock has expired, the seller can no longer get paid
+        assert_eq!(
+            seller.receive_pre_signature(&pre_signature, &clock).err(),
+            Some(SellingError::TimelockExpired)
+        );
+    }
+
+    // every deviation of the other party is rejected and kept as evidence, and the honest party
+    // can go on with the valid message or, for the buyer, get its coins back
+    #[test]
+    fn selling_deviations() {
+        type G = SignatureGoods<C, Sha256>;
+        let (terms, signature, sk_lock, sk_buyer) = terms();
+        let mut clock = Clock::new();
+        let mut seller = Seller::<C, Sha256, G>::new(terms.clone(), (), signature).unwrap();
+        let mut buyer = Buyer::<C, Sha256, G>::new(terms.clone(), (), sk_lock, sk_buyer);
+        let (w_other, x_other) = NonZeroScalar::<C>::gen(&Point::<C>::GENERATOR);
+
+        // an offer whose statement is not the one of its proof
+        let offer = seller.offer().unwrap();
+        let mut bad_offer = G::offer_to_byte_vector(&offer);
+        let x_other_bytes = point_to_byte_vector::<C>(&x_other);
+        bad_offer[..x_other_bytes.len()].copy_from_slice(&x_other_bytes);
+        let decoded = G::offer_from_byte_vector(&terms, &(), &bad_offer).unwrap();
+        assert_eq!(
+            buyer.receive_offer(decoded).err(),
+            Some(SellingError::InvalidOffer)
+        );
+        assert!(matches!(buyer.state(), BuyerState::Ready));
+        let pre_signature = buyer.receive_offer(offer).unwrap();
+
+        // a lock signed by another key
+        let bad_lock = LockMessage {
+            signature: SchnorrSignatureScheme::<C, Sha256>::sign(&w_other, &terms.tx_lock),
+        };
+        assert_eq!(
+            seller.receive_lock(&bad_lock, clock.now()).err(),
+            Some(SellingError::InvalidLock)
+        );
+        assert!(matches!(seller.state(), SellerState::Offered { .. }));
+        let lock = buyer.lock().unwrap();
+        buyer.confirm_lock(clock.now()).unwrap();
+        seller.receive_lock(&lock, clock.now()).unwrap();
+
+        // a pre-signature for another statement
+        let bad_pre_signature = PreSignatureMessage {
+            pre_signature: ASig::<C, Sha256>::pre_sign(&sk_buyer, &terms.tx_pay, &x_other),
+        };
+        assert_eq!(
+            seller
+                .receive_pre_signature(&bad_pre_signature, &clock)
+                .err(),
+            Some(SellingError::InvalidPreSignature)
+        );
+        assert!(matches!(seller.state(), SellerState::Locked { .. }));
+        seller
+            .receive_pre_signature(&pre_signature, &clock)
+            .unwrap();
+        assert_eq!(
+            seller.evidence(),
+            &[
+                Evidence::new(
+                    Party::Buyer,
+                    SellingError::InvalidLock,
+                    bad_lock.to_byte_vector()
+                ),
+                Evidence::new(
+                    Party::Buyer,
+                    SellingError::InvalidPreSignature,
+                    bad_pre_signature.to_byte_vector()
+                ),
+            ]
+        );
+
+        // a payment adapted with another witness, and a valid signature of tx_pay that does not
+        // come from the pre-signature of the buyer
+        let bad_payment = PaymentMessage {
+            signature: ASig::<C, Sha256>::adapt(
+                &terms.pk_buyer,
+                &pre_signature.pre_signature,
+                &w_other,
+            ),
+        };
+        assert_eq!(
+            buyer.receive_payment(&bad_payment).err(),
+            Some(SellingError::InvalidPayment)
+        );
+        let unrelated_payment = PaymentMessage {
+            signature: ASig::<C, Sha256>::adapt(
+                &terms.pk_buyer,
+                &bad_pre_signature.pre_signature,
+                &w_other,
+            ),
+        };
+        assert_eq!(
+            buyer.receive_payment(&unrelated_payment).err(),
+            Some(SellingError::ExtractionFailed)
+        );
+        assert_eq!(
+            buyer.evidence(),
+            &[
+                Evidence::new(Party::Seller, SellingError::InvalidOffer, bad_offer),
+                Evidence::new(
+                    Party::Seller,
+                    SellingError::InvalidPayment,
+                    bad_payment.to_byte_vector()
+                ),
+                Evidence::new(
+                    Party::Seller,
+                    SellingError::ExtractionFailed,
+                    unrelated_payment.to_byte_vector()
+                ),
+            ]
+        );
+        assert!(buyer.evidence()[0].to_string().starts_with("seller: "));
+
+        // the seller withholds the valid payment: the buyer waits for the timelock
+        assert!(matches!(buyer.state(), BuyerState::Locked { .. }));
+        clock.advance(TIMEOUT);
+        buyer.refund(&clock).unwrap();
+    }
+
+    type Sig = SchnorrSignatureScheme<C, Sha256>;
+    type Tx = Transaction<C, SchnorrSignature<C>>;
+
+    struct LedgerSetup {
+        ledger: Ledger<C, Sig, ASig<C, Sha256>>,
+        lock_outpoint: OutPoint,
+        tx_lock: Tx,
+        tx_pay: Tx,
+        tx_recover: Tx,
+        sk_seller: NonZeroScalar<C>,
+    }
+
+    // Funds the buyer on a ledger and builds tx_lock, tx_pay and tx_recover, with their messages
+    // in the terms
+    fn ledger_setup(terms: &mut Terms<C>) -> LedgerSetup {
+        let mut ledger = Ledger::new();
+        let (sk_seller, pk_seller) = Sig::gen();
+        let (_, pk_seller_2) = Sig::gen();
+        let (_, pk_buyer_3) = Sig::gen();
+        let funding = ledger.mint(Output::to_key(100, terms.pk_lock));
+        let lock = Output::new(
+            100,
+            vec![
+                Branch::new(
+                    vec![Key::Adaptor(terms.pk_buyer), Key::Signature(pk_seller)],
+                    0,
+                ),
+                Branch::new(vec![Key::Signature(terms.pk_buyer)], terms.timeout),
+            ],
+        );
+        let tx_lock = Tx::new(vec![funding], vec![lock]);
+        let lock_outpoint = tx_lock.outpoint(0);
+        let tx_pay = Tx::new(vec![lock_outpoint], vec![Output::to_key(100, pk_seller_2)]);
+        let tx_recover = Tx::new(vec![lock_outpoint], vec![Output::to_key(100, pk_buyer_3)]);
+        terms.tx_lock = tx_lock.message();
+        terms.tx_pay = tx_pay.message();
+        terms.tx_recover = tx_recover.message();
+
+        LedgerSetup {
+            ledger,
+            lock_outpoint,
+            tx_lock,
+            tx_pay,
+            tx_recover,
+            sk_seller,
+        }
+    }
+
+    #[test]
+    fn selling_on_ledger() {
+        type G = SignatureGoods<C, Sha256>;
+        let (mut terms, signature, sk_lock, sk_buyer) = terms();
+        let sig = signature.sig;
+        let mut setup = ledger_setup(&mut terms);
+        let ledger = &mut setup.ledger;
+        let mut seller = Seller::<C, Sha256, G>::new(terms.clone(), (), signature).unwrap();
+        let mut buyer = Buyer::<C, Sha256, G>::new(terms, (), sk_lock, sk_buyer);
+
+        let offer = seller.offer().unwrap();
+        let pre_signature = buyer.receive_offer(offer).unwrap();
+        let lock = buyer.lock().unwrap();
+        setup.tx_lock.witnesses = vec![Witness::new(0, vec![lock.signature])];
+        let lock_txid = ledger.submit(setup.tx_lock).unwrap();
+        ledger.mine();
+        let confirmed_at = ledger.confirmation_height(&lock_txid).unwrap();
+        buyer.confirm_lock(confirmed_at).unwrap();
+
+        // the seller reads tx_lock from the ledger
+        let published_lock = ledger.transaction(&lock_txid).unwrap();
+        let lock = LockMessage {
+            signature: published_lock.witnesses[0].signatures[0].clone(),
+        };
+        seller.receive_lock(&lock, confirmed_at).unwrap();
+        let payment = seller
+            .receive_pre_signature(&pre_signature, ledger.clock())
+            .unwrap();
+        let signature_seller = Sig::sign(&setup.sk_seller, &setup.tx_pay.message());
+        setup.tx_pay.witnesses = vec![Witness::new(0, vec![payment.signature, signature_seller])];
+        ledger.submit(setup.tx_pay).unwrap();
+        ledger.mine();
+
+        // the buyer reads tx_pay from the ledger, and can no longer publish tx_recover
+        let published_pay = ledger.spender(&setup.lock_outpoint).unwrap();
+        let payment = PaymentMessage {
+            signature: published_pay.witnesses[0].signatures[0].clone(),
+        };
+        let extracted_signature = buyer.receive_payment(&payment).unwrap();
+        assert!(extracted_signature.sig == sig);
+
+        let signature_recover = Sig::sign(&sk_buyer, &setup.tx_recover.message());
+        setup.tx_recover.witnesses = vec![Witness::new(1, vec![signature_recover])];
+        assert_eq!(
+            ledger.submit(setup.tx_recover),
+            Err(LedgerError::AlreadySpent)
+        );
+    }
+
+    #[test]
+    fn refund_on_ledger() {
+        type G = SignatureGoods<C, Sha256>;
+        let (mut terms, signature, sk_lock, sk_buyer) = terms();
+        let mut setup = ledger_setup(&mut terms);
+        let ledger = &mut setup.ledger;
+        let mut seller = Seller::<C, Sha256, G>::new(terms.clone(), (), signature).unwrap();
+        let mut buyer = Buyer::<C, Sha256, G>::new(terms, (), sk_lock, sk_buyer);
+
+        let offer = seller.offer().unwrap();
+        buyer.receive_offer(offer).unwrap();
+        let lock = buyer.lock().unwrap();
+        setup.tx_lock.witnesses = vec![Witness::new(0, vec![lock.signature])];
+        let lock_txid = ledger.submit(setup.tx_lock).unwrap();
+        ledger.mine();
+        let confirmed_at = ledger.confirmation_height(&lock_txid).unwrap();
+        buyer.confirm_lock(confirmed_at).unwrap();
+
+        // the seller aborts: nothing spends tx_lock until the timelock expires
+        while buyer.refund(ledger.clock()).err() == Some(SellingError::TimelockNotExpired) {
+            ledger.mine();
+        }
+        assert!(matches!(buyer.state(), BuyerState::Refunded));
+        assert_eq!(ledger.height(), confirmed_at + TIMEOUT);
+        let signature_recover = Sig::sign(&sk_buyer, &setup.tx_recover.message());
+        setup.tx_recover.witnesses = vec![Witness::new(1, vec![signature_recover])];
+        let recover_txid = ledger.submit(setup.tx_recover).unwrap();
+        ledger.mine();
+        assert_eq!(
+            ledger.spender(&setup.lock_outpoint).unwrap().txid(),
+            recover_txid
+        );
+    }
+
+    // the seller reads tx_lock late: the timelock counts from the block that confirmed tx_lock,
+    // not from the time the seller reads it, so the seller no longer completes tx_pay
+    #[test]
+    fn selling_late_lock() {
+        type G = SignatureGoods<C, Sha256>;
+        let (mut terms, signature, sk_lock, sk_buyer) = terms();
+        let mut setup = ledger_setup(&mut terms);
+        let ledger = &mut setup.ledger;
+        let mut seller = Seller::<C, Sha256, G>::new(terms.clone(), (), signature).unwrap();
+        let mut buyer = Buyer::<C, Sha256, G>::new(terms.clone(), (), sk_lock, sk_buyer);
+
+        let offer = seller.offer().unwrap();
+        let pre_signature = buyer.receive_offer(offer).unwrap();
+        let lock = buyer.lock().unwrap();
+        assert_eq!(
+            buyer.refund(ledger.clock()).err(),
+            Some(SellingError::UnexpectedMessage)
+        );
+        setup.tx_lock.witnesses = vec![Witness::new(0, vec![lock.signature.clone()])];
+        let lock_txid = ledger.submit(setup.tx_lock).unwrap();
+        ledger.mine();
+        let confirmed_at = ledger.confirmation_height(&lock_txid).unwrap();
+        buyer.confirm_lock(confirmed_at).unwrap();
+        assert_eq!(
+            buyer.confirm_lock(confirmed_at).err(),
+            Some(SellingError::UnexpectedMessage)
+        );
+
+        while ledger.height() < terms.payment_deadline(confirmed_at) {
+            ledger.mine();
+        }
+        seller.receive_lock(&lock, confirmed_at).unwrap();
+        assert_eq!(
+            seller
+                .receive_pre_signature(&pre_signature, ledger.clock())
+                .err(),
+            Some(SellingError::TimelockExpired)
+        );
+        assert!(matches!(seller.state(), SellerState::Locked { .. }));
+        assert!(seller.evidence().is_empty());
+
+        // the buyer still gets its coins back once the timelock expires
+        while buyer.refund(ledger.clock()).err() == Some(SellingError::TimelockNotExpired) {
+            ledger.mine();
+        }
+        assert_eq!(ledger.height(), terms.deadline(confirmed_at));
+    }
+}
@@ -0,0 +1,398 @@
+use crate::elgamal::ElGamal;
+use crate::hard_relation::HardRelation;
+use crate::nizk::NIZK;
+use crate::public_key_encryption_scheme::PublicKeyEncryptionScheme;
+use crate::utils::{point_to_byte_vector, scalar_to_byte_vector};
+use digest::Digest;
+use elliptic_curve::ff::Field;
+use elliptic_curve::CurveArithmetic;
+use rand_core::{OsRng, RngCore};
+use std::marker::PhantomData;
+
+type CypherText<C> = <ElGamal<C> as PublicKeyEncryptionScheme>::CypherText;
+
+// Verifiable shuffle of a list of ElGamal cyphertexts: the output is a permutation of the
+// re-encryptions of the input, and the proof shows it without revealing the permutation, so an
+// intermediary (a mix) can unlink the cyphertexts from their senders.
+//
+// This is the cut-and-choose proof of Sako and Kilian ("Receipt-Free Mix-Type Voting Scheme"),
+// made non-interactive with Fiat-Shamir. In each of the ROUNDS rounds the prover shuffles the
+// input into an intermediate list with a fresh random Shuffle, and then for each challenge bit
+// it opens either the shuffle from the input to the intermediate list (bit 0) or the one from the
+// intermediate list to the output (bit 1). Each opening alone is a random shuffle, so it reveals
+// nothing about the permutation of the witness, and a cheating prover can answer at most one of
+// the two challenges of a round, so it is caught except with probability 2^-ROUNDS. The proof
+// has ROUNDS * n cyphertexts, which is fine for the small lists of our use cases.
+
+pub const ROUNDS: usize = 128;
+
+pub struct ShuffleProof<C, H>
+where
+    C: CurveArithmetic,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+{
+    _curve_marker: PhantomData<C>,
+    _hash_marker: PhantomData<H>,
+}
+
+// A shuffle maps a list of cyphertexts to the list whose element i is a re-encryption of the
+// element permutation[i] with randomness[i].
+pub struct Shuffle<C>
+where
+    C: CurveArithmetic,
+{
+    permutation: Vec<usize>,
+    randomness: Vec<C::Scalar>,
+}
+
+impl<C> Shuffle<C>
+where
+    C: CurveArithmetic,
+{
+    pub fn new(permutation: Vec<usize>, randomness: Vec<C::Scalar>) -> Self {
+        Self {
+            permutation,
+            randomness,
+        }
+    }
+
+    // uniform permutation (Fisher-Yates) and randomness for lists of n cyphertexts
+    pub fn random(n: usize) -> Self {
+        let mut permutation: Vec<usize> = (0..n).collect();
+        for i in (1..n).rev() {
+            let j = (OsRng.next_u64() % (i as u64 + 1)) as usize;
+            permutation.swap(i, j);
+        }
+        let randomness = (0..n).map(|_| C::Scalar::random(&mut OsRng)).collect();
+
+        Self::new(permutation, randomness)
+    }
+
+    pub fn len(&self) -> usize {
+        self.permutation.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.permutation.is_empty()
+    }
+
+    // None if the shuffle is not a permutation of the indices of cts
+    pub fn apply(
+        &self,
+        pk: &C::ProjectivePoint,
+        cts: &[CypherText<C>],
+    ) -> Option<Vec<CypherText<C>>> {
+        if !self.is_permutation(cts.len()) {
+            return None;
+        }
+
+        Some(
+            self.permutation
+                .iter()
+                .zip(&self.randomness)
+                .map(|(j, r)| ElGamal::<C>::rerandomize_with(pk, &cts[*j], r))
+                .collect(),
+        )
+    }
+
+    // the shuffle t such that t.apply(self.apply(cts)) == other.apply(cts)
+    fn then(&self, other: &Self) -> Self {
+        let mut inverse = vec![0; self.len()];
+        for (i, j) in self.permutation.iter().enumerate() {
+            inverse[*j] = i;
+        }
+        let (permutation, randomness) = other
+            .permutation
+            .iter()
+            .zip(&other.randomness)
+            .map(|(j, r)| (inverse[*j], *r - self.randomness[inverse[*j]]))
+            .unzip();
+
+        Self::new(permutation, randomness)
+    }
+
+    fn is_permutation(&self, n: usize) -> bool {
+        let mut seen = vec![false; n];
+        self.permutation.len() == n
+            && self.randomness.len() == n
+            && self
+                .permutation
+                .iter()
+                .all(|j| *j < n && !std::mem::replace(&mut seen[*j], true))
+    }
+
+    pub fn to_byte_vector(&self) -> Vec<u8> {
+        let mut v: Vec<u8> = Vec::new();
+        for (j, r) in self.permutation.iter().zip(&self.randomness) {
+            v.extend_from_slice(&(*j as u32).to_be_bytes());
+            v.append(&mut scalar_to_byte_vector::<C>(r));
+        }
+
+        v
+    }
+}
+
+pub type Witness<C> = Shuffle<C>;
+
+pub struct Statement<C>
+where
+    C: CurveArithmetic,
+{
+    pk: C::ProjectivePoint,
+    input: Vec<CypherText<C>>,
+    output: Vec<CypherText<C>>,
+}
+
+impl<C> Statement<C>
+where
+    C: CurveArithmetic,
+{
+    pub fn new(
+        pk: C::ProjectivePoint,
+        input: Vec<CypherText<C>>,
+        output: Vec<CypherText<C>>,
+    ) -> Self {
+        Self { pk, input, output }
+    }
+
+    pub fn to_byte_vector(&self) -> Vec<u8> {
+        let mut v: Vec<u8> = point_to_byte_vector::<C>(&self.pk);
+        v.append(&mut cts_to_byte_vector::<C>(&self.input));
+        v.append(&mut cts_to_byte_vector::<C>(&self.output));
+
+        v
+    }
+}
+
+// Relation_shuffle = {(pk, input, output; shuffle) | output = shuffle.apply(pk, input)}
+// The public parameters are the public key pk and the input list.
+impl<C> HardRelation<Statement<C>, Witness<C>> for Witness<C>
+where
+    C: CurveArithmetic,
+{
+    type PP = (C::ProjectivePoint, Vec<CypherText<C>>);
+
+    fn R(_pp: &Self::PP, w: &Witness<C>, x: &Statement<C>) -> bool {
+        w.apply(&x.pk, &x.input)
+            .is_some_and(|output| output == x.output)
+    }
+    // From a Witness w, compute a Statement s such that R(w, s) == true. The shuffle must be a
+    // permutation of the indices of the input.
+    fn statement(pp: &Self::PP, w: &Witness<C>) -> Statement<C> {
+        let (pk, input) = pp;
+        let output = w
+            .apply(pk, input)
+            .expect("the shuffle is a permutation of the indices of the input");
+
+        Statement::new(*pk, input.clone(), output)
+    }
+
+    fn gen(pp: &Self::PP) -> (Witness<C>, Statement<C>) {
+        let w = Shuffle::random(pp.1.len());
+        let x = Self::statement(pp, &w);
+
+        (w, x)
+    }
+}
+
+// the intermediate lists of all the rounds and, for each round, the opened shuffle
+pub struct Proof<C>
+where
+    C: CurveArithmetic,
+{
+    intermediate: Vec<Vec<CypherText<C>>>,
+    openings: Vec<Shuffle<C>>,
+}
+
+impl<C> Proof<C>
+where
+    C: CurveArithmetic,
+{
+    pub fn to_byte_vector(&self) -> Vec<u8> {
+        let mut v: Vec<u8> = Vec::new();
+        for (list, opening) in self.intermediate.iter().zip(&self.openings) {
+            v.append(&mut cts_to_byte_vector::<C>(list));
+            v.append(&mut opening.to_byte_vector());
+        }
+
+        v
+    }
+}
+
+fn cts_to_byte_vector<C: CurveArithmetic>(cts: &[CypherText<C>]) -> Vec<u8> {
+    let mut v: Vec<u8> = Vec::new();
+    for (a, b) in cts {
+        v.append(&mut point_to_byte_vector::<C>(a));
+        v.append(&mut point_to_byte_vector::<C>(b));
+    }
+
+    v
+}
+
+// the ROUNDS challenge bits, from the hash of the statement and all the intermediate lists
+fn compute_challenge<C, H>(x: &Statement<C>, intermediate: &[Vec<CypherText<C>>]) -> Vec<bool>
+where
+    C: CurveArithmetic,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+{
+    let mut hasher = H::new().chain_update(x.to_byte_vector());
+    for list in intermediate {
+        hasher.update(cts_to_byte_vector::<C>(list));
+    }
+    let digest = hasher.finalize();
+
+    (0..ROUNDS)
+        .map(|k| (digest[k / 8] >> (k % 8)) & 1 == 1)
+        .collect()
+}
+
+impl<C, H> NIZK for ShuffleProof<C, H>
+where
+    C: CurveArithmetic,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+{
+    type CRS = ();
+    type Statement = Statement<C>;
+    type Witness = Witness<C>;
+    type Proof = Proof<C>;
+
+    fn crs_gen() -> Self::CRS {}
+
+    fn prove(_crs: &Self::CRS, x: &Self::Statement, w: &Self::Witness) -> Self::Proof {
+        let n = x.input.len();
+        let shuffles: Vec<Shuffle<C>> = (0..ROUNDS).map(|_| Shuffle::random(n)).collect();
+        let intermediate: Vec<Vec<CypherText<C>>> = shuffles
+            .iter()
+            .map(|s| s.apply(&x.pk, &x.input).unwrap())
+            .collect();
+        let challenge = compute_challenge::<C, H>(x, &intermediate);
+        let openings = shuffles
+            .into_iter()
+            .zip(challenge)
+            .map(|(s, bit)| if bit { s.then(w) } else { s })
+            .collect();
+
+        Proof {
+            intermediate,
+            openings,
+        }
+    }
+
+    fn verify(_crs: &Self::CRS, x: &Self::Statement, p: &Self::Proof) -> bool {
+        if x.input.len() != x.output.len()
+            || p.intermediate.len() != ROUNDS
+            || p.openings.len() != ROUNDS
+        {
+            return false;
+        }
+        let challenge = compute_challenge::<C, H>(x, &p.intermediate);
+
+        p.intermediate
+            .iter()
+            .zip(&p.openings)
+            .zip(challenge)
+            .all(|((list, opening), bit)| {
+                let (from, to) = if bit {
+                    (list, &x.output)
+                } else {
+                    (&x.input, list)
+                };
+                opening
+                    .apply(&x.pk, from)
+                    .is_some_and(|result| result == *to)
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k256::Secp256k1;
+    use sha2::Sha256;
+
+    type C = Secp256k1;
+    type Point = <C as CurveArithmetic>::ProjectivePoint;
+    type Scalar = <C as CurveArithmetic>::Scalar;
+    type Nizk = ShuffleProof<C, Sha256>;
+
+    fn input(pk: &Point, n: u64) -> (Vec<Point>, Vec<CypherText<C>>) {
+        let msgs: Vec<Point> = (1..=n)
+            .map(|m| Point::GENERATOR * Scalar::from(m))
+            .collect();
+        let cts = msgs.iter().map(|m| ElGamal::<C>::enc(pk, m).0).collect();
+
+        (msgs, cts)
+    }
+
+    #[test]
+    fn shuffle_proof() {
+        let (sk, pk) = ElGamal::<C>::gen();
+        let (msgs, input) = input(&pk, 5);
+        let shuffle = Shuffle::<C>::random(5);
+        let output = shuffle.apply(&pk, &input).unwrap();
+
+        // the output decrypts to the permuted messages
+        for (i, ct) in output.iter().enumerate() {
+            assert!(ElGamal::<C>::dec(&sk, ct).unwrap() == msgs[shuffle.permutation[i]]);
+        }
+
+        let statement = Statement::<C>::new(pk, input.clone(), output);
+        assert!(Witness::<C>::R(&(pk, input), &shuffle, &statement));
+        let proof = Nizk::prove(&(), &statement, &shuffle);
+        assert!(Nizk::verify(&(), &statement, &proof));
+        assert_eq!(proof.to_byte_vector().len(), ROUNDS * 5 * (2 * 33 + 4 + 32));
+    }
+
+    #[test]
+    fn shuffle_proof_of_a_wrong_shuffle() {
+        let (_, pk) = ElGamal::<C>::gen();
+        let (_, input) = input(&pk, 4);
+        let shuffle = Shuffle::<C>::random(4);
+
+        // the first output is replaced by a re-encryption of another input, so the output is not
+        // a permutation of the input
+        let mut output = shuffle.apply(&pk, &input).unwrap();
+        output[0] = ElGamal::<C>::rerandomize(&pk, &output[1]).0;
+        let statement = Statement::<C>::new(pk, input.clone(), output);
+        assert!(!Witness::<C>::R(&(pk, input.clone()), &shuffle, &statement));
+        let proof = Nizk::prove(&(), &statement, &shuffle);
+        assert!(!Nizk::verify(&(), &statement, &proof));
+
+        // the output changes one of the messages
+        let mut output = shuffle.apply(&pk, &input).unwrap();
+        output[2].1 += Point::GENERATOR;
+        let statement = Statement::<C>::new(pk, input.clone(), output);
+        let proof = Nizk::prove(&(), &statement, &shuffle);
+        assert!(!Nizk::verify(&(), &statement, &proof));
+
+        // a valid proof does not verify for another statement
+        let output = shuffle.apply(&pk, &input).unwrap();
+        let statement = Statement::<C>::new(pk, input.clone(), output);
+        let proof = Nizk::prove(&(), &statement, &shuffle);
+        let output = Shuffle::<C>::random(4).apply(&pk, &input).unwrap();
+        let other_statement = Statement::<C>::new(pk, input, output);
+        assert!(!Nizk::verify(&(), &other_statement, &proof));
+    }
+
+    #[test]
+    fn shuffle_proof_gen() {
+        let (_, pk) = ElGamal::<C>::gen();
+        let (_, input) = input(&pk, 3);
+        let pp = (pk, input);
+
+        let (shuffle, statement) = Witness::<C>::gen(&pp);
+        assert!(Witness::<C>::R(&pp, &shuffle, &statement));
+        let proof = Nizk::prove(&(), &statement, &shuffle);
+        assert!(Nizk::verify(&(), &statement, &proof));
+    }
+
+    #[test]
+    fn shuffle_is_permutation() {
+        assert!(Shuffle::<C>::random(6).is_permutation(6));
+        assert!(!Shuffle::<C>::random(6).is_permutation(5));
+        let r = vec![Scalar::ONE; 3];
+        assert!(!Shuffle::<C>::new(vec![0, 1, 1], r.clone()).is_permutation(3));
+        assert!(!Shuffle::<C>::new(vec![0, 1, 3], r.clone()).is_permutation(3));
+        assert!(Shuffle::<C>::new(vec![2, 0, 1], r).is_permutation(3));
+    }
+}
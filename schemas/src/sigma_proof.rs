@@ -1,7 +1,10 @@
-use crate::nizk::NIZK;
-use crate::utils::proj;
+use crate::nizk::{EncodableProof, ProofEncoding, NIZK};
+use crate::sigma_protocol::{SigmaProtocol, SpecialSoundness};
+use crate::utils::{point_to_byte_vector, proj, scalar_to_byte_vector, ByteReader};
 use digest::Digest;
+use elliptic_curve::ff::Field;
 use elliptic_curve::ops::Reduce;
+use elliptic_curve::point::DecompressPoint;
 use elliptic_curve::scalar::NonZeroScalar;
 use elliptic_curve::{CurveArithmetic, Group};
 use rand_core::OsRng;
@@ -16,6 +19,31 @@ where
     _hash_marker: PhantomData<H>,
 }
 
+impl<C, H> DLogSigmaProof<C, H>
+where
+    C: CurveArithmetic,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+{
+    fn compute_challenge(
+        g: &C::ProjectivePoint,
+        x: &C::ProjectivePoint,
+        a: &C::ProjectivePoint,
+    ) -> C::Scalar {
+        let proj_a = proj::<C>(a);
+        let proj_x = proj::<C>(x);
+        let proj_g = proj::<C>(g);
+
+        let hasher = H::new();
+        <C::Scalar as Reduce<C::Uint>>::reduce_bytes(
+            &hasher
+                .chain_update(proj_g)
+                .chain_update(proj_x)
+                .chain_update(proj_a)
+                .finalize(),
+        )
+    }
+}
+
 impl<C, H> NIZK for DLogSigmaProof<C, H>
 where
     C: CurveArithmetic,
@@ -31,43 +59,154 @@ where
     }
 
     fn prove(crs: &Self::CRS, x: &Self::Statement, w: &Self::Witness) -> Self::Proof {
+        let (a, u) = Self::commit(crs, x, w);
+
+        let c = Self::compute_challenge(crs, x, &a);
+
+        let r = Self::respond(crs, x, w, &u, &c);
+
+        (a, r)
+    }
+
+    fn verify(crs: &Self::CRS, x: &Self::Statement, p: &Self::Proof) -> bool {
+        let (a, r) = *p;
+
+        let c = Self::compute_challenge(crs, x, &a);
+
+        Self::check(crs, x, &a, &c, &r)
+    }
+}
+
+impl<C, H> SigmaProtocol for DLogSigmaProof<C, H>
+where
+    C: CurveArithmetic,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+{
+    type Commitment = C::ProjectivePoint;
+    type State = C::Scalar;
+    type Challenge = C::Scalar;
+    type Response = C::Scalar;
+
+    fn commit(
+        crs: &Self::CRS,
+        _x: &Self::Statement,
+        _w: &Self::Witness,
+    ) -> (Self::Commitment, Self::State) {
         let u = Self::Witness::random(&mut OsRng);
         let a = (*crs) * u.as_ref();
 
-        let proj_a = proj::<C>(&a);
-        let proj_x = proj::<C>(x);
-        let proj_g = proj::<C>(crs);
+        (a, *u.as_ref())
+    }
 
-        let hasher = H::new();
-        let c = <C::Scalar as Reduce<C::Uint>>::reduce_bytes(
-            &hasher
-                .chain_update(proj_g)
-                .chain_update(proj_x)
-                .chain_update(proj_a)
-                .finalize(),
-        );
+    fn respond(
+        _crs: &Self::CRS,
+        _x: &Self::Statement,
+        w: &Self::Witness,
+        st: &Self::State,
+        c: &Self::Challenge,
+    ) -> Self::Response {
+        *st + *c * w.as_ref()
+    }
 
-        let r = (*u.as_ref()) + c * w.as_ref();
+    fn check(
+        crs: &Self::CRS,
+        x: &Self::Statement,
+        a: &Self::Commitment,
+        c: &Self::Challenge,
+        r: &Self::Response,
+    ) -> bool {
+        (*crs) * r == *a + (*x) * c
+    }
+
+    fn simulate(
+        crs: &Self::CRS,
+        x: &Self::Statement,
+        c: &Self::Challenge,
+    ) -> (Self::Commitment, Self::Response) {
+        let r = C::Scalar::random(&mut OsRng);
+        let a = (*crs) * r - (*x) * c;
 
         (a, r)
     }
+}
 
-    fn verify(crs: &Self::CRS, x: &Self::Statement, p: &Self::Proof) -> bool {
+impl<C, H> SpecialSoundness for DLogSigmaProof<C, H>
+where
+    C: CurveArithmetic,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+{
+    // w = (r1 - r2) / (c1 - c2)
+    fn extract(
+        crs: &Self::CRS,
+        x: &Self::Statement,
+        a: &Self::Commitment,
+        c1: &Self::Challenge,
+        r1: &Self::Response,
+        c2: &Self::Challenge,
+        r2: &Self::Response,
+    ) -> Option<Self::Witness> {
+        if !Self::check(crs, x, a, c1, r1) || !Self::check(crs, x, a, c2, r2) {
+            return None;
+        }
+        let inv: Option<C::Scalar> = (*c1 - *c2).invert().into();
+        let w = (*r1 - *r2) * inv?;
+
+        NonZeroScalar::new(w).into()
+    }
+}
+
+impl<C, H> EncodableProof for DLogSigmaProof<C, H>
+where
+    C: CurveArithmetic,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+    C::AffinePoint: DecompressPoint<C>,
+{
+    fn encode_proof(
+        crs: &Self::CRS,
+        x: &Self::Statement,
+        p: &Self::Proof,
+        encoding: ProofEncoding,
+    ) -> Vec<u8> {
         let (a, r) = *p;
+        let mut v: Vec<u8> = Vec::new();
+        match encoding {
+            ProofEncoding::Commitments => {
+                v.append(&mut point_to_byte_vector::<C>(&a));
+            }
+            ProofEncoding::ChallengeResponse => {
+                let c = Self::compute_challenge(crs, x, &a);
+                v.append(&mut scalar_to_byte_vector::<C>(&c));
+            }
+        }
+        v.append(&mut scalar_to_byte_vector::<C>(&r));
 
-        let proj_a = proj::<C>(&a);
-        let proj_x = proj::<C>(x);
-        let proj_g = proj::<C>(crs);
+        v
+    }
 
-        let hasher = H::new();
-        let c = <C::Scalar as Reduce<C::Uint>>::reduce_bytes(
-            &hasher
-                .chain_update(proj_g)
-                .chain_update(proj_x)
-                .chain_update(proj_a)
-                .finalize(),
-        );
+    fn decode_proof(
+        crs: &Self::CRS,
+        x: &Self::Statement,
+        bytes: &[u8],
+        encoding: ProofEncoding,
+    ) -> Option<Self::Proof> {
+        let mut reader = ByteReader::new(bytes);
+        let p = match encoding {
+            ProofEncoding::Commitments => {
+                let a = reader.read_point::<C>()?;
+                let r = reader.read_scalar::<C>()?;
+                (a, r)
+            }
+            ProofEncoding::ChallengeResponse => {
+                let c = reader.read_scalar::<C>()?;
+                let r = reader.read_scalar::<C>()?;
+                let a = (*crs) * r - (*x) * c;
+                if Self::compute_challenge(crs, x, &a) != c {
+                    return None;
+                }
+                (a, r)
+            }
+        };
 
-        (Self::CRS::generator() * r) == a + ((*x) * c)
+        reader.is_empty().then_some(p)
     }
 }
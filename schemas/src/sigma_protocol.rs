@@ -0,0 +1,567 @@
+use crate::nizk::NIZK;
+
+// The interactive sigma protocol behind a NIZK obtained with the Fiat-Shamir transform:
+// - commit and respond are the two moves of the prover. The state keeps the randomness used in
+//   the commitment so it can be used in the response.
+// - check verifies a transcript (a, c, r) for a challenge c chosen by the verifier.
+// - simulate is the honest-verifier zero-knowledge simulator: given only the statement and a
+//   challenge c, it outputs (a, r) such that (a, c, r) is distributed as a real transcript.
+//   Simulators are needed to compose sigma protocols with OR (see PoRSchnorrSignature).
+pub trait SigmaProtocol: NIZK {
+    type Commitment;
+    type State;
+    type Challenge;
+    type Response;
+
+    fn commit(
+        crs: &Self::CRS,
+        x: &Self::Statement,
+        w: &Self::Witness,
+    ) -> (Self::Commitment, Self::State);
+    fn respond(
+        crs: &Self::CRS,
+        x: &Self::Statement,
+        w: &Self::Witness,
+        st: &Self::State,
+        c: &Self::Challenge,
+    ) -> Self::Response;
+    fn check(
+        crs: &Self::CRS,
+        x: &Self::Statement,
+        a: &Self::Commitment,
+        c: &Self::Challenge,
+        r: &Self::Response,
+    ) -> bool;
+    fn simulate(
+        crs: &Self::CRS,
+        x: &Self::Statement,
+        c: &Self::Challenge,
+    ) -> (Self::Commitment, Self::Response);
+}
+
+// Special soundness: from two accepting transcripts (a, c1, r1) and (a, c2, r2) with the same
+// commitment and different challenges it is possible to compute a witness of the statement. This
+// is the rewinding extractor used in the soundness proofs, and it lets us check that our sigma
+// protocols are sound. extract returns None if the transcripts are not accepting or c1 == c2.
+pub trait SpecialSoundness: SigmaProtocol {
+    fn extract(
+        crs: &Self::CRS,
+        x: &Self::Statement,
+        a: &Self::Commitment,
+        c1: &Self::Challenge,
+        r1: &Self::Response,
+        c2: &Self::Challenge,
+        r2: &Self::Response,
+    ) -> Option<Self::Witness>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bls_signatures::BLSSignatureScheme;
+    use crate::cross_group_dleq;
+    use crate::dleq::{self, DLEQProof};
+    use crate::ecdsa_signatures::ECDSA;
+    use crate::elgamal::ElGamal;
+    use crate::hard_relation::HardRelation;
+    use crate::pedersen_commitment::PedersenGens;
+    use crate::pok_bls_signature::{self, PoKBLSSignature};
+    use crate::pok_ecdsa_signature::{self, PoKECDSASignature};
+    use crate::pok_schnorr_signature::{self, PoKSchnorrSignature};
+    use crate::por_schnorr_signature::{self, PoRSchnorrSignature};
+    use crate::proof_f::{self, SigmaProofF};
+    use crate::proof_phi::{self, SigmaProofPhi};
+    use crate::proof_star::{self, SigmaProofStar};
+    use crate::public_key_encryption_scheme::PublicKeyEncryptionScheme;
+    use crate::schnorr_signatures::SchnorrSignatureScheme;
+    use crate::sigma_proof::DLogSigmaProof;
+    use crate::signature_scheme::SignatureScheme;
+    use crate::utils::{point_to_byte_vector, scalar_to_byte_vector};
+    use elliptic_curve::ff::Field;
+    use elliptic_curve::scalar::NonZeroScalar;
+    use elliptic_curve::CurveArithmetic;
+    use k256::Secp256k1;
+    use p256::NistP256;
+    use rand_core::{OsRng, RngCore};
+    use sha2::Sha256;
+
+    type C = Secp256k1;
+    type Point = <C as CurveArithmetic>::ProjectivePoint;
+    type Scalar = <C as CurveArithmetic>::Scalar;
+    type Sig = SchnorrSignatureScheme<C, Sha256>;
+
+    type DLog = DLogSigmaProof<C, Sha256>;
+    type Dleq = DLEQProof<C, Sha256>;
+    type ProofF = SigmaProofF<C, Sha256>;
+    type PoK = PoKSchnorrSignature<C, Sha256>;
+    type PoR = PoRSchnorrSignature<C, Sha256>;
+    type PoKBLS = PoKBLSSignature<C, Sha256>;
+    type PoKECDSA = PoKECDSASignature<C, Sha256>;
+    type ProofPhi = SigmaProofPhi<C, NistP256, Sha256, Sha256>;
+    type ProofStar = SigmaProofStar<C, Sha256>;
+
+    const SAMPLES: usize = 256;
+    // every transcript of SigmaProofPhi has a ring signature for each bit of the secret
+    const PHI_SAMPLES: usize = 32;
+    // 99.9999% quantile of the chi-square distribution with 15 degrees of freedom is ~51
+    const CHI_SQUARE_BOUND: f64 = 60.0;
+
+    fn p(point: &Point) -> Vec<u8> {
+        point_to_byte_vector::<C>(point)
+    }
+
+    fn s(scalar: &Scalar) -> Vec<u8> {
+        scalar_to_byte_vector::<C>(scalar)
+    }
+
+    fn p2(point: &<NistP256 as CurveArithmetic>::ProjectivePoint) -> Vec<u8> {
+        point_to_byte_vector::<NistP256>(point)
+    }
+
+    fn s2(scalar: &<NistP256 as CurveArithmetic>::Scalar) -> Vec<u8> {
+        scalar_to_byte_vector::<NistP256>(scalar)
+    }
+
+    // two-sample chi-square statistic of two histograms with the same number of samples
+    fn chi_square(real: &[u32; 16], simulated: &[u32; 16]) -> f64 {
+        real.iter()
+            .zip(simulated.iter())
+            .filter(|(r, s)| **r + **s > 0)
+            .map(|(r, s)| {
+                let d = *r as f64 - *s as f64;
+                d * d / (*r + *s) as f64
+            })
+            .sum()
+    }
+
+    // Features of a transcript whose distribution we compare: the high nibble of the second byte
+    // of every component (the first one is biased for points) and of the difference of the second
+    // bytes of every pair of components.
+    fn features(components: &[Vec<u8>]) -> Vec<usize> {
+        let bytes: Vec<u8> = components.iter().map(|v| v[1]).collect();
+        let mut features: Vec<usize> = bytes.iter().map(|b| (*b >> 4) as usize).collect();
+        for i in 0..bytes.len() {
+            for j in (i + 1)..bytes.len() {
+                features.push((bytes[i].wrapping_sub(bytes[j]) >> 4) as usize);
+            }
+        }
+
+        features
+    }
+
+    // Samples real and simulated transcripts for the challenge c, checks that all of them are
+    // accepting and that the distributions of their features cannot be told apart.
+    fn compare_transcripts<S, F>(
+        crs: &S::CRS,
+        x: &S::Statement,
+        w: &S::Witness,
+        c: &S::Challenge,
+        samples: usize,
+        components: F,
+    ) where
+        S: SigmaProtocol,
+        F: Fn(&S::Commitment, &S::Response) -> Vec<Vec<u8>>,
+    {
+        let mut real: Vec<[u32; 16]> = Vec::new();
+        let mut simulated: Vec<[u32; 16]> = Vec::new();
+        for _ in 0..samples {
+            let (a, st) = S::commit(crs, x, w);
+            let r = S::respond(crs, x, w, &st, c);
+            assert!(S::check(crs, x, &a, c, &r));
+            let real_features = features(&components(&a, &r));
+
+            let (a, r) = S::simulate(crs, x, c);
+            assert!(S::check(crs, x, &a, c, &r));
+            let simulated_features = features(&components(&a, &r));
+
+            real.resize(real_features.len(), [0; 16]);
+            simulated.resize(simulated_features.len(), [0; 16]);
+            for (i, f) in real_features.iter().enumerate() {
+                real[i][*f] += 1;
+            }
+            for (i, f) in simulated_features.iter().enumerate() {
+                simulated[i][*f] += 1;
+            }
+        }
+
+        for (i, (r, s)) in real.iter().zip(simulated.iter()).enumerate() {
+            let statistic = chi_square(r, s);
+            assert!(
+                statistic < CHI_SQUARE_BOUND,
+                "feature {} is distinguishable: chi-square = {}",
+                i,
+                statistic
+            );
+        }
+    }
+
+    // Runs the prover twice with the same commitment (as a rewinding extractor would) and checks
+    // that the extracted witness is in the relation.
+    fn extract_witness<S: SpecialSoundness>(
+        crs: &S::CRS,
+        x: &S::Statement,
+        w: &S::Witness,
+        c1: &S::Challenge,
+        c2: &S::Challenge,
+    ) -> S::Witness {
+        let (a, st) = S::commit(crs, x, w);
+        let r1 = S::respond(crs, x, w, &st, c1);
+        let r2 = S::respond(crs, x, w, &st, c2);
+
+        assert!(S::extract(crs, x, &a, c1, &r1, c1, &r1).is_none());
+        assert!(S::extract(crs, x, &a, c1, &r1, c2, &r1).is_none());
+
+        S::extract(crs, x, &a, c1, &r1, c2, &r2).unwrap()
+    }
+
+    fn dlog_instance() -> (Point, Point, NonZeroScalar<C>) {
+        let crs = DLog::crs_gen();
+        let (w, x) = NonZeroScalar::<C>::gen(&crs);
+
+        (crs, x, w)
+    }
+
+    fn dleq_instance() -> (dleq::Statement<C>, dleq::Witness<C>) {
+        let g = Point::GENERATOR;
+        let h = PedersenGens::<C>::nums().h;
+        let (w, x) = dleq::Witness::<C>::gen(&(g, h));
+
+        (x, w)
+    }
+
+    fn proof_f_instance() -> (Point, proof_f::Statement<C>, proof_f::Witness<C>) {
+        let g = Point::GENERATOR;
+        let h = PedersenGens::<C>::nums().h;
+        let secret = Scalar::random(&mut OsRng);
+        let e = Scalar::random(&mut OsRng);
+        let x = proof_f::Statement::<C>::new(g, h, g * secret, g * e + h * secret);
+        let w = proof_f::Witness::<C>::new(secret, e);
+
+        (ProofF::crs_gen(), x, w)
+    }
+
+    fn pok_instance() -> (
+        pok_schnorr_signature::Statement<C, Sha256>,
+        pok_schnorr_signature::Witness<C>,
+    ) {
+        let g = Point::GENERATOR;
+        let (sk, pk) = Sig::gen();
+        let (w, x) = NonZeroScalar::<C>::gen(&g);
+        let msg = "message";
+        let signature = Sig::sign(&sk, msg);
+        let ct = signature.sig + *w;
+        let statement = pok_schnorr_signature::Statement::<C, Sha256>::new(
+            g * signature.sig,
+            x,
+            pk,
+            signature.proof,
+            ct,
+            msg.into(),
+        );
+        let witness = pok_schnorr_signature::Witness::<C>::new(signature.sig, *w);
+
+        (statement, witness)
+    }
+
+    fn pok_ecdsa_instance() -> (
+        pok_ecdsa_signature::Statement<C, Sha256>,
+        pok_ecdsa_signature::Witness<C>,
+    ) {
+        let g = Point::GENERATOR;
+        let (sk, pk) = ECDSA::<C, Sha256>::gen();
+        let (w, x) = NonZeroScalar::<C>::gen(&g);
+        let msg = "message";
+        let signature = ECDSA::<C, Sha256>::sign(&sk, msg);
+        let nonce_point = ECDSA::<C, Sha256>::nonce_point(&pk, msg, &signature);
+        let ct = *signature.proof + *w;
+        let statement =
+            pok_ecdsa_signature::Statement::<C, Sha256>::new(x, pk, nonce_point, ct, msg.into());
+        let witness = pok_ecdsa_signature::Witness::<C>::new(*signature.proof, *w);
+
+        (statement, witness)
+    }
+
+    fn por_instance() -> (
+        por_schnorr_signature::Crs<C>,
+        por_schnorr_signature::Statement<C, Sha256>,
+        por_schnorr_signature::Witness<C>,
+    ) {
+        let g = Point::GENERATOR;
+        let (sk, pk) = Sig::gen();
+        let (w, x) = NonZeroScalar::<C>::gen(&g);
+        let msg = "message";
+        let signature = Sig::sign(&sk, msg);
+        let statement = por_schnorr_signature::Statement::<C, Sha256>::new(
+            x,
+            pk,
+            g * signature.sig,
+            signature.proof,
+            msg.into(),
+        );
+        let witness = por_schnorr_signature::Witness::<C>::new(signature.sig, *w);
+
+        (PoR::crs_gen(), statement, witness)
+    }
+
+    fn pok_bls_instance() -> (
+        pok_bls_signature::Crs<C>,
+        pok_bls_signature::Statement<C>,
+        pok_bls_signature::Witness<C>,
+    ) {
+        let (sk, pk) = BLSSignatureScheme::gen();
+        let (w, x) = pok_bls_signature::gen_witness::<C>(&Point::GENERATOR);
+        let msg = "message";
+        let signature = BLSSignatureScheme::sign(&sk, msg);
+        let (ct, y) = pok_bls_signature::encrypt::<C>(&signature, &w).unwrap();
+        let statement = pok_bls_signature::Statement::<C>::new(x, y, ct, pk, msg.into());
+        let witness = pok_bls_signature::Witness::<C>::new(signature.sig, *w);
+
+        (PoKBLS::crs_gen(), statement, witness)
+    }
+
+    fn proof_phi_instance() -> (
+        cross_group_dleq::Crs<C, NistP256>,
+        proof_phi::Statement<C, NistP256>,
+        proof_phi::Witness<C>,
+    ) {
+        let g = Point::GENERATOR;
+        let n = cross_group_dleq::bit_length::<C, NistP256>();
+        let bits: Vec<bool> = (0..n).map(|_| OsRng.next_u32() & 1 == 1).collect();
+        let secret = cross_group_dleq::from_bits::<C>(&bits);
+        let secret_2 = cross_group_dleq::from_bits::<NistP256>(&bits);
+        let (w, x) = NonZeroScalar::<C>::gen(&g);
+        let y = Scalar::random(&mut OsRng);
+        let point = g * secret;
+        let point_2 = <NistP256 as CurveArithmetic>::ProjectivePoint::GENERATOR * secret_2;
+        let statement =
+            proof_phi::Statement::<C, NistP256>::new(point_2, x, (g * y, x * y + point));
+        let witness = proof_phi::Witness::<C>::new(secret, *w, y, point);
+
+        (ProofPhi::crs_gen(), statement, witness)
+    }
+
+    fn proof_star_instance() -> (Point, proof_star::Statement<C>, proof_star::Witness<C>) {
+        let g = Point::GENERATOR;
+        let h = PedersenGens::<C>::nums().h;
+        let secret = Scalar::random(&mut OsRng);
+        let e = Scalar::random(&mut OsRng);
+        let (w, x) = NonZeroScalar::<C>::gen(&g);
+        let (ct, y) = ElGamal::<C>::enc(&x, &(g * secret));
+        let statement = proof_star::Statement::<C>::new(h, g * e + h * secret, ct, x, x);
+        let witness = proof_star::Witness::<C>::new(secret, e, y, *w);
+
+        (ProofStar::crs_gen(), statement, witness)
+    }
+
+    #[test]
+    fn dlog_proof_simulator() {
+        let (crs, x, w) = dlog_instance();
+        let c = Scalar::random(&mut OsRng);
+
+        compare_transcripts::<DLog, _>(&crs, &x, &w, &c, SAMPLES, |a, r| vec![p(a), s(r)]);
+    }
+
+    #[test]
+    fn dleq_proof_simulator() {
+        let (x, w) = dleq_instance();
+        let c = Scalar::random(&mut OsRng);
+
+        compare_transcripts::<Dleq, _>(&(), &x, &w, &c, SAMPLES, |a, r| {
+            vec![p(&a.0), p(&a.1), s(r)]
+        });
+    }
+
+    #[test]
+    fn proof_f_simulator() {
+        let (crs, x, w) = proof_f_instance();
+        let c = Scalar::random(&mut OsRng);
+
+        compare_transcripts::<ProofF, _>(&crs, &x, &w, &c, SAMPLES, |a, r| {
+            vec![p(&a.0), p(&a.1), s(&r.0), s(&r.1)]
+        });
+    }
+
+    #[test]
+    fn pok_schnorr_signature_simulator() {
+        let (x, w) = pok_instance();
+        let c = Scalar::random(&mut OsRng);
+
+        compare_transcripts::<PoK, _>(&(), &x, &w, &c, SAMPLES, |a, r| {
+            vec![p(&a.0), p(&a.1), s(&r.0), s(&r.1)]
+        });
+    }
+
+    #[test]
+    fn pok_ecdsa_signature_simulator() {
+        let (x, w) = pok_ecdsa_instance();
+        let c = Scalar::random(&mut OsRng);
+
+        compare_transcripts::<PoKECDSA, _>(&(), &x, &w, &c, SAMPLES, |a, r| {
+            vec![p(&a.0), p(&a.1), s(r)]
+        });
+    }
+
+    #[test]
+    fn por_schnorr_signature_simulator() {
+        let (crs, x, w) = por_instance();
+        let c = Scalar::random(&mut OsRng);
+
+        compare_transcripts::<PoR, _>(&crs, &x, &w, &c, SAMPLES, |a, r| {
+            let ((r_g, r_sig, r_h), (c1, c2)) = r;
+            vec![
+                p(&a.0),
+                p(&a.1),
+                p(&a.2),
+                s(r_g),
+                s(r_sig),
+                s(r_h),
+                s(c1),
+                s(c2),
+            ]
+        });
+    }
+
+    // the bit commitments are only compared for the first and the last bits
+    #[test]
+    fn proof_phi_simulator() {
+        let (crs, x, w) = proof_phi_instance();
+        let c = cross_group_dleq::random_challenge();
+
+        compare_transcripts::<ProofPhi, _>(&crs, &x, &w, &c, PHI_SAMPLES, |a, r| {
+            let (a_dleq, (a1, a2, a3)) = a;
+            let (r_dleq, (r_y, r_w, r_t)) = r;
+            let mut components = vec![p(a1), p(a2), p(a3), s(r_y), s(r_w), s(r_t)];
+            for i in [0, a_dleq.len() - 1] {
+                let (c1, c2, ring) = &a_dleq[i];
+                let (e0, z) = &r_dleq[i];
+                components.push(p(c1));
+                components.push(p2(c2));
+                for ((r1, r2), (z1, z2)) in ring.iter().zip(z.iter()) {
+                    components.push(p(r1));
+                    components.push(p2(r2));
+                    components.push(s(z1));
+                    components.push(s2(z2));
+                }
+                components.push(e0.to_be_bytes().to_vec());
+            }
+
+            components
+        });
+    }
+
+    #[test]
+    fn proof_star_simulator() {
+        let (crs, x, w) = proof_star_instance();
+        let c = Scalar::random(&mut OsRng);
+
+        compare_transcripts::<ProofStar, _>(&crs, &x, &w, &c, SAMPLES, |a, r| {
+            vec![
+                p(&a.0),
+                p(&a.1),
+                p(&a.2),
+                p(&a.3),
+                s(&r.0),
+                s(&r.1),
+                s(&r.2),
+                s(&r.3),
+            ]
+        });
+    }
+
+    #[test]
+    fn dlog_proof_extractor() {
+        let (crs, x, w) = dlog_instance();
+        let c1 = Scalar::random(&mut OsRng);
+        let c2 = Scalar::random(&mut OsRng);
+        let extracted = extract_witness::<DLog>(&crs, &x, &w, &c1, &c2);
+
+        assert!(NonZeroScalar::<C>::R(&crs, &extracted, &x));
+        assert_eq!(*extracted, *w);
+    }
+
+    #[test]
+    fn dleq_proof_extractor() {
+        let (x, w) = dleq_instance();
+        let c1 = Scalar::random(&mut OsRng);
+        let c2 = Scalar::random(&mut OsRng);
+        let extracted = extract_witness::<Dleq>(&(), &x, &w, &c1, &c2);
+
+        let pp = (Point::GENERATOR, PedersenGens::<C>::nums().h);
+        assert!(dleq::Witness::<C>::R(&pp, &extracted, &x));
+        assert_eq!(extracted.w(), w.w());
+    }
+
+    #[test]
+    fn proof_f_extractor() {
+        let (crs, x, w) = proof_f_instance();
+        let c1 = Scalar::random(&mut OsRng);
+        let c2 = Scalar::random(&mut OsRng);
+        let extracted = extract_witness::<ProofF>(&crs, &x, &w, &c1, &c2);
+
+        assert!(proof_f::Witness::<C>::R(&crs, &extracted, &x));
+    }
+
+    #[test]
+    fn pok_schnorr_signature_extractor() {
+        let (x, w) = pok_instance();
+        let c1 = Scalar::random(&mut OsRng);
+        let c2 = Scalar::random(&mut OsRng);
+        let extracted = extract_witness::<PoK>(&(), &x, &w, &c1, &c2);
+
+        assert!(pok_schnorr_signature::Witness::<C>::R(
+            &Point::GENERATOR,
+            &extracted,
+            &x
+        ));
+    }
+
+    #[test]
+    fn pok_ecdsa_signature_extractor() {
+        let (x, w) = pok_ecdsa_instance();
+        let c1 = Scalar::random(&mut OsRng);
+        let c2 = Scalar::random(&mut OsRng);
+        let extracted = extract_witness::<PoKECDSA>(&(), &x, &w, &c1, &c2);
+
+        assert!(pok_ecdsa_signature::Witness::<C>::R(
+            &Point::GENERATOR,
+            &extracted,
+            &x
+        ));
+    }
+
+    #[test]
+    fn por_schnorr_signature_extractor() {
+        let (crs, x, w) = por_instance();
+        let c1 = Scalar::random(&mut OsRng);
+        let c2 = Scalar::random(&mut OsRng);
+        let extracted = extract_witness::<PoR>(&crs, &x, &w, &c1, &c2);
+
+        assert!(por_schnorr_signature::Witness::<C>::R(&crs, &extracted, &x));
+    }
+
+    #[test]
+    fn pok_bls_signature_extractor() {
+        let (crs, x, w) = pok_bls_instance();
+        let c1 = cross_group_dleq::random_challenge();
+        let c2 = cross_group_dleq::random_challenge();
+        let extracted = extract_witness::<PoKBLS>(&crs, &x, &w, &c1, &c2);
+
+        assert!(pok_bls_signature::Witness::<C>::R(&crs, &extracted, &x));
+
+        // a ciphertext of a signature on another message does not verify
+        let (_, other, _) = pok_bls_instance();
+        let p = PoKBLS::prove(&crs, &x, &w);
+        assert!(PoKBLS::verify(&crs, &x, &p));
+        assert!(!PoKBLS::verify(&crs, &other, &p));
+    }
+
+    #[test]
+    fn proof_phi_extractor() {
+        let (crs, x, w) = proof_phi_instance();
+        let c1 = cross_group_dleq::random_challenge();
+        let c2 = cross_group_dleq::random_challenge();
+        let extracted = extract_witness::<ProofPhi>(&crs, &x, &w, &c1, &c2);
+
+        assert!(proof_phi::Witness::<C>::R(&crs, &extracted, &x));
+    }
+}
@@ -4,5 +4,6 @@ pub trait SymmetricEncryptionScheme {
     type CypherText;
 
     fn enc(key: &Self::Key, msg: &Self::Message) -> Self::CypherText;
-    fn dec(sk: &Self::Key, ct: &Self::CypherText) -> Self::Message;
+    // None if ct is not a valid cyphertext under the key
+    fn dec(sk: &Self::Key, ct: &Self::CypherText) -> Option<Self::Message>;
 }
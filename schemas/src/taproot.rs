@@ -0,0 +1,1170 @@
+use crate::bip340::{
+    self, hash_to_scalar, lift_x, tagged_hash, x_only, AggregateKey, Signature, XOnly,
+};
+use crate::utils::ByteReader;
+use k256::{NonZeroScalar, ProjectivePoint};
+use sha2::{Digest, Sha256};
+use std::fmt;
+
+// Bitcoin transactions spending and creating Taproot (BIP-341) outputs, their signature hashes,
+// and an interpreter for the witnesses of Taproot inputs: key-path spends and the tapscripts
+// (BIP-342) that the selling protocol uses. Only SIGHASH_DEFAULT and SIGHASH_ALL are supported,
+// and annexes, unknown leaf versions and unknown public key types are rejected instead of being
+// left to future soft forks.
+
+pub const LEAF_VERSION_TAPSCRIPT: u8 = 0xc0;
+pub const SEQUENCE_FINAL: u32 = 0xffff_ffff;
+const SEQUENCE_LOCKTIME_DISABLE_FLAG: u32 = 1 << 31;
+const SEQUENCE_LOCKTIME_TYPE_FLAG: u32 = 1 << 22;
+const SEQUENCE_LOCKTIME_MASK: u32 = 0x0000_ffff;
+
+pub const OP_0: u8 = 0x00;
+pub const OP_PUSHDATA1: u8 = 0x4c;
+pub const OP_PUSHDATA2: u8 = 0x4d;
+pub const OP_PUSHDATA4: u8 = 0x4e;
+pub const OP_1NEGATE: u8 = 0x4f;
+pub const OP_1: u8 = 0x51;
+pub const OP_16: u8 = 0x60;
+pub const OP_VERIFY: u8 = 0x69;
+pub const OP_DROP: u8 = 0x75;
+pub const OP_DUP: u8 = 0x76;
+pub const OP_EQUAL: u8 = 0x87;
+pub const OP_EQUALVERIFY: u8 = 0x88;
+pub const OP_CHECKSIG: u8 = 0xac;
+pub const OP_CHECKSIGVERIFY: u8 = 0xad;
+pub const OP_CHECKSEQUENCEVERIFY: u8 = 0xb2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScriptError {
+    NotTaproot,
+    EmptyWitness,
+    AnnexNotSupported,
+    InvalidSignatureEncoding,
+    InvalidSignature,
+    InvalidControlBlock,
+    UnknownLeafVersion,
+    WitnessProgramMismatch,
+    UnsupportedOpcode(u8),
+    UnsupportedPublicKey,
+    BadPush,
+    StackUnderflow,
+    InvalidNumber,
+    NegativeLocktime,
+    UnsatisfiedLocktime,
+    VerifyFailed,
+    EvalFalse,
+    CleanStack,
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScriptError::NotTaproot => write!(f, "the spent output is not a taproot output"),
+            ScriptError::EmptyWitness => write!(f, "the witness is empty"),
+            ScriptError::AnnexNotSupported => write!(f, "annexes are not supported"),
+            ScriptError::InvalidSignatureEncoding => write!(f, "invalid signature encoding"),
+            ScriptError::InvalidSignature => write!(f, "invalid signature"),
+            ScriptError::InvalidControlBlock => write!(f, "invalid control block"),
+            ScriptError::UnknownLeafVersion => write!(f, "unknown leaf version"),
+            ScriptError::WitnessProgramMismatch => {
+                write!(f, "the script does not commit to the output key")
+            }
+            ScriptError::UnsupportedOpcode(op) => write!(f, "unsupported opcode 0x{:02x}", op),
+            ScriptError::UnsupportedPublicKey => write!(f, "unsupported public key type"),
+            ScriptError::BadPush => write!(f, "push past the end of the script"),
+            ScriptError::StackUnderflow => write!(f, "stack underflow"),
+            ScriptError::InvalidNumber => write!(f, "invalid script number"),
+            ScriptError::NegativeLocktime => write!(f, "negative locktime"),
+            ScriptError::UnsatisfiedLocktime => write!(f, "locktime requirement not satisfied"),
+            ScriptError::VerifyFailed => write!(f, "verify failed"),
+            ScriptError::EvalFalse => write!(f, "the script evaluated to false"),
+            ScriptError::CleanStack => write!(f, "the stack does not have exactly one element"),
+        }
+    }
+}
+
+impl std::error::Error for ScriptError {}
+
+pub type Txid = [u8; 32];
+
+fn write_compact_size(bytes: &mut Vec<u8>, n: usize) {
+    match n {
+        0..=0xfc => bytes.push(n as u8),
+        0xfd..=0xffff => {
+            bytes.push(0xfd);
+            bytes.extend((n as u16).to_le_bytes());
+        }
+        0x1_0000..=0xffff_ffff => {
+            bytes.push(0xfe);
+            bytes.extend((n as u32).to_le_bytes());
+        }
+        _ => {
+            bytes.push(0xff);
+            bytes.extend((n as u64).to_le_bytes());
+        }
+    }
+}
+
+// Only the shortest encoding of n is accepted, as in Bitcoin
+fn read_compact_size(reader: &mut ByteReader) -> Option<usize> {
+    let (n, min) = match reader.read_bytes(1)?[0] {
+        0xfd => (u16::from_le_bytes(reader.read_bytes(2)?.try_into().ok()?) as u64, 0xfd),
+        0xfe => (u32::from_le_bytes(reader.read_bytes(4)?.try_into().ok()?) as u64, 0x1_0000),
+        0xff => (u64::from_le_bytes(reader.read_bytes(8)?.try_into().ok()?), 0x1_0000_0000),
+        n => return Some(n as usize),
+    };
+
+    (n >= min).then_some(usize::try_from(n).ok()?)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutPoint {
+    pub txid: Txid,
+    pub vout: u32,
+}
+
+impl OutPoint {
+    pub fn new(txid: Txid, vout: u32) -> Self {
+        Self { txid, vout }
+    }
+
+    pub fn to_byte_vector(self) -> Vec<u8> {
+        let mut bytes = self.txid.to_vec();
+        bytes.extend(self.vout.to_le_bytes());
+
+        bytes
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TxIn {
+    pub previous_output: OutPoint,
+    pub sequence: u32,
+    pub witness: Vec<Vec<u8>>,
+}
+
+impl TxIn {
+    pub fn new(previous_output: OutPoint, sequence: u32) -> Self {
+        Self {
+            previous_output,
+            sequence,
+            witness: vec![],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TxOut {
+    pub value: u64,
+    pub script_pubkey: Vec<u8>,
+}
+
+impl TxOut {
+    pub fn new(value: u64, script_pubkey: Vec<u8>) -> Self {
+        Self {
+            value,
+            script_pubkey,
+        }
+    }
+
+    pub fn to_byte_vector(&self) -> Vec<u8> {
+        let mut bytes = self.value.to_le_bytes().to_vec();
+        write_compact_size(&mut bytes, self.script_pubkey.len());
+        bytes.extend(&self.script_pubkey);
+
+        bytes
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transaction {
+    pub version: i32,
+    pub lock_time: u32,
+    pub inputs: Vec<TxIn>,
+    pub outputs: Vec<TxOut>,
+}
+
+impl Transaction {
+    // A version 2 transaction, so that its inputs can have relative timelocks (BIP-68)
+    pub fn new(inputs: Vec<TxIn>, outputs: Vec<TxOut>) -> Self {
+        Self {
+            version: 2,
+            lock_time: 0,
+            inputs,
+            outputs,
+        }
+    }
+
+    fn serialize(&self, with_witness: bool) -> Vec<u8> {
+        let with_witness = with_witness && self.inputs.iter().any(|i| !i.witness.is_empty());
+        let mut bytes = self.version.to_le_bytes().to_vec();
+        if with_witness {
+            bytes.extend([0x00, 0x01]);
+        }
+        write_compact_size(&mut bytes, self.inputs.len());
+        for input in &self.inputs {
+            bytes.extend(input.previous_output.to_byte_vector());
+            // empty script_sig
+            write_compact_size(&mut bytes, 0);
+            bytes.extend(input.sequence.to_le_bytes());
+        }
+        write_compact_size(&mut bytes, self.outputs.len());
+        for output in &self.outputs {
+            bytes.extend(output.to_byte_vector());
+        }
+        if with_witness {
+            for input in &self.inputs {
+                write_compact_size(&mut bytes, input.witness.len());
+                for item in &input.witness {
+                    write_compact_size(&mut bytes, item.len());
+                    bytes.extend(item);
+                }
+            }
+        }
+        bytes.extend(self.lock_time.to_le_bytes());
+
+        bytes
+    }
+
+    // The serialization of BIP-144, with the witnesses if any input has one
+    pub fn to_byte_vector(&self) -> Vec<u8> {
+        self.serialize(true)
+    }
+
+    // Inverse of to_byte_vector, for transactions whose inputs have an empty script_sig
+    pub fn from_byte_vector(bytes: &[u8]) -> Option<Self> {
+        let mut reader = ByteReader::new(bytes);
+        let version = i32::from_le_bytes(reader.read_bytes(4)?.try_into().ok()?);
+        let mut n_inputs = read_compact_size(&mut reader)?;
+        // the marker and the flag of BIP-144
+        let with_witness = n_inputs == 0;
+        if with_witness {
+            if reader.read_bytes(1)? != [0x01] {
+                return None;
+            }
+            n_inputs = read_compact_size(&mut reader)?;
+        }
+        let mut inputs = vec![];
+        for _ in 0..n_inputs {
+            let txid = reader.read_bytes(32)?.try_into().ok()?;
+            let vout = u32::from_le_bytes(reader.read_bytes(4)?.try_into().ok()?);
+            if read_compact_size(&mut reader)? != 0 {
+                return None;
+            }
+            let sequence = u32::from_le_bytes(reader.read_bytes(4)?.try_into().ok()?);
+            inputs.push(TxIn::new(OutPoint::new(txid, vout), sequence));
+        }
+        let mut outputs = vec![];
+        for _ in 0..read_compact_size(&mut reader)? {
+            let value = u64::from_le_bytes(reader.read_bytes(8)?.try_into().ok()?);
+            let len = read_compact_size(&mut reader)?;
+            outputs.push(TxOut::new(value, reader.read_bytes(len)?.to_vec()));
+        }
+        if with_witness {
+            for input in &mut inputs {
+                for _ in 0..read_compact_size(&mut reader)? {
+                    let len = read_compact_size(&mut reader)?;
+                    input.witness.push(reader.read_bytes(len)?.to_vec());
+                }
+            }
+        }
+        let lock_time = u32::from_le_bytes(reader.read_bytes(4)?.try_into().ok()?);
+        let tx = Self {
+            version,
+            lock_time,
+            inputs,
+            outputs,
+        };
+
+        // a serialization with the marker but no witness is not canonical
+        (reader.is_empty() && with_witness == tx.inputs.iter().any(|i| !i.witness.is_empty()))
+            .then_some(tx)
+    }
+
+    // The double SHA-256 of the serialization without witnesses, in internal byte order
+    pub fn txid(&self) -> Txid {
+        Sha256::digest(Sha256::digest(self.serialize(false))).into()
+    }
+
+    // The txid as Bitcoin displays it, in reversed byte order
+    pub fn message(&self) -> String {
+        self.txid()
+            .iter()
+            .rev()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+
+    pub fn outpoint(&self, vout: u32) -> OutPoint {
+        OutPoint::new(self.txid(), vout)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SighashType {
+    Default = 0x00,
+    All = 0x01,
+}
+
+// The signature hash of BIP-341 of the input index, which spends prevouts[index]. leaf_hash is the
+// hash of the executed leaf for script-path spends and None for key-path spends.
+pub fn taproot_sighash(
+    tx: &Transaction,
+    index: usize,
+    prevouts: &[TxOut],
+    hash_type: SighashType,
+    leaf_hash: Option<&[u8; 32]>,
+) -> [u8; 32] {
+    assert_eq!(tx.inputs.len(), prevouts.len());
+
+    let mut sha_prevouts = Sha256::new();
+    let mut sha_sequences = Sha256::new();
+    for input in &tx.inputs {
+        sha_prevouts.update(input.previous_output.to_byte_vector());
+        sha_sequences.update(input.sequence.to_le_bytes());
+    }
+    let mut sha_amounts = Sha256::new();
+    let mut sha_script_pubkeys = Sha256::new();
+    for prevout in prevouts {
+        sha_amounts.update(prevout.value.to_le_bytes());
+        let mut script_pubkey = vec![];
+        write_compact_size(&mut script_pubkey, prevout.script_pubkey.len());
+        script_pubkey.extend(&prevout.script_pubkey);
+        sha_script_pubkeys.update(script_pubkey);
+    }
+    let mut sha_outputs = Sha256::new();
+    for output in &tx.outputs {
+        sha_outputs.update(output.to_byte_vector());
+    }
+
+    // epoch 0
+    let mut msg = vec![0x00, hash_type as u8];
+    msg.extend(tx.version.to_le_bytes());
+    msg.extend(tx.lock_time.to_le_bytes());
+    msg.extend(sha_prevouts.finalize());
+    msg.extend(sha_amounts.finalize());
+    msg.extend(sha_script_pubkeys.finalize());
+    msg.extend(sha_sequences.finalize());
+    msg.extend(sha_outputs.finalize());
+    // spend type: ext_flag * 2 + annex_present
+    msg.push(if leaf_hash.is_some() { 2 } else { 0 });
+    msg.extend((index as u32).to_le_bytes());
+    if let Some(leaf_hash) = leaf_hash {
+        msg.extend(leaf_hash);
+        // key version 0 and no OP_CODESEPARATOR
+        msg.push(0x00);
+        msg.extend(0xffff_ffffu32.to_le_bytes());
+    }
+
+    tagged_hash("TapSighash", &[&msg])
+}
+
+pub fn tap_leaf_hash(script: &[u8]) -> [u8; 32] {
+    let mut data = vec![LEAF_VERSION_TAPSCRIPT];
+    write_compact_size(&mut data, script.len());
+    data.extend(script);
+
+    tagged_hash("TapLeaf", &[&data])
+}
+
+pub fn tap_branch_hash(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+    if a < b {
+        tagged_hash("TapBranch", &[a, b])
+    } else {
+        tagged_hash("TapBranch", &[b, a])
+    }
+}
+
+pub fn tap_tweak(internal_key: &XOnly, merkle_root: Option<&[u8; 32]>) -> k256::Scalar {
+    match merkle_root {
+        Some(merkle_root) => hash_to_scalar("TapTweak", &[internal_key, merkle_root]),
+        None => hash_to_scalar("TapTweak", &[internal_key]),
+    }
+}
+
+// The output key of an internal key and a script tree, and whether it has odd y
+pub fn output_key(internal_key: &XOnly, merkle_root: Option<&[u8; 32]>) -> Option<(XOnly, bool)> {
+    let p = lift_x(internal_key)?;
+    let q = p + ProjectivePoint::GENERATOR * tap_tweak(internal_key, merkle_root);
+
+    Some((x_only(&q), !bip340::has_even_y(&q)))
+}
+
+// The secret key that signs key-path spends of the output with internal key g^sk
+pub fn tweak_secret_key(sk: &NonZeroScalar, merkle_root: Option<&[u8; 32]>) -> NonZeroScalar {
+    let p = ProjectivePoint::GENERATOR * sk.as_ref();
+    let d = if bip340::has_even_y(&p) {
+        *sk.as_ref()
+    } else {
+        -*sk.as_ref()
+    };
+    let t = tap_tweak(&x_only(&p), merkle_root);
+
+    // zero only if the tweak is the discrete logarithm of -p
+    NonZeroScalar::new(d + t).expect("the tweaked key is not zero")
+}
+
+// OP_1 <output_key>
+pub fn p2tr_script_pubkey(output_key: &XOnly) -> Vec<u8> {
+    let mut script = vec![OP_1, 32];
+    script.extend(output_key);
+
+    script
+}
+
+fn push_bytes(script: &mut Vec<u8>, data: &[u8]) {
+    match data.len() {
+        0..=0x4b => script.push(data.len() as u8),
+        0x4c..=0xff => script.extend([OP_PUSHDATA1, data.len() as u8]),
+        0x100..=0xffff => {
+            script.push(OP_PUSHDATA2);
+            script.extend((data.len() as u16).to_le_bytes());
+        }
+        _ => {
+            script.push(OP_PUSHDATA4);
+            script.extend((data.len() as u32).to_le_bytes());
+        }
+    }
+    script.extend(data);
+}
+
+// The minimal little-endian sign-magnitude encoding of script numbers
+fn encode_number(n: i64) -> Vec<u8> {
+    let mut bytes = vec![];
+    let mut abs = n.unsigned_abs();
+    while abs > 0 {
+        bytes.push(abs as u8);
+        abs >>= 8;
+    }
+    if let Some(last) = bytes.last_mut() {
+        if *last & 0x80 != 0 {
+            bytes.push(if n < 0 { 0x80 } else { 0x00 });
+        } else if n < 0 {
+            *last |= 0x80;
+        }
+    }
+
+    bytes
+}
+
+fn decode_number(bytes: &[u8], max_len: usize) -> Result<i64, ScriptError> {
+    if bytes.len() > max_len {
+        return Err(ScriptError::InvalidNumber);
+    }
+    let Some(last) = bytes.last() else {
+        return Ok(0);
+    };
+    // minimal encoding: the last byte is needed for the value or for the sign
+    if last & 0x7f == 0 && (bytes.len() == 1 || bytes[bytes.len() - 2] & 0x80 == 0) {
+        return Err(ScriptError::InvalidNumber);
+    }
+    let mut abs = 0i64;
+    for (i, byte) in bytes.iter().enumerate() {
+        let byte = if i == bytes.len() - 1 {
+            byte & 0x7f
+        } else {
+            *byte
+        };
+        abs |= (byte as i64) << (8 * i);
+    }
+
+    Ok(if last & 0x80 != 0 { -abs } else { abs })
+}
+
+fn push_number(script: &mut Vec<u8>, n: i64) {
+    match n {
+        0 => script.push(OP_0),
+        -1 => script.push(OP_1NEGATE),
+        1..=16 => script.push(OP_1 + (n as u8) - 1),
+        _ => push_bytes(script, &encode_number(n)),
+    }
+}
+
+// <timeout> OP_CHECKSEQUENCEVERIFY OP_DROP <pk> OP_CHECKSIG: pk can spend once the output is
+// timeout blocks deep
+pub fn timelock_script(pk: &XOnly, timeout: u16) -> Vec<u8> {
+    let mut script = vec![];
+    push_number(&mut script, timeout as i64);
+    script.extend([OP_CHECKSEQUENCEVERIFY, OP_DROP]);
+    push_bytes(&mut script, pk);
+    script.push(OP_CHECKSIG);
+
+    script
+}
+
+// The consensus rule of BIP-68 for block-based relative timelocks: whether the input index can be
+// included in a block age blocks after the one that confirmed the output it spends
+pub fn sequence_lock_satisfied(tx: &Transaction, index: usize, age: u32) -> bool {
+    let sequence = tx.inputs[index].sequence;
+    if tx.version < 2 || sequence & SEQUENCE_LOCKTIME_DISABLE_FLAG != 0 {
+        return true;
+    }
+    // time-based locks are not supported
+    if sequence & SEQUENCE_LOCKTIME_TYPE_FLAG != 0 {
+        return false;
+    }
+
+    sequence & SEQUENCE_LOCKTIME_MASK <= age
+}
+
+fn cast_to_bool(bytes: &[u8]) -> bool {
+    bytes
+        .iter()
+        .enumerate()
+        .any(|(i, byte)| *byte != 0 && !(i == bytes.len() - 1 && *byte == 0x80))
+}
+
+fn parse_signature(bytes: &[u8]) -> Result<(Signature, SighashType), ScriptError> {
+    let hash_type = match bytes.len() {
+        64 => SighashType::Default,
+        65 if bytes[64] == SighashType::All as u8 => SighashType::All,
+        _ => return Err(ScriptError::InvalidSignatureEncoding),
+    };
+    let sig = bytes[..64].try_into().expect("the length was checked");
+
+    Ok((sig, hash_type))
+}
+
+struct Context<'a> {
+    tx: &'a Transaction,
+    index: usize,
+    prevouts: &'a [TxOut],
+    leaf_hash: Option<[u8; 32]>,
+}
+
+impl Context<'_> {
+    fn check_signature(&self, sig: &[u8], pk: &XOnly) -> Result<(), ScriptError> {
+        let (sig, hash_type) = parse_signature(sig)?;
+        let sighash = taproot_sighash(
+            self.tx,
+            self.index,
+            self.prevouts,
+            hash_type,
+            self.leaf_hash.as_ref(),
+        );
+        if !bip340::verify(pk, &sighash, &sig) {
+            return Err(ScriptError::InvalidSignature);
+        }
+
+        Ok(())
+    }
+
+    // OP_CHECKSEQUENCEVERIFY of BIP-112
+    fn check_sequence(&self, n: i64) -> bool {
+        let tx_sequence = self.tx.inputs[self.index].sequence;
+        if self.tx.version < 2 || tx_sequence & SEQUENCE_LOCKTIME_DISABLE_FLAG != 0 {
+            return false;
+        }
+        let mask = SEQUENCE_LOCKTIME_TYPE_FLAG | SEQUENCE_LOCKTIME_MASK;
+        let sequence = n as u32 & mask;
+        let tx_sequence = tx_sequence & mask;
+        if (sequence < SEQUENCE_LOCKTIME_TYPE_FLAG) != (tx_sequence < SEQUENCE_LOCKTIME_TYPE_FLAG) {
+            return false;
+        }
+
+        sequence <= tx_sequence
+    }
+
+    fn execute(&self, script: &[u8], mut stack: Vec<Vec<u8>>) -> Result<(), ScriptError> {
+        let mut pc = 0;
+        while pc < script.len() {
+            let op = script[pc];
+            pc += 1;
+            match op {
+                OP_0..=OP_PUSHDATA4 => {
+                    let (len, size) = match op {
+                        OP_PUSHDATA1 => (script.get(pc).map(|b| *b as usize), 1),
+                        OP_PUSHDATA2 => (
+                            script
+                                .get(pc..pc + 2)
+                                .map(|b| u16::from_le_bytes([b[0], b[1]]) as usize),
+                            2,
+                        ),
+                        OP_PUSHDATA4 => (
+                            script
+                                .get(pc..pc + 4)
+                                .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize),
+                            4,
+                        ),
+                        _ => (Some(op as usize), 0),
+                    };
+                    let start = pc + size;
+                    let data = len
+                        .and_then(|len| script.get(start..start + len))
+                        .ok_or(ScriptError::BadPush)?;
+                    stack.push(data.to_vec());
+                    pc = start + data.len();
+                }
+                OP_1NEGATE => stack.push(encode_number(-1)),
+                OP_1..=OP_16 => stack.push(encode_number((op - OP_1 + 1) as i64)),
+                OP_VERIFY => {
+                    if !cast_to_bool(&stack.pop().ok_or(ScriptError::StackUnderflow)?) {
+                        return Err(ScriptError::VerifyFailed);
+                    }
+                }
+                OP_DROP => {
+                    stack.pop().ok_or(ScriptError::StackUnderflow)?;
+                }
+                OP_DUP => {
+                    let top = stack.last().ok_or(ScriptError::StackUnderflow)?.clone();
+                    stack.push(top);
+                }
+                OP_EQUAL | OP_EQUALVERIFY => {
+                    let a = stack.pop().ok_or(ScriptError::StackUnderflow)?;
+                    let b = stack.pop().ok_or(ScriptError::StackUnderflow)?;
+                    if op == OP_EQUAL {
+                        stack.push(if a == b { vec![1] } else { vec![] });
+                    } else if a != b {
+                        return Err(ScriptError::VerifyFailed);
+                    }
+                }
+                OP_CHECKSIG | OP_CHECKSIGVERIFY => {
+                    let pk = stack.pop().ok_or(ScriptError::StackUnderflow)?;
+                    let sig = stack.pop().ok_or(ScriptError::StackUnderflow)?;
+                    let pk: XOnly = pk
+                        .try_into()
+                        .map_err(|_| ScriptError::UnsupportedPublicKey)?;
+                    // an empty signature fails the check, any other invalid one fails the script
+                    let success = !sig.is_empty();
+                    if success {
+                        self.check_signature(&sig, &pk)?;
+                    }
+                    if op == OP_CHECKSIG {
+                        stack.push(if success { vec![1] } else { vec![] });
+                    } else if !success {
+                        return Err(ScriptError::VerifyFailed);
+                    }
+                }
+                OP_CHECKSEQUENCEVERIFY => {
+                    let n = decode_number(stack.last().ok_or(ScriptError::StackUnderflow)?, 5)?;
+                    if n < 0 {
+                        return Err(ScriptError::NegativeLocktime);
+                    }
+                    if n & SEQUENCE_LOCKTIME_DISABLE_FLAG as i64 == 0 && !self.check_sequence(n) {
+                        return Err(ScriptError::UnsatisfiedLocktime);
+                    }
+                }
+                _ => return Err(ScriptError::UnsupportedOpcode(op)),
+            }
+        }
+
+        match stack.as_slice() {
+            [top] if cast_to_bool(top) => Ok(()),
+            [_] => Err(ScriptError::EvalFalse),
+            _ => Err(ScriptError::CleanStack),
+        }
+    }
+}
+
+// Verifies the witness of the input index of tx, which spends prevouts[index]
+pub fn verify_input(tx: &Transaction, index: usize, prevouts: &[TxOut]) -> Result<(), ScriptError> {
+    let program: XOnly = match prevouts[index].script_pubkey.as_slice() {
+        [OP_1, 32, program @ ..] => program.try_into().map_err(|_| ScriptError::NotTaproot)?,
+        _ => return Err(ScriptError::NotTaproot),
+    };
+    let witness = &tx.inputs[index].witness;
+    if witness.len() >= 2 && witness[witness.len() - 1].first() == Some(&0x50) {
+        return Err(ScriptError::AnnexNotSupported);
+    }
+
+    match witness.as_slice() {
+        [] => Err(ScriptError::EmptyWitness),
+        // key path
+        [sig] => Context {
+            tx,
+            index,
+            prevouts,
+            leaf_hash: None,
+        }
+        .check_signature(sig, &program),
+        // script path
+        [stack @ .., script, control_block] => {
+            if control_block.len() < 33 || (control_block.len() - 33) % 32 != 0 {
+                return Err(ScriptError::InvalidControlBlock);
+            }
+            if control_block[0] & 0xfe != LEAF_VERSION_TAPSCRIPT {
+                return Err(ScriptError::UnknownLeafVersion);
+            }
+            let internal_key: XOnly = control_block[1..33].try_into().expect("33 bytes or more");
+            let leaf_hash = tap_leaf_hash(script);
+            let merkle_root = control_block[33..].chunks(32).fold(leaf_hash, |k, node| {
+                tap_branch_hash(&k, node.try_into().expect("chunks of 32 bytes"))
+            });
+            let (q, odd) = output_key(&internal_key, Some(&merkle_root))
+                .ok_or(ScriptError::InvalidControlBlock)?;
+            if q != program || odd != (control_block[0] & 1 == 1) {
+                return Err(ScriptError::WitnessProgramMismatch);
+            }
+
+            Context {
+                tx,
+                index,
+                prevouts,
+                leaf_hash: Some(leaf_hash),
+            }
+            .execute(script, stack.to_vec())
+        }
+    }
+}
+
+// The transactions of the selling protocol on Bitcoin. tx_lock moves the coins of the buyer in
+// funding to an output whose key path is the 2-of-2 of the buyer and the seller, and whose only
+// leaf lets the buyer take them back after timeout blocks. tx_pay spends the key path with the
+// adaptor signature of both parties, and tx_recover the leaf.
+pub struct SellingTransactions {
+    pub key: AggregateKey,
+    pub funding_output: TxOut,
+    pub lock_output: TxOut,
+    pub refund_script: Vec<u8>,
+    pub control_block: Vec<u8>,
+    pub tx_lock: Transaction,
+    pub tx_pay: Transaction,
+    pub tx_recover: Transaction,
+}
+
+impl SellingTransactions {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        funding: OutPoint,
+        funding_output: TxOut,
+        pk_buyer: &ProjectivePoint,
+        pk_seller: &ProjectivePoint,
+        pk_refund: &XOnly,
+        timeout: u16,
+        script_pubkey_pay: Vec<u8>,
+        script_pubkey_recover: Vec<u8>,
+        fee: u64,
+    ) -> Self {
+        let refund_script = timelock_script(pk_refund, timeout);
+        let leaf_hash = tap_leaf_hash(&refund_script);
+        let key = AggregateKey::new(pk_buyer, pk_seller);
+        let internal_key = key.internal_key();
+        let key = key.with_tweak(tap_tweak(&internal_key, Some(&leaf_hash)));
+        let (q, odd) = output_key(&internal_key, Some(&leaf_hash)).expect("a valid internal key");
+        debug_assert_eq!(q, key.output_key());
+        let mut control_block = vec![LEAF_VERSION_TAPSCRIPT | odd as u8];
+        control_block.extend(internal_key);
+
+        let lock_output = TxOut::new(
+            funding_output.value.saturating_sub(fee),
+            p2tr_script_pubkey(&key.output_key()),
+        );
+        let tx_lock = Transaction::new(
+            vec![TxIn::new(funding, SEQUENCE_FINAL)],
+            vec![lock_output.clone()],
+        );
+        let value = lock_output.value.saturating_sub(fee);
+        let tx_pay = Transaction::new(
+            vec![TxIn::new(tx_lock.outpoint(0), SEQUENCE_FINAL)],
+            vec![TxOut::new(value, script_pubkey_pay)],
+        );
+        let tx_recover = Transaction::new(
+            vec![TxIn::new(tx_lock.outpoint(0), timeout as u32)],
+            vec![TxOut::new(value, script_pubkey_recover)],
+        );
+
+        Self {
+            key,
+            funding_output,
+            lock_output,
+            refund_script,
+            control_block,
+            tx_lock,
+            tx_pay,
+            tx_recover,
+        }
+    }
+
+    pub fn sighash_lock(&self) -> [u8; 32] {
+        taproot_sighash(
+            &self.tx_lock,
+            0,
+            std::slice::from_ref(&self.funding_output),
+            SighashType::Default,
+            None,
+        )
+    }
+
+    pub fn sighash_pay(&self) -> [u8; 32] {
+        taproot_sighash(
+            &self.tx_pay,
+            0,
+            std::slice::from_ref(&self.lock_output),
+            SighashType::Default,
+            None,
+        )
+    }
+
+    pub fn sighash_recover(&self) -> [u8; 32] {
+        taproot_sighash(
+            &self.tx_recover,
+            0,
+            std::slice::from_ref(&self.lock_output),
+            SighashType::Default,
+            Some(&tap_leaf_hash(&self.refund_script)),
+        )
+    }
+
+    // Signs the key-path spend of funding, a key-path-only output of g^sk_funding
+    pub fn sign_lock(&mut self, sk_funding: &NonZeroScalar) -> Signature {
+        let sig = bip340::sign(&tweak_secret_key(sk_funding, None), &self.sighash_lock());
+        self.complete_lock(&sig);
+
+        sig
+    }
+
+    // The witness of tx_lock signed by the buyer, as the seller reads it from the blockchain
+    pub fn complete_lock(&mut self, sig: &Signature) {
+        self.tx_lock.inputs[0].witness = vec![sig.to_vec()];
+    }
+
+    pub fn complete_pay(&mut self, sig: &Signature) {
+        self.tx_pay.inputs[0].witness = vec![sig.to_vec()];
+    }
+
+    pub fn sign_recover(&mut self, sk_refund: &NonZeroScalar) {
+        let sig = bip340::sign(sk_refund, &self.sighash_recover());
+        self.tx_recover.inputs[0].witness = vec![
+            sig.to_vec(),
+            self.refund_script.clone(),
+            self.control_block.clone(),
+        ];
+    }
+
+    pub fn verify_lock(&self) -> Result<(), ScriptError> {
+        verify_input(&self.tx_lock, 0, std::slice::from_ref(&self.funding_output))
+    }
+
+    pub fn verify_pay(&self) -> Result<(), ScriptError> {
+        verify_input(&self.tx_pay, 0, std::slice::from_ref(&self.lock_output))
+    }
+
+    pub fn verify_recover(&self) -> Result<(), ScriptError> {
+        verify_input(&self.tx_recover, 0, std::slice::from_ref(&self.lock_output))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bip340::{nonce, AdaptorSession};
+    use crate::hard_relation::HardRelation;
+    use crate::schnorr_signatures::SchnorrSignatureScheme;
+    use crate::selling_protocol::{Goods, SignatureGoods, Terms};
+    use crate::signature_scheme::SignatureScheme;
+    use elliptic_curve::ff::PrimeField;
+    use k256::Secp256k1;
+
+    const TIMEOUT: u16 = 144;
+    const FEE: u64 = 500;
+
+    fn key_path_output(value: u64, sk: &NonZeroScalar) -> TxOut {
+        let (q, _) = output_key(&bip340::public_key(sk), None).unwrap();
+
+        TxOut::new(value, p2tr_script_pubkey(&q))
+    }
+
+    fn from_hex(hex: &str) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).unwrap();
+        }
+
+        bytes
+    }
+
+    #[test]
+    fn bip341_test_vector() {
+        // the first scriptPubKey of the wallet test vectors of BIP-341, without scripts
+        let internal_key =
+            from_hex("d6889cb081036e0faefa3a35157ad71086b123b2b144b649798b494c300a961d");
+        let tweak = from_hex("b86e7be8f39bab32a6f2c0443abbc210f0edac0e2c53d501b36b64437d9c6c70");
+        let tweaked = from_hex("53a1f6e454df1aa2776a2814a721372d6258050de330b3c6d10ee8f4e0dda343");
+        assert_eq!(tap_tweak(&internal_key, None).to_repr().as_slice(), tweak);
+        assert_eq!(output_key(&internal_key, None).unwrap().0, tweaked);
+    }
+
+    fn bytes_from_hex(hex: &str) -> Vec<u8> {
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    // the key-path spends of the wallet test vectors of BIP-341 whose hash types are supported:
+    // input 3 with SIGHASH_ALL and input 4 with SIGHASH_DEFAULT
+    #[test]
+    fn bip341_sighash_test_vectors() {
+        let raw = bytes_from_hex(
+            "02000000097de20cbff686da83a54981d2b9bab3586f4ca7e48f57f5b55963115f3b334e9c0100000000\
+             00000000d7b7cab57b1393ace2d064f4d4a2cb8af6def61273e127517d44759b6dafdd990000000000ff\
+             fffffff8e1f583384333689228c5d28eac13366be082dc57441760d957275419a418420000000000ffff\
+             fffff0689180aa63b30cb162a73c6d2a38b7eeda2a83ece74310fda0843ad604853b0100000000feffff\
+             ffaa5202bdf6d8ccd2ee0f0202afbbb7461d9264a25e5bfd3c5a52ee1239e0ba6c0000000000feffffff\
+             956149bdc66faa968eb2be2d2faa29718acbfe3941215893a2a3446d32acd050000000000000000000e6\
+             64b9773b88c09c32cb70a2a3e4da0ced63b7ba3b22f848531bbb1d5d5f4c94010000000000000000e9aa\
+             6b8e6c9de67619e6a3924ae25696bb7b694bb677a632a74ef7eadfd4eabf0000000000ffffffffa778eb\
+             6a263dc090464cd125c466b5a99667720b1c110468831d058aa1b82af10100000000ffffffff0200ca9a\
+             3b000000001976a91406afd46bcdfd22ef94ac122aa11f241244a37ecc88ac807840cb0000000020ac9a\
+             87f5594be208f8532db38cff670c450ed2fea8fcdefcc9a663f78bab962b0065cd1d",
+        );
+        let prevouts: Vec<TxOut> = [
+            ("512053a1f6e454df1aa2776a2814a721372d6258050de330b3c6d10ee8f4e0dda343", 420000000),
+            ("5120147c9c57132f6e7ecddba9800bb0c4449251c92a1e60371ee77557b6620f3ea3", 462000000),
+            ("76a914751e76e8199196d454941c45d1b3a323f1433bd688ac", 294000000),
+            ("5120e4d810fd50586274face62b8a807eb9719cef49c04177cc6b76a9a4251d5450e", 504000000),
+            ("512091b64d5324723a985170e4dc5a0f84c041804f2cd12660fa5dec09fc21783605", 630000000),
+            ("00147dd65592d0ab2fe0d0257d571abf032cd9db93dc", 378000000),
+            ("512075169f4001aa68f15bbed28b218df1d0a62cbbcf1188c6665110c293c907b831", 672000000),
+            ("5120712447206d7a5238acc7ff53fbe94a3b64539ad291c7cdbc490b7577e4b17df5", 546000000),
+            ("512077e30a5522dd9f894c3f8b8bd4c4b2cf82ca7da8a3ea6a239655c39c050ab220", 588000000),
+        ]
+        .iter()
+        .map(|(script_pubkey, value)| TxOut::new(*value, bytes_from_hex(script_pubkey)))
+        .collect();
+
+        let tx = Transaction::from_byte_vector(&raw).unwrap();
+        assert_eq!(tx.inputs.len(), 9);
+        assert_eq!(tx.to_byte_vector(), raw);
+        assert!(Transaction::from_byte_vector(&raw[..raw.len() - 1]).is_none());
+        assert_eq!(
+            taproot_sighash(&tx, 3, &prevouts, SighashType::All, None),
+            from_hex("bf013ea93474aa67815b1b6cc441d23b64fa310911d991e713cd34c7f5d46669")
+        );
+        assert_eq!(
+            taproot_sighash(&tx, 4, &prevouts, SighashType::Default, None),
+            from_hex("4f900a0bae3f1446fd48490c2958b5a023228f01661cda3496a11da502a7f7ef")
+        );
+    }
+
+    #[test]
+    fn script_numbers() {
+        for n in [
+            0,
+            1,
+            -1,
+            16,
+            127,
+            128,
+            -128,
+            144,
+            255,
+            256,
+            0x7fff_ffff,
+            -0x7fff_ffff,
+        ] {
+            assert_eq!(decode_number(&encode_number(n), 5), Ok(n));
+        }
+        assert_eq!(encode_number(144), vec![0x90, 0x00]);
+        assert_eq!(encode_number(-1), vec![0x81]);
+        assert_eq!(
+            decode_number(&[0x01, 0x00], 5),
+            Err(ScriptError::InvalidNumber)
+        );
+        assert_eq!(decode_number(&[0x80], 5), Err(ScriptError::InvalidNumber));
+        assert_eq!(
+            timelock_script(&[0; 32], TIMEOUT)[..5],
+            [0x02, 0x90, 0x00, 0xb2, 0x75]
+        );
+    }
+
+    #[test]
+    fn taproot_key_path_spend() {
+        let (sk, _) = NonZeroScalar::gen(&ProjectivePoint::GENERATOR);
+        let prevout = key_path_output(100_000, &sk);
+        let mut tx = Transaction::new(
+            vec![TxIn::new(OutPoint::new([1; 32], 0), SEQUENCE_FINAL)],
+            vec![TxOut::new(99_500, p2tr_script_pubkey(&[2; 32]))],
+        );
+        let txid = tx.txid();
+        let sighash = taproot_sighash(
+            &tx,
+            0,
+            std::slice::from_ref(&prevout),
+            SighashType::Default,
+            None,
+        );
+        let sig = bip340::sign(&tweak_secret_key(&sk, None), &sighash);
+        tx.inputs[0].witness = vec![sig.to_vec()];
+        assert_eq!(verify_input(&tx, 0, std::slice::from_ref(&prevout)), Ok(()));
+        // the witness is not part of the txid
+        assert_eq!(tx.txid(), txid);
+        assert_ne!(tx.to_byte_vector(), tx.serialize(false));
+
+        // SIGHASH_ALL signatures carry their type
+        let sighash = taproot_sighash(
+            &tx,
+            0,
+            std::slice::from_ref(&prevout),
+            SighashType::All,
+            None,
+        );
+        let mut sig = bip340::sign(&tweak_secret_key(&sk, None), &sighash).to_vec();
+        sig.push(SighashType::All as u8);
+        tx.inputs[0].witness = vec![sig.clone()];
+        assert_eq!(verify_input(&tx, 0, std::slice::from_ref(&prevout)), Ok(()));
+
+        tx.outputs[0].value -= 1;
+        assert_eq!(
+            verify_input(&tx, 0, std::slice::from_ref(&prevout)),
+            Err(ScriptError::InvalidSignature)
+        );
+        // the untweaked key cannot spend the output
+        tx.outputs[0].value += 1;
+        let sighash = taproot_sighash(
+            &tx,
+            0,
+            std::slice::from_ref(&prevout),
+            SighashType::Default,
+            None,
+        );
+        tx.inputs[0].witness = vec![bip340::sign(&sk, &sighash).to_vec()];
+        assert_eq!(
+            verify_input(&tx, 0, std::slice::from_ref(&prevout)),
+            Err(ScriptError::InvalidSignature)
+        );
+    }
+
+    #[test]
+    fn taproot_selling_transactions() {
+        let (sk_funding, _) = NonZeroScalar::gen(&ProjectivePoint::GENERATOR);
+        let (sk_buyer, pk_buyer) = NonZeroScalar::gen(&ProjectivePoint::GENERATOR);
+        let (sk_seller, pk_seller) = NonZeroScalar::gen(&ProjectivePoint::GENERATOR);
+        let (sk_refund, _) = NonZeroScalar::gen(&ProjectivePoint::GENERATOR);
+        let (sk_pay, _) = NonZeroScalar::gen(&ProjectivePoint::GENERATOR);
+        let (w, x) = NonZeroScalar::gen(&ProjectivePoint::GENERATOR);
+
+        let mut txs = SellingTransactions::new(
+            OutPoint::new([1; 32], 0),
+            key_path_output(100_000, &sk_funding),
+            &pk_buyer,
+            &pk_seller,
+            &bip340::public_key(&sk_refund),
+            TIMEOUT,
+            key_path_output(0, &sk_pay).script_pubkey,
+            key_path_output(0, &sk_refund).script_pubkey,
+            FEE,
+        );
+        txs.sign_lock(&sk_funding);
+        assert_eq!(txs.verify_lock(), Ok(()));
+        assert_eq!(
+            txs.tx_pay.inputs[0].previous_output,
+            txs.tx_lock.outpoint(0)
+        );
+
+        // pay: the buyer and the seller pre-sign the key path w.r.t. x
+        let (k_buyer, r_buyer) = nonce();
+        let (k_seller, r_seller) = nonce();
+        let session = AdaptorSession::new(&txs.key, [r_buyer, r_seller], &x, &txs.sighash_pay());
+        let s_seller = session.partial_sign(1, &sk_seller, &k_seller);
+        assert!(session.partial_verify(1, &s_seller));
+        let s_buyer = session.partial_sign(0, &sk_buyer, &k_buyer);
+        assert!(session.partial_verify(0, &s_buyer));
+        let pre_signature = session.pre_signature(&[s_buyer, s_seller]);
+
+        let sig = session.adapt(&pre_signature, &w);
+        let mut pre_signed = sig;
+        pre_signed[32..].copy_from_slice(&pre_signature.to_repr());
+        txs.complete_pay(&pre_signed);
+        assert_eq!(txs.verify_pay(), Err(ScriptError::InvalidSignature));
+        txs.complete_pay(&sig);
+        assert_eq!(txs.verify_pay(), Ok(()));
+        let published = txs.tx_pay.inputs[0].witness[0].clone();
+        let extracted_w = session
+            .extract(&pre_signature, &published.try_into().unwrap())
+            .unwrap();
+        assert!(extracted_w.as_ref() == w.as_ref());
+
+        // recover: the leaf needs the sequence of the timeout
+        txs.sign_recover(&sk_refund);
+        assert_eq!(txs.verify_recover(), Ok(()));
+        assert!(!sequence_lock_satisfied(
+            &txs.tx_recover,
+            0,
+            TIMEOUT as u32 - 1
+        ));
+        assert!(sequence_lock_satisfied(&txs.tx_recover, 0, TIMEOUT as u32));
+
+        txs.tx_recover.inputs[0].sequence = TIMEOUT as u32 - 1;
+        txs.sign_recover(&sk_refund);
+        assert_eq!(txs.verify_recover(), Err(ScriptError::UnsatisfiedLocktime));
+        txs.tx_recover.inputs[0].sequence = TIMEOUT as u32;
+        txs.sign_recover(&sk_pay);
+        assert_eq!(txs.verify_recover(), Err(ScriptError::InvalidSignature));
+
+        // a leaf that is not in the tree does not spend the output
+        txs.sign_recover(&sk_refund);
+        txs.tx_recover.inputs[0].witness[1] = timelock_script(&bip340::public_key(&sk_pay), 0);
+        assert_eq!(
+            txs.verify_recover(),
+            Err(ScriptError::WitnessProgramMismatch)
+        );
+    }
+
+    #[test]
+    fn taproot_selling_signature() {
+        let (sk_notary, pk_notary) = NonZeroScalar::gen(&ProjectivePoint::GENERATOR);
+        let (sk_funding, _) = NonZeroScalar::gen(&ProjectivePoint::GENERATOR);
+        let (sk_buyer, pk_buyer) = NonZeroScalar::gen(&ProjectivePoint::GENERATOR);
+        let (sk_seller, pk_seller) = NonZeroScalar::gen(&ProjectivePoint::GENERATOR);
+        let (sk_refund, _) = NonZeroScalar::gen(&ProjectivePoint::GENERATOR);
+        let mut txs = SellingTransactions::new(
+            OutPoint::new([1; 32], 0),
+            key_path_output(100_000, &sk_funding),
+            &pk_buyer,
+            &pk_seller,
+            &bip340::public_key(&sk_refund),
+            TIMEOUT,
+            key_path_output(0, &sk_seller).script_pubkey,
+            key_path_output(0, &sk_refund).script_pubkey,
+            FEE,
+        );
+
+        let msg = "Lorem ipsum dolor sit amet";
+        let terms = Terms::<Secp256k1> {
+            pk_notary,
+            msg: msg.into(),
+            pk_lock: pk_buyer,
+            pk_buyer,
+            tx_lock: txs.tx_lock.message(),
+            tx_pay: txs.tx_pay.message(),
+            tx_recover: txs.tx_recover.message(),
+            timeout: TIMEOUT as u64,
+        };
+        let signature = SchnorrSignatureScheme::<Secp256k1, Sha256>::sign(&sk_notary, msg);
+
+        // seller: offer
+        let (w, x) = NonZeroScalar::gen(&ProjectivePoint::GENERATOR);
+        let offer = SignatureGoods::<Secp256k1, Sha256>::offer(&terms, &(), &signature, &w, &x);
+        let (k_seller, r_seller) = nonce();
+
+        // buyer: checks the offer and locks
+        assert!(SignatureGoods::<Secp256k1, Sha256>::verify_offer(
+            &terms, &(), &offer
+        ));
+        let x = SignatureGoods::<Secp256k1, Sha256>::statement(&offer);
+        let (k_buyer, r_buyer) = nonce();
+        txs.sign_lock(&sk_funding);
+        assert_eq!(txs.verify_lock(), Ok(()));
+
+        // both: partial signatures, the seller's first
+        let session = AdaptorSession::new(&txs.key, [r_buyer, r_seller], &x, &txs.sighash_pay());
+        let s_seller = session.partial_sign(1, &sk_seller, &k_seller);
+        assert!(session.partial_verify(1, &s_seller));
+        let s_buyer = session.partial_sign(0, &sk_buyer, &k_buyer);
+        assert!(session.partial_verify(0, &s_buyer));
+
+        // seller: completes tx_pay with the witness
+        let pre_signature = session.pre_signature(&[s_buyer, s_seller]);
+        txs.complete_pay(&session.adapt(&pre_signature, &w));
+        assert_eq!(txs.verify_pay(), Ok(()));
+
+        // buyer: extracts the witness from tx_pay and decrypts the signature
+        let published: Signature = txs.tx_pay.inputs[0].witness[0].clone().try_into().unwrap();
+        let w = session.extract(&pre_signature, &published).unwrap();
+        let goods = SignatureGoods::<Secp256k1, Sha256>::recover(&terms, &(), &offer, &w).unwrap();
+        assert!(SchnorrSignatureScheme::<Secp256k1, Sha256>::verify(
+            &pk_notary, msg, &goods
+        ));
+    }
+}
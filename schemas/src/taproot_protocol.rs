@@ -0,0 +1,801 @@
+use crate::bip340::{self, AdaptorSession, Signature};
+use crate::hard_relation::HardRelation;
+use crate::ledger::Clock;
+use crate::selling_protocol::{Evidence, Goods, Party, SellingError, Terms};
+use crate::taproot::{verify_input, SellingTransactions, Transaction};
+use crate::utils::{point_to_byte_vector, scalar_to_byte_vector, ByteReader};
+use k256::{NonZeroScalar, ProjectivePoint, Scalar, Secp256k1};
+use sha2::Sha256;
+use std::marker::PhantomData;
+
+// The selling protocol on Bitcoin, as a pair of state machines over the Taproot transactions of
+// SellingTransactions. The goods and the offer are the ones of schemas::selling_protocol, but
+// tx_pay spends the key path of tx_lock, a 2-of-2 of the buyer (party 0) and the seller (party 1),
+// so the pre-signature of tx_pay is a BIP-340 adaptor signature that both parties compute
+// together from their nonces and partial signatures:
+//
+//   TaprootSeller::offer                       -> Offer, Nonce     (seller to buyer)
+//   TaprootBuyer::receive_offer                -> Nonce            (buyer to seller)
+//   TaprootBuyer::lock                         -> Lock             (buyer publishes tx_lock)
+//   TaprootBuyer::confirm_lock                                     (tx_lock is confirmed)
+//   TaprootSeller::receive_lock                -> PartialSignature (seller to buyer)
+//   TaprootBuyer::receive_partial_signature    -> PartialSignature (buyer to seller)
+//   TaprootSeller::receive_partial_signature   -> Payment          (seller publishes tx_pay)
+//   TaprootBuyer::receive_payment              -> the goods        (buyer reads tx_pay)
+//
+// The seller only signs once tx_lock is confirmed, and each nonce is used in one session only:
+// a role leaves the state that holds its nonce as soon as it has signed with it. As in
+// selling_protocol, both roles count the timelock of tx_recover from the height that confirmed
+// tx_lock, the seller stops PAYMENT_MARGIN ticks before it, a failed step leaves the role in its
+// state, the messages of the other party that a role rejects are kept as Evidence, and a buyer
+// that gets no valid tx_pay gets its coins back with TaprootBuyer::refund.
+
+// The nonce of a party for the 2-of-2 signature of tx_pay
+pub struct NonceMessage {
+    pub nonce: ProjectivePoint,
+}
+
+impl NonceMessage {
+    pub fn to_byte_vector(&self) -> Vec<u8> {
+        point_to_byte_vector::<Secp256k1>(&self.nonce)
+    }
+
+    pub fn from_byte_vector(bytes: &[u8]) -> Option<Self> {
+        let mut reader = ByteReader::new(bytes);
+        let nonce = reader.read_point::<Secp256k1>()?;
+
+        reader.is_empty().then_some(Self { nonce })
+    }
+}
+
+// The partial signature of a party on tx_pay
+pub struct PartialSignatureMessage {
+    pub partial_signature: Scalar,
+}
+
+impl PartialSignatureMessage {
+    pub fn to_byte_vector(&self) -> Vec<u8> {
+        scalar_to_byte_vector::<Secp256k1>(&self.partial_signature)
+    }
+
+    pub fn from_byte_vector(bytes: &[u8]) -> Option<Self> {
+        let mut reader = ByteReader::new(bytes);
+        let partial_signature = reader.read_scalar::<Secp256k1>()?;
+
+        reader.is_empty().then_some(Self { partial_signature })
+    }
+}
+
+// The BIP-340 signature of the key-path spend of the funding output in tx_lock
+pub struct TaprootLockMessage {
+    pub signature: Signature,
+}
+
+impl TaprootLockMessage {
+    pub fn to_byte_vector(&self) -> Vec<u8> {
+        self.signature.to_vec()
+    }
+
+    pub fn from_byte_vector(bytes: &[u8]) -> Option<Self> {
+        Some(Self {
+            signature: bytes.try_into().ok()?,
+        })
+    }
+}
+
+// The BIP-340 signature of the key-path spend of tx_lock in tx_pay, published by the seller
+pub struct TaprootPaymentMessage {
+    pub signature: Signature,
+}
+
+impl TaprootPaymentMessage {
+    pub fn to_byte_vector(&self) -> Vec<u8> {
+        self.signature.to_vec()
+    }
+
+    pub fn from_byte_vector(bytes: &[u8]) -> Option<Self> {
+        Some(Self {
+            signature: bytes.try_into().ok()?,
+        })
+    }
+}
+
+pub enum TaprootSellerState<S> {
+    // Holds the secret of the goods, waiting to make an offer
+    Ready {
+        secret: S,
+    },
+    // The offer and the nonce are sent, waiting for tx_lock to be confirmed
+    Offered {
+        w: NonZeroScalar,
+        x: ProjectivePoint,
+        k: NonZeroScalar,
+    },
+    // The partial signature of the seller is sent, waiting for the one of the buyer
+    Locked {
+        w: NonZeroScalar,
+        session: Box<AdaptorSession>,
+        partial_signature: Scalar,
+        locked_at: u64,
+    },
+    // tx_pay is published, so the buyer can recover the goods
+    Paid,
+}
+
+pub struct TaprootSeller<G>
+where
+    G: Goods<Secp256k1, Sha256>,
+{
+    terms: Terms<Secp256k1>,
+    params: G::Params,
+    transactions: SellingTransactions,
+    sk: NonZeroScalar,
+    state: TaprootSellerState<G::Secret>,
+    evidence: Vec<Evidence>,
+    _goods_marker: PhantomData<G>,
+}
+
+impl<G> TaprootSeller<G>
+where
+    G: Goods<Secp256k1, Sha256>,
+{
+    // transactions are the ones of the terms, and sk is the key of the seller in their 2-of-2
+    pub fn new(
+        terms: Terms<Secp256k1>,
+        params: G::Params,
+        transactions: SellingTransactions,
+        sk: NonZeroScalar,
+        secret: G::Secret,
+    ) -> Result<Self, SellingError> {
+        if !G::verify_secret(&terms, &params, &secret) {
+            return Err(SellingError::InvalidNotarySignature);
+        }
+
+        Ok(Self {
+            terms,
+            params,
+            transactions,
+            sk,
+            state: TaprootSellerState::Ready { secret },
+            evidence: vec![],
+            _goods_marker: PhantomData,
+        })
+    }
+
+    pub fn state(&self) -> &TaprootSellerState<G::Secret> {
+        &self.state
+    }
+
+    // The messages of the buyer rejected so far
+    pub fn evidence(&self) -> &[Evidence] {
+        &self.evidence
+    }
+
+    // The transactions with the witnesses known to the seller
+    pub fn transactions(&self) -> &SellingTransactions {
+        &self.transactions
+    }
+
+    fn blame(&mut self, error: SellingError, message: Vec<u8>) -> SellingError {
+        self.evidence
+            .push(Evidence::new(Party::Buyer, error, message));
+
+        error
+    }
+
+    pub fn offer(&mut self) -> Result<(G::Offer, NonceMessage), SellingError> {
+        let TaprootSellerState::Ready { secret } = &self.state else {
+            return Err(SellingError::UnexpectedMessage);
+        };
+        let (w, x) = NonZeroScalar::gen(&ProjectivePoint::GENERATOR);
+        let offer = G::offer(&self.terms, &self.params, secret, &w, &x);
+        let (k, nonce) = bip340::nonce();
+        self.state = TaprootSellerState::Offered { w, x, k };
+
+        Ok((offer, NonceMessage { nonce }))
+    }
+
+    // nonce is the one of the buyer, and confirmed_at the height of the block that confirmed
+    // tx_lock with the witness of msg
+    pub fn receive_lock(
+        &mut self,
+        nonce: &NonceMessage,
+        msg: &TaprootLockMessage,
+        confirmed_at: u64,
+    ) -> Result<PartialSignatureMessage, SellingError> {
+        let TaprootSellerState::Offered { w, x, k } = &self.state else {
+            return Err(SellingError::UnexpectedMessage);
+        };
+        let mut tx_lock = self.transactions.tx_lock.clone();
+        tx_lock.inputs[0].witness = vec![msg.signature.to_vec()];
+        let funding_output = std::slice::from_ref(&self.transactions.funding_output);
+        if verify_input(&tx_lock, 0, funding_output).is_err() {
+            return Err(self.blame(SellingError::InvalidLock, msg.to_byte_vector()));
+        }
+        let session = AdaptorSession::new(
+            &self.transactions.key,
+            [nonce.nonce, ProjectivePoint::GENERATOR * k.as_ref()],
+            x,
+            &self.transactions.sighash_pay(),
+        );
+        let partial_signature = session.partial_sign(1, &self.sk, k);
+        let w = *w;
+        self.transactions.complete_lock(&msg.signature);
+        self.state = TaprootSellerState::Locked {
+            w,
+            session: Box::new(session),
+            partial_signature,
+            locked_at: confirmed_at,
+        };
+
+        Ok(PartialSignatureMessage { partial_signature })
+    }
+
+    pub fn receive_partial_signature(
+        &mut self,
+        msg: &PartialSignatureMessage,
+        clock: &Clock,
+    ) -> Result<TaprootPaymentMessage, SellingError> {
+        let TaprootSellerState::Locked {
+            w,
+            session,
+            partial_signature,
+            locked_at,
+        } = &self.state
+        else {
+            return Err(SellingError::UnexpectedMessage);
+        };
+        if clock.now() >= self.terms.payment_deadline(*locked_at) {
+            return Err(SellingError::TimelockExpired);
+        }
+        if !session.partial_verify(0, &msg.partial_signature) {
+            return Err(self.blame(SellingError::InvalidPartialSignature, msg.to_byte_vector()));
+        }
+        let pre_signature = session.pre_signature(&[msg.partial_signature, *partial_signature]);
+        let signature = session.adapt(&pre_signature, w);
+        self.transactions.complete_pay(&signature);
+        if self.transactions.verify_pay().is_err() {
+            self.transactions.tx_pay.inputs[0].witness.clear();
+            return Err(self.blame(SellingError::InvalidPartialSignature, msg.to_byte_vector()));
+        }
+        self.state = TaprootSellerState::Paid;
+
+        Ok(TaprootPaymentMessage { signature })
+    }
+}
+
+pub enum TaprootBuyerState<O> {
+    // Waiting for the offer of the seller
+    Ready,
+    // The offer is accepted and the nonce sent, waiting to publish tx_lock
+    Offered {
+        offer: O,
+        k: NonZeroScalar,
+        nonces: [ProjectivePoint; 2],
+    },
+    // tx_lock is signed, waiting for it to be confirmed
+    Locking {
+        offer: O,
+        k: NonZeroScalar,
+        nonces: [ProjectivePoint; 2],
+    },
+    // tx_lock is confirmed, waiting for the partial signature of the seller
+    Locked {
+        offer: O,
+        k: NonZeroScalar,
+        nonces: [ProjectivePoint; 2],
+        locked_at: u64,
+    },
+    // The partial signature of the buyer is sent, waiting for tx_pay to appear on the blockchain
+    PreSigned {
+        offer: O,
+        session: Box<AdaptorSession>,
+        pre_signature: Scalar,
+        locked_at: u64,
+    },
+    // The goods have been recovered from tx_pay
+    Done,
+    // The seller did not publish tx_pay in time, and tx_recover is published
+    Refunded,
+}
+
+pub struct TaprootBuyer<G>
+where
+    G: Goods<Secp256k1, Sha256>,
+{
+    terms: Terms<Secp256k1>,
+    params: G::Params,
+    transactions: SellingTransactions,
+    sk_funding: NonZeroScalar,
+    sk: NonZeroScalar,
+    sk_refund: NonZeroScalar,
+    state: TaprootBuyerState<G::Offer>,
+    evidence: Vec<Evidence>,
+}
+
+impl<G> TaprootBuyer<G>
+where
+    G: Goods<Secp256k1, Sha256>,
+{
+    // sk_funding spends the funding output of tx_lock, sk is the key of the buyer in the 2-of-2
+    // and sk_refund the key of the leaf of tx_recover
+    pub fn new(
+        terms: Terms<Secp256k1>,
+        params: G::Params,
+        transactions: SellingTransactions,
+        sk_funding: NonZeroScalar,
+        sk: NonZeroScalar,
+        sk_refund: NonZeroScalar,
+    ) -> Self {
+        Self {
+            terms,
+            params,
+            transactions,
+            sk_funding,
+            sk,
+            sk_refund,
+            state: TaprootBuyerState::Ready,
+            evidence: vec![],
+        }
+    }
+
+    pub fn state(&self) -> &TaprootBuyerState<G::Offer> {
+        &self.state
+    }
+
+    // The messages of the seller rejected so far
+    pub fn evidence(&self) -> &[Evidence] {
+        &self.evidence
+    }
+
+    // The transactions with the witnesses known to the buyer
+    pub fn transactions(&self) -> &SellingTransactions {
+        &self.transactions
+    }
+
+    fn blame(&mut self, error: SellingError, message: Vec<u8>) -> SellingError {
+        self.evidence
+            .push(Evidence::new(Party::Seller, error, message));
+
+        error
+    }
+
+    // nonce is the one of the seller, sent with the offer
+    pub fn receive_offer(
+        &mut self,
+        offer: G::Offer,
+        nonce: &NonceMessage,
+    ) -> Result<NonceMessage, SellingError> {
+        let TaprootBuyerState::Ready = &self.state else {
+            return Err(SellingError::UnexpectedMessage);
+        };
+        if !G::verify_offer(&self.terms, &self.params, &offer) {
+            let bytes = G::offer_to_byte_vector(&offer);
+            return Err(self.blame(SellingError::InvalidOffer, bytes));
+        }
+        let (k, nonce_buyer) = bip340::nonce();
+        self.state = TaprootBuyerState::Offered {
+            offer,
+            k,
+            nonces: [nonce_buyer, nonce.nonce],
+        };
+
+        Ok(NonceMessage { nonce: nonce_buyer })
+    }
+
+    pub fn lock(&mut self) -> Result<TaprootLockMessage, SellingError> {
+        match std::mem::replace(&mut self.state, TaprootBuyerState::Ready) {
+            TaprootBuyerState::Offered { offer, k, nonces } => {
+                let signature = self.transactions.sign_lock(&self.sk_funding);
+                self.state = TaprootBuyerState::Locking { offer, k, nonces };
+
+                Ok(TaprootLockMessage { signature })
+            }
+            state => {
+                self.state = state;
+                Err(SellingError::UnexpectedMessage)
+            }
+        }
+    }
+
+    // confirmed_at is the height of the block that confirmed tx_lock, from which the timelock of
+    // tx_recover counts
+    pub fn confirm_lock(&mut self, confirmed_at: u64) -> Result<(), SellingError> {
+        match std::mem::replace(&mut self.state, TaprootBuyerState::Ready) {
+            TaprootBuyerState::Locking { offer, k, nonces } => {
+                self.state = TaprootBuyerState::Locked {
+                    offer,
+                    k,
+                    nonces,
+                    locked_at: confirmed_at,
+                };
+
+                Ok(())
+            }
+            state => {
+                self.state = state;
+                Err(SellingError::UnexpectedMessage)
+            }
+        }
+    }
+
+    pub fn receive_partial_signature(
+        &mut self,
+        msg: &PartialSignatureMessage,
+    ) -> Result<PartialSignatureMessage, SellingError> {
+        match std::mem::replace(&mut self.state, TaprootBuyerState::Ready) {
+            TaprootBuyerState::Locked {
+                offer,
+                k,
+                nonces,
+                locked_at,
+            } => {
+                let session = AdaptorSession::new(
+                    &self.transactions.key,
+                    nonces,
+                    &G::statement(&offer),
+                    &self.transactions.sighash_pay(),
+                );
+                if !session.partial_verify(1, &msg.partial_signature) {
+                    self.state = TaprootBuyerState::Locked {
+                        offer,
+                        k,
+                        nonces,
+                        locked_at,
+                    };
+                    let bytes = msg.to_byte_vector();
+                    return Err(self.blame(SellingError::InvalidPartialSignature, bytes));
+                }
+                let partial_signature = session.partial_sign(0, &self.sk, &k);
+                let pre_signature =
+                    session.pre_signature(&[partial_signature, msg.partial_signature]);
+                self.state = TaprootBuyerState::PreSigned {
+                    offer,
+                    session: Box::new(session),
+                    pre_signature,
+                    locked_at,
+                };
+
+                Ok(PartialSignatureMessage { partial_signature })
+            }
+            state => {
+                self.state = state;
+                Err(SellingError::UnexpectedMessage)
+            }
+        }
+    }
+
+    // msg is the witness of tx_pay read from the blockchain
+    pub fn receive_payment(
+        &mut self,
+        msg: &TaprootPaymentMessage,
+    ) -> Result<G::Output, SellingError> {
+        let TaprootBuyerState::PreSigned {
+            offer,
+            session,
+            pre_signature,
+            ..
+        } = &self.state
+        else {
+            return Err(SellingError::UnexpectedMessage);
+        };
+        let mut tx_pay = self.transactions.tx_pay.clone();
+        tx_pay.inputs[0].witness = vec![msg.signature.to_vec()];
+        let lock_output = std::slice::from_ref(&self.transactions.lock_output);
+        if verify_input(&tx_pay, 0, lock_output).is_err() {
+            return Err(self.blame(SellingError::InvalidPayment, msg.to_byte_vector()));
+        }
+        let w = session.extract(pre_signature, &msg.signature);
+        let Some(goods) = w.and_then(|w| G::recover(&self.terms, &self.params, offer, &w)) else {
+            return Err(self.blame(SellingError::ExtractionFailed, msg.to_byte_vector()));
+        };
+        self.transactions.complete_pay(&msg.signature);
+        self.state = TaprootBuyerState::Done;
+
+        Ok(goods)
+    }
+
+    // tx_recover, signed once the timelock has expired
+    pub fn refund(&mut self, clock: &Clock) -> Result<Transaction, SellingError> {
+        let (TaprootBuyerState::Locked { locked_at, .. }
+        | TaprootBuyerState::PreSigned { locked_at, .. }) = &self.state
+        else {
+            return Err(SellingError::UnexpectedMessage);
+        };
+        if clock.now() < self.terms.deadline(*locked_at) {
+            return Err(SellingError::TimelockNotExpired);
+        }
+        self.transactions.sign_recover(&self.sk_refund);
+        self.state = TaprootBuyerState::Refunded;
+
+        Ok(self.transactions.tx_recover.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schnorr_signatures::{SchnorrSignature, SchnorrSignatureScheme};
+    use crate::selling_protocol::{SignatureGoods, PAYMENT_MARGIN};
+    use crate::signature_scheme::SignatureScheme;
+    use crate::taproot::{output_key, p2tr_script_pubkey, OutPoint, TxOut};
+
+    type G = SignatureGoods<Secp256k1, Sha256>;
+
+    const TIMEOUT: u16 = 144;
+
+    struct Parties {
+        seller: TaprootSeller<G>,
+        buyer: TaprootBuyer<G>,
+        terms: Terms<Secp256k1>,
+        sk_buyer: NonZeroScalar,
+    }
+
+    fn key_path_output(value: u64, sk: &NonZeroScalar) -> TxOut {
+        let (q, _) = output_key(&bip340::public_key(sk), None).unwrap();
+
+        TxOut::new(value, p2tr_script_pubkey(&q))
+    }
+
+    fn parties() -> Parties {
+        let (sk_notary, pk_notary) = NonZeroScalar::gen(&ProjectivePoint::GENERATOR);
+        let (sk_funding, pk_funding) = NonZeroScalar::gen(&ProjectivePoint::GENERATOR);
+        let (sk_buyer, pk_buyer) = NonZeroScalar::gen(&ProjectivePoint::GENERATOR);
+        let (sk_seller, pk_seller) = NonZeroScalar::gen(&ProjectivePoint::GENERATOR);
+        let (sk_refund, _) = NonZeroScalar::gen(&ProjectivePoint::GENERATOR);
+        let transactions = || {
+            SellingTransactions::new(
+                OutPoint::new([1; 32], 0),
+                key_path_output(100_000, &sk_funding),
+                &pk_buyer,
+                &pk_seller,
+                &bip340::public_key(&sk_refund),
+                TIMEOUT,
+                key_path_output(0, &sk_seller).script_pubkey,
+                key_path_output(0, &sk_refund).script_pubkey,
+                500,
+            )
+        };
+        let txs = transactions();
+        let msg = String::from("Lorem ipsum dolor sit amet");
+        let terms = Terms::<Secp256k1> {
+            pk_notary,
+            msg: msg.clone(),
+            pk_lock: pk_funding,
+            pk_buyer,
+            tx_lock: txs.tx_lock.message(),
+            tx_pay: txs.tx_pay.message(),
+            tx_recover: txs.tx_recover.message(),
+            timeout: TIMEOUT as u64,
+        };
+        let signature = SchnorrSignatureScheme::<Secp256k1, Sha256>::sign(&sk_notary, &msg);
+        let seller = TaprootSeller::<G>::new(terms.clone(), (), txs, sk_seller, signature).unwrap();
+        let buyer = TaprootBuyer::<G>::new(
+            terms.clone(),
+            (),
+            transactions(),
+            sk_funding,
+            sk_buyer,
+            sk_refund,
+        );
+
+        Parties {
+            seller,
+            buyer,
+            terms,
+            sk_buyer,
+        }
+    }
+
+    #[test]
+    fn taproot_selling() {
+        let Parties {
+            mut seller,
+            mut buyer,
+            terms,
+            ..
+        } = parties();
+        let mut clock = Clock::new();
+
+        let (offer, nonce_seller) = seller.offer().unwrap();
+        let nonce_buyer = buyer.receive_offer(offer, &nonce_seller).unwrap();
+        let lock = buyer.lock().unwrap();
+        assert_eq!(buyer.transactions().verify_lock(), Ok(()));
+        clock.advance(1);
+        buyer.confirm_lock(clock.now()).unwrap();
+        let partial_seller = seller
+            .receive_lock(&nonce_buyer, &lock, clock.now())
+            .unwrap();
+        let partial_buyer = buyer.receive_partial_signature(&partial_seller).unwrap();
+        clock.advance(TIMEOUT as u64 - PAYMENT_MARGIN - 1);
+        let payment = seller
+            .receive_partial_signature(&partial_buyer, &clock)
+            .unwrap();
+        assert!(matches!(seller.state(), TaprootSellerState::Paid));
+        assert_eq!(seller.transactions().verify_pay(), Ok(()));
+
+        let signature = buyer.receive_payment(&payment).unwrap();
+        assert!(matches!(buyer.state(), TaprootBuyerState::Done));
+        assert!(SchnorrSignatureScheme::<Secp256k1, Sha256>::verify(
+            &terms.pk_notary,
+            &terms.msg,
+            &signature
+        ));
+        assert_eq!(
+            buyer.refund(&clock).err(),
+            Some(SellingError::UnexpectedMessage)
+        );
+    }
+
+    // every deviation is rejected and kept as evidence, and the honest party can go on with the
+    // valid message
+    #[test]
+    fn taproot_selling_deviations() {
+        let Parties {
+            mut seller,
+            mut buyer,
+            terms,
+            sk_buyer,
+        } = parties();
+        let clock = Clock::new();
+
+        // an offer of another signature
+        let (offer, nonce_seller) = seller.offer().unwrap();
+        let forged = SchnorrSignature {
+            proof: Default::default(),
+            sig: Default::default(),
+        };
+        let (w, x) = NonZeroScalar::gen(&ProjectivePoint::GENERATOR);
+        let bad_offer = G::offer(&terms, &(), &forged, &w, &x);
+        assert_eq!(
+            buyer.receive_offer(bad_offer, &nonce_seller).err(),
+            Some(SellingError::InvalidOffer)
+        );
+        let nonce_buyer = buyer.receive_offer(offer, &nonce_seller).unwrap();
+        assert_eq!(buyer.lock().map(|_| ()).err(), None);
+        buyer.confirm_lock(0).unwrap();
+        let lock = TaprootLockMessage::from_byte_vector(
+            &buyer.transactions().tx_lock.inputs[0].witness[0],
+        )
+        .unwrap();
+
+        // tx_lock signed by another key
+        let mut bad_lock = TaprootLockMessage {
+            signature: lock.signature,
+        };
+        bad_lock.signature[63] ^= 1;
+        assert_eq!(
+            seller.receive_lock(&nonce_buyer, &bad_lock, 0).err(),
+            Some(SellingError::InvalidLock)
+        );
+        assert!(matches!(seller.state(), TaprootSellerState::Offered { .. }));
+        let partial_seller = seller.receive_lock(&nonce_buyer, &lock, 0).unwrap();
+
+        // partial signatures that do not verify, on both sides
+        let bad_partial_seller = PartialSignatureMessage {
+            partial_signature: partial_seller.partial_signature + Scalar::ONE,
+        };
+        assert_eq!(
+            buyer.receive_partial_signature(&bad_partial_seller).err(),
+            Some(SellingError::InvalidPartialSignature)
+        );
+        assert!(matches!(buyer.state(), TaprootBuyerState::Locked { .. }));
+        let partial_buyer = buyer.receive_partial_signature(&partial_seller).unwrap();
+        let bad_partial_buyer = PartialSignatureMessage {
+            partial_signature: *sk_buyer,
+        };
+        assert_eq!(
+            seller
+                .receive_partial_signature(&bad_partial_buyer, &clock)
+                .err(),
+            Some(SellingError::InvalidPartialSignature)
+        );
+        let payment = seller
+            .receive_partial_signature(&partial_buyer, &clock)
+            .unwrap();
+        assert_eq!(
+            seller.evidence(),
+            &[
+                Evidence::new(
+                    Party::Buyer,
+                    SellingError::InvalidLock,
+                    bad_lock.to_byte_vector()
+                ),
+                Evidence::new(
+                    Party::Buyer,
+                    SellingError::InvalidPartialSignature,
+                    bad_partial_buyer.to_byte_vector()
+                ),
+            ]
+        );
+
+        // a payment that does not spend tx_lock
+        let mut bad_payment = TaprootPaymentMessage {
+            signature: payment.signature,
+        };
+        bad_payment.signature[0] ^= 1;
+        assert_eq!(
+            buyer.receive_payment(&bad_payment).err(),
+            Some(SellingError::InvalidPayment)
+        );
+        assert_eq!(buyer.evidence().len(), 3);
+        assert_eq!(
+            buyer.evidence()[1].error,
+            SellingError::InvalidPartialSignature
+        );
+        assert!(buyer.receive_payment(&payment).is_ok());
+    }
+
+    // the seller reads tx_lock late: the timelock counts from the block that confirmed it, so the
+    // seller no longer completes tx_pay and the buyer takes its coins back
+    #[test]
+    fn taproot_selling_late_lock() {
+        let Parties {
+            mut seller,
+            mut buyer,
+            terms,
+            ..
+        } = parties();
+        let mut clock = Clock::new();
+
+        let (offer, nonce_seller) = seller.offer().unwrap();
+        let nonce_buyer = buyer.receive_offer(offer, &nonce_seller).unwrap();
+        let lock = buyer.lock().unwrap();
+        assert_eq!(
+            buyer.refund(&clock).err(),
+            Some(SellingError::UnexpectedMessage)
+        );
+        clock.advance(1);
+        let confirmed_at = clock.now();
+        buyer.confirm_lock(confirmed_at).unwrap();
+
+        clock.advance(terms.payment_deadline(confirmed_at) - confirmed_at);
+        let partial_seller = seller
+            .receive_lock(&nonce_buyer, &lock, confirmed_at)
+            .unwrap();
+        let partial_buyer = buyer.receive_partial_signature(&partial_seller).unwrap();
+        assert_eq!(
+            seller
+                .receive_partial_signature(&partial_buyer, &clock)
+                .err(),
+            Some(SellingError::TimelockExpired)
+        );
+        assert!(seller.evidence().is_empty());
+
+        clock.advance(PAYMENT_MARGIN - 1);
+        assert_eq!(
+            buyer.refund(&clock).err(),
+            Some(SellingError::TimelockNotExpired)
+        );
+        clock.advance(1);
+        let tx_recover = buyer.refund(&clock).unwrap();
+        assert!(matches!(buyer.state(), TaprootBuyerState::Refunded));
+        assert_eq!(buyer.transactions().verify_recover(), Ok(()));
+        assert!(crate::taproot::sequence_lock_satisfied(
+            &tx_recover,
+            0,
+            (clock.now() - confirmed_at) as u32
+        ));
+    }
+
+    #[test]
+    fn taproot_messages() {
+        let (_, nonce) = bip340::nonce();
+        let bytes = NonceMessage { nonce }.to_byte_vector();
+        assert!(NonceMessage::from_byte_vector(&bytes).unwrap().nonce == nonce);
+        assert!(NonceMessage::from_byte_vector(&bytes[1..]).is_none());
+        let partial_signature = Scalar::ONE;
+        let bytes = PartialSignatureMessage { partial_signature }.to_byte_vector();
+        assert_eq!(bytes.len(), 32);
+        assert!(
+            PartialSignatureMessage::from_byte_vector(&[bytes.clone(), vec![0]].concat()).is_none()
+        );
+        assert!(TaprootLockMessage::from_byte_vector(&[0; 63]).is_none());
+        assert_eq!(
+            TaprootPaymentMessage::from_byte_vector(&[7; 64])
+                .unwrap()
+                .signature,
+            [7; 64]
+        );
+    }
+}
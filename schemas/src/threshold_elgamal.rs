@@ -0,0 +1,414 @@
+use crate::dleq::{self, DLEQProof};
+use crate::nizk::NIZK;
+use digest::Digest;
+use elliptic_curve::ff::Field;
+use elliptic_curve::{CurveArithmetic, Group};
+use rand_core::OsRng;
+use std::marker::PhantomData;
+
+// Threshold ElGamal among n parties with indices 1, ..., n: the secret key s is shared with a
+// polynomial f of degree t - 1 as s = f(0), party j holds f(j), and any t parties can decrypt an
+// ElGamal cyphertext (a, b) = (g^y, pk^y * M) while fewer than t parties learn nothing about s.
+//
+// Distributed key generation (Feldman VSS by every party, so no dealer ever knows s):
+// 1. every party i calls deal, broadcasts the commitments g^a_ik to the coefficients of its
+//    random polynomial f_i and sends share(j) = f_i(j) privately to every party j,
+// 2. every party j checks the shares it received with verify_share and complains about the
+//    dealers whose share is wrong,
+// 3. with the commitments of all the dealers, everybody computes the public ThresholdKey: the
+//    public key g^s with s = sum_i f_i(0) and the verification keys g^f(j) of all the parties,
+//    and party j computes its KeyShare f(j) = sum_i f_i(j).
+// The last dealer to broadcast its commitments can bias the public key (see Gennaro et al.,
+// "Secure Distributed Key Generation for Discrete-Log Based Cryptosystems"), which does not
+// help to decrypt.
+//
+// Decryption: every party j publishes the decryption share a^f(j) with a DLEQ proof that it has
+// the discrete logarithm of its verification key, and t valid shares are combined with Lagrange
+// interpolation in the exponent: a^s = prod_j (a^f(j))^lambda_j, so M = b / a^s.
+pub struct ThresholdElGamal<C, H>
+where
+    C: CurveArithmetic,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+{
+    _curve_marker: PhantomData<C>,
+    _hash_marker: PhantomData<H>,
+}
+
+// The dealing of party i: the commitments are broadcast, and share(j) is sent to party j only.
+pub struct Dealing<C>
+where
+    C: CurveArithmetic,
+{
+    pub commitments: Vec<C::ProjectivePoint>,
+    coefficients: Vec<C::Scalar>,
+    n: usize,
+}
+
+impl<C> Dealing<C>
+where
+    C: CurveArithmetic,
+{
+    // f_i(j), None if there is no party j: f_i(0) is the contribution of the dealer to the secret
+    // key
+    pub fn share(&self, j: u64) -> Option<C::Scalar> {
+        is_party(j, self.n).then(|| self.evaluate(j))
+    }
+
+    // f_i(j), by Horner's method
+    fn evaluate(&self, j: u64) -> C::Scalar {
+        let j = C::Scalar::from(j);
+
+        self.coefficients
+            .iter()
+            .rev()
+            .fold(C::Scalar::ZERO, |acc, a| acc * j + a)
+    }
+}
+
+// the public key and the verification keys g^f(1), ..., g^f(n)
+pub struct ThresholdKey<C>
+where
+    C: CurveArithmetic,
+{
+    pub pk: C::ProjectivePoint,
+    pub verification_keys: Vec<C::ProjectivePoint>,
+    pub t: usize,
+}
+
+impl<C> ThresholdKey<C>
+where
+    C: CurveArithmetic,
+{
+    pub fn n(&self) -> usize {
+        self.verification_keys.len()
+    }
+
+    // verification key of party j, None if there is no such party
+    pub fn verification_key(&self, j: u64) -> Option<C::ProjectivePoint> {
+        let index = (j as usize).checked_sub(1)?;
+
+        self.verification_keys.get(index).copied()
+    }
+}
+
+pub struct KeyShare<C>
+where
+    C: CurveArithmetic,
+{
+    pub index: u64,
+    secret: C::Scalar,
+}
+
+pub struct DecryptionShare<C>
+where
+    C: CurveArithmetic,
+{
+    pub index: u64,
+    pub d: C::ProjectivePoint,
+    pub proof: dleq::Proof<C>,
+}
+
+impl<C, H> ThresholdElGamal<C, H>
+where
+    C: CurveArithmetic,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+{
+    // random polynomial of degree t - 1 of a dealer among n parties and the commitments to its
+    // coefficients
+    pub fn deal(t: usize, n: usize) -> Dealing<C> {
+        assert!(t > 0, "the threshold must be at least 1");
+        let g = C::ProjectivePoint::generator();
+        let coefficients: Vec<C::Scalar> = (0..t).map(|_| C::Scalar::random(&mut OsRng)).collect();
+        let commitments = coefficients.iter().map(|a| g * a).collect();
+
+        Dealing {
+            commitments,
+            coefficients,
+            n,
+        }
+    }
+
+    // g^f_i(j) == prod_k (g^a_ik)^(j^k), for a party j among n
+    pub fn verify_share(
+        commitments: &[C::ProjectivePoint],
+        n: usize,
+        j: u64,
+        share: &C::Scalar,
+    ) -> bool {
+        is_party(j, n)
+            && C::ProjectivePoint::generator() * share == Self::evaluate_commitments(commitments, j)
+    }
+
+    // the commitments of all the n dealers, which must all have t commitments. None if there is
+    // not one dealing per party or the commitments have different lengths.
+    pub fn threshold_key(
+        commitments: &[Vec<C::ProjectivePoint>],
+        n: usize,
+    ) -> Option<ThresholdKey<C>> {
+        let t = commitments.first()?.len();
+        if commitments.len() != n || t == 0 || t > n || commitments.iter().any(|c| c.len() != t) {
+            return None;
+        }
+        // commitments to the coefficients of f = sum_i f_i
+        let sum: Vec<C::ProjectivePoint> = (0..t)
+            .map(|k| commitments.iter().map(|c| c[k]).sum())
+            .collect();
+        let verification_keys = (1..=n as u64)
+            .map(|j| Self::evaluate_commitments(&sum, j))
+            .collect();
+
+        Some(ThresholdKey {
+            pk: sum[0],
+            verification_keys,
+            t,
+        })
+    }
+
+    // the shares received by party j from all the dealers, None if there is no party j or one of
+    // the shares is not consistent with the commitments of its dealer
+    pub fn key_share(
+        commitments: &[Vec<C::ProjectivePoint>],
+        j: u64,
+        shares: &[C::Scalar],
+    ) -> Option<KeyShare<C>> {
+        let n = commitments.len();
+        if n != shares.len()
+            || commitments
+                .iter()
+                .zip(shares)
+                .any(|(c, share)| !Self::verify_share(c, n, j, share))
+        {
+            return None;
+        }
+
+        Some(KeyShare {
+            index: j,
+            secret: shares.iter().sum(),
+        })
+    }
+
+    pub fn partial_decrypt(
+        key_share: &KeyShare<C>,
+        ct: &(C::ProjectivePoint, C::ProjectivePoint),
+    ) -> DecryptionShare<C> {
+        let g = C::ProjectivePoint::generator();
+        let (a, _) = *ct;
+        let d = a * key_share.secret;
+        let statement = dleq::Statement::new(g, g * key_share.secret, a, d);
+        let witness = dleq::Witness::new(key_share.secret);
+        let proof = DLEQProof::<C, H>::prove(&(), &statement, &witness);
+
+        DecryptionShare {
+            index: key_share.index,
+            d,
+            proof,
+        }
+    }
+
+    pub fn verify_decryption_share(
+        key: &ThresholdKey<C>,
+        ct: &(C::ProjectivePoint, C::ProjectivePoint),
+        share: &DecryptionShare<C>,
+    ) -> bool {
+        let Some(verification_key) = key.verification_key(share.index) else {
+            return false;
+        };
+        let (a, _) = *ct;
+        let statement = dleq::Statement::new(
+            C::ProjectivePoint::generator(),
+            verification_key,
+            a,
+            share.d,
+        );
+
+        DLEQProof::<C, H>::verify(&(), &statement, &share.proof)
+    }
+
+    // M = b / a^s from t decryption shares with different indices. It returns None if there are
+    // not enough shares with different indices or one of them is not valid.
+    pub fn combine(
+        key: &ThresholdKey<C>,
+        ct: &(C::ProjectivePoint, C::ProjectivePoint),
+        shares: &[DecryptionShare<C>],
+    ) -> Option<C::ProjectivePoint> {
+        let mut indices: Vec<u64> = Vec::new();
+        for share in shares {
+            if !Self::verify_decryption_share(key, ct, share) {
+                return None;
+            }
+            if !indices.contains(&share.index) {
+                indices.push(share.index);
+            }
+        }
+        if indices.len() < key.t {
+            return None;
+        }
+        indices.truncate(key.t);
+
+        let a_s: C::ProjectivePoint = indices
+            .iter()
+            .map(|j| {
+                let share = shares.iter().find(|share| share.index == *j)?;
+                Some(share.d * lagrange_coefficient::<C>(&indices, *j)?)
+            })
+            .sum::<Option<C::ProjectivePoint>>()?;
+        let (_, b) = *ct;
+
+        Some(b - a_s)
+    }
+
+    fn evaluate_commitments(commitments: &[C::ProjectivePoint], j: u64) -> C::ProjectivePoint {
+        let j = C::Scalar::from(j);
+
+        commitments
+            .iter()
+            .rev()
+            .fold(C::ProjectivePoint::identity(), |acc, a| acc * j + a)
+    }
+}
+
+// the parties have the indices 1, ..., n
+fn is_party(j: u64, n: usize) -> bool {
+    (1..=n as u64).contains(&j)
+}
+
+// lambda_j = prod_{m != j} m / (m - j), the coefficient of f(j) in the interpolation of f(0) from
+// the evaluations at indices. None if two indices are equal.
+fn lagrange_coefficient<C: CurveArithmetic>(indices: &[u64], j: u64) -> Option<C::Scalar> {
+    let j_scalar = C::Scalar::from(j);
+    let (num, den) = indices.iter().filter(|m| **m != j).fold(
+        (C::Scalar::ONE, C::Scalar::ONE),
+        |(num, den), m| {
+            let m = C::Scalar::from(*m);
+            (num * m, den * (m - j_scalar))
+        },
+    );
+    let inv: Option<C::Scalar> = den.invert().into();
+
+    Some(num * inv?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elgamal::ElGamal;
+    use crate::public_key_encryption_scheme::PublicKeyEncryptionScheme;
+    use elliptic_curve::scalar::NonZeroScalar;
+    use k256::Secp256k1;
+    use sha2::Sha256;
+
+    type C = Secp256k1;
+    type Point = <C as CurveArithmetic>::ProjectivePoint;
+    type Scalar = <C as CurveArithmetic>::Scalar;
+    type TElGamal = ThresholdElGamal<C, Sha256>;
+
+    // distributed key generation of a t-of-n key, where all the parties are honest
+    fn dkg(t: usize, n: usize) -> (ThresholdKey<C>, Vec<KeyShare<C>>) {
+        let dealings: Vec<Dealing<C>> = (0..n).map(|_| TElGamal::deal(t, n)).collect();
+        let commitments: Vec<Vec<Point>> = dealings.iter().map(|d| d.commitments.clone()).collect();
+        let key = TElGamal::threshold_key(&commitments, n).unwrap();
+        let key_shares = (1..=n as u64)
+            .map(|j| {
+                let shares: Vec<Scalar> = dealings.iter().map(|d| d.share(j).unwrap()).collect();
+                TElGamal::key_share(&commitments, j, &shares).unwrap()
+            })
+            .collect();
+
+        (key, key_shares)
+    }
+
+    #[test]
+    fn threshold_decryption() {
+        let (key, key_shares) = dkg(3, 5);
+        let msg = Point::GENERATOR * Scalar::random(&mut OsRng);
+        let (ct, _) = ElGamal::<C>::enc(&key.pk, &msg);
+        let shares: Vec<DecryptionShare<C>> = key_shares
+            .iter()
+            .map(|key_share| TElGamal::partial_decrypt(key_share, &ct))
+            .collect();
+        assert!(shares
+            .iter()
+            .all(|share| TElGamal::verify_decryption_share(&key, &ct, share)));
+
+        // any 3 of the 5 shares decrypt
+        assert_eq!(TElGamal::combine(&key, &ct, &shares[..3]).unwrap(), msg);
+        assert_eq!(TElGamal::combine(&key, &ct, &shares[2..]).unwrap(), msg);
+        assert_eq!(TElGamal::combine(&key, &ct, &shares).unwrap(), msg);
+        // 2 shares, or 3 shares of which 2 are equal, are not enough
+        assert!(TElGamal::combine(&key, &ct, &shares[..2]).is_none());
+        let repeated = [
+            TElGamal::partial_decrypt(&key_shares[0], &ct),
+            TElGamal::partial_decrypt(&key_shares[0], &ct),
+            TElGamal::partial_decrypt(&key_shares[1], &ct),
+        ];
+        assert!(TElGamal::combine(&key, &ct, &repeated).is_none());
+
+        // the key is the one of the secret interpolated from the key shares
+        let indices = [1, 2, 3];
+        let s: Scalar = key_shares[..3]
+            .iter()
+            .map(|k| k.secret * lagrange_coefficient::<C>(&indices, k.index).unwrap())
+            .sum();
+        let sk = NonZeroScalar::<C>::new(s).unwrap();
+        assert_eq!(ElGamal::<C>::dec(&sk, &ct).unwrap(), msg);
+    }
+
+    #[test]
+    fn threshold_key_generation_with_a_cheating_dealer() {
+        let (t, n) = (2, 3);
+        let dealings: Vec<Dealing<C>> = (0..n).map(|_| TElGamal::deal(t, n)).collect();
+        let commitments: Vec<Vec<Point>> = dealings.iter().map(|d| d.commitments.clone()).collect();
+
+        // dealer 0 sends a wrong share to party 1
+        let mut shares: Vec<Scalar> = dealings.iter().map(|d| d.share(1).unwrap()).collect();
+        assert!(TElGamal::verify_share(&commitments[0], n, 1, &shares[0]));
+        shares[0] += Scalar::ONE;
+        assert!(!TElGamal::verify_share(&commitments[0], n, 1, &shares[0]));
+        assert!(TElGamal::key_share(&commitments, 1, &shares).is_none());
+
+        // the commitments of the dealers must all have t elements
+        let mut wrong_commitments = commitments.clone();
+        wrong_commitments[2].pop();
+        assert!(TElGamal::threshold_key(&wrong_commitments, n).is_none());
+        assert!(TElGamal::threshold_key(&commitments, 1).is_none());
+        // and there is one dealing per party
+        assert!(TElGamal::threshold_key(&commitments[..2], n).is_none());
+        assert!(TElGamal::threshold_key(&commitments, 4).is_none());
+    }
+
+    #[test]
+    fn threshold_key_generation_of_parties_that_do_not_exist() {
+        let (t, n) = (2, 3);
+        let dealings: Vec<Dealing<C>> = (0..n).map(|_| TElGamal::deal(t, n)).collect();
+        let commitments: Vec<Vec<Point>> = dealings.iter().map(|d| d.commitments.clone()).collect();
+
+        // f_i(0) is the contribution of dealer i to the secret key, and there is no party n + 1
+        for j in [0, n as u64 + 1] {
+            assert!(dealings[0].share(j).is_none());
+            let shares: Vec<Scalar> = dealings.iter().map(|d| d.evaluate(j)).collect();
+            assert!(!TElGamal::verify_share(&commitments[0], n, j, &shares[0]));
+            assert!(TElGamal::key_share(&commitments, j, &shares).is_none());
+        }
+    }
+
+    #[test]
+    fn forged_decryption_share() {
+        let (key, key_shares) = dkg(2, 3);
+        let msg = Point::GENERATOR * Scalar::random(&mut OsRng);
+        let (ct, _) = ElGamal::<C>::enc(&key.pk, &msg);
+        let mut shares: Vec<DecryptionShare<C>> = key_shares
+            .iter()
+            .map(|key_share| TElGamal::partial_decrypt(key_share, &ct))
+            .collect();
+
+        // party 1 publishes a wrong decryption share with the proof of the right one
+        shares[0].d += Point::GENERATOR;
+        assert!(!TElGamal::verify_decryption_share(&key, &ct, &shares[0]));
+        assert!(TElGamal::combine(&key, &ct, &shares).is_none());
+        assert_eq!(TElGamal::combine(&key, &ct, &shares[1..]).unwrap(), msg);
+
+        // a share of a party that does not exist
+        shares[1].index = 4;
+        assert!(!TElGamal::verify_decryption_share(&key, &ct, &shares[1]));
+    }
+}
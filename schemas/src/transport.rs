@@ -0,0 +1,305 @@
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+#[cfg(unix)]
+use std::path::Path;
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+// How the parties of a protocol exchange messages when they do not share an address space. A
+// message is sent as one frame: its length as a 4-byte big-endian integer followed by its bytes,
+// so that the receiver reads exactly one message per call whatever the stream splits it into.
+//
+// The transports count the bytes that go through them, frame headers included, so that the
+// communication costs are the ones on the wire.
+
+// Frames longer than this are rejected before reading them, so that a peer cannot make the
+// receiver allocate an arbitrary amount of memory
+pub const MAX_FRAME_LENGTH: usize = 1 << 24;
+
+pub const FRAME_HEADER_LENGTH: usize = 4;
+
+pub trait Transport {
+    fn send(&mut self, message: &[u8]) -> io::Result<()>;
+    // Blocks until a whole message is received
+    fn receive(&mut self) -> io::Result<Vec<u8>>;
+    fn bytes_sent(&self) -> u64;
+    fn bytes_received(&self) -> u64;
+}
+
+fn frame_length(message: &[u8]) -> io::Result<u32> {
+    if message.len() > MAX_FRAME_LENGTH {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "the message exceeds the maximum frame length",
+        ));
+    }
+
+    Ok(message.len() as u32)
+}
+
+pub fn write_frame<W: Write>(writer: &mut W, message: &[u8]) -> io::Result<()> {
+    writer.write_all(&frame_length(message)?.to_be_bytes())?;
+    writer.write_all(message)?;
+
+    writer.flush()
+}
+
+pub fn read_frame<R: Read>(reader: &mut R) -> io::Result<Vec<u8>> {
+    let mut header = [0u8; FRAME_HEADER_LENGTH];
+    reader.read_exact(&mut header)?;
+    let length = u32::from_be_bytes(header) as usize;
+    if length > MAX_FRAME_LENGTH {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "the frame exceeds the maximum frame length",
+        ));
+    }
+    let mut message = vec![0u8; length];
+    reader.read_exact(&mut message)?;
+
+    Ok(message)
+}
+
+// A transport over a byte stream, such as a TCP connection or a Unix socket
+pub struct StreamTransport<S>
+where
+    S: Read + Write,
+{
+    stream: S,
+    sent: u64,
+    received: u64,
+}
+
+impl<S> StreamTransport<S>
+where
+    S: Read + Write,
+{
+    pub fn new(stream: S) -> Self {
+        Self {
+            stream,
+            sent: 0,
+            received: 0,
+        }
+    }
+
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+impl<S> Transport for StreamTransport<S>
+where
+    S: Read + Write,
+{
+    fn send(&mut self, message: &[u8]) -> io::Result<()> {
+        write_frame(&mut self.stream, message)?;
+        self.sent += (FRAME_HEADER_LENGTH + message.len()) as u64;
+
+        Ok(())
+    }
+
+    fn receive(&mut self) -> io::Result<Vec<u8>> {
+        let message = read_frame(&mut self.stream)?;
+        self.received += (FRAME_HEADER_LENGTH + message.len()) as u64;
+
+        Ok(message)
+    }
+
+    fn bytes_sent(&self) -> u64 {
+        self.sent
+    }
+
+    fn bytes_received(&self) -> u64 {
+        self.received
+    }
+}
+
+pub type TcpTransport = StreamTransport<TcpStream>;
+
+impl StreamTransport<TcpStream> {
+    pub fn connect<A: ToSocketAddrs>(address: A) -> io::Result<Self> {
+        let stream = TcpStream::connect(address)?;
+        // the messages are small and each one waits for an answer
+        stream.set_nodelay(true)?;
+
+        Ok(Self::new(stream))
+    }
+
+    // Waits for one peer to connect to the listener
+    pub fn accept(listener: &TcpListener) -> io::Result<Self> {
+        let (stream, _) = listener.accept()?;
+        stream.set_nodelay(true)?;
+
+        Ok(Self::new(stream))
+    }
+}
+
+#[cfg(unix)]
+pub type UnixTransport = StreamTransport<UnixStream>;
+
+#[cfg(unix)]
+impl StreamTransport<UnixStream> {
+    pub fn connect<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(Self::new(UnixStream::connect(path)?))
+    }
+
+    pub fn accept(listener: &UnixListener) -> io::Result<Self> {
+        let (stream, _) = listener.accept()?;
+
+        Ok(Self::new(stream))
+    }
+}
+
+// A transport between two parties of the same process, for instance in two threads. Messages are
+// never split, but the frame headers are counted as if they were sent on a stream.
+pub struct MemoryTransport {
+    sender: Sender<Vec<u8>>,
+    receiver: Receiver<Vec<u8>>,
+    sent: u64,
+    received: u64,
+}
+
+impl MemoryTransport {
+    // The two ends of a connection
+    pub fn pair() -> (Self, Self) {
+        let (sender_a, receiver_b) = channel();
+        let (sender_b, receiver_a) = channel();
+        let a = Self {
+            sender: sender_a,
+            receiver: receiver_a,
+            sent: 0,
+            received: 0,
+        };
+        let b = Self {
+            sender: sender_b,
+            receiver: receiver_b,
+            sent: 0,
+            received: 0,
+        };
+
+        (a, b)
+    }
+}
+
+impl Transport for MemoryTransport {
+    fn send(&mut self, message: &[u8]) -> io::Result<()> {
+        frame_length(message)?;
+        self.sender
+            .send(message.to_vec())
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "the peer is disconnected"))?;
+        self.sent += (FRAME_HEADER_LENGTH + message.len()) as u64;
+
+        Ok(())
+    }
+
+    fn receive(&mut self) -> io::Result<Vec<u8>> {
+        let message = self.receiver.recv().map_err(|_| {
+            io::Error::new(io::ErrorKind::UnexpectedEof, "the peer is disconnected")
+        })?;
+        self.received += (FRAME_HEADER_LENGTH + message.len()) as u64;
+
+        Ok(message)
+    }
+
+    fn bytes_sent(&self) -> u64 {
+        self.sent
+    }
+
+    fn bytes_received(&self) -> u64 {
+        self.received
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use std::thread;
+
+    // a ping-pong of messages of several sizes, the empty one included
+    fn exchange<T: Transport + Send + 'static>(mut a: T, mut b: T) {
+        let messages: Vec<Vec<u8>> = vec![vec![], vec![1], vec![2; 1000], vec![3; 100_000]];
+        let expected = messages.clone();
+        let echo = thread::spawn(move || {
+            for _ in 0..messages.len() {
+                let message = b.receive().unwrap();
+                b.send(&message).unwrap();
+            }
+            b
+        });
+        for message in &expected {
+            a.send(message).unwrap();
+            assert_eq!(&a.receive().unwrap(), message);
+        }
+        let b = echo.join().unwrap();
+
+        let total: u64 = expected
+            .iter()
+            .map(|m| (FRAME_HEADER_LENGTH + m.len()) as u64)
+            .sum();
+        assert_eq!(a.bytes_sent(), total);
+        assert_eq!(a.bytes_received(), total);
+        assert_eq!(b.bytes_sent(), total);
+        assert_eq!(b.bytes_received(), total);
+    }
+
+    #[test]
+    fn framing() {
+        let mut bytes = vec![];
+        write_frame(&mut bytes, b"hello").unwrap();
+        write_frame(&mut bytes, b"").unwrap();
+        assert_eq!(bytes[..9], [0, 0, 0, 5, b'h', b'e', b'l', b'l', b'o']);
+        let mut reader = Cursor::new(bytes.clone());
+        assert_eq!(read_frame(&mut reader).unwrap(), b"hello");
+        assert_eq!(read_frame(&mut reader).unwrap(), b"");
+        // end of the stream
+        let error = read_frame(&mut reader).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+        // truncated frame
+        let error = read_frame(&mut Cursor::new(&bytes[..7])).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+        // frame too long
+        let header = ((MAX_FRAME_LENGTH + 1) as u32).to_be_bytes();
+        let error = read_frame(&mut Cursor::new(header)).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        let error = write_frame(&mut vec![], &vec![0; MAX_FRAME_LENGTH + 1]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn memory_transport() {
+        let (a, b) = MemoryTransport::pair();
+        exchange(a, b);
+
+        let (mut a, b) = MemoryTransport::pair();
+        drop(b);
+        assert!(a.send(b"hello").is_err());
+        assert_eq!(
+            a.receive().unwrap_err().kind(),
+            io::ErrorKind::UnexpectedEof
+        );
+    }
+
+    #[test]
+    fn tcp_transport() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let client = thread::spawn(move || TcpTransport::connect(address).unwrap());
+        let server = TcpTransport::accept(&listener).unwrap();
+        exchange(client.join().unwrap(), server);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unix_transport() {
+        let path = std::env::temp_dir().join(format!("schemas_transport_{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+        let client_path = path.clone();
+        let client = thread::spawn(move || UnixTransport::connect(client_path).unwrap());
+        let server = UnixTransport::accept(&listener).unwrap();
+        exchange(client.join().unwrap(), server);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
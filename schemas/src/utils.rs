@@ -1,4 +1,6 @@
-use elliptic_curve::point::AffineCoordinates;
+use elliptic_curve::ff::PrimeField;
+use elliptic_curve::point::{AffineCoordinates, DecompressPoint};
+use elliptic_curve::subtle::Choice;
 use elliptic_curve::FieldBytes;
 use elliptic_curve::{AffinePoint, CurveArithmetic};
 
@@ -46,3 +48,74 @@ pub fn scalar_to_byte_vector<C: CurveArithmetic>(scalar: &C::Scalar) -> Vec<u8>
 
     v
 }
+
+// inverse of point_to_byte_vector: the x coordinate followed by the parity of y
+pub fn point_from_byte_vector<C>(bytes: &[u8]) -> Option<C::ProjectivePoint>
+where
+    C: CurveArithmetic,
+    C::AffinePoint: DecompressPoint<C>,
+{
+    let (parity, x) = bytes.split_last()?;
+    if x.len() != FieldBytes::<C>::default().len() || *parity > 1 {
+        return None;
+    }
+    let x = FieldBytes::<C>::clone_from_slice(x);
+    let affine: Option<AffinePoint<C>> =
+        AffinePoint::<C>::decompress(&x, Choice::from(*parity)).into();
+
+    affine.map(|affine| affine.into())
+}
+
+// inverse of scalar_to_byte_vector
+pub fn scalar_from_byte_vector<C: CurveArithmetic>(bytes: &[u8]) -> Option<C::Scalar> {
+    if bytes.len() != FieldBytes::<C>::default().len() {
+        return None;
+    }
+
+    C::Scalar::from_repr(FieldBytes::<C>::clone_from_slice(bytes)).into()
+}
+
+// Reads the concatenation of encoded points and scalars produced by the to_byte_vector
+// functions, element by element.
+pub struct ByteReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> ByteReader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+
+    pub fn read_bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        if self.bytes.len() < len {
+            return None;
+        }
+        let (head, tail) = self.bytes.split_at(len);
+        self.bytes = tail;
+
+        Some(head)
+    }
+
+    pub fn read_point<C>(&mut self) -> Option<C::ProjectivePoint>
+    where
+        C: CurveArithmetic,
+        C::AffinePoint: DecompressPoint<C>,
+    {
+        let len = FieldBytes::<C>::default().len() + 1;
+        point_from_byte_vector::<C>(self.read_bytes(len)?)
+    }
+
+    pub fn read_scalar<C: CurveArithmetic>(&mut self) -> Option<C::Scalar> {
+        let len = FieldBytes::<C>::default().len();
+        scalar_from_byte_vector::<C>(self.read_bytes(len)?)
+    }
+
+    // The bytes not read yet, for elements with their own decoder
+    pub fn read_rest(&mut self) -> &'a [u8] {
+        std::mem::take(&mut self.bytes)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+}
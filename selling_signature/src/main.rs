@@ -0,0 +1,650 @@
+mod other_goods;
+mod parties;
+
+use bp256::r1::BrainpoolP256r1;
+use elliptic_curve::group::cofactor::CofactorGroup;
+use elliptic_curve::hash2curve::GroupDigest;
+use elliptic_curve::point::DecompressPoint;
+use elliptic_curve::CurveArithmetic;
+use k256::Secp256k1;
+use p256::NistP256;
+use p384::NistP384;
+use schemas::ledger::Transaction;
+use schemas::protocol_message::{Ciphersuite, CurveId, HashId};
+use schemas::schnorr_signatures::{SchnorrSignature, SchnorrSignatureScheme};
+use schemas::selling_protocol::{BatchSignatureGoods, Goods, ServiceGoods, SignatureGoods, Terms};
+use schemas::signature_scheme::SignatureScheme;
+#[cfg(unix)]
+use schemas::transport::UnixTransport;
+use schemas::transport::{MemoryTransport, TcpTransport, Transport};
+use sha2::{Digest, Sha256, Sha384};
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io::{self, BufWriter, Write};
+use std::net::TcpListener;
+#[cfg(unix)]
+use std::os::unix::net::UnixListener;
+use std::process::exit;
+use std::thread;
+use std::time::Duration;
+
+// timelock of tx_recover, in blocks of the ledger
+const TIMEOUT: u64 = 144;
+// coins locked by the buyer
+const AMOUNT: u64 = 100_000;
+// fee of each Bitcoin transaction, in satoshis
+const FEE: u64 = 500;
+
+// message whose signature is sold
+const MSG: &str = "Lorem ipsum dolor sit amet, consectetur adipiscing elit, sed do eiusmod tempor incididunt ut labore et dolore magna aliqua";
+// seed of the key pair of the notary, which the buyer knows out of band
+const NOTARY: &str = "selling_signature notary";
+// number of signatures sold by the batch variant, on MSG followed by their index
+const BATCH: usize = 10;
+
+type Sig<C, H> = SchnorrSignatureScheme<C, H>;
+type Tx<C> = Transaction<C, SchnorrSignature<C>>;
+
+const USAGE: &str = "usage: selling_signature [--curve secp256k1|nistp256|brainpoolp256r1|nistp384] [--variant signature|service|batch|committed_secret|bls_signature|ecdsa_signature] [--transactions ledger|taproot] [--role seller|buyer] [--transport memory|tcp|unix] [--address <ADDRESS>]";
+const TCP_ADDRESS: &str = "127.0.0.1:7000";
+const UNIX_ADDRESS: &str = "/tmp/selling_signature.sock";
+
+#[derive(Clone, Copy)]
+enum Curve {
+    Secp256k1,
+    NistP256,
+    BrainpoolP256r1,
+    NistP384,
+}
+
+impl Curve {
+    fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "secp256k1" => Some(Curve::Secp256k1),
+            "nistp256" | "p256" => Some(Curve::NistP256),
+            "brainpoolp256r1" | "bp256" => Some(Curve::BrainpoolP256r1),
+            "nistp384" | "p384" => Some(Curve::NistP384),
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Curve::Secp256k1 => "Secp256k1",
+            Curve::NistP256 => "NistP256",
+            Curve::BrainpoolP256r1 => "BrainpoolP256r1",
+            Curve::NistP384 => "NistP384",
+        }
+    }
+
+    // The curve and the hash that the parties announce in the handshake
+    fn ciphersuite(&self) -> Ciphersuite {
+        match self {
+            Curve::Secp256k1 => Ciphersuite::new(CurveId::Secp256k1, HashId::Sha256),
+            Curve::NistP256 => Ciphersuite::new(CurveId::NistP256, HashId::Sha256),
+            Curve::BrainpoolP256r1 => Ciphersuite::new(CurveId::BrainpoolP256r1, HashId::Sha256),
+            Curve::NistP384 => Ciphersuite::new(CurveId::NistP384, HashId::Sha384),
+        }
+    }
+}
+
+// The signature variant sells the signature of the notary; the service variant sells the witness
+// of a proof of representation of it; the batch variant sells BATCH signatures for one payment.
+// The committed_secret, bls_signature and ecdsa_signature variants sell the goods of other_goods,
+// with both parties inline.
+#[derive(Clone, Copy)]
+enum Variant {
+    Signature,
+    Service,
+    Batch,
+    CommittedSecret,
+    BlsSignature,
+    EcdsaSignature,
+}
+
+impl Variant {
+    fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "signature" => Some(Variant::Signature),
+            "service" => Some(Variant::Service),
+            "batch" => Some(Variant::Batch),
+            "committed_secret" => Some(Variant::CommittedSecret),
+            "bls_signature" => Some(Variant::BlsSignature),
+            "ecdsa_signature" => Some(Variant::EcdsaSignature),
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Variant::Signature => "signature",
+            Variant::Service => "service",
+            Variant::Batch => "batch",
+            Variant::CommittedSecret => "committed_secret",
+            Variant::BlsSignature => "bls_signature",
+            Variant::EcdsaSignature => "ecdsa_signature",
+        }
+    }
+
+    fn times_file_prefix(&self) -> &'static str {
+        match self {
+            Variant::Signature => "selling_signature_",
+            Variant::Service => "selling_signature_service_",
+            Variant::Batch => "selling_signature_batch_",
+            Variant::CommittedSecret => "selling_committed_secret_",
+            Variant::BlsSignature => "selling_bls_signature_",
+            Variant::EcdsaSignature => "selling_ecdsa_signature_",
+        }
+    }
+
+    // the variants whose goods are not sold through schemas::selling_protocol
+    fn inline_only(&self) -> bool {
+        matches!(
+            self,
+            Variant::CommittedSecret | Variant::BlsSignature | Variant::EcdsaSignature
+        )
+    }
+}
+
+// The transactions are published either on the in-process ledger or, for Secp256k1 only, as
+// Bitcoin Taproot transactions checked by the script interpreter of schemas::taproot
+#[derive(Clone, Copy)]
+enum Transactions {
+    Ledger,
+    Taproot,
+}
+
+impl Transactions {
+    fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "ledger" => Some(Transactions::Ledger),
+            "taproot" => Some(Transactions::Taproot),
+            _ => None,
+        }
+    }
+
+    fn times_file_prefix(&self) -> &'static str {
+        match self {
+            Transactions::Ledger => "",
+            Transactions::Taproot => "taproot_",
+        }
+    }
+}
+
+// Without --role and --transport, both parties run inline: in two threads over a MemoryTransport,
+// with one times file for both. --transport memory runs them the same way with a times file per
+// party, and --role runs one of them in this process: the seller listens on the address and the
+// buyer connects to it.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Role {
+    Seller,
+    Buyer,
+}
+
+impl Role {
+    fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "seller" => Some(Role::Seller),
+            "buyer" => Some(Role::Buyer),
+            _ => None,
+        }
+    }
+
+    fn times_file_prefix(&self) -> &'static str {
+        match self {
+            Role::Seller => "seller_times_",
+            Role::Buyer => "buyer_times_",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum TransportKind {
+    Memory,
+    Tcp,
+    Unix,
+}
+
+impl TransportKind {
+    fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "memory" => Some(TransportKind::Memory),
+            "tcp" => Some(TransportKind::Tcp),
+            "unix" => Some(TransportKind::Unix),
+            _ => None,
+        }
+    }
+}
+
+struct Args {
+    curve: Curve,
+    variant: Variant,
+    transactions: Transactions,
+    role: Option<Role>,
+    transport: Option<TransportKind>,
+    address: Option<String>,
+}
+
+fn parse_args() -> Option<Args> {
+    let mut parsed = Args {
+        curve: Curve::Secp256k1,
+        variant: Variant::Signature,
+        transactions: Transactions::Ledger,
+        role: None,
+        transport: None,
+        address: None,
+    };
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--curve" => parsed.curve = Curve::parse(&args.next()?)?,
+            "--variant" => parsed.variant = Variant::parse(&args.next()?)?,
+            "--transactions" => parsed.transactions = Transactions::parse(&args.next()?)?,
+            "--role" => parsed.role = Some(Role::parse(&args.next()?)?),
+            "--transport" => parsed.transport = Some(TransportKind::parse(&args.next()?)?),
+            "--address" => parsed.address = Some(args.next()?),
+            _ => return None,
+        }
+    }
+
+    Some(parsed)
+}
+
+// The messages of the batch variant
+fn batch_messages() -> Vec<String> {
+    (0..BATCH).map(|i| format!("{} {}", MSG, i)).collect()
+}
+
+// The parameters both parties use for the goods, and how the notary signs their secret
+trait Notarize<C, H>: Goods<C, H>
+where
+    C: CurveArithmetic,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+{
+    fn params() -> Self::Params;
+
+    fn notarize(
+        sk_notary: &elliptic_curve::NonZeroScalar<C>,
+        terms: &Terms<C>,
+        params: &Self::Params,
+    ) -> Self::Secret;
+}
+
+impl<C, H> Notarize<C, H> for SignatureGoods<C, H>
+where
+    C: CurveArithmetic,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+{
+    fn params() {}
+
+    fn notarize(
+        sk_notary: &elliptic_curve::NonZeroScalar<C>,
+        terms: &Terms<C>,
+        _params: &(),
+    ) -> Self::Secret {
+        Sig::<C, H>::sign(sk_notary, &terms.msg)
+    }
+}
+
+impl<C, H> Notarize<C, H> for ServiceGoods<C, H>
+where
+    C: GroupDigest,
+    C::ProjectivePoint: CofactorGroup,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+{
+    fn params() {}
+
+    fn notarize(
+        sk_notary: &elliptic_curve::NonZeroScalar<C>,
+        terms: &Terms<C>,
+        _params: &(),
+    ) -> Self::Secret {
+        Sig::<C, H>::sign(sk_notary, &terms.msg)
+    }
+}
+
+impl<C, H> Notarize<C, H> for BatchSignatureGoods<C, H>
+where
+    C: CurveArithmetic,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+{
+    fn params() -> Vec<String> {
+        batch_messages()
+    }
+
+    fn notarize(
+        sk_notary: &elliptic_curve::NonZeroScalar<C>,
+        _terms: &Terms<C>,
+        msgs: &Vec<String>,
+    ) -> Self::Secret {
+        msgs.iter()
+            .map(|msg| Sig::<C, H>::sign(sk_notary, msg))
+            .collect()
+    }
+}
+
+type InlineFlow = fn(&mut BufWriter<File>) -> parties::Result<()>;
+type PartyFlow = fn(&mut dyn Transport, Ciphersuite) -> parties::Result<Vec<Duration>>;
+
+// The flows of the protocol for a curve, a hash and goods. The goods of other_goods are not sold
+// through schemas::selling_protocol and only have an inline flow, which writes its times itself.
+enum Flows {
+    Inline(InlineFlow),
+    Parties { seller: PartyFlow, buyer: PartyFlow },
+}
+
+fn flows<C, H, G>() -> Flows
+where
+    C: CurveArithmetic,
+    C::AffinePoint: DecompressPoint<C>,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+    G: Notarize<C, H>,
+{
+    Flows::Parties {
+        seller: parties::seller::<C, H, G>,
+        buyer: parties::buyer::<C, H, G>,
+    }
+}
+
+// The flows of the goods G on the Taproot transactions
+fn taproot_flows<G>() -> Flows
+where
+    G: Notarize<Secp256k1, Sha256>,
+{
+    Flows::Parties {
+        seller: parties::taproot_seller::<G>,
+        buyer: parties::taproot_buyer::<G>,
+    }
+}
+
+// The flows of every variant, for the curves that implement hash_to_curve
+fn variant_flows<C, H>(variant: Variant) -> Flows
+where
+    C: GroupDigest,
+    C::ProjectivePoint: CofactorGroup,
+    C::AffinePoint: DecompressPoint<C>,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+{
+    match variant {
+        Variant::Signature => flows::<C, H, SignatureGoods<C, H>>(),
+        Variant::Service => flows::<C, H, ServiceGoods<C, H>>(),
+        Variant::Batch => flows::<C, H, BatchSignatureGoods<C, H>>(),
+        Variant::CommittedSecret => Flows::Inline(other_goods::selling_committed_secret::<C, H>),
+        Variant::BlsSignature => Flows::Inline(other_goods::selling_bls_signature::<C, H>),
+        Variant::EcdsaSignature => Flows::Inline(other_goods::selling_ecdsa_signature::<C, H>),
+    }
+}
+
+fn write_times(time_file: &mut BufWriter<File>, durations: &[Duration]) {
+    let data = durations
+        .iter()
+        .map(|d| d.as_nanos().to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+    time_file
+        .write_all((data + "\n").as_bytes())
+        .expect("Unable to write to file");
+}
+
+// Both parties in this process, the seller in another thread, over a MemoryTransport. It returns
+// the times of their steps in the order of the protocol, without the times of the whole runs.
+fn inline(
+    seller: PartyFlow,
+    buyer: PartyFlow,
+    ciphersuite: Ciphersuite,
+) -> parties::Result<Vec<Duration>> {
+    let (mut seller_end, mut buyer_end) = MemoryTransport::pair();
+    let seller = thread::spawn(move || {
+        seller(&mut seller_end, ciphersuite).map_err(|error| format!("seller: {}", error))
+    });
+    let buyer_times = buyer(&mut buyer_end, ciphersuite);
+    // a seller that waits for the buyer stops once the transport is closed
+    drop(buyer_end);
+    let seller_times = seller.join().expect("the seller panicked");
+    let buyer_times = buyer_times.map_err(|error| format!("buyer: {}", error))?;
+    let seller_times = seller_times?;
+
+    let steps = seller_times.len().min(buyer_times.len()) - 1;
+    Ok((0..steps)
+        .flat_map(|i| [seller_times[i], buyer_times[i]])
+        .collect())
+}
+
+// Runs the flow once in debug builds and 999 times in release builds, and exits if it fails
+fn repeat(name: &str, mut flow: impl FnMut() -> parties::Result<()>) {
+    let mut run_once = || {
+        if let Err(error) = flow() {
+            eprintln!("{}: {}", name, error);
+            exit(1);
+        }
+    };
+    #[cfg(debug_assertions)]
+    {
+        run_once();
+    }
+    #[cfg(not(debug_assertions))]
+    {
+        for _ in 1..1000 {
+            run_once();
+        }
+    }
+}
+
+fn run_party(
+    role: Role,
+    flow: PartyFlow,
+    ciphersuite: Ciphersuite,
+    transport: &mut dyn Transport,
+    time_file: &mut BufWriter<File>,
+) {
+    let name = match role {
+        Role::Seller => "seller",
+        Role::Buyer => "buyer",
+    };
+    repeat(name, || {
+        let times = flow(transport, ciphersuite)?;
+        write_times(time_file, &times);
+        Ok(())
+    });
+}
+
+// The seller waits for the buyer to connect, and the buyer retries until the seller listens
+fn connect(role: Role, kind: TransportKind, address: &str) -> io::Result<Box<dyn Transport>> {
+    let retry = |connect: &dyn Fn() -> io::Result<Box<dyn Transport>>| {
+        let mut attempts = 0;
+        loop {
+            match connect() {
+                Err(_) if attempts < 50 => {
+                    attempts += 1;
+                    thread::sleep(Duration::from_millis(100));
+                }
+                result => return result,
+            }
+        }
+    };
+
+    match (kind, role) {
+        (TransportKind::Tcp, Role::Seller) => {
+            let listener = TcpListener::bind(address)?;
+            Ok(Box::new(TcpTransport::accept(&listener)?))
+        }
+        (TransportKind::Tcp, Role::Buyer) => {
+            retry(&|| Ok(Box::new(TcpTransport::connect(address)?)))
+        }
+        #[cfg(unix)]
+        (TransportKind::Unix, Role::Seller) => {
+            // a socket left by a previous run
+            let _ = std::fs::remove_file(address);
+            let listener = UnixListener::bind(address)?;
+            Ok(Box::new(UnixTransport::accept(&listener)?))
+        }
+        #[cfg(unix)]
+        (TransportKind::Unix, Role::Buyer) => {
+            retry(&|| Ok(Box::new(UnixTransport::connect(address)?)))
+        }
+        #[cfg(not(unix))]
+        (TransportKind::Unix, _) => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "unix sockets are not available on this platform",
+        )),
+        (TransportKind::Memory, _) => unreachable!(),
+    }
+}
+
+fn open_times_file(name: String) -> BufWriter<File> {
+    let time_file = OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(name)
+        .expect("Unable to open the file");
+
+    BufWriter::new(time_file)
+}
+
+fn main() {
+    let Some(args) = parse_args() else {
+        eprintln!("{}", USAGE);
+        exit(2);
+    };
+    let (curve, variant, transactions) = (args.curve, args.variant, args.transactions);
+    // the proof of representation, the Pedersen commitment and the proof of the BLS signature
+    // derive their CRS with hash_to_curve, which bp256 does not implement
+    if let (
+        Curve::BrainpoolP256r1,
+        Variant::Service | Variant::CommittedSecret | Variant::BlsSignature,
+    ) = (curve, variant)
+    {
+        eprintln!(
+            "the {} variant is not available for BrainpoolP256r1 (no hash_to_curve)",
+            variant.name()
+        );
+        exit(2);
+    }
+    if let (Transactions::Taproot, Curve::NistP256 | Curve::BrainpoolP256r1 | Curve::NistP384) =
+        (transactions, curve)
+    {
+        eprintln!("taproot transactions are only available for Secp256k1");
+        exit(2);
+    }
+    let transport = match (args.role, args.transport) {
+        (None, None) => None,
+        (None, Some(TransportKind::Memory)) => Some(TransportKind::Memory),
+        (Some(_), Some(TransportKind::Memory)) => {
+            eprintln!("the memory transport runs both roles in this process, without --role");
+            exit(2);
+        }
+        (None, Some(_)) => {
+            eprintln!("--transport tcp and unix need --role");
+            exit(2);
+        }
+        (Some(_), kind) => Some(kind.unwrap_or(TransportKind::Tcp)),
+    };
+    if variant.inline_only()
+        && (transport.is_some() || matches!(transactions, Transactions::Taproot))
+    {
+        eprintln!(
+            "the {} variant only runs with both roles inline, on the ledger",
+            variant.name()
+        );
+        exit(2);
+    }
+
+    let mut flows = match curve {
+        Curve::Secp256k1 => variant_flows::<Secp256k1, Sha256>(variant),
+        Curve::NistP256 => variant_flows::<NistP256, Sha256>(variant),
+        Curve::NistP384 => variant_flows::<NistP384, Sha384>(variant),
+        Curve::BrainpoolP256r1 => match variant {
+            Variant::Signature => {
+                flows::<BrainpoolP256r1, Sha256, SignatureGoods<BrainpoolP256r1, Sha256>>()
+            }
+            Variant::Batch => {
+                flows::<BrainpoolP256r1, Sha256, BatchSignatureGoods<BrainpoolP256r1, Sha256>>()
+            }
+            Variant::EcdsaSignature => {
+                Flows::Inline(other_goods::selling_ecdsa_signature::<BrainpoolP256r1, Sha256>)
+            }
+            Variant::Service | Variant::CommittedSecret | Variant::BlsSignature => unreachable!(),
+        },
+    };
+    if let Transactions::Taproot = transactions {
+        flows = match variant {
+            Variant::Signature => taproot_flows::<SignatureGoods<Secp256k1, Sha256>>(),
+            Variant::Service => taproot_flows::<ServiceGoods<Secp256k1, Sha256>>(),
+            Variant::Batch => taproot_flows::<BatchSignatureGoods<Secp256k1, Sha256>>(),
+            Variant::CommittedSecret | Variant::BlsSignature | Variant::EcdsaSignature => {
+                unreachable!()
+            }
+        };
+    }
+
+    let times_file_name = |suffix: &str| {
+        String::from(variant.times_file_prefix())
+            + transactions.times_file_prefix()
+            + suffix
+            + curve.name()
+            + ".csv"
+    };
+    let ciphersuite = curve.ciphersuite();
+    let (seller_flow, buyer_flow) = match (flows, transport) {
+        (Flows::Inline(flow), None) => {
+            let mut time_file = open_times_file(times_file_name("times_"));
+            repeat("aborted", || flow(&mut time_file));
+            return;
+        }
+        (Flows::Parties { seller, buyer }, _) => (seller, buyer),
+        (Flows::Inline(_), Some(_)) => unreachable!(),
+    };
+    match (args.role, transport) {
+        (_, None) => {
+            let mut time_file = open_times_file(times_file_name("times_"));
+            repeat("aborted", || {
+                let times = inline(seller_flow, buyer_flow, ciphersuite)?;
+                write_times(&mut time_file, &times);
+                Ok(())
+            });
+        }
+        (_, Some(TransportKind::Memory)) => {
+            let (mut seller_end, mut buyer_end) = MemoryTransport::pair();
+            let mut seller_file =
+                open_times_file(times_file_name(Role::Seller.times_file_prefix()));
+            let mut buyer_file = open_times_file(times_file_name(Role::Buyer.times_file_prefix()));
+            let seller = thread::spawn(move || {
+                run_party(
+                    Role::Seller,
+                    seller_flow,
+                    ciphersuite,
+                    &mut seller_end,
+                    &mut seller_file,
+                )
+            });
+            run_party(
+                Role::Buyer,
+                buyer_flow,
+                ciphersuite,
+                &mut buyer_end,
+                &mut buyer_file,
+            );
+            seller.join().expect("the seller panicked");
+        }
+        (Some(role), Some(kind)) => {
+            let address = args.address.unwrap_or_else(|| {
+                match kind {
+                    TransportKind::Unix => UNIX_ADDRESS,
+                    _ => TCP_ADDRESS,
+                }
+                .into()
+            });
+            let mut transport = connect(role, kind, &address).unwrap_or_else(|error| {
+                eprintln!("cannot connect through {}: {}", address, error);
+                exit(1);
+            });
+            let flow = match role {
+                Role::Seller => seller_flow,
+                Role::Buyer => buyer_flow,
+            };
+            let mut time_file = open_times_file(times_file_name(role.times_file_prefix()));
+            run_party(role, flow, ciphersuite, transport.as_mut(), &mut time_file);
+        }
+        (None, Some(_)) => unreachable!(),
+    }
+}
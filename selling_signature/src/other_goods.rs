@@ -0,0 +1,378 @@
+use crate::{parties, MSG};
+use elliptic_curve::ff::Field;
+use elliptic_curve::group::cofactor::CofactorGroup;
+use elliptic_curve::hash2curve::GroupDigest;
+use elliptic_curve::point::DecompressPoint;
+use elliptic_curve::scalar::NonZeroScalar;
+use elliptic_curve::{CurveArithmetic, Group};
+use rand_core::OsRng;
+use schemas::adaptor_signatures::AdaptorSignatureScheme;
+use schemas::bls_signatures::BLSSignatureScheme;
+use schemas::ecdsa_signatures::{ECDSASignature, ECDSA};
+use schemas::elgamal::ElGamal;
+use schemas::hard_relation::HardRelation;
+use schemas::nizk::{EncodableProof, ProofEncoding, NIZK};
+use schemas::one_time_pad::OneTimePad;
+use schemas::pedersen_commitment::PedersenGens;
+use schemas::pok_bls_signature::{self, PoKBLSSignature};
+use schemas::pok_ecdsa_signature::{self, PoKECDSASignature};
+use schemas::proof_star::{self, SigmaProofStar};
+use schemas::public_key_encryption_scheme::PublicKeyEncryptionScheme;
+use schemas::schnorr_adaptor_signatures::SchnorrAdaptorSignature;
+use schemas::schnorr_signatures::SchnorrSignatureScheme;
+use schemas::selling_protocol::SellingError;
+use schemas::signature_scheme::SignatureScheme;
+use schemas::symmetric_encryption::SymmetricEncryptionScheme;
+use sha2::Digest;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::time::Instant;
+
+// The goods that are not sold through schemas::selling_protocol: a secret committed with
+// Pedersen, a BLS signature and an ECDSA signature. Both parties run inline, and the
+// transactions are only described, as in the first version of the protocol:
+//
+//   tx_lock    = (alpha, pk_b_1) -> (alpha, (pk_b_2 && pk_s) || (pk_b_2 + t))
+//   tx_pay     = (alpha, (pk_b_2 && pk_s) || (pk_b_2 + t)) -> (alpha, pk_s_2)
+//   tx_recover = (alpha, (pk_b_2 && pk_s) || (pk_b_2 + t)) -> (alpha, pk_b_3)
+//
+// Every flow writes the time of the four steps to the times file.
+
+const TX_LOCK: &str = "(alpha, pk_b_1) -> (alpha, (pk_b_2 && pk_s) || (pk_b_2 + t))";
+const TX_PAY: &str = "(alpha, (pk_b_2 && pk_s) || (pk_b_2 + t)) -> (alpha, pk_s_2)";
+
+type ASig<C, H> = SchnorrAdaptorSignature<C, H>;
+type Sig<C, H> = SchnorrSignatureScheme<C, H>;
+type PreSignature<C, H> = <ASig<C, H> as AdaptorSignatureScheme>::PreSignature;
+type AdaptorSignature<C, H> = <ASig<C, H> as AdaptorSignatureScheme>::Signature;
+
+fn write_time(time_file: &mut BufWriter<File>, duration: u128, last: bool) {
+    let data = format!("{}{}", duration, if last { "\n" } else { ", " });
+    time_file
+        .write_all(data.as_bytes())
+        .expect("Unable to write to file");
+}
+
+// The buyer checks the offer, signs tx_lock and pre-signs tx_pay under the statement x
+fn buyer_lock<C, H>(
+    time_file: &mut BufWriter<File>,
+    start: Instant,
+    sk_b_1: &NonZeroScalar<C>,
+    sk_b_2: &NonZeroScalar<C>,
+    pk_b_1: &C::ProjectivePoint,
+    x: &C::ProjectivePoint,
+) -> parties::Result<PreSignature<C, H>>
+where
+    C: CurveArithmetic,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+{
+    let signature_lock = Sig::<C, H>::sign(sk_b_1, TX_LOCK);
+    if !Sig::<C, H>::verify(pk_b_1, TX_LOCK, &signature_lock) {
+        return Err(SellingError::InvalidLock.into());
+    }
+    // Publish(tx_lock, sig_lock)
+    let pre_signature_pay = ASig::<C, H>::pre_sign(sk_b_2, TX_PAY, x);
+    let duration = start.elapsed();
+
+    let pre_signature_pay_size = pre_signature_pay.to_byte_vector().len();
+    let signature_lock_size = signature_lock.to_byte_vector().len();
+    schemas::debug_print!("buyer sends to seller {} bytes:", pre_signature_pay_size);
+    schemas::debug_print!("\t-pre_sig: {} bytes", pre_signature_pay_size);
+    schemas::debug_print!(
+        "buyer publishes to blockchain {} bytes:",
+        signature_lock_size
+    );
+    schemas::debug_print!("\t-sig_lock: {} bytes", signature_lock_size);
+    write_time(time_file, duration.as_nanos(), false);
+
+    Ok(pre_signature_pay)
+}
+
+// The seller completes the pre-signature of tx_pay with the witness w and publishes tx_pay, and
+// the buyer extracts w from the published signature
+fn pay<C, H>(
+    time_file: &mut BufWriter<File>,
+    pk_b_2: &C::ProjectivePoint,
+    x: &C::ProjectivePoint,
+    w: &NonZeroScalar<C>,
+    pre_signature_pay: &PreSignature<C, H>,
+) -> parties::Result<(Instant, NonZeroScalar<C>)>
+where
+    C: CurveArithmetic,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+{
+    let start = Instant::now();
+    if !ASig::<C, H>::pre_verify(pk_b_2, TX_PAY, x, pre_signature_pay) {
+        return Err(SellingError::InvalidPreSignature.into());
+    }
+    let signature_pay: AdaptorSignature<C, H> = ASig::<C, H>::adapt(pk_b_2, pre_signature_pay, w);
+    if !ASig::<C, H>::verify(pk_b_2, TX_PAY, &signature_pay) {
+        return Err(SellingError::InvalidPreSignature.into());
+    }
+    let duration = start.elapsed();
+    // Publish(tx_pay, signature_pay)
+    write_time(time_file, duration.as_nanos(), false);
+
+    let signature_pay_size = signature_pay.to_byte_vector().len();
+    schemas::debug_print!(
+        "seller publishes to blockchain {} bytes:",
+        signature_pay_size
+    );
+    schemas::debug_print!("\t-sig_pay: {} bytes", signature_pay_size);
+
+    // signature_pay read from ledger
+    let start = Instant::now();
+    let extracted_w: Option<NonZeroScalar<C>> =
+        ASig::<C, H>::extract(pk_b_2, pre_signature_pay, &signature_pay).into();
+    let extracted_w = extracted_w.ok_or(SellingError::ExtractionFailed)?;
+
+    Ok((start, extracted_w))
+}
+
+// The seller sells g^s for the secret s committed in a public Pedersen commitment c_s. g^s is
+// sent encrypted with ElGamal under the statement x of the adaptor signature, so the buyer can
+// decrypt it once the payment reveals the witness w. The buyer does not learn s itself, only g^s.
+pub fn selling_committed_secret<C, H>(time_file: &mut BufWriter<File>) -> parties::Result<()>
+where
+    C: GroupDigest,
+    C::ProjectivePoint: CofactorGroup,
+    C::AffinePoint: DecompressPoint<C>,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+{
+    let (sk_b_1, pk_b_1) = ASig::<C, H>::gen();
+    let (sk_b_2, pk_b_2) = ASig::<C, H>::gen();
+
+    let g = C::ProjectivePoint::generator();
+    let h = PedersenGens::<C>::nums().h;
+
+    // the secret and its commitment c_s = g^e * h^s are known before the exchange starts
+    let s = C::Scalar::random(&mut OsRng);
+    let e = C::Scalar::random(&mut OsRng);
+    let c_s = g * e + h * s;
+
+    // seller
+    let start = Instant::now();
+    let (w, x) = NonZeroScalar::<C>::gen(&g);
+    let (ct, y) = ElGamal::<C>::enc(&x, &(g * s));
+    let witness = proof_star::Witness::new(s, e, y, *w);
+    let statement = proof_star::Statement::new(h, c_s, ct, x, x);
+    let crs = SigmaProofStar::<C, H>::crs_gen();
+    let proof = SigmaProofStar::<C, H>::prove(&crs, &statement, &witness);
+    let duration = start.elapsed();
+
+    schemas::debug_print!(
+        "seller sends to buyer {} bytes ({} bytes with the challenge-response encoding)",
+        proof.to_byte_vector().len() + statement.to_byte_vector().len(),
+        SigmaProofStar::<C, H>::encode_proof(
+            &crs,
+            &statement,
+            &proof,
+            ProofEncoding::ChallengeResponse
+        )
+        .len()
+            + statement.to_byte_vector().len()
+    );
+    write_time(time_file, duration.as_nanos(), false);
+
+    // buyer
+    let start = Instant::now();
+    if !SigmaProofStar::<C, H>::verify(&crs, &statement, &proof) {
+        return Err(SellingError::InvalidOffer.into());
+    }
+    let pre_signature_pay = buyer_lock::<C, H>(time_file, start, &sk_b_1, &sk_b_2, &pk_b_1, &x)?;
+
+    // seller, then buyer
+    let (start, extracted_w) = pay::<C, H>(time_file, &pk_b_2, &x, &w, &pre_signature_pay)?;
+    let extracted_secret =
+        ElGamal::<C>::dec(&extracted_w, &statement.ct()).ok_or(SellingError::ExtractionFailed)?;
+    let duration = start.elapsed();
+
+    if extracted_secret != g * s {
+        return Err(SellingError::ExtractionFailed.into());
+    }
+    write_time(time_file, duration.as_nanos(), true);
+
+    Ok(())
+}
+
+// The notary signature is a BLS signature over BLS12-381. The signature is encrypted under the
+// statement x of the adaptor signature (see pok_bls_signature), so the buyer decrypts it once
+// the payment reveals the witness w.
+pub fn selling_bls_signature<C, H>(time_file: &mut BufWriter<File>) -> parties::Result<()>
+where
+    C: GroupDigest,
+    C::ProjectivePoint: CofactorGroup,
+    C::AffinePoint: DecompressPoint<C>,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+{
+    let (sk_b_1, pk_b_1) = ASig::<C, H>::gen();
+    let (sk_b_2, pk_b_2) = ASig::<C, H>::gen();
+    let (sk_notary, pk_notary) = BLSSignatureScheme::gen();
+
+    let g = C::ProjectivePoint::generator();
+
+    // seller
+    let start = Instant::now();
+    let (w, x) = pok_bls_signature::gen_witness::<C>(&g);
+    let signature = BLSSignatureScheme::sign(&sk_notary, MSG);
+    let (ct, y) = pok_bls_signature::encrypt::<C>(&signature, &w)
+        .ok_or("the witness does not fit in the order of BLS12-381")?;
+    let witness = pok_bls_signature::Witness::new(signature.sig, *w);
+    let statement = pok_bls_signature::Statement::new(x, y, ct, pk_notary, MSG.into());
+    let crs = PoKBLSSignature::<C, H>::crs_gen();
+    let proof = PoKBLSSignature::<C, H>::prove(&crs, &statement, &witness);
+    let duration = start.elapsed();
+
+    schemas::debug_print!(
+        "seller sends to buyer {} bytes",
+        proof.to_byte_vector().len() + statement.to_byte_vector().len()
+    );
+    write_time(time_file, duration.as_nanos(), false);
+
+    // buyer
+    let start = Instant::now();
+    if !PoKBLSSignature::<C, H>::verify(&crs, &statement, &proof) {
+        return Err(SellingError::InvalidOffer.into());
+    }
+    let pre_signature_pay = buyer_lock::<C, H>(time_file, start, &sk_b_1, &sk_b_2, &pk_b_1, &x)?;
+
+    // seller, then buyer
+    let (start, extracted_w) = pay::<C, H>(time_file, &pk_b_2, &x, &w, &pre_signature_pay)?;
+    let extracted_signature = pok_bls_signature::decrypt::<C>(&statement.ct(), &extracted_w)
+        .ok_or(SellingError::ExtractionFailed)?;
+    let duration = start.elapsed();
+
+    if extracted_signature.sig != signature.sig
+        || !BLSSignatureScheme::verify(&pk_notary, MSG, &extracted_signature)
+    {
+        return Err(SellingError::ExtractionFailed.into());
+    }
+    write_time(time_file, duration.as_nanos(), true);
+
+    Ok(())
+}
+
+// The notary signature is an ECDSA signature (r, s). The seller publishes r and the nonce point
+// R of the signature, and sells s encrypted under the statement x of the adaptor signature.
+pub fn selling_ecdsa_signature<C, H>(time_file: &mut BufWriter<File>) -> parties::Result<()>
+where
+    C: CurveArithmetic,
+    C::AffinePoint: DecompressPoint<C>,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+{
+    let (sk_b_1, pk_b_1) = ASig::<C, H>::gen();
+    let (sk_b_2, pk_b_2) = ASig::<C, H>::gen();
+    let (sk_notary, pk_notary) = ECDSA::<C, H>::gen();
+
+    let g = C::ProjectivePoint::generator();
+
+    // seller
+    let start = Instant::now();
+    let (w, x) = NonZeroScalar::<C>::gen(&g);
+    let signature = ECDSA::<C, H>::sign(&sk_notary, MSG);
+    let nonce_point = ECDSA::<C, H>::nonce_point(&pk_notary, MSG, &signature);
+    let ct = OneTimePad::<C::Scalar>::enc(&w, &signature.proof);
+    let witness = pok_ecdsa_signature::Witness::new(*signature.proof, *w);
+    let statement =
+        pok_ecdsa_signature::Statement::<C, H>::new(x, pk_notary, nonce_point, ct, MSG.into());
+    let proof = PoKECDSASignature::<C, H>::prove(&(), &statement, &witness);
+    let duration = start.elapsed();
+
+    schemas::debug_print!(
+        "seller sends to buyer {} bytes ({} bytes with the challenge-response encoding)",
+        proof.to_byte_vector().len() + statement.to_byte_vector().len(),
+        PoKECDSASignature::<C, H>::encode_proof(
+            &(),
+            &statement,
+            &proof,
+            ProofEncoding::ChallengeResponse
+        )
+        .len()
+            + statement.to_byte_vector().len()
+    );
+    write_time(time_file, duration.as_nanos(), false);
+
+    // buyer
+    let start = Instant::now();
+    if !PoKECDSASignature::<C, H>::verify(&(), &statement, &proof) {
+        return Err(SellingError::InvalidOffer.into());
+    }
+    let pre_signature_pay = buyer_lock::<C, H>(time_file, start, &sk_b_1, &sk_b_2, &pk_b_1, &x)?;
+
+    // seller, then buyer
+    let (start, extracted_w) = pay::<C, H>(time_file, &pk_b_2, &x, &w, &pre_signature_pay)?;
+    let extracted_secret =
+        OneTimePad::<C::Scalar>::dec(&extracted_w, &ct).ok_or(SellingError::ExtractionFailed)?;
+    let extracted_signature = ECDSASignature::<C> {
+        sig: signature.sig,
+        proof: Option::from(NonZeroScalar::new(extracted_secret))
+            .ok_or(SellingError::ExtractionFailed)?,
+    };
+    let duration = start.elapsed();
+
+    if extracted_secret != *signature.proof
+        || !ECDSA::<C, H>::verify(&pk_notary, MSG, &extracted_signature)
+    {
+        return Err(SellingError::ExtractionFailed.into());
+    }
+    write_time(time_file, duration.as_nanos(), true);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k256::Secp256k1;
+    use p256::NistP256;
+    use sha2::Sha256;
+    use std::fs;
+
+    // Runs the flow with a times file in the temporary directory, and checks that it wrote the
+    // time of the four steps on one line
+    fn run(name: &str, flow: fn(&mut BufWriter<File>) -> parties::Result<()>) {
+        let path = std::env::temp_dir().join(format!(
+            "selling_signature_{}_{}.csv",
+            name,
+            std::process::id()
+        ));
+        let mut time_file = BufWriter::new(File::create(&path).unwrap());
+        let result = flow(&mut time_file);
+        drop(time_file);
+        let times = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert!(result.is_ok(), "{}", result.unwrap_err());
+        let lines: Vec<&str> = times.lines().collect();
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0].split(", ").count(), 4);
+        assert!(lines[0]
+            .split(", ")
+            .all(|time| time.parse::<u128>().is_ok()));
+    }
+
+    #[test]
+    fn committed_secret() {
+        run(
+            "committed_secret",
+            selling_committed_secret::<Secp256k1, Sha256>,
+        );
+    }
+
+    #[test]
+    fn bls_signature() {
+        run("bls_signature", selling_bls_signature::<Secp256k1, Sha256>);
+    }
+
+    #[test]
+    fn ecdsa_signature() {
+        run(
+            "ecdsa_signature",
+            selling_ecdsa_signature::<Secp256k1, Sha256>,
+        );
+        run(
+            "ecdsa_signature_p256",
+            selling_ecdsa_signature::<NistP256, Sha256>,
+        );
+    }
+}
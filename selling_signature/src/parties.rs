@@ -0,0 +1,1230 @@
+use crate::{Notarize, Sig, Tx, AMOUNT, FEE, MSG, NOTARY, TIMEOUT};
+use elliptic_curve::ops::Reduce;
+use elliptic_curve::point::DecompressPoint;
+use elliptic_curve::{CurveArithmetic, Group};
+use k256::{NonZeroScalar, ProjectivePoint, Secp256k1};
+use schemas::adaptor_signatures::AdaptorSignatureScheme;
+use schemas::bip340;
+use schemas::hard_relation::HardRelation;
+use schemas::ledger::{Branch, Clock, Key, Ledger, OutPoint, Output, Witness};
+use schemas::protocol_message::{
+    negotiate_version, AbortReason, Ciphersuite, GoodsId, MessageError, ProtocolMessage,
+    PROTOCOL_VERSION, SUPPORTED_VERSIONS,
+};
+use schemas::schnorr_adaptor_signatures::SchnorrAdaptorSignature;
+use schemas::selling_protocol::{
+    Buyer, Evidence, Goods, Party, PaymentMessage, Seller, SellingError, Terms,
+};
+use schemas::signature_scheme::SignatureScheme;
+use schemas::taproot::{self, SellingTransactions, TxOut};
+use schemas::taproot_protocol::{
+    NonceMessage, PartialSignatureMessage, TaprootBuyer, TaprootLockMessage, TaprootPaymentMessage,
+    TaprootSeller,
+};
+use schemas::transport::Transport;
+use sha2::{Digest, Sha256};
+use std::error::Error;
+use std::io;
+use std::marker::PhantomData;
+use std::time::{Duration, Instant};
+
+// The seller and the buyer as separate parties that only share a Transport. Each one keeps its
+// own replica of the ledger: publishing a transaction is submitting it to the own replica and
+// relaying its witnesses to the other party, which submits it to its replica.
+//
+//   buyer  -> seller: Hello (supported versions, ciphersuite, goods)
+//   seller -> buyer:  Accept (negotiated version, ciphersuite, goods)
+//   buyer  -> seller: Keys (pk_b_1, pk_b_2, pk_b_3)
+//   seller -> buyer:  Keys (pk_s_1, pk_s_2)
+//   seller -> buyer:  Offer
+//   buyer  -> seller: Lock, PreSignature
+//   seller -> buyer:  Payment (adaptor signature and signature of the seller of tx_pay)
+//
+// The buyer knows the key of the notary out of band (see notary), so the seller cannot choose it.
+// A seller that announces a notary before its keys must announce that one, or the buyer aborts
+// with UnknownNotary.
+//
+// The messages are the ProtocolMessages of schemas. A party that rejects a message sends an Abort
+// with the reason before stopping, with the rejected message as evidence in its error, and a party
+// that receives an Abort stops with it. Once tx_lock is published, a buyer that does not get a
+// valid tx_pay waits for the timelock and publishes tx_recover before stopping.
+//
+// With the Taproot transactions of schemas::taproot, the same keys are exchanged and the 2-of-2
+// signature of tx_pay is computed by both parties with the roles of schemas::taproot_protocol:
+//
+//   seller -> buyer:  Offer, Nonce
+//   buyer  -> seller: Nonce, TaprootLock
+//   seller -> buyer:  PartialSignature
+//   buyer  -> seller: PartialSignature
+//   seller -> buyer:  TaprootPayment (signature of tx_pay)
+//
+// There is no chain of Bitcoin transactions to share, so each party counts the blocks with its own
+// Clock, and tx_lock is confirmed in the block that follows TaprootLock.
+//
+// Each party returns the time of its steps followed by the time of the whole run, which includes
+// the latency of the transport.
+
+type Point<C> = <C as CurveArithmetic>::ProjectivePoint;
+pub type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+// The key pair of the notary, derived from NOTARY. The buyer only takes the public key; the
+// seller, which obtains the notarization in this demo, also signs with the secret key.
+pub fn notary<C, H>() -> (elliptic_curve::NonZeroScalar<C>, Point<C>)
+where
+    C: CurveArithmetic,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+{
+    let sk = <C::Scalar as Reduce<C::Uint>>::reduce_bytes(&H::digest(NOTARY));
+    let sk = Option::from(elliptic_curve::NonZeroScalar::<C>::new(sk))
+        .expect("the hash of NOTARY is not zero");
+
+    (sk, Point::<C>::generator() * *sk)
+}
+
+pub struct Setup<C, H>
+where
+    C: CurveArithmetic,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+{
+    pub ledger: Ledger<C, Sig<C, H>, SchnorrAdaptorSignature<C, H>>,
+    pub terms: Terms<C>,
+    pub tx_lock: Tx<C>,
+    pub tx_pay: Tx<C>,
+    pub tx_recover: Tx<C>,
+}
+
+// Mints the coins of the buyer on a fresh ledger and builds tx_lock, tx_pay and tx_recover. They
+// only depend on the public keys, so both parties build the same ones.
+pub fn setup<C, H>(
+    pk_notary: Point<C>,
+    pk_s_1: Point<C>,
+    pk_s_2: Point<C>,
+    pk_b_1: Point<C>,
+    pk_b_2: Point<C>,
+    pk_b_3: Point<C>,
+) -> Setup<C, H>
+where
+    C: CurveArithmetic,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+{
+    let mut ledger = Ledger::<C, Sig<C, H>, SchnorrAdaptorSignature<C, H>>::new();
+    let alpha = ledger.mint(Output::to_key(AMOUNT, pk_b_1));
+    // (alpha, pk_b_1) -> (alpha, (pk_b_2 && pk_s) || (pk_b_2 + t))
+    let tx_lock = Tx::<C>::new(
+        vec![alpha],
+        vec![Output::new(
+            AMOUNT,
+            vec![
+                Branch::new(vec![Key::Adaptor(pk_b_2), Key::Signature(pk_s_1)], 0),
+                Branch::new(vec![Key::Signature(pk_b_2)], TIMEOUT),
+            ],
+        )],
+    );
+    // (alpha, (pk_b_2 && pk_s) || (pk_b_2 + t)) -> (alpha, pk_s_2)
+    let tx_pay = Tx::<C>::new(
+        vec![tx_lock.outpoint(0)],
+        vec![Output::to_key(AMOUNT, pk_s_2)],
+    );
+    // (alpha, (pk_b_2 && pk_s) || (pk_b_2 + t)) -> (alpha, pk_b_3)
+    let tx_recover = Tx::<C>::new(
+        vec![tx_lock.outpoint(0)],
+        vec![Output::to_key(AMOUNT, pk_b_3)],
+    );
+
+    let terms = Terms {
+        pk_notary,
+        msg: MSG.into(),
+        pk_lock: pk_b_1,
+        pk_buyer: pk_b_2,
+        tx_lock: tx_lock.message(),
+        tx_pay: tx_pay.message(),
+        tx_recover: tx_recover.message(),
+        timeout: TIMEOUT,
+    };
+
+    Setup {
+        ledger,
+        terms,
+        tx_lock,
+        tx_pay,
+        tx_recover,
+    }
+}
+
+pub struct TaprootSetup {
+    pub terms: Terms<Secp256k1>,
+    pub transactions: SellingTransactions,
+}
+
+// A Bitcoin output that only the key path of pk spends
+fn key_path_output(value: u64, pk: &ProjectivePoint) -> TxOut {
+    let (q, _) = taproot::output_key(&bip340::x_only(pk), None).expect("a valid internal key");
+
+    TxOut::new(value, taproot::p2tr_script_pubkey(&q))
+}
+
+// Same as setup, for the Taproot transactions: tx_lock spends a key-path output of pk_b_1, tx_pay
+// pays to pk_s_2 and tx_recover to pk_b_3, which also signs the leaf of the timelock
+pub fn taproot_setup(
+    pk_notary: ProjectivePoint,
+    pk_s_1: ProjectivePoint,
+    pk_s_2: ProjectivePoint,
+    pk_b_1: ProjectivePoint,
+    pk_b_2: ProjectivePoint,
+    pk_b_3: ProjectivePoint,
+) -> TaprootSetup {
+    let transactions = SellingTransactions::new(
+        taproot::OutPoint::new([0; 32], 0),
+        key_path_output(AMOUNT, &pk_b_1),
+        &pk_b_2,
+        &pk_s_1,
+        &bip340::x_only(&pk_b_3),
+        TIMEOUT as u16,
+        key_path_output(0, &pk_s_2).script_pubkey,
+        key_path_output(0, &pk_b_3).script_pubkey,
+        FEE,
+    );
+    let terms = Terms {
+        pk_notary,
+        msg: MSG.into(),
+        pk_lock: pk_b_1,
+        pk_buyer: pk_b_2,
+        tx_lock: transactions.tx_lock.message(),
+        tx_pay: transactions.tx_pay.message(),
+        tx_recover: transactions.tx_recover.message(),
+        timeout: TIMEOUT,
+    };
+
+    TaprootSetup {
+        terms,
+        transactions,
+    }
+}
+
+// The connection with the other party once the version is negotiated
+struct Session<'a, C>
+where
+    C: CurveArithmetic,
+{
+    transport: &'a mut dyn Transport,
+    version: u16,
+    peer: Party,
+    // the evidence if that message turns out to be invalid
+    last_received: Vec<u8>,
+    _curve: PhantomData<C>,
+}
+
+impl<'a, C> Session<'a, C>
+where
+    C: CurveArithmetic,
+    C::AffinePoint: DecompressPoint<C>,
+{
+    fn new(transport: &'a mut dyn Transport, peer: Party) -> Self {
+        Self {
+            transport,
+            version: PROTOCOL_VERSION,
+            peer,
+            last_received: vec![],
+            _curve: PhantomData,
+        }
+    }
+
+    fn send(&mut self, message: &ProtocolMessage<C>) -> io::Result<()> {
+        let bytes = message
+            .to_byte_vector(self.version)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidInput, error))?;
+
+        self.transport.send(&bytes)
+    }
+
+    // Tells the peer why we stop and returns the error to stop with
+    fn abort<E: Into<Box<dyn Error>>>(&mut self, reason: AbortReason, error: E) -> Box<dyn Error> {
+        // the peer may be gone already, and the error is ours anyway
+        let _ = self.send(&ProtocolMessage::Abort(reason));
+
+        error.into()
+    }
+
+    // Aborts because of a message of the peer, with the message in the error
+    fn deviation(&mut self, evidence: Evidence) -> Box<dyn Error> {
+        let error = format!("rejected a message of the {}", evidence);
+
+        self.abort(evidence.error.into(), error)
+    }
+
+    // Aborts if a step of the role failed, with the evidence that the role kept if it failed
+    // because of the peer
+    fn check<T>(
+        &mut self,
+        result: std::result::Result<T, SellingError>,
+        evidence: &[Evidence],
+    ) -> Result<T> {
+        result.map_err(|error| match evidence.last() {
+            Some(evidence) if evidence.error == error => self.deviation(evidence.clone()),
+            _ => self.abort(error.into(), error),
+        })
+    }
+
+    fn unexpected(&mut self, message: &ProtocolMessage<C>, expected: &str) -> Box<dyn Error> {
+        let bytes = self.last_received.clone();
+        let evidence = Evidence::new(self.peer, SellingError::UnexpectedMessage, bytes);
+        let error = self.deviation(evidence);
+
+        format!(
+            "expected {}, received {}: {}",
+            expected,
+            message.name(),
+            error
+        )
+        .into()
+    }
+
+    fn receive(&mut self) -> Result<ProtocolMessage<C>> {
+        self.last_received = self.transport.receive()?;
+        match ProtocolMessage::<C>::from_byte_vector(&self.last_received) {
+            Ok((_, ProtocolMessage::Abort(reason))) => {
+                Err(format!("the {} aborted: {}", self.peer, reason).into())
+            }
+            // the handshake is read before the version is negotiated
+            Ok((_, message @ (ProtocolMessage::Hello { .. } | ProtocolMessage::Accept { .. }))) => {
+                Ok(message)
+            }
+            Ok((version, message)) if version == self.version => Ok(message),
+            Ok((version, _)) => Err(self.abort(
+                AbortReason::UnsupportedVersion,
+                MessageError::UnsupportedVersion(version),
+            )),
+            Err(error @ MessageError::UnsupportedVersion(_)) => {
+                Err(self.abort(AbortReason::UnsupportedVersion, error))
+            }
+            Err(_) => {
+                let bytes = self.last_received.clone();
+                let evidence = Evidence::new(self.peer, SellingError::MalformedMessage, bytes);
+                Err(self.deviation(evidence))
+            }
+        }
+    }
+
+    fn receive_keys<const N: usize>(&mut self) -> Result<[Point<C>; N]> {
+        match self.receive()? {
+            ProtocolMessage::Keys(keys) => <[Point<C>; N]>::try_from(keys).map_err(|_| {
+                let bytes = self.last_received.clone();
+                let evidence = Evidence::new(self.peer, SellingError::MalformedMessage, bytes);
+                self.deviation(evidence)
+            }),
+            message => Err(self.unexpected(&message, "Keys")),
+        }
+    }
+
+    // pk_s_1 and pk_s_2, possibly after the notary that the seller announces
+    fn receive_seller_keys(&mut self, pk_notary: &Point<C>) -> Result<[Point<C>; 2]> {
+        let keys = match self.receive()? {
+            ProtocolMessage::Keys(keys) => keys,
+            message => return Err(self.unexpected(&message, "Keys")),
+        };
+        let error = match keys[..] {
+            [pk_s_1, pk_s_2] => return Ok([pk_s_1, pk_s_2]),
+            [announced, pk_s_1, pk_s_2] if announced == *pk_notary => return Ok([pk_s_1, pk_s_2]),
+            [_, _, _] => SellingError::UnknownNotary,
+            _ => SellingError::MalformedMessage,
+        };
+        let bytes = self.last_received.clone();
+
+        Err(self.deviation(Evidence::new(self.peer, error, bytes)))
+    }
+}
+
+// The seller side of the handshake: the buyer proposes, the seller decides
+fn accept<C>(
+    transport: &mut dyn Transport,
+    ciphersuite: Ciphersuite,
+    goods: GoodsId,
+) -> Result<Session<'_, C>>
+where
+    C: CurveArithmetic,
+    C::AffinePoint: DecompressPoint<C>,
+{
+    let mut session = Session::new(transport, Party::Buyer);
+    let (versions, theirs, their_goods) = match session.receive()? {
+        ProtocolMessage::Hello {
+            versions,
+            ciphersuite,
+            goods,
+        } => (versions, ciphersuite, goods),
+        message => return Err(session.unexpected(&message, "Hello")),
+    };
+    if theirs != ciphersuite {
+        let error = format!("the buyer uses {:?}, the seller {:?}", theirs, ciphersuite);
+        return Err(session.abort(AbortReason::UnsupportedCiphersuite, error));
+    }
+    if their_goods != goods {
+        let error = format!(
+            "the buyer buys {:?}, the seller sells {:?}",
+            their_goods, goods
+        );
+        return Err(session.abort(AbortReason::UnsupportedGoods, error));
+    }
+    let Some(version) = negotiate_version(&SUPPORTED_VERSIONS, &versions) else {
+        let error = format!("the buyer only supports the versions {:?}", versions);
+        return Err(session.abort(AbortReason::UnsupportedVersion, error));
+    };
+    session.version = version;
+    session.send(&ProtocolMessage::Accept {
+        version,
+        ciphersuite,
+        goods,
+    })?;
+
+    Ok(session)
+}
+
+fn hello<C>(
+    transport: &mut dyn Transport,
+    ciphersuite: Ciphersuite,
+    goods: GoodsId,
+) -> Result<Session<'_, C>>
+where
+    C: CurveArithmetic,
+    C::AffinePoint: DecompressPoint<C>,
+{
+    let mut session = Session::new(transport, Party::Seller);
+    session.send(&ProtocolMessage::Hello {
+        versions: SUPPORTED_VERSIONS.to_vec(),
+        ciphersuite,
+        goods,
+    })?;
+    let (version, theirs, their_goods) = match session.receive()? {
+        ProtocolMessage::Accept {
+            version,
+            ciphersuite,
+            goods,
+        } => (version, ciphersuite, goods),
+        message => return Err(session.unexpected(&message, "Accept")),
+    };
+    if theirs != ciphersuite {
+        let error = format!("the seller accepted {:?}, not {:?}", theirs, ciphersuite);
+        return Err(session.abort(AbortReason::UnsupportedCiphersuite, error));
+    }
+    if their_goods != goods {
+        let error = format!("the seller accepted {:?}, not {:?}", their_goods, goods);
+        return Err(session.abort(AbortReason::UnsupportedGoods, error));
+    }
+    if !SUPPORTED_VERSIONS.contains(&version) {
+        let error = format!("the seller accepted the unsupported version {}", version);
+        return Err(session.abort(AbortReason::UnsupportedVersion, error));
+    }
+    session.version = version;
+
+    Ok(session)
+}
+
+// The offer of the seller, decoded for the terms and the parameters of the goods
+fn receive_offer<C, H, G>(
+    session: &mut Session<C>,
+    terms: &Terms<C>,
+    params: &G::Params,
+) -> Result<G::Offer>
+where
+    C: CurveArithmetic,
+    C::AffinePoint: DecompressPoint<C>,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+    G: Goods<C, H>,
+{
+    let offer = match session.receive()? {
+        ProtocolMessage::Offer { statement, proof } => {
+            ProtocolMessage::<C>::decode_offer::<H, G>(terms, params, &statement, &proof)
+        }
+        message => return Err(session.unexpected(&message, "Offer")),
+    };
+
+    offer.ok_or_else(|| {
+        let bytes = session.last_received.clone();
+        let evidence = Evidence::new(Party::Seller, SellingError::MalformedMessage, bytes);
+        session.deviation(evidence)
+    })
+}
+
+// tx_pay relayed by the seller, submitted to the ledger of the buyer
+fn relay_payment<C, H>(
+    session: &mut Session<C>,
+    ledger: &mut Ledger<C, Sig<C, H>, SchnorrAdaptorSignature<C, H>>,
+    mut tx_pay: Tx<C>,
+) -> Result<()>
+where
+    C: CurveArithmetic,
+    C::AffinePoint: DecompressPoint<C>,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+{
+    let (signature_pay, signature_seller) = match session.receive()? {
+        ProtocolMessage::Payment {
+            payment,
+            signature_seller,
+        } => (payment, signature_seller),
+        message => return Err(session.unexpected(&message, "Payment")),
+    };
+    tx_pay.witnesses = vec![Witness::new(
+        0,
+        vec![signature_pay.signature, signature_seller],
+    )];
+    if ledger.submit(tx_pay).is_err() {
+        let bytes = session.last_received.clone();
+        let evidence = Evidence::new(Party::Seller, SellingError::InvalidPayment, bytes);
+        return Err(session.deviation(evidence));
+    }
+    ledger.mine();
+
+    Ok(())
+}
+
+// Waits on the ledger for the timelock of tx_lock, and publishes tx_recover
+fn refund<C, H, G>(
+    buyer: &mut Buyer<C, H, G>,
+    ledger: &mut Ledger<C, Sig<C, H>, SchnorrAdaptorSignature<C, H>>,
+    mut tx_recover: Tx<C>,
+) -> Result<()>
+where
+    C: CurveArithmetic,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+    G: Goods<C, H>,
+{
+    let signature_recover = loop {
+        match buyer.refund(ledger.clock()) {
+            Err(SellingError::TimelockNotExpired) => {
+                ledger.mine();
+            }
+            result => break result?,
+        }
+    };
+    tx_recover.witnesses = vec![Witness::new(1, vec![signature_recover.signature])];
+    ledger.submit(tx_recover)?;
+    ledger.mine();
+
+    Ok(())
+}
+
+pub fn seller<C, H, G>(
+    transport: &mut dyn Transport,
+    ciphersuite: Ciphersuite,
+) -> Result<Vec<Duration>>
+where
+    C: CurveArithmetic,
+    C::AffinePoint: DecompressPoint<C>,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+    G: Notarize<C, H>,
+{
+    let run = Instant::now();
+    let (sk_s_1, pk_s_1) = SchnorrAdaptorSignature::<C, H>::gen();
+    let (_sk_s_2, pk_s_2) = SchnorrAdaptorSignature::<C, H>::gen();
+    let (sk_notary, pk_notary) = notary::<C, H>();
+
+    let mut session = accept::<C>(transport, ciphersuite, G::ID)?;
+    let [pk_b_1, pk_b_2, pk_b_3] = session.receive_keys()?;
+    session.send(&ProtocolMessage::Keys(vec![pk_s_1, pk_s_2]))?;
+    let Setup {
+        mut ledger,
+        terms,
+        mut tx_lock,
+        mut tx_pay,
+        ..
+    } = setup::<C, H>(pk_notary, pk_s_1, pk_s_2, pk_b_1, pk_b_2, pk_b_3);
+    let params = G::params();
+    let secret = G::notarize(&sk_notary, &terms, &params);
+    let mut seller = Seller::<C, H, G>::new(terms, params, secret)?;
+
+    //--------------------------------------------------------------------------------------
+    // SELLER 1
+    //--------------------------------------------------------------------------------------
+    let start = Instant::now();
+    let offer = seller.offer()?;
+    let duration_1 = start.elapsed();
+    session.send(&ProtocolMessage::offer::<H, G>(&offer))?;
+
+    // tx_lock relayed by the buyer
+    let signature_lock = match session.receive()? {
+        ProtocolMessage::Lock(signature_lock) => signature_lock,
+        message => return Err(session.unexpected(&message, "Lock")),
+    };
+    tx_lock.witnesses = vec![Witness::new(0, vec![signature_lock.signature.clone()])];
+    let Ok(tx_lock_id) = ledger.submit(tx_lock) else {
+        let bytes = session.last_received.clone();
+        let evidence = Evidence::new(Party::Buyer, SellingError::InvalidLock, bytes);
+        return Err(session.deviation(evidence));
+    };
+    ledger.mine();
+    let confirmed_at = ledger
+        .confirmation_height(&tx_lock_id)
+        .ok_or("tx_lock is not confirmed")?;
+    let pre_signature_pay = match session.receive()? {
+        ProtocolMessage::PreSignature(pre_signature_pay) => pre_signature_pay,
+        message => return Err(session.unexpected(&message, "PreSignature")),
+    };
+
+    //--------------------------------------------------------------------------------------
+    // SELLER 2
+    //--------------------------------------------------------------------------------------
+    let start = Instant::now();
+    let locked = seller.receive_lock(&signature_lock, confirmed_at);
+    session.check(locked, seller.evidence())?;
+    let signed = seller.receive_pre_signature(&pre_signature_pay, ledger.clock());
+    let signature_pay = session.check(signed, seller.evidence())?;
+    let signature_seller = Sig::<C, H>::sign(&sk_s_1, &tx_pay.message());
+    let duration_2 = start.elapsed();
+
+    session.send(&ProtocolMessage::Payment {
+        payment: PaymentMessage {
+            signature: signature_pay.signature.clone(),
+        },
+        signature_seller: signature_seller.clone(),
+    })?;
+    tx_pay.witnesses = vec![Witness::new(
+        0,
+        vec![signature_pay.signature, signature_seller],
+    )];
+    ledger.submit(tx_pay)?;
+    ledger.mine();
+
+    schemas::debug_print!(
+        "seller sent {} bytes and received {} bytes",
+        session.transport.bytes_sent(),
+        session.transport.bytes_received()
+    );
+
+    Ok(vec![duration_1, duration_2, run.elapsed()])
+}
+
+pub fn buyer<C, H, G>(
+    transport: &mut dyn Transport,
+    ciphersuite: Ciphersuite,
+) -> Result<Vec<Duration>>
+where
+    C: CurveArithmetic,
+    C::AffinePoint: DecompressPoint<C>,
+    H: Digest<OutputSize = C::FieldBytesSize>,
+    G: Notarize<C, H>,
+{
+    let run = Instant::now();
+    let (sk_b_1, pk_b_1) = SchnorrAdaptorSignature::<C, H>::gen();
+    let (sk_b_2, pk_b_2) = SchnorrAdaptorSignature::<C, H>::gen();
+    let (_sk_b_3, pk_b_3) = SchnorrAdaptorSignature::<C, H>::gen();
+    let (_, pk_notary) = notary::<C, H>();
+
+    let mut session = hello::<C>(transport, ciphersuite, G::ID)?;
+    session.send(&ProtocolMessage::Keys(vec![pk_b_1, pk_b_2, pk_b_3]))?;
+    let [pk_s_1, pk_s_2] = session.receive_seller_keys(&pk_notary)?;
+    let Setup {
+        mut ledger,
+        terms,
+        mut tx_lock,
+        tx_pay,
+        tx_recover,
+    } = setup::<C, H>(pk_notary, pk_s_1, pk_s_2, pk_b_1, pk_b_2, pk_b_3);
+    let params = G::params();
+    let offer = receive_offer::<C, H, G>(&mut session, &terms, &params)?;
+    let mut buyer = Buyer::<C, H, G>::new(terms, params, sk_b_1, sk_b_2);
+
+    //--------------------------------------------------------------------------------------
+    // BUYER 1
+    //--------------------------------------------------------------------------------------
+    let start = Instant::now();
+    let received = buyer.receive_offer(offer);
+    let pre_signature_pay = session.check(received, buyer.evidence())?;
+    let signature_lock = session.check(buyer.lock(), buyer.evidence())?;
+    let duration_1 = start.elapsed();
+
+    tx_lock.witnesses = vec![Witness::new(0, vec![signature_lock.signature.clone()])];
+    let tx_lock_id = ledger.submit(tx_lock)?;
+    ledger.mine();
+    let confirmed_at = ledger
+        .confirmation_height(&tx_lock_id)
+        .ok_or("tx_lock is not confirmed")?;
+    buyer.confirm_lock(confirmed_at)?;
+    session.send(&ProtocolMessage::Lock(signature_lock))?;
+    session.send(&ProtocolMessage::PreSignature(pre_signature_pay))?;
+
+    // tx_lock is published: without a valid tx_pay, the coins only come back with tx_recover
+    if let Err(error) = relay_payment::<C, H>(&mut session, &mut ledger, tx_pay) {
+        refund(&mut buyer, &mut ledger, tx_recover)?;
+        return Err(format!("{}, and tx_recover is published", error).into());
+    }
+
+    //--------------------------------------------------------------------------------------
+    // BUYER 2
+    //--------------------------------------------------------------------------------------
+    let published_pay = ledger
+        .spender(&OutPoint::new(tx_lock_id, 0))
+        .ok_or("tx_pay is not on the ledger")?;
+    let signature_pay = PaymentMessage {
+        signature: published_pay.witnesses[0].signatures[0].clone(),
+    };
+    let start = Instant::now();
+    let _goods = buyer.receive_payment(&signature_pay)?;
+    let duration_2 = start.elapsed();
+
+    schemas::debug_print!(
+        "buyer sent {} bytes and received {} bytes",
+        session.transport.bytes_sent(),
+        session.transport.bytes_received()
+    );
+
+    Ok(vec![duration_1, duration_2, run.elapsed()])
+}
+
+fn receive_nonce(session: &mut Session<Secp256k1>) -> Result<NonceMessage> {
+    match session.receive()? {
+        ProtocolMessage::Nonce(nonce) => Ok(NonceMessage { nonce }),
+        message => Err(session.unexpected(&message, "Nonce")),
+    }
+}
+
+fn receive_partial_signature(session: &mut Session<Secp256k1>) -> Result<PartialSignatureMessage> {
+    match session.receive()? {
+        ProtocolMessage::PartialSignature(partial_signature) => {
+            Ok(PartialSignatureMessage { partial_signature })
+        }
+        message => Err(session.unexpected(&message, "PartialSignature")),
+    }
+}
+
+pub fn taproot_seller<G>(
+    transport: &mut dyn Transport,
+    ciphersuite: Ciphersuite,
+) -> Result<Vec<Duration>>
+where
+    G: Notarize<Secp256k1, Sha256>,
+{
+    let run = Instant::now();
+    let (sk_s_1, pk_s_1) = NonZeroScalar::gen(&ProjectivePoint::GENERATOR);
+    let (_sk_s_2, pk_s_2) = NonZeroScalar::gen(&ProjectivePoint::GENERATOR);
+    let (sk_notary, pk_notary) = notary::<Secp256k1, Sha256>();
+
+    let mut session = accept::<Secp256k1>(transport, ciphersuite, G::ID)?;
+    let [pk_b_1, pk_b_2, pk_b_3] = session.receive_keys()?;
+    session.send(&ProtocolMessage::Keys(vec![pk_s_1, pk_s_2]))?;
+    let TaprootSetup {
+        terms,
+        transactions,
+    } = taproot_setup(pk_notary, pk_s_1, pk_s_2, pk_b_1, pk_b_2, pk_b_3);
+    let params = G::params();
+    let secret = G::notarize(&sk_notary, &terms, &params);
+    let mut seller = TaprootSeller::<G>::new(terms, params, transactions, sk_s_1, secret)?;
+    let mut clock = Clock::new();
+
+    //--------------------------------------------------------------------------------------
+    // SELLER 1
+    //--------------------------------------------------------------------------------------
+    let start = Instant::now();
+    let (offer, nonce_seller) = seller.offer()?;
+    let duration_1 = start.elapsed();
+    session.send(&ProtocolMessage::offer::<Sha256, G>(&offer))?;
+    session.send(&ProtocolMessage::Nonce(nonce_seller.nonce))?;
+
+    let nonce_buyer = receive_nonce(&mut session)?;
+    // tx_lock relayed by the buyer, confirmed in the next block
+    let lock = match session.receive()? {
+        ProtocolMessage::TaprootLock(signature) => TaprootLockMessage { signature },
+        message => return Err(session.unexpected(&message, "TaprootLock")),
+    };
+    clock.advance(1);
+
+    //--------------------------------------------------------------------------------------
+    // SELLER 2
+    //--------------------------------------------------------------------------------------
+    let start = Instant::now();
+    let locked = seller.receive_lock(&nonce_buyer, &lock, clock.now());
+    let partial_seller = session.check(locked, seller.evidence())?;
+    let duration_2 = start.elapsed();
+    session.send(&ProtocolMessage::PartialSignature(
+        partial_seller.partial_signature,
+    ))?;
+    let partial_buyer = receive_partial_signature(&mut session)?;
+
+    //--------------------------------------------------------------------------------------
+    // SELLER 3
+    //--------------------------------------------------------------------------------------
+    let start = Instant::now();
+    let paid = seller.receive_partial_signature(&partial_buyer, &clock);
+    let payment = session.check(paid, seller.evidence())?;
+    let duration_3 = start.elapsed();
+    session.send(&ProtocolMessage::TaprootPayment(payment.signature))?;
+
+    schemas::debug_print!(
+        "seller sent {} bytes and received {} bytes",
+        session.transport.bytes_sent(),
+        session.transport.bytes_received()
+    );
+
+    Ok(vec![duration_1, duration_2, duration_3, run.elapsed()])
+}
+
+// The steps of the buyer once tx_lock is published, with their durations
+fn taproot_payment<G>(
+    session: &mut Session<Secp256k1>,
+    buyer: &mut TaprootBuyer<G>,
+) -> Result<[Duration; 2]>
+where
+    G: Goods<Secp256k1, Sha256>,
+{
+    let partial_seller = receive_partial_signature(session)?;
+
+    //--------------------------------------------------------------------------------------
+    // BUYER 2
+    //--------------------------------------------------------------------------------------
+    let start = Instant::now();
+    let received = buyer.receive_partial_signature(&partial_seller);
+    let partial_buyer = session.check(received, buyer.evidence())?;
+    let duration_2 = start.elapsed();
+    session.send(&ProtocolMessage::PartialSignature(
+        partial_buyer.partial_signature,
+    ))?;
+
+    // tx_pay relayed by the seller
+    let payment = match session.receive()? {
+        ProtocolMessage::TaprootPayment(signature) => TaprootPaymentMessage { signature },
+        message => return Err(session.unexpected(&message, "TaprootPayment")),
+    };
+
+    //--------------------------------------------------------------------------------------
+    // BUYER 3
+    //--------------------------------------------------------------------------------------
+    let start = Instant::now();
+    let received = buyer.receive_payment(&payment);
+    let _goods = session.check(received, buyer.evidence())?;
+    let duration_3 = start.elapsed();
+
+    Ok([duration_2, duration_3])
+}
+
+// Waits for the timelock of tx_lock, and publishes tx_recover
+fn taproot_refund<G>(buyer: &mut TaprootBuyer<G>, clock: &mut Clock) -> Result<()>
+where
+    G: Goods<Secp256k1, Sha256>,
+{
+    loop {
+        match buyer.refund(clock) {
+            Err(SellingError::TimelockNotExpired) => clock.advance(1),
+            result => {
+                result?;
+                break;
+            }
+        }
+    }
+    buyer
+        .transactions()
+        .verify_recover()
+        .map_err(|error| format!("tx_recover is invalid: {:?}", error))?;
+
+    Ok(())
+}
+
+pub fn taproot_buyer<G>(
+    transport: &mut dyn Transport,
+    ciphersuite: Ciphersuite,
+) -> Result<Vec<Duration>>
+where
+    G: Notarize<Secp256k1, Sha256>,
+{
+    let run = Instant::now();
+    let (sk_b_1, pk_b_1) = NonZeroScalar::gen(&ProjectivePoint::GENERATOR);
+    let (sk_b_2, pk_b_2) = NonZeroScalar::gen(&ProjectivePoint::GENERATOR);
+    let (sk_b_3, pk_b_3) = NonZeroScalar::gen(&ProjectivePoint::GENERATOR);
+    let (_, pk_notary) = notary::<Secp256k1, Sha256>();
+
+    let mut session = hello::<Secp256k1>(transport, ciphersuite, G::ID)?;
+    session.send(&ProtocolMessage::Keys(vec![pk_b_1, pk_b_2, pk_b_3]))?;
+    let [pk_s_1, pk_s_2] = session.receive_seller_keys(&pk_notary)?;
+    let TaprootSetup {
+        terms,
+        transactions,
+    } = taproot_setup(pk_notary, pk_s_1, pk_s_2, pk_b_1, pk_b_2, pk_b_3);
+    let params = G::params();
+    let offer = receive_offer::<Secp256k1, Sha256, G>(&mut session, &terms, &params)?;
+    let nonce_seller = receive_nonce(&mut session)?;
+    let mut buyer = TaprootBuyer::<G>::new(terms, params, transactions, sk_b_1, sk_b_2, sk_b_3);
+    let mut clock = Clock::new();
+
+    //--------------------------------------------------------------------------------------
+    // BUYER 1
+    //--------------------------------------------------------------------------------------
+    let start = Instant::now();
+    let received = buyer.receive_offer(offer, &nonce_seller);
+    let nonce_buyer = session.check(received, buyer.evidence())?;
+    let lock = session.check(buyer.lock(), buyer.evidence())?;
+    let duration_1 = start.elapsed();
+
+    // tx_lock is published and confirmed in the next block
+    clock.advance(1);
+    buyer.confirm_lock(clock.now())?;
+    session.send(&ProtocolMessage::Nonce(nonce_buyer.nonce))?;
+    session.send(&ProtocolMessage::TaprootLock(lock.signature))?;
+
+    // tx_lock is published: without a valid tx_pay, the coins only come back with tx_recover
+    let [duration_2, duration_3] = match taproot_payment(&mut session, &mut buyer) {
+        Ok(durations) => durations,
+        Err(error) => {
+            taproot_refund(&mut buyer, &mut clock)?;
+            return Err(format!("{}, and tx_recover is published", error).into());
+        }
+    };
+
+    schemas::debug_print!(
+        "buyer sent {} bytes and received {} bytes",
+        session.transport.bytes_sent(),
+        session.transport.bytes_received()
+    );
+
+    Ok(vec![duration_1, duration_2, duration_3, run.elapsed()])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PartyFlow;
+    use p256::NistP256;
+    use schemas::protocol_message::{CurveId, HashId};
+    use schemas::selling_protocol::{BatchSignatureGoods, ServiceGoods, SignatureGoods};
+    use schemas::transport::MemoryTransport;
+    use std::thread;
+
+    type S = Secp256k1;
+    type H = Sha256;
+
+    fn ciphersuite(curve: CurveId) -> Ciphersuite {
+        Ciphersuite::new(curve, HashId::Sha256)
+    }
+
+    // Runs the seller in another thread and the buyer in this one over a MemoryTransport, and
+    // returns their results
+    fn run_pair(
+        seller: PartyFlow,
+        buyer: PartyFlow,
+        ciphersuite: Ciphersuite,
+    ) -> (
+        std::result::Result<Vec<Duration>, String>,
+        std::result::Result<Vec<Duration>, String>,
+    ) {
+        let (mut seller_end, mut buyer_end) = MemoryTransport::pair();
+        let seller = thread::spawn(move || {
+            seller(&mut seller_end, ciphersuite).map_err(|error| error.to_string())
+        });
+        let buyer = buyer(&mut buyer_end, ciphersuite).map_err(|error| error.to_string());
+        drop(buyer_end);
+
+        (seller.join().unwrap(), buyer)
+    }
+
+    // Both parties complete, with the time of each step and of the whole run
+    fn completes(seller: PartyFlow, buyer: PartyFlow, ciphersuite: Ciphersuite, steps: usize) {
+        let (seller, buyer) = run_pair(seller, buyer, ciphersuite);
+
+        assert_eq!(seller.unwrap().len(), steps + 1);
+        assert_eq!(buyer.unwrap().len(), steps + 1);
+    }
+
+    #[test]
+    fn signature_goods() {
+        completes(
+            seller::<S, H, SignatureGoods<S, H>>,
+            buyer::<S, H, SignatureGoods<S, H>>,
+            ciphersuite(CurveId::Secp256k1),
+            2,
+        );
+        completes(
+            seller::<NistP256, H, SignatureGoods<NistP256, H>>,
+            buyer::<NistP256, H, SignatureGoods<NistP256, H>>,
+            ciphersuite(CurveId::NistP256),
+            2,
+        );
+    }
+
+    #[test]
+    fn service_goods() {
+        completes(
+            seller::<S, H, ServiceGoods<S, H>>,
+            buyer::<S, H, ServiceGoods<S, H>>,
+            ciphersuite(CurveId::Secp256k1),
+            2,
+        );
+    }
+
+    #[test]
+    fn batch_signature_goods() {
+        completes(
+            seller::<S, H, BatchSignatureGoods<S, H>>,
+            buyer::<S, H, BatchSignatureGoods<S, H>>,
+            ciphersuite(CurveId::Secp256k1),
+            2,
+        );
+    }
+
+    #[test]
+    fn taproot() {
+        completes(
+            taproot_seller::<SignatureGoods<S, H>>,
+            taproot_buyer::<SignatureGoods<S, H>>,
+            ciphersuite(CurveId::Secp256k1),
+            3,
+        );
+        completes(
+            taproot_seller::<ServiceGoods<S, H>>,
+            taproot_buyer::<ServiceGoods<S, H>>,
+            ciphersuite(CurveId::Secp256k1),
+            3,
+        );
+        completes(
+            taproot_seller::<BatchSignatureGoods<S, H>>,
+            taproot_buyer::<BatchSignatureGoods<S, H>>,
+            ciphersuite(CurveId::Secp256k1),
+            3,
+        );
+    }
+
+    // The transport of a party that deviates from the protocol: tamper rewrites the messages it
+    // sends, or drops them if it returns None. The messages sent and received are kept.
+    struct Tampering {
+        transport: MemoryTransport,
+        tamper: Box<dyn FnMut(Vec<u8>) -> Option<Vec<u8>> + Send>,
+        sent: Vec<Vec<u8>>,
+        received: Vec<Vec<u8>>,
+    }
+
+    impl Transport for Tampering {
+        fn send(&mut self, message: &[u8]) -> io::Result<()> {
+            let Some(message) = (self.tamper)(message.to_vec()) else {
+                return Ok(());
+            };
+            self.sent.push(message.clone());
+
+            self.transport.send(&message)
+        }
+
+        fn receive(&mut self) -> io::Result<Vec<u8>> {
+            let message = self.transport.receive()?;
+            self.received.push(message.clone());
+
+            Ok(message)
+        }
+
+        fn bytes_sent(&self) -> u64 {
+            self.transport.bytes_sent()
+        }
+
+        fn bytes_received(&self) -> u64 {
+            self.transport.bytes_received()
+        }
+    }
+
+    fn decode(bytes: &[u8]) -> ProtocolMessage<S> {
+        ProtocolMessage::<S>::from_byte_vector(bytes).unwrap().1
+    }
+
+    // Rewrites the messages of the type name
+    fn rewrite(
+        name: &'static str,
+        mut f: impl FnMut(Vec<u8>) -> Option<Vec<u8>> + Send + 'static,
+    ) -> impl FnMut(Vec<u8>) -> Option<Vec<u8>> + Send + 'static {
+        move |bytes| {
+            if decode(&bytes).name() == name {
+                f(bytes)
+            } else {
+                Some(bytes)
+            }
+        }
+    }
+
+    // The last byte is the last byte of a scalar, which stays in the field
+    fn flip_last_byte(mut bytes: Vec<u8>) -> Option<Vec<u8>> {
+        *bytes.last_mut().unwrap() ^= 1;
+
+        Some(bytes)
+    }
+
+    // Runs the honest party in this thread against the deviating one, over a MemoryTransport.
+    // It returns the error of the honest party, the messages that the deviating one sent and
+    // those it received, including the ones sent after it stopped.
+    fn deviate(
+        honest: PartyFlow,
+        deviating: PartyFlow,
+        tamper: impl FnMut(Vec<u8>) -> Option<Vec<u8>> + Send + 'static,
+    ) -> (String, Vec<Vec<u8>>, Vec<Vec<u8>>) {
+        let (mut honest_end, deviating_end) = MemoryTransport::pair();
+        let mut transport = Tampering {
+            transport: deviating_end,
+            tamper: Box::new(tamper),
+            sent: vec![],
+            received: vec![],
+        };
+        let deviating = thread::spawn(move || {
+            let _ = deviating(&mut transport, ciphersuite(CurveId::Secp256k1));
+            while transport.receive().is_ok() {}
+
+            (transport.sent, transport.received)
+        });
+        let error = honest(&mut honest_end, ciphersuite(CurveId::Secp256k1))
+            .expect_err("the honest party accepted the deviation");
+        drop(honest_end);
+        let (sent, received) = deviating.join().unwrap();
+
+        (error.to_string(), sent, received)
+    }
+
+    // The honest party sends Abort with the reason, and its error has the evidence: the culprit,
+    // the error and the body of the message of type name
+    fn aborts(
+        (error, sent, received): (String, Vec<Vec<u8>>, Vec<Vec<u8>>),
+        reason: AbortReason,
+        culprit: Party,
+        selling_error: SellingError,
+        name: &str,
+    ) -> String {
+        let abort = received.last().map(|bytes| decode(bytes));
+        assert!(
+            matches!(abort, Some(ProtocolMessage::Abort(r)) if r == reason),
+            "{}",
+            error
+        );
+        let message = sent
+            .iter()
+            .find(|bytes| decode(bytes).name() == name)
+            .unwrap();
+        let body: String = message[3..].iter().map(|b| format!("{:02x}", b)).collect();
+        assert!(
+            error.contains(&format!("{}: {}", culprit, selling_error)),
+            "{}",
+            error
+        );
+        assert!(error.contains(&body), "{}", error);
+
+        error
+    }
+
+    #[test]
+    fn invalid_offer() {
+        aborts(
+            deviate(
+                buyer::<S, H, SignatureGoods<S, H>>,
+                seller::<S, H, SignatureGoods<S, H>>,
+                rewrite("Offer", flip_last_byte),
+            ),
+            AbortReason::InvalidOffer,
+            Party::Seller,
+            SellingError::InvalidOffer,
+            "Offer",
+        );
+    }
+
+    #[test]
+    fn malformed_offer() {
+        aborts(
+            deviate(
+                buyer::<S, H, SignatureGoods<S, H>>,
+                seller::<S, H, SignatureGoods<S, H>>,
+                rewrite("Offer", |mut bytes| {
+                    bytes.pop();
+                    Some(bytes)
+                }),
+            ),
+            AbortReason::MalformedMessage,
+            Party::Seller,
+            SellingError::MalformedMessage,
+            "Offer",
+        );
+    }
+
+    #[test]
+    fn invalid_pre_signature() {
+        aborts(
+            deviate(
+                seller::<S, H, SignatureGoods<S, H>>,
+                buyer::<S, H, SignatureGoods<S, H>>,
+                rewrite("PreSignature", flip_last_byte),
+            ),
+            AbortReason::InvalidPreSignature,
+            Party::Buyer,
+            SellingError::InvalidPreSignature,
+            "PreSignature",
+        );
+    }
+
+    #[test]
+    fn invalid_payment() {
+        let error = aborts(
+            deviate(
+                buyer::<S, H, SignatureGoods<S, H>>,
+                seller::<S, H, SignatureGoods<S, H>>,
+                rewrite("Payment", flip_last_byte),
+            ),
+            AbortReason::InvalidPayment,
+            Party::Seller,
+            SellingError::InvalidPayment,
+            "Payment",
+        );
+        assert!(error.ends_with("and tx_recover is published"));
+    }
+
+    #[test]
+    fn unexpected_message() {
+        // the buyer does not relay tx_lock and sends its pre-signature first
+        let error = aborts(
+            deviate(
+                seller::<S, H, SignatureGoods<S, H>>,
+                buyer::<S, H, SignatureGoods<S, H>>,
+                rewrite("Lock", |_| None),
+            ),
+            AbortReason::UnexpectedMessage,
+            Party::Buyer,
+            SellingError::UnexpectedMessage,
+            "PreSignature",
+        );
+        assert!(error.starts_with("expected Lock, received PreSignature"));
+    }
+
+    // The seller announces the notary before its keys
+    fn announce(pk: Point<S>) -> impl FnMut(Vec<u8>) -> Option<Vec<u8>> + Send + 'static {
+        rewrite("Keys", move |bytes| {
+            let ProtocolMessage::Keys(keys) = decode(&bytes) else {
+                unreachable!()
+            };
+            let keys = ProtocolMessage::<S>::Keys([vec![pk], keys].concat());
+
+            Some(keys.to_byte_vector(PROTOCOL_VERSION).unwrap())
+        })
+    }
+
+    #[test]
+    fn unknown_notary() {
+        let (_, pk_other) = SchnorrAdaptorSignature::<S, H>::gen();
+
+        aborts(
+            deviate(
+                buyer::<S, H, SignatureGoods<S, H>>,
+                seller::<S, H, SignatureGoods<S, H>>,
+                announce(pk_other),
+            ),
+            AbortReason::UnknownNotary,
+            Party::Seller,
+            SellingError::UnknownNotary,
+            "Keys",
+        );
+    }
+
+    #[test]
+    fn announced_notary() {
+        let (_, pk_notary) = notary::<S, H>();
+        let (mut buyer_end, seller_end) = MemoryTransport::pair();
+        let mut transport = Tampering {
+            transport: seller_end,
+            tamper: Box::new(announce(pk_notary)),
+            sent: vec![],
+            received: vec![],
+        };
+        let seller = thread::spawn(move || {
+            seller::<S, H, SignatureGoods<S, H>>(&mut transport, ciphersuite(CurveId::Secp256k1))
+                .map_err(|error| error.to_string())
+        });
+        let buyer =
+            buyer::<S, H, SignatureGoods<S, H>>(&mut buyer_end, ciphersuite(CurveId::Secp256k1));
+
+        assert!(buyer.is_ok());
+        assert!(seller.join().unwrap().is_ok());
+    }
+}